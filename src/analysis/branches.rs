@@ -0,0 +1,114 @@
+use core::cmp::Ordering;
+use core::fmt;
+use crate::{ControlFlowGraph, Edge};
+use crate::types::*;
+use crate::prelude::*;
+
+/// How often a conditional jump went each way, derived from the two edges it created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchStat {
+    /// The address of the conditional jump.
+    pub pc: u64,
+    /// The position of the block ending with the conditional jump.
+    pub block: BlockId,
+    /// The traversal count of the edge to the success address.
+    pub taken: u64,
+    /// The traversal count of the edge to the failure address.
+    pub not_taken: u64,
+    /// The share of executions which went the more common way, from 0.5 for an even split to 1.0 for a branch which
+    /// always went the same way, or 0.0 for a branch which was never executed.
+    pub bias: f64,
+}
+
+/// The branch statistics formatted as a table, with the most biased branches first.
+pub struct BranchTable(pub Vec<BranchStat>);
+
+impl fmt::Display for BranchTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut branches: Vec<&BranchStat> = self.0.iter().collect();
+        branches.sort_by(|a, b| b.bias.partial_cmp(&a.bias).unwrap_or(Ordering::Equal).then(a.pc.cmp(&b.pc)));
+
+        writeln!(f, "{:>12} {:>10} {:>10} {:>8}", "pc", "taken", "not taken", "bias")?;
+        for branch in branches {
+            writeln!(f, "{:>12} {:>10} {:>10} {:>7.2}%", format!("{:#x}", branch.pc), branch.taken, branch.not_taken, branch.bias * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the statistics of every conditional jump ending a block, ordered by the address of the jump.
+    ///
+    /// A jump whose edge to one of its targets is missing, such as after pruning, reports a count of zero for it.
+    pub fn branch_stats(&self) -> Vec<BranchStat> {
+        let count = |block: BlockId, address: u64| {
+            let target = self.starts.get(&address)?;
+            self.blocks[block].edge_to(*target).map(Edge::count)
+        };
+
+        let mut branches = Vec::new();
+        for &block in self.starts.values() {
+            let last = self.blocks[block].block.iter().next_back();
+            if let Some((&pc, BlockType::Jump(_, success, JumpType::ConditionalTaken | JumpType::ConditionalNotTaken, Some(failure)))) = last {
+                let taken = count(block, *success).unwrap_or(0);
+                let not_taken = count(block, *failure).unwrap_or(0);
+                // Summed as floats, as two counts near the maximum would overflow.
+                let total = taken as f64 + not_taken as f64;
+                let bias = if total == 0.0 { 0.0 } else { taken.max(not_taken) as f64 / total };
+                branches.push(BranchStat { pc, block, taken, not_taken, bias });
+            }
+        }
+        branches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::analysis::BranchTable;
+    use crate::types::*;
+
+    #[test]
+    fn skewed_branch() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for iteration in 0..10 {
+            let jump_type = if iteration == 4 { JumpType::ConditionalNotTaken } else { JumpType::ConditionalTaken };
+            cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, jump_type, Some(10)))?;
+            let start = cfg.block(cfg.current_block_index()).unwrap().start();
+            cfg.execute(start, BlockType::Jump("JNZ".to_string(), 0, JumpType::ConditionalTaken, Some(start + 1)))?;
+        }
+
+        let stats = cfg.branch_stats();
+        assert_eq!(3, stats.len());
+        let branch = &stats[0];
+        assert_eq!((0, BlockId(0), 9, 1), (branch.pc, branch.block, branch.taken, branch.not_taken));
+        assert!((branch.bias - 0.9).abs() < 1e-9);
+        // The backward jumps always went back and never reached their failure address.
+        assert_eq!((10, 1, 0, 1.0), (stats[1].pc, stats[1].taken, stats[1].not_taken, stats[1].bias));
+        assert_eq!((20, 9, 0, 1.0), (stats[2].pc, stats[2].taken, stats[2].not_taken, stats[2].bias));
+
+        let expected = concat!(
+            "          pc      taken  not taken     bias\n",
+            "         0xa          1          0  100.00%\n",
+            "        0x14          9          0  100.00%\n",
+            "         0x0          9          1   90.00%\n",
+        );
+        assert_eq!(expected, BranchTable(stats).to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_counterpart_edge() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(10)))?;
+        cfg.execute(20, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.prune_unreachable();
+
+        let stats = cfg.branch_stats();
+        assert_eq!(1, stats.len());
+        assert_eq!((1, 0), (stats[0].taken, stats[0].not_taken));
+
+        Ok(())
+    }
+}
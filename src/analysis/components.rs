@@ -0,0 +1,97 @@
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Finds the weakly connected components of the graph, the groups of blocks connected by edges followed in either
+    /// direction, whether they were traversed or not.
+    ///
+    /// The blocks of each component are in ascending order, and the components are ordered by their first block, so
+    /// the component of the entry always comes first.
+    pub fn weakly_connected_components(&self) -> Vec<Vec<BlockId>> {
+        let mut component_of = vec![None; self.blocks.len()];
+        let mut components = Vec::new();
+        for root in 0..self.blocks.len() {
+            if component_of[root].is_some() {
+                continue;
+            }
+            let component = components.len();
+            component_of[root] = Some(component);
+            let mut members = Vec::new();
+            let mut worklist = vec![BlockId(root)];
+            while let Some(block) = worklist.pop() {
+                members.push(block);
+                let successors = self.blocks[block].edges.iter().map(|edge| edge.target());
+                for neighbor in successors.chain(self.predecessors[block.index()].iter().copied()) {
+                    if component_of[neighbor.index()].is_none() {
+                        component_of[neighbor.index()] = Some(component);
+                        worklist.push(neighbor);
+                    }
+                }
+            }
+            members.sort();
+            components.push(members);
+        }
+        components
+    }
+
+    /// Returns the weakly connected components which don't contain the entry, the islands which no analysis starting
+    /// from the entry such as dominators or reachability ever visits.
+    ///
+    /// A graph usually ends up with these after merging graphs from separate entry points or jumping elsewhere with
+    /// `set_current_block_by_address`. The components are ordered like `weakly_connected_components` orders them.
+    pub fn orphan_components(&self) -> Vec<Vec<BlockId>> {
+        self.weakly_connected_components().split_off(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    /// Runs a program from 0 which calls into 0x20, and a separate one jumping from 0x100 back and forth with 0x110.
+    fn islands() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(1)))?;
+        cfg.execute(0x20, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+
+        let mut other = ControlFlowGraph::new(0);
+        other.set_current_block_by_address(0x100);
+        other.execute(0x100, BlockType::Jump("JZ".to_string(), 0x110, JumpType::ConditionalTaken, Some(0x101)))?;
+        other.execute(0x110, BlockType::Jump("JMP".to_string(), 0x100, JumpType::UnconditionalJump, None))?;
+        cfg.merge(&other)?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn disconnected_merge() -> Result<(), CFGError> {
+        let cfg = islands()?;
+        let block = |address| cfg.starts[&address];
+
+        let components = cfg.weakly_connected_components();
+        assert_eq!(vec![vec![cfg.entry_index(), block(0x20), block(1)], vec![block(0x100), block(0x101), block(0x110)]], components);
+        assert_eq!(components[1..], cfg.orphan_components()[..]);
+        assert!(cfg.reachable_from_entry(false).iter().all(|&block| !components[1].contains(&block)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn connected_graph() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        assert_eq!(vec![vec![cfg.entry_index()]], cfg.weakly_connected_components());
+        assert!(cfg.orphan_components().is_empty());
+
+        // An island stays one until an edge reaches it from either side.
+        cfg.set_current_block_by_address(0x10);
+        cfg.execute(0x10, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        assert_eq!(vec![vec![cfg.starts[&0x10], cfg.starts[&0x20]]], cfg.orphan_components());
+        cfg.set_current_block_by_address(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        assert_eq!(1, cfg.weakly_connected_components().len());
+        assert!(cfg.orphan_components().is_empty());
+
+        Ok(())
+    }
+}
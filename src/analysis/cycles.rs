@@ -0,0 +1,114 @@
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+/// The state of a block during a depth-first search.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    Unvisited,
+    OnPath,
+    Finished,
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Finds the back edges of a depth-first search, which are the edges to a block still on the search path.
+    ///
+    /// The search starts at the entry and then at every block it didn't reach in order of position, so cycles in
+    /// unreachable blocks are found as well. The edges are returned as the positions of their source and target,
+    /// ordered by source and then target.
+    pub fn back_edges(&self) -> Vec<(BlockId, BlockId)> {
+        let mut state = vec![Visit::Unvisited; self.blocks.len()];
+        let mut back_edges = Vec::new();
+
+        for root in 0..self.blocks.len() {
+            if state[root] != Visit::Unvisited {
+                continue;
+            }
+            // Each frame is a block and the position of the next edge to visit from it.
+            let mut stack = vec![(root, 0)];
+            state[root] = Visit::OnPath;
+            while let Some((block, next)) = stack.last_mut() {
+                match self.blocks[*block].edges.get(*next) {
+                    Some(edge) => {
+                        let edge = edge.target();
+                        *next += 1;
+                        if state[edge.index()] == Visit::OnPath {
+                            back_edges.push((BlockId(*block), edge));
+                        } else if state[edge.index()] == Visit::Unvisited {
+                            state[edge.index()] = Visit::OnPath;
+                            stack.push((edge.index(), 0));
+                        }
+                    }
+                    None => {
+                        state[*block] = Visit::Finished;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        back_edges.sort_unstable();
+        back_edges
+    }
+
+    /// Returns whether the observed control flow contains no cycles, including self loops.
+    pub fn is_acyclic(&self) -> bool {
+        self.back_edges().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn simple_loop() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..3 {
+            cfg.execute(10, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute(11, BlockType::Jump("JNZ".to_string(), 10, JumpType::ConditionalTaken, Some(12)))?;
+        }
+
+        let header = cfg.starts[&10];
+        assert_eq!(vec![(header, header)], cfg.back_edges());
+        assert_eq!(10, cfg.block(header).unwrap().start());
+        assert!(!cfg.is_acyclic());
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_loops() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..2 {
+            cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+            for _ in 0..2 {
+                cfg.execute(20, BlockType::Jump("JNZ".to_string(), 20, JumpType::ConditionalTaken, Some(21)))?;
+            }
+            cfg.execute(20, BlockType::Jump("JNZ".to_string(), 20, JumpType::ConditionalNotTaken, Some(21)))?;
+            cfg.execute(21, BlockType::Jump("JNZ".to_string(), 10, JumpType::ConditionalTaken, Some(22)))?;
+        }
+
+        let (outer, inner, latch) = (cfg.starts[&10], cfg.starts[&20], cfg.starts[&21]);
+        assert_eq!(vec![(inner, inner), (latch, outer)], cfg.back_edges());
+        assert!(!cfg.is_acyclic());
+
+        Ok(())
+    }
+
+    #[test]
+    fn acyclic_diamond() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(10)))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(30, BlockType::Instruction("RET".to_string(), None))?;
+
+        assert!(cfg.back_edges().is_empty());
+        assert!(cfg.is_acyclic());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use crate::ControlFlowGraph;
+use crate::analysis::dominators_from;
+use crate::types::{BlockId, CFGError};
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Computes the immediate dominator of every block, indexed by block position.
+    ///
+    /// The entry block is its own immediate dominator and blocks which are unreachable from the entry are `None`.
+    /// This is the iterative algorithm of Cooper, Harvey, and Kennedy over the reverse post-order of the graph.
+    pub fn dominators(&self) -> Result<Vec<Option<BlockId>>, CFGError> {
+        dominators_from(self, 0)
+    }
+
+    /// Returns whether the block at position `a` dominates the block at position `b`, every block dominates itself.
+    ///
+    /// Blocks which are unreachable from the entry are not dominated by any block.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        let Ok(idom) = self.dominators() else { return false };
+        if idom.get(b.index()).copied().flatten().is_none() {
+            return false;
+        }
+        let mut current = b;
+        loop {
+            if current == a {
+                return true;
+            }
+            match idom.get(current.index()).copied().flatten() {
+                Some(parent) if parent != current => current = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Computes the immediate post-dominator of every block, indexed by block position, such as to find where the
+    /// paths leaving a branch converge again.
+    ///
+    /// Post-dominators are the dominators of the reversed graph of traversed edges, rooted at a virtual exit which
+    /// every exit block and every block without a traversed outgoing edge leads to. Blocks which are only
+    /// post-dominated by the virtual exit, and blocks which can't reach it, such as those inside of an infinite loop,
+    /// are `None`.
+    pub fn post_dominators(&self) -> Result<Vec<Option<BlockId>>, CFGError> {
+        let (ipdom, _) = self.post_dominator_tree()?;
+        let exit = self.blocks.len();
+        Ok(ipdom[..exit].iter().map(|dominator| dominator.filter(|dominator| *dominator != exit).map(BlockId)).collect())
+    }
+
+    /// Returns the immediate post-dominator of the block at the given position, see [post_dominators](Self::post_dominators).
+    pub fn immediate_post_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.post_dominators().ok()?.get(block.index()).copied().flatten()
+    }
+
+    /// Returns the nearest block which post-dominates both blocks, such as the block where both sides of a branch
+    /// meet again, every block post-dominating itself.
+    ///
+    /// Returns `None` if either block can't reach the virtual exit or only the virtual exit post-dominates both.
+    pub fn common_post_dominator(&self, a: BlockId, b: BlockId) -> Option<BlockId> {
+        let (ipdom, rank) = self.post_dominator_tree().ok()?;
+        let exit = self.blocks.len();
+        if a.index() >= exit || b.index() >= exit || ipdom[a.index()].is_none() || ipdom[b.index()].is_none() {
+            return None;
+        }
+        let common = intersect(&ipdom, &rank, a.index(), b.index());
+        (common != exit).then_some(BlockId(common))
+    }
+
+    /// Computes the immediate post-dominators, with the virtual exit at the position after the last block, along with
+    /// the rank of every block in the reverse post-order of the reversed graph.
+    fn post_dominator_tree(&self) -> Result<(Vec<Option<usize>>, Vec<usize>), CFGError> {
+        let exit = self.blocks.len();
+        // The successors of every block are its predecessors in the reversed graph.
+        let mut successors = Vec::with_capacity(exit + 1);
+        for block in self.blocks.iter() {
+            let mut next = Vec::new();
+            for edge in block.edges.iter().filter(|edge| edge.count() > 0) {
+                if edge.target().index() >= exit {
+                    return Err(CFGError::MissingBlock { index: edge.target().index() });
+                }
+                next.push(edge.target().index());
+            }
+            if next.is_empty() || block.exit {
+                next.push(exit);
+            }
+            successors.push(next);
+        }
+        successors.push(Vec::new());
+        let mut predecessors = vec![Vec::new(); exit + 1];
+        for (block, next) in successors.iter().enumerate() {
+            for &successor in next.iter() {
+                predecessors[successor].push(block);
+            }
+        }
+
+        let order = reverse_post_order(&predecessors, exit);
+        let mut rank = vec![usize::MAX; exit + 1];
+        for (position, block) in order.iter().enumerate() {
+            rank[*block] = position;
+        }
+
+        let mut ipdom = vec![None; exit + 1];
+        ipdom[exit] = Some(exit);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut processed = successors[block].iter().copied().filter(|s| ipdom[*s].is_some());
+                let Some(first) = processed.next() else { continue };
+                let new_ipdom = processed.fold(first, |a, b| intersect(&ipdom, &rank, a, b));
+                if ipdom[block] != Some(new_ipdom) {
+                    ipdom[block] = Some(new_ipdom);
+                    changed = true;
+                }
+            }
+        }
+
+        Ok((ipdom, rank))
+    }
+}
+
+/// Computes the immediate dominator of every node from the predecessors of every node and the reverse post-order of
+/// the nodes reachable from the root, which is the first node of the order and its own immediate dominator.
+pub(crate) fn immediate_dominators(predecessors: &[Vec<usize>], order: &[usize]) -> Vec<Option<BlockId>> {
+    let mut rank = vec![usize::MAX; predecessors.len()];
+    for (position, block) in order.iter().enumerate() {
+        rank[*block] = position;
+    }
+
+    let mut idom = vec![None; predecessors.len()];
+    idom[order[0]] = Some(order[0]);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in order.iter().skip(1) {
+            let mut processed = predecessors[block].iter().copied().filter(|p| idom[*p].is_some());
+            let Some(first) = processed.next() else { continue };
+            let new_idom = processed.fold(first, |a, b| intersect(&idom, &rank, a, b));
+            if idom[block] != Some(new_idom) {
+                idom[block] = Some(new_idom);
+                changed = true;
+            }
+        }
+    }
+
+    idom.into_iter().map(|dominator| dominator.map(BlockId)).collect()
+}
+
+/// Returns the nodes reachable from the root along the given adjacency lists in reverse post-order.
+fn reverse_post_order(adjacency: &[Vec<usize>], root: usize) -> Vec<usize> {
+    let mut visited = vec![false; adjacency.len()];
+    let mut order = Vec::new();
+    let mut stack = vec![(root, 0)];
+    visited[root] = true;
+    while let Some((node, next)) = stack.last_mut() {
+        match adjacency[*node].get(*next) {
+            Some(&successor) => {
+                *next += 1;
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            None => {
+                order.push(*node);
+                stack.pop();
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// Walks both blocks up the dominator tree until they meet at their common dominator.
+fn intersect(idom: &[Option<usize>], rank: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while rank[a] > rank[b] {
+            a = idom[a].expect("processed blocks have a dominator");
+        }
+        while rank[b] > rank[a] {
+            b = idom[b].expect("processed blocks have a dominator");
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BasicBlock, ControlFlowGraph};
+    use crate::types::*;
+
+    #[test]
+    fn diamond() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(5)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+
+        let (left, right, join) = (cfg.starts[&5], cfg.starts[&10], cfg.starts[&20]);
+        let idom = cfg.dominators()?;
+        let entry = cfg.entry_index();
+        assert_eq!(Some(entry), idom[0]);
+        assert_eq!(Some(entry), idom[left.index()]);
+        assert_eq!(Some(entry), idom[right.index()]);
+        assert_eq!(Some(entry), idom[join.index()]);
+        assert!(cfg.dominates(entry, join));
+        assert!(!cfg.dominates(left, join));
+        assert!(!cfg.dominates(right, join));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loop_body() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..3 {
+            cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+            cfg.execute(20, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        }
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalTaken, Some(20)))?;
+
+        let (header, body, exit) = (cfg.starts[&10], cfg.starts[&20], cfg.starts[&30]);
+        let idom = cfg.dominators()?;
+        assert_eq!(Some(cfg.entry_index()), idom[header.index()]);
+        assert_eq!(Some(header), idom[body.index()]);
+        assert_eq!(Some(header), idom[exit.index()]);
+        assert!(cfg.dominates(header, body));
+        assert!(!cfg.dominates(body, header));
+
+        Ok(())
+    }
+
+    #[test]
+    fn diamond_post_dominators() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(5)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 30, JumpType::UnconditionalJump, None))?;
+
+        let (entry, left, right, join, end) = (cfg.entry_index(), cfg.starts[&5], cfg.starts[&10], cfg.starts[&20], cfg.starts[&30]);
+        let ipdom = cfg.post_dominators()?;
+        assert_eq!(Some(join), ipdom[entry.index()]);
+        assert_eq!(Some(join), ipdom[left.index()]);
+        assert_eq!(Some(join), ipdom[right.index()]);
+        assert_eq!(Some(end), ipdom[join.index()]);
+        assert_eq!(None, ipdom[end.index()]);
+        assert_eq!(Some(join), cfg.common_post_dominator(left, right));
+        assert_eq!(Some(join), cfg.common_post_dominator(left, join));
+        assert_eq!(Some(end), cfg.common_post_dominator(end, end));
+
+        // Without the jump out of the join, every block loops forever and nothing reaches the exit.
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(5)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        assert_eq!(vec![None; 3], cfg.post_dominators()?);
+        assert_eq!(None, cfg.common_post_dominator(cfg.starts[&5], cfg.starts[&10]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loop_with_break_post_dominators() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..3 {
+            cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+            cfg.execute(20, BlockType::Jump("JNZ".to_string(), 40, JumpType::ConditionalNotTaken, Some(21)))?;
+            cfg.execute(21, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        }
+        // The break out of the middle of the body skips the regular exit at 30.
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+        cfg.execute(20, BlockType::Jump("JNZ".to_string(), 40, JumpType::ConditionalTaken, Some(21)))?;
+
+        let (entry, header, body, latch, exit, broken) = (cfg.entry_index(), cfg.starts[&10], cfg.starts[&20], cfg.starts[&21], cfg.starts[&30], cfg.starts[&40]);
+        assert_eq!(Some(header), cfg.immediate_post_dominator(entry));
+        assert_eq!(Some(body), cfg.immediate_post_dominator(header));
+        assert_eq!(Some(broken), cfg.immediate_post_dominator(body));
+        assert_eq!(Some(header), cfg.immediate_post_dominator(latch));
+        assert_eq!(None, cfg.immediate_post_dominator(exit));
+        assert_eq!(Some(header), cfg.common_post_dominator(entry, latch));
+        assert_eq!(None, cfg.common_post_dominator(exit, broken));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable_block() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        let island = cfg.add_block(BasicBlock::new(40));
+
+        let idom = cfg.dominators()?;
+        let entry = cfg.entry_index();
+        assert_eq!(vec![Some(entry), Some(entry), None], idom);
+        assert!(!cfg.dominates(entry, island));
+        assert!(cfg.dominates(entry, entry));
+        assert!(!cfg.dominates(island, island));
+
+        Ok(())
+    }
+}
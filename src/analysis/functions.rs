@@ -0,0 +1,151 @@
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use crate::ControlFlowGraph;
+use crate::types::*;
+use crate::prelude::*;
+
+/// The functions of a ControlFlowGraph and the calls between them, with functions identified by their entry address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraph {
+    /// The entry address of every function in ascending order, which are the entry of the graph and every call target.
+    pub functions: Vec<u64>,
+    /// The calls between functions as `(caller, callee, count)`, with the counts summed across every call site.
+    pub calls: Vec<(u64, u64, u64)>,
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the entry address of every function, which are the entry of the graph and the targets of calls.
+    fn function_entries(&self) -> BTreeSet<u64> {
+        let mut entries = BTreeSet::from([self.entry().start]);
+        for block in self.blocks.iter() {
+            for edge in block.edges().filter(|edge| edge.kind() == EdgeKind::Call) {
+                entries.insert(self.blocks[edge.target()].start);
+            }
+        }
+        entries
+    }
+
+    /// Returns the entry address of the function every block belongs to, indexed by block position.
+    ///
+    /// A function is made up of the blocks reachable from its entry without following calls, returns, or edges into
+    /// the entry of another function, and continues at the return address of each of its calls. A block reachable from
+    /// several functions belongs to the one with the lowest entry address, and a block reachable from none, such as
+    /// one a thread started in, belongs to no function.
+    pub(crate) fn function_of(&self) -> Vec<Option<u64>> {
+        let entries = self.function_entries();
+        let mut function_of = vec![None; self.blocks.len()];
+        for &entry in entries.iter() {
+            let root = self.starts[&entry];
+            function_of[root.index()] = Some(entry);
+            let mut worklist = VecDeque::from([root]);
+            while let Some(block) = worklist.pop_front() {
+                let mut successors: Vec<BlockId> = self.blocks[block].edges()
+                    .filter(|edge| !matches!(edge.kind(), EdgeKind::Call | EdgeKind::Return))
+                    .map(|edge| edge.target())
+                    .collect();
+                if let Some((_, BlockType::Jump(_, _, JumpType::Call, Some(return_address)))) = self.blocks[block].block.iter().next_back() {
+                    successors.extend(self.starts.get(return_address).copied());
+                }
+                for successor in successors {
+                    if function_of[successor.index()].is_none() && !entries.contains(&self.blocks[successor].start) {
+                        function_of[successor.index()] = Some(entry);
+                        worklist.push_back(successor);
+                    }
+                }
+            }
+        }
+        function_of
+    }
+
+    /// Groups the blocks by the function they belong to, keyed by the entry address of the function, with the
+    /// positions of the blocks in ascending order.
+    ///
+    /// See [call_graph](Self::call_graph) for how blocks are assigned to functions.
+    pub fn functions(&self) -> BTreeMap<u64, Vec<BlockId>> {
+        let mut functions: BTreeMap<u64, Vec<BlockId>> = self.function_entries().into_iter().map(|entry| (entry, Vec::new())).collect();
+        for (index, function) in self.function_of().into_iter().enumerate() {
+            if let Some(function) = function {
+                functions.get_mut(&function).unwrap().push(BlockId(index));
+            }
+        }
+        functions
+    }
+
+    /// Builds the call graph, whose nodes are the functions of the graph and whose edges are the calls between them.
+    ///
+    /// The entry of the graph and the target of every call are the functions. A function is made up of the blocks
+    /// reachable from its entry without following calls, returns, or edges into the entry of another function, and
+    /// continues at the return address of each of its calls. A block reachable from several functions belongs to the
+    /// one with the lowest entry address, and calls from a block which belongs to no function are left out.
+    pub fn call_graph(&self) -> CallGraph {
+        let function_of = self.function_of();
+        let mut calls = BTreeMap::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let Some(caller) = function_of[index] else { continue };
+            for edge in block.edges().filter(|edge| edge.kind() == EdgeKind::Call) {
+                let count: &mut u64 = calls.entry((caller, self.blocks[edge.target()].start)).or_insert(0);
+                *count = count.saturating_add(edge.count());
+            }
+        }
+
+        CallGraph {
+            functions: self.function_entries().into_iter().collect(),
+            calls: calls.into_iter().map(|((caller, callee), count)| (caller, callee, count)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    /// Runs a main function at 0x100 which calls a helper at 0x200 from a loop three times, the helper branching on
+    /// every call.
+    fn run() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x100);
+        for lap in 0..3 {
+            cfg.execute(0x101, BlockType::Jump("CALL".to_string(), 0x200, JumpType::Call, Some(0x102)))?;
+            let jump_type = if lap == 1 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(0x201, BlockType::Jump("JZ".to_string(), 0x210, jump_type, Some(0x202)))?;
+            let ret = if lap == 1 { 0x211 } else { 0x203 };
+            cfg.execute(ret, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+            let exit = if lap == 2 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(0x103, BlockType::Jump("JNZ".to_string(), 0x110, exit, Some(0x100)))?;
+        }
+        Ok(cfg)
+    }
+
+    #[test]
+    fn two_functions() -> Result<(), CFGError> {
+        let cfg = run()?;
+
+        let call_graph = cfg.call_graph();
+        assert_eq!(vec![0x100, 0x200], call_graph.functions);
+        assert_eq!(vec![(0x100, 0x200, 3)], call_graph.calls);
+
+        let functions = cfg.functions();
+        let starts = |function: u64| -> Vec<u64> {
+            let mut starts: Vec<u64> = functions[&function].iter().map(|block| cfg.block(*block).unwrap().start()).collect();
+            starts.sort();
+            starts
+        };
+        assert_eq!(vec![0x100, 0x102, 0x110], starts(0x100));
+        assert_eq!(vec![0x200, 0x202, 0x210], starts(0x200));
+        assert_eq!(cfg.len(), functions.values().map(Vec::len).sum::<usize>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn function_without_calls() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+
+        let call_graph = cfg.call_graph();
+        assert_eq!(vec![0], call_graph.functions);
+        assert!(call_graph.calls.is_empty());
+        assert_eq!(vec![BlockId(0), BlockId(1)], cfg.functions()[&0]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::collections::VecDeque;
+use crate::types::{BlockId, CFGError};
+use crate::analysis::immediate_dominators;
+use crate::prelude::*;
+
+/// The shape of a graph which the traversals and the dominator computation walk, implemented by the graph and by the
+/// views of it so that those don't need a copy of the blocks.
+pub(crate) trait GraphLike {
+    /// Returns the number of blocks.
+    fn block_count(&self) -> usize;
+
+    /// Returns the positions of the blocks the block at the given position has an edge to, leaving out the edges which
+    /// were never traversed with `only_traversed`.
+    fn successor_ids(&self, block: BlockId, only_traversed: bool) -> impl Iterator<Item = BlockId> + '_;
+}
+
+/// Returns the predecessors of every block, indexed by block position.
+pub(crate) fn predecessor_lists<G: GraphLike>(graph: &G) -> Result<Vec<Vec<usize>>, CFGError> {
+    let mut predecessors = vec![Vec::new(); graph.block_count()];
+    for index in 0..graph.block_count() {
+        for successor in graph.successor_ids(BlockId(index), false) {
+            predecessors.get_mut(successor.index()).ok_or(CFGError::MissingBlock { index: successor.index() })?.push(index);
+        }
+    }
+    Ok(predecessors)
+}
+
+/// Returns the blocks reachable from the given root in the order a breadth-first search visits them, the root first.
+pub(crate) fn breadth_first_order<G: GraphLike>(graph: &G, root: usize, only_traversed: bool) -> Vec<BlockId> {
+    let mut visited = vec![false; graph.block_count()];
+    visited[root] = true;
+    let mut order = vec![BlockId(root)];
+    let mut worklist = VecDeque::from([BlockId(root)]);
+    while let Some(block) = worklist.pop_front() {
+        for successor in graph.successor_ids(block, only_traversed) {
+            if !visited[successor.index()] {
+                visited[successor.index()] = true;
+                order.push(successor);
+                worklist.push_back(successor);
+            }
+        }
+    }
+    order
+}
+
+/// Marks every block reachable from the given root with a breadth-first search.
+pub(crate) fn reachable_mask<G: GraphLike>(graph: &G, root: usize, only_traversed: bool) -> Vec<bool> {
+    let mut reachable = vec![false; graph.block_count()];
+    for block in breadth_first_order(graph, root, only_traversed) {
+        reachable[block.index()] = true;
+    }
+    reachable
+}
+
+/// Returns the blocks reachable from the given root in reverse post-order of a depth-first search.
+pub(crate) fn reverse_post_order_from<G: GraphLike>(graph: &G, root: usize) -> Result<Vec<usize>, CFGError> {
+    let mut visited = vec![false; graph.block_count()];
+    let mut order = Vec::new();
+    // Each frame is a block and its successors which are left to visit.
+    *visited.get_mut(root).ok_or(CFGError::MissingBlock { index: root })? = true;
+    let mut stack = vec![(root, graph.successor_ids(BlockId(root), false))];
+
+    while let Some((block, successors)) = stack.last_mut() {
+        match successors.next() {
+            Some(successor) => {
+                let successor = successor.index();
+                let seen = visited.get_mut(successor).ok_or(CFGError::MissingBlock { index: successor })?;
+                if !*seen {
+                    *seen = true;
+                    stack.push((successor, graph.successor_ids(BlockId(successor), false)));
+                }
+            }
+            None => {
+                order.push(*block);
+                stack.pop();
+            }
+        }
+    }
+
+    order.reverse();
+    Ok(order)
+}
+
+/// Computes the immediate dominator of every block with the given root, indexed by block position.
+pub(crate) fn dominators_from<G: GraphLike>(graph: &G, root: usize) -> Result<Vec<Option<BlockId>>, CFGError> {
+    let predecessors = predecessor_lists(graph)?;
+    let order = reverse_post_order_from(graph, root)?;
+    Ok(immediate_dominators(&predecessors, &order))
+}
@@ -0,0 +1,69 @@
+use core::cmp::Reverse;
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Follows the most traversed outgoing edge of each block from the entry, returning the positions of the visited blocks.
+    ///
+    /// The path stops after `max_len` blocks, at a block without a traversed edge, or before revisiting a block. Ties
+    /// between edges with the same count go to the edge which was added first.
+    pub fn hottest_path(&self, max_len: usize) -> Vec<BlockId> {
+        let mut path = Vec::new();
+        let mut visited = vec![false; self.blocks.len()];
+        let mut current = BlockId(0);
+
+        while path.len() < max_len && !visited[current.index()] {
+            visited[current.index()] = true;
+            path.push(current);
+            let hottest = self.blocks[current].edges.iter()
+                .filter(|edge| edge.count() > 0)
+                .min_by_key(|edge| Reverse(edge.count()));
+            match hottest {
+                Some(edge) => current = edge.target(),
+                None => break,
+            }
+        }
+
+        path
+    }
+
+    /// Returns the `n` most traversed edges as `(source, target, count)`, ties ordered by the source and then the target.
+    pub fn top_edges(&self, n: usize) -> Vec<(BlockId, BlockId, u64)> {
+        let mut edges: Vec<(BlockId, BlockId, u64)> = self.blocks.iter().enumerate()
+            .flat_map(|(index, block)| block.edges.iter().map(move |edge| (BlockId(index), edge.target(), edge.count())))
+            .collect();
+        edges.sort_by_key(|(source, target, count)| (Reverse(*count), *source, *target));
+        edges.truncate(n);
+        edges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn dominant_branch() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for iteration in 0..10 {
+            let jump_type = if iteration % 5 == 0 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, jump_type, Some(20)))?;
+            let block = cfg.current_block_index();
+            let start = cfg.block(block).unwrap().start();
+            cfg.execute(start, BlockType::Jump("JMP".to_string(), 40, JumpType::UnconditionalJump, None))?;
+            cfg.execute(40, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        }
+
+        let (header, hot, cold, latch) = (cfg.starts[&10], cfg.starts[&20], cfg.starts[&30], cfg.starts[&40]);
+        let entry = cfg.entry_index();
+        assert_eq!(vec![entry, header, hot, latch], cfg.hottest_path(10));
+        assert_eq!(vec![entry, header], cfg.hottest_path(2));
+        assert_eq!(vec![(latch, header, 10), (header, hot, 8), (hot, latch, 8)], cfg.top_edges(3));
+        assert_eq!((header, cold, 2), cfg.top_edges(10)[3]);
+
+        Ok(())
+    }
+}
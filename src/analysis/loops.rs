@@ -0,0 +1,165 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use crate::ControlFlowGraph;
+use crate::types::{BlockId, CFGError};
+use crate::prelude::*;
+
+/// A natural loop, which is formed by a back edge from a latch block to a header block dominating it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+    /// The position of the loop header, the target of the back edge.
+    pub header: BlockId,
+    /// The position of the block the back edge originates from.
+    pub latch: BlockId,
+    /// The positions of every block inside of the loop, including the header and latch.
+    pub body: BTreeSet<BlockId>,
+    /// The traversal count of the back edge, which estimates how many times the loop iterated.
+    pub iterations: u64,
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Finds the natural loop of every back edge, ordered by the position of the latch and then the header.
+    ///
+    /// A back edge is an edge whose target dominates its source, blocks unreachable from the entry are never part of a loop.
+    pub fn loops(&self) -> Result<Vec<NaturalLoop>, CFGError> {
+        let idom = self.dominators()?;
+        let predecessors = self.predecessor_lists()?;
+        let dominates = |a: BlockId, mut b: BlockId| loop {
+            if a == b {
+                return true;
+            }
+            match idom[b.index()] {
+                Some(parent) if parent != b => b = parent,
+                _ => return false,
+            }
+        };
+
+        let mut loops = Vec::new();
+        for (latch, block) in self.blocks.iter().enumerate() {
+            let latch = BlockId(latch);
+            if idom[latch.index()].is_none() {
+                continue;
+            }
+            for edge in block.edges.iter() {
+                let (header, count) = (edge.target(), edge.count());
+                if !dominates(header, latch) {
+                    continue;
+                }
+
+                // Walk backwards from the latch, the header bounds the walk since it dominates the whole body.
+                let mut body = BTreeSet::from([header]);
+                let mut worklist = vec![latch];
+                while let Some(member) = worklist.pop() {
+                    if body.insert(member) {
+                        worklist.extend(predecessors[member.index()].iter().copied().filter(|p| idom[*p].is_some()).map(BlockId));
+                    }
+                }
+                loops.push(NaturalLoop { header, latch, body, iterations: count });
+            }
+        }
+
+        loops.sort_by_key(|l| (l.latch, l.header));
+        Ok(loops)
+    }
+
+    /// Returns the loop nesting depth of every block, indexed by block position, which is the number of loops it is in.
+    ///
+    /// Natural loops sharing a header count as a single loop, so a header with several latches doesn't deepen its body.
+    /// Blocks outside of every loop, including those unreachable from the entry, have a depth of zero.
+    pub fn loop_depths(&self) -> Result<Vec<usize>, CFGError> {
+        let mut bodies: BTreeMap<BlockId, BTreeSet<BlockId>> = BTreeMap::new();
+        for natural_loop in self.loops()? {
+            bodies.entry(natural_loop.header).or_default().extend(natural_loop.body);
+        }
+        let mut depths = vec![0; self.blocks.len()];
+        for block in bodies.values().flatten() {
+            depths[block.index()] += 1;
+        }
+        Ok(depths)
+    }
+
+    /// Returns the deepest loop nesting depth of any block, zero when the graph has no loops.
+    pub fn max_loop_depth(&self) -> Result<usize, CFGError> {
+        Ok(self.loop_depths()?.into_iter().max().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn loop_iterations() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..10 {
+            cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+            cfg.execute(20, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(21, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        }
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalTaken, Some(20)))?;
+        cfg.execute(30, BlockType::Instruction("INC".to_string(), None))?;
+
+        let (header, body) = (cfg.starts[&10], cfg.starts[&20]);
+        let loops = cfg.loops()?;
+        assert_eq!(1, loops.len());
+        assert_eq!(header, loops[0].header);
+        assert_eq!(body, loops[0].latch);
+        assert_eq!(BTreeSet::from([header, body]), loops[0].body);
+        assert_eq!(10, loops[0].iterations);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_loop_depths() -> Result<(), CFGError> {
+        // The outer loop at 10 runs twice around the inner loop at 20, which has two latches at 21 and 25 back to its
+        // header, before leaving to the tail at 40. The block at 50 is never reached.
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.preseed_edge(50, 10)?;
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for outer in 0..2 {
+            cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+            // The inner loop goes around through 21, then through 25, and leaves through 21.
+            for inner in 0..3 {
+                let through_25 = inner == 1;
+                let branch = if through_25 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+                cfg.execute(20, BlockType::Jump("JZ".to_string(), 25, branch, Some(21)))?;
+                if through_25 {
+                    cfg.execute(25, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+                } else {
+                    let back = if inner == 0 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+                    cfg.execute(21, BlockType::Jump("JNZ".to_string(), 20, back, Some(22)))?;
+                }
+            }
+            let exit = if outer == 0 { JumpType::ConditionalNotTaken } else { JumpType::ConditionalTaken };
+            cfg.execute(22, BlockType::Jump("JZ".to_string(), 40, exit, Some(23)))?;
+            if outer == 0 {
+                cfg.execute(23, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+            }
+        }
+        cfg.execute(40, BlockType::Instruction("RET".to_string(), None))?;
+
+        let depths = cfg.loop_depths()?;
+        let depth = |address: u64| depths[cfg.starts[&address].index()];
+        assert_eq!(2, cfg.loops()?.iter().filter(|l| l.header == cfg.starts[&20]).count());
+        assert_eq!((0, 1, 2, 2, 2), (depth(0), depth(10), depth(20), depth(21), depth(25)));
+        assert_eq!((1, 1, 0, 0), (depth(22), depth(23), depth(40), depth(50)));
+        assert_eq!(2, cfg.max_loop_depth()?);
+        assert_eq!(0, ControlFlowGraph::<String>::new(0).max_loop_depth()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn acyclic_has_no_loops() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+
+        assert!(cfg.loops()?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use core::fmt;
+use crate::{ControlFlowGraph, Edge};
+use crate::types::*;
+use crate::prelude::*;
+
+/// A summary of the size and shape of a ControlFlowGraph, such as for complexity reports.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CfgMetrics {
+    /// The number of blocks.
+    pub nodes: usize,
+    /// The number of edges, including those which were never traversed.
+    pub edges: usize,
+    /// The number of edges which were traversed at least once.
+    pub traversed_edges: usize,
+    /// The number of edges which were pre-seeded as statically possible, such as from disassembly.
+    pub static_edges: usize,
+    /// The number of pre-seeded edges which were traversed at least once.
+    pub traversed_static_edges: usize,
+    /// The cyclomatic complexity `E - N + 2`, which is the number of linearly independent paths through a connected graph.
+    pub cyclomatic_complexity: isize,
+    /// The greatest number of outgoing edges of a single block.
+    pub max_out_degree: usize,
+    /// The mean number of outgoing edges per block.
+    pub avg_out_degree: f64,
+    /// The number of conditional jumps recorded in the graph.
+    pub conditional_branches: usize,
+    /// The number of blocks other than the entry which were never entered through an edge.
+    pub never_executed: usize,
+    /// The greatest number of instructions inside of a single block.
+    pub longest_block: usize,
+    /// Whether any hit or traversal count stopped at `u64::MAX`, making the counts lower bounds.
+    pub counts_saturated: bool,
+}
+
+impl fmt::Display for CfgMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "blocks: {}", self.nodes)?;
+        writeln!(f, "edges: {}", self.edges)?;
+        writeln!(f, "traversed edges: {}/{} ({:.2}%)", self.traversed_edges, self.edges, self.edge_coverage())?;
+        if self.static_edges > 0 {
+            writeln!(f, "traversed static edges: {}/{}", self.traversed_static_edges, self.static_edges)?;
+        }
+        writeln!(f, "cyclomatic complexity: {}", self.cyclomatic_complexity)?;
+        writeln!(f, "max out-degree: {}", self.max_out_degree)?;
+        writeln!(f, "average out-degree: {:.2}", self.avg_out_degree)?;
+        writeln!(f, "conditional branches: {}", self.conditional_branches)?;
+        writeln!(f, "never executed blocks: {}", self.never_executed)?;
+        writeln!(f, "longest block: {} instructions", self.longest_block)?;
+        if self.counts_saturated {
+            writeln!(f, "counts saturated: hit and traversal counts are lower bounds")?;
+        }
+        Ok(())
+    }
+}
+
+impl CfgMetrics {
+    /// Returns the percentage of the edges which were traversed, a graph without edges being fully covered.
+    ///
+    /// With pre-seeded edges this is the ratio of executed to possible edges, as long as every edge found by executing
+    /// was pre-seeded as well.
+    pub fn edge_coverage(&self) -> f64 {
+        match self.edges {
+            0 => 100.0,
+            edges => self.traversed_edges as f64 * 100.0 / edges as f64,
+        }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Computes the CfgMetrics of the graph.
+    ///
+    /// A block counts as never executed when every edge into it has a count of zero, so a block without any incoming
+    /// edges, such as one a thread started in, counts as well.
+    pub fn metrics(&self) -> CfgMetrics {
+        let nodes = self.blocks.len();
+        let edges = self.edge_count();
+
+        let mut entered = vec![false; nodes];
+        for block in self.blocks.iter() {
+            for edge in block.edges.iter() {
+                entered[edge.target().index()] |= edge.count() > 0;
+            }
+        }
+
+        let conditional_branches = self.blocks.iter()
+            .flat_map(|block| block.block.values())
+            .filter(|instruction| matches!(instruction, BlockType::Jump(_, _, JumpType::ConditionalTaken | JumpType::ConditionalNotTaken, _)))
+            .count();
+
+        let static_edges: Vec<&Edge> = self.blocks.iter().flat_map(|block| block.edges.iter()).filter(|edge| edge.is_static()).collect();
+
+        CfgMetrics {
+            nodes,
+            edges,
+            traversed_edges: self.traversed_edge_count(),
+            static_edges: static_edges.len(),
+            traversed_static_edges: static_edges.iter().filter(|edge| edge.count() > 0).count(),
+            cyclomatic_complexity: edges as isize - nodes as isize + 2,
+            max_out_degree: self.blocks.iter().map(|block| block.edges.len()).max().unwrap_or(0),
+            avg_out_degree: edges as f64 / nodes as f64,
+            conditional_branches,
+            never_executed: entered.iter().skip(1).filter(|entered| !**entered).count(),
+            longest_block: self.blocks.iter().map(|block| block.block.len()).max().unwrap_or(0),
+            counts_saturated: self.saturated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::analysis::CfgMetrics;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_metrics() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("A".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        let metrics = cfg.metrics();
+        let expected = CfgMetrics {
+            nodes: 3,
+            edges: 2,
+            traversed_edges: 1,
+            static_edges: 0,
+            traversed_static_edges: 0,
+            cyclomatic_complexity: 1,
+            max_out_degree: 2,
+            avg_out_degree: 2.0 / 3.0,
+            conditional_branches: 1,
+            never_executed: 1,
+            longest_block: 3,
+            counts_saturated: false,
+        };
+        assert_eq!(expected, metrics);
+
+        let report = "\
+blocks: 3
+edges: 2
+traversed edges: 1/2 (50.00%)
+cyclomatic complexity: 1
+max out-degree: 2
+average out-degree: 0.67
+conditional branches: 1
+never executed blocks: 1
+longest block: 3 instructions
+";
+        assert_eq!(report, metrics.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_and_return_metrics() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(2)))?;
+        cfg.execute(0x21, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(3, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+
+        let metrics = cfg.metrics();
+        assert_eq!((4, 3, 1), (metrics.nodes, metrics.edges, metrics.cyclomatic_complexity));
+        assert_eq!((1, 0.75), (metrics.max_out_degree, metrics.avg_out_degree));
+        assert_eq!((0, 0, 1), (metrics.conditional_branches, metrics.never_executed, metrics.longest_block));
+
+        Ok(())
+    }
+
+    #[test]
+    fn preseeded_edges() -> Result<(), CFGError> {
+        // A check at 0x0 which statically branches to 0x10 or 0x20, both of which jump to 0x30.
+        let mut cfg = ControlFlowGraph::new(0);
+        for start in [0x10, 0x20, 0x30] {
+            cfg.preseed_block(start)?;
+        }
+        cfg.preseed_edge(0, 0x10)?;
+        cfg.preseed_edge(0, 0x20)?;
+        assert_eq!(4, cfg.len());
+
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(0x20)))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+        assert_eq!(4, cfg.len());
+
+        let edges: Vec<_> = cfg.edges().map(|edge| (edge.source_start(), edge.target_start(), edge.count(), edge.kind(), edge.is_static())).collect();
+        assert_eq!(vec![
+            (0, 0x10, 1, EdgeKind::Taken, true),
+            (0, 0x20, 0, EdgeKind::FallThrough, true),
+            (0x10, 0x30, 1, EdgeKind::Unconditional, false),
+        ], edges);
+
+        let metrics = cfg.metrics();
+        assert_eq!((3, 2, 2, 1), (metrics.edges, metrics.traversed_edges, metrics.static_edges, metrics.traversed_static_edges));
+        assert!(metrics.to_string().contains("traversed edges: 2/3 (66.67%)\ntraversed static edges: 1/2\n"));
+        assert_eq!(1, metrics.never_executed);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_metrics() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+
+        let json = serde_json::to_string(&cfg.metrics()).unwrap();
+        let expected = r#"{"nodes":1,"edges":1,"traversed_edges":1,"static_edges":0,"traversed_static_edges":0,"cyclomatic_complexity":2,"max_out_degree":1,"avg_out_degree":1.0,"conditional_branches":0,"never_executed":0,"longest_block":1,"counts_saturated":false}"#;
+        assert_eq!(expected, json);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+//! Analyses computed over the edges of a ControlFlowGraph.
+
+use crate::{ControlFlowGraph, Edge};
+use crate::types::{BlockId, CFGError};
+use crate::prelude::*;
+
+mod branches;
+mod components;
+mod cycles;
+mod dominators;
+mod functions;
+mod graph;
+mod hot;
+mod loops;
+mod metrics;
+mod order;
+mod paths;
+mod reachability;
+mod scc;
+
+pub use branches::{BranchStat, BranchTable};
+pub use functions::CallGraph;
+pub use loops::NaturalLoop;
+pub use metrics::CfgMetrics;
+pub use scc::Condensation;
+pub(crate) use dominators::immediate_dominators;
+pub(crate) use graph::GraphLike;
+pub(crate) use graph::{breadth_first_order, dominators_from, reachable_mask};
+
+impl<I> GraphLike for ControlFlowGraph<I> {
+    fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    fn successor_ids(&self, block: BlockId, only_traversed: bool) -> impl Iterator<Item = BlockId> + '_ {
+        self.blocks[block].edges.iter().filter(move |edge| edge.count() > 0 || !only_traversed).map(Edge::target)
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the predecessors of every block, indexed by block position.
+    pub(crate) fn predecessor_lists(&self) -> Result<Vec<Vec<usize>>, CFGError> {
+        graph::predecessor_lists(self)
+    }
+}
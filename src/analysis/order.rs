@@ -0,0 +1,112 @@
+use crate::{ControlFlowGraph, Edge};
+use crate::types::BlockId;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the blocks in the post-order of a depth-first search from the entry.
+    ///
+    /// The successors of a block are visited in order of their start address, so the order only depends on the shape
+    /// of the graph. With `only_traversed`, edges which were never traversed are not followed. With
+    /// `include_unreachable`, the blocks the search didn't reach are appended in order of their start address.
+    pub fn post_order(&self, only_traversed: bool, include_unreachable: bool) -> Vec<BlockId> {
+        let (mut order, visited) = self.post_order_from_entry(only_traversed);
+        if include_unreachable {
+            order.extend(self.starts.values().filter(|block| !visited[block.index()]));
+        }
+        order
+    }
+
+    /// Returns the blocks in the reverse post-order of a depth-first search from the entry, the order most dataflow
+    /// analyses visit blocks in.
+    ///
+    /// The search is the one of [post_order](Self::post_order), and with `include_unreachable` the blocks it didn't
+    /// reach are still appended at the end in order of their start address.
+    pub fn reverse_post_order(&self, only_traversed: bool, include_unreachable: bool) -> Vec<BlockId> {
+        let (mut order, visited) = self.post_order_from_entry(only_traversed);
+        order.reverse();
+        if include_unreachable {
+            order.extend(self.starts.values().filter(|block| !visited[block.index()]));
+        }
+        order
+    }
+
+    /// Searches the graph from the entry, returning the post-order and which blocks were reached.
+    fn post_order_from_entry(&self, only_traversed: bool) -> (Vec<BlockId>, Vec<bool>) {
+        let successors = |block: BlockId| {
+            let mut successors: Vec<BlockId> = self.blocks[block].edges.iter()
+                .filter(|edge| edge.count() > 0 || !only_traversed)
+                .map(Edge::target)
+                .collect();
+            successors.sort_by_key(|edge| self.blocks[*edge].start);
+            successors
+        };
+
+        let mut visited = vec![false; self.blocks.len()];
+        let mut order = Vec::new();
+        // Each frame is a block, its sorted successors, and the position of the next one to visit.
+        let mut stack = vec![(BlockId(0), successors(BlockId(0)), 0)];
+        visited[0] = true;
+        while let Some((block, edges, next)) = stack.last_mut() {
+            match edges.get(*next) {
+                Some(&edge) => {
+                    *next += 1;
+                    if !visited[edge.index()] {
+                        visited[edge.index()] = true;
+                        stack.push((edge, successors(edge), 0));
+                    }
+                }
+                None => {
+                    order.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+
+        (order, visited)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BasicBlock, ControlFlowGraph};
+    use crate::types::*;
+
+    #[test]
+    fn diamond_order() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        // The failure block at 10 is created before the success block at 5.
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalTaken, Some(10)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+
+        let (entry, left, right, join) = (cfg.entry_index(), cfg.starts[&5], cfg.starts[&10], cfg.starts[&20]);
+        assert_eq!(vec![join, left, right, entry], cfg.post_order(false, false));
+        assert_eq!(vec![join, left, entry], cfg.post_order(true, false));
+        assert_eq!(vec![join, left, entry, right], cfg.post_order(true, true));
+        assert_eq!(vec![entry, left, join, right], cfg.reverse_post_order(true, true));
+
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalNotTaken, Some(10)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        assert_eq!(vec![join, left, right, entry], cfg.post_order(true, false));
+        assert_eq!(vec![entry, right, left, join], cfg.reverse_post_order(true, false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loop_order() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalTaken, Some(20)))?;
+        let island = cfg.add_block(BasicBlock::new(5));
+
+        let (entry, header, body, exit) = (cfg.entry_index(), cfg.starts[&10], cfg.starts[&20], cfg.starts[&30]);
+        assert_eq!(vec![body, exit, header, entry], cfg.post_order(true, false));
+        assert_eq!(vec![entry, header, exit, body], cfg.reverse_post_order(true, false));
+        assert_eq!(vec![entry, header, exit, body, island], cfg.reverse_post_order(true, true));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use alloc::collections::VecDeque;
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Enumerates up to `max_paths` simple paths from the block at `from` to the block at `to` along traversed edges,
+    /// such as every way execution was seen to reach a vulnerable block, returning the positions of the blocks along each.
+    ///
+    /// A path includes both ends and never visits a block twice, so loops are left out, and a path from a block to
+    /// itself is that one block. Paths longer than `max_len` blocks are never considered, which bounds the search on
+    /// graphs with many loops. The paths are ordered by increasing length, and paths of the same length by the order
+    /// the edges along them were added. Returns no paths if either block doesn't exist.
+    pub fn simple_paths(&self, from: BlockId, to: BlockId, max_paths: usize, max_len: usize) -> Vec<Vec<BlockId>> {
+        if from.index() >= self.blocks.len() || to.index() >= self.blocks.len() {
+            return Vec::new();
+        }
+        let distances = self.distances_to(to);
+        let Some(shortest) = distances[from.index()] else {
+            return Vec::new();
+        };
+        let mut search = PathSearch { cfg: self, to, max_paths, distances, on_path: vec![false; self.blocks.len()], paths: Vec::new() };
+        // Every length is searched on its own so that shorter paths come first, the distances to the target pruning
+        // the blocks which can't reach it within the remaining length. A simple path visits every block at most once.
+        for len in shortest + 1..=max_len.min(self.blocks.len()) {
+            if search.paths.len() >= max_paths {
+                break;
+            }
+            search.extend(from, len);
+        }
+        search.paths
+    }
+
+    /// Returns the fewest traversed edges leading from every block to the given one, indexed by block position.
+    fn distances_to(&self, to: BlockId) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.blocks.len()];
+        distances[to.index()] = Some(0);
+        let mut worklist = VecDeque::from([to]);
+        while let Some(block) = worklist.pop_front() {
+            let distance = distances[block.index()].unwrap();
+            for &predecessor in self.predecessors[block.index()].iter() {
+                let traversed = self.blocks[predecessor].edge_to(block).is_some_and(|edge| edge.count() > 0);
+                if traversed && distances[predecessor.index()].is_none() {
+                    distances[predecessor.index()] = Some(distance + 1);
+                    worklist.push_back(predecessor);
+                }
+            }
+        }
+        distances
+    }
+}
+
+/// The state of enumerating the simple paths to a block, one length at a time.
+struct PathSearch<'a, I> {
+    cfg: &'a ControlFlowGraph<I>,
+    to: BlockId,
+    max_paths: usize,
+    /// The fewest traversed edges from every block to the target
+    distances: Vec<Option<usize>>,
+    /// Whether every block is on the path being extended
+    on_path: Vec<bool>,
+    paths: Vec<Vec<BlockId>>,
+}
+
+impl<I> PathSearch<'_, I> {
+    /// Extends the path from the given block along every traversed edge which can still reach the target in exactly
+    /// `len` blocks, keeping the completed paths until there are `max_paths` of them.
+    fn extend(&mut self, from: BlockId, len: usize) {
+        // The blocks along the path, each with the position of the next of its edges to follow.
+        let mut path = vec![(from, 0)];
+        self.on_path[from.index()] = true;
+        while let Some(&(block, position)) = path.last() {
+            let edges = &self.cfg.blocks[block].edges;
+            // A simple path ends the first time it reaches the target.
+            let next = if path.len() == len || block == self.to {
+                None
+            } else {
+                (position..edges.len()).find(|&index| {
+                    let next = edges[index].target();
+                    let reachable = self.distances[next.index()].is_some_and(|distance| path.len() + 1 + distance <= len);
+                    edges[index].count() > 0 && !self.on_path[next.index()] && reachable
+                })
+            };
+            match next {
+                Some(index) => {
+                    path.last_mut().unwrap().1 = index + 1;
+                    let next = edges[index].target();
+                    self.on_path[next.index()] = true;
+                    path.push((next, 0));
+                }
+                None => {
+                    if path.len() == len && block == self.to {
+                        self.paths.push(path.iter().map(|(block, _)| *block).collect());
+                        if self.paths.len() >= self.max_paths {
+                            path.iter().for_each(|(block, _)| self.on_path[block.index()] = false);
+                            return;
+                        }
+                    }
+                    self.on_path[block.index()] = false;
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    /// Runs both sides of a diamond from 0x10 which meet again at 0x40.
+    fn diamond() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        for jump_type in [JumpType::ConditionalTaken, JumpType::ConditionalNotTaken] {
+            cfg.set_current_block_by_address(0x10);
+            cfg.execute(0x10, BlockType::Jump("JZ".to_string(), 0x30, jump_type, Some(0x20)))?;
+            let side = if jump_type == JumpType::ConditionalTaken { 0x30 } else { 0x20 };
+            cfg.execute(side, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None))?;
+        }
+        Ok(cfg)
+    }
+
+    #[test]
+    fn diamond_paths() -> Result<(), CFGError> {
+        let cfg = diamond()?;
+        let block = |address| cfg.starts[&address];
+        let (entry, join) = (cfg.entry_index(), block(0x40));
+
+        // The not-taken side was added first.
+        assert_eq!(vec![vec![entry, block(0x20), join], vec![entry, block(0x30), join]], cfg.simple_paths(entry, join, 10, 10));
+        assert_eq!(vec![vec![join]], cfg.simple_paths(join, join, 10, 10));
+        assert!(cfg.simple_paths(join, entry, 10, 10).is_empty());
+        // Paths are cut off at the given number of blocks.
+        assert!(cfg.simple_paths(entry, join, 10, 2).is_empty());
+        assert!(cfg.simple_paths(entry, BlockId(100), 10, 10).is_empty());
+        // Lengths past the number of blocks are never searched.
+        assert_eq!(2, cfg.simple_paths(entry, join, 10, usize::MAX).len());
+        assert!(cfg.simple_paths(join, entry, 10, usize::MAX).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn loops_are_excluded() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        for jump_type in [JumpType::ConditionalTaken, JumpType::ConditionalTaken, JumpType::ConditionalNotTaken] {
+            cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x10, jump_type, Some(0x12)))?;
+        }
+        cfg.execute(0x12, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        let (body, exit) = (cfg.starts[&0x10], cfg.starts[&0x20]);
+
+        // Going around the loop any number of times would revisit its body.
+        let paths = cfg.simple_paths(cfg.entry_index(), exit, 10, 100);
+        assert_eq!(vec![vec![cfg.entry_index(), body, cfg.starts[&0x12], exit]], paths);
+        assert_eq!(vec![vec![body]], cfg.simple_paths(body, body, 10, 100));
+
+        Ok(())
+    }
+
+    #[test]
+    fn capped_paths() -> Result<(), CFGError> {
+        // A chain of three diamonds has eight paths through it, the shorter ones going through more jumps not taken.
+        let mut cfg = ControlFlowGraph::new(0);
+        for lap in 0..8 {
+            cfg.set_current_block_by_address(0);
+            for diamond in 0..3 {
+                let base = diamond * 0x100;
+                let taken = lap >> diamond & 1 == 1;
+                let jump_type = if taken { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+                cfg.execute(base, BlockType::Jump("JZ".to_string(), base + 0x20, jump_type, Some(base + 1)))?;
+                if taken {
+                    cfg.execute(base + 0x20, BlockType::Jump("JMP".to_string(), base + 0x21, JumpType::UnconditionalJump, None))?;
+                    cfg.execute(base + 0x21, BlockType::Jump("JMP".to_string(), base + 0x100, JumpType::UnconditionalJump, None))?;
+                } else {
+                    cfg.execute(base + 1, BlockType::Jump("JMP".to_string(), base + 0x100, JumpType::UnconditionalJump, None))?;
+                }
+            }
+        }
+        let exit = cfg.starts[&0x300];
+
+        let all = cfg.simple_paths(cfg.entry_index(), exit, 100, 100);
+        assert_eq!(8, all.len());
+        assert!(all.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+        assert_eq!((7, 10), (all[0].len(), all[7].len()));
+
+        let capped = cfg.simple_paths(cfg.entry_index(), exit, 3, 100);
+        assert_eq!(all[..3], capped[..]);
+        assert_eq!(all, cfg.simple_paths(cfg.entry_index(), exit, 100, 100));
+
+        Ok(())
+    }
+
+    #[test]
+    fn long_chain() -> Result<(), CFGError> {
+        const BLOCKS: u64 = 100_000;
+        let mut cfg = ControlFlowGraph::new(0);
+        for start in 0..BLOCKS {
+            cfg.execute(start, BlockType::Jump("JMP".to_string(), start + 1, JumpType::UnconditionalJump, None))?;
+        }
+
+        let last = cfg.starts[&BLOCKS];
+        let paths = cfg.simple_paths(cfg.entry_index(), last, 1, usize::MAX);
+        assert_eq!(1, paths.len());
+        assert_eq!(BLOCKS as usize + 1, paths[0].len());
+        assert_eq!(Some(&last), paths[0].last());
+
+        Ok(())
+    }
+}
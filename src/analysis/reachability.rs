@@ -0,0 +1,141 @@
+use crate::ControlFlowGraph;
+use crate::analysis::reachable_mask;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the positions of the blocks reachable from the entry block in ascending order, including the entry itself.
+    ///
+    /// With `only_traversed`, edges which were never traversed are not followed.
+    pub fn reachable_from_entry(&self, only_traversed: bool) -> Vec<BlockId> {
+        self.reachable_mask(0, only_traversed)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, reachable)| reachable.then_some(BlockId(index)))
+            .collect()
+    }
+
+    /// Removes every block which was never reached from the entry by a traversed edge, returning how many were removed.
+    ///
+    /// The current block of the graph and of every thread is always kept, and the positions of the remaining blocks are compacted.
+    pub fn prune_unreachable(&mut self) -> usize {
+        let mut keep = self.reachable_mask(0, true);
+        keep[self.current_block.index()] = true;
+        for cursor in self.threads.values() {
+            keep[cursor.current_block.index()] = true;
+        }
+        let removed = keep.iter().filter(|kept| !**kept).count();
+        self.retain_blocks(&keep);
+        removed
+    }
+
+    /// Finds a path from the block containing `from_addr` to the block containing `to_addr`, such as from the entry to
+    /// the address of a crash, returning the positions of the blocks along it.
+    ///
+    /// The path is a shortest one by number of blocks and includes both ends, so two addresses inside of the same block
+    /// give a path of that one block. With `only_traversed`, edges which were never traversed are not followed. Returns
+    /// `None` if either address is outside of every block or no path exists.
+    pub fn find_path(&self, from_addr: u64, to_addr: u64, only_traversed: bool) -> Option<Vec<BlockId>> {
+        let (from, to) = (self.find_block_containing(from_addr)?, self.find_block_containing(to_addr)?);
+        let mut parent: Vec<Option<BlockId>> = vec![None; self.blocks.len()];
+        parent[from.index()] = Some(from);
+        let mut worklist = alloc::collections::VecDeque::from([from]);
+        while let Some(block) = worklist.pop_front() {
+            if block == to {
+                let mut path = vec![to];
+                let mut block = to;
+                while block != from {
+                    block = parent[block.index()].unwrap();
+                    path.push(block);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for edge in self.blocks[block].edges.iter() {
+                if (edge.count() > 0 || !only_traversed) && parent[edge.target().index()].is_none() {
+                    parent[edge.target().index()] = Some(block);
+                    worklist.push_back(edge.target());
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns whether a path exists from the block containing `from_addr` to the block containing `to_addr`, without
+    /// building it like [find_path](Self::find_path) does.
+    pub fn path_exists(&self, from_addr: u64, to_addr: u64, only_traversed: bool) -> bool {
+        match (self.find_block_containing(from_addr), self.find_block_containing(to_addr)) {
+            (Some(from), Some(to)) => self.reachable_mask(from.index(), only_traversed)[to.index()],
+            _ => false,
+        }
+    }
+
+    /// Marks every block reachable from the given root with a breadth-first search.
+    pub(crate) fn reachable_mask(&self, root: usize, only_traversed: bool) -> Vec<bool> {
+        reachable_mask(self, root, only_traversed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn prune_untaken_branches() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(5)))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(30, BlockType::Jump("JMP".to_string(), 5, JumpType::UnconditionalJump, None))?;
+
+        assert_eq!((0..6).map(BlockId::from).collect::<Vec<_>>(), cfg.reachable_from_entry(false));
+        assert_eq!([0, 1, 4, 5].map(BlockId::from).to_vec(), cfg.reachable_from_entry(true));
+
+        assert_eq!(2, cfg.prune_unreachable());
+        let starts: Vec<u64> = cfg.blocks().map(|bb| bb.start).collect();
+        assert_eq!(vec![0, 5, 20, 30], starts);
+        assert_eq!(vec![(BlockId(1), 1)], cfg.blocks[0].edge_counts());
+        assert_eq!(vec![(BlockId(2), 1)], cfg.blocks[1].edge_counts());
+        assert_eq!(vec![(BlockId(3), 1)], cfg.blocks[2].edge_counts());
+        assert_eq!(vec![(BlockId(1), 1)], cfg.blocks[3].edge_counts());
+        assert_eq!(BlockId(1), cfg.current_block);
+
+        // The compacted graph keeps executing against the remapped positions.
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(6)))?;
+        assert_eq!(vec![(BlockId(2), 2), (BlockId(4), 0)], cfg.blocks[1].edge_counts());
+
+        Ok(())
+    }
+
+    #[test]
+    fn paths_between_addresses() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(2)))?;
+        cfg.execute(0x11, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x12, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x21, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x31, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+
+        // Any address inside of a block can be passed, not only its start.
+        let (body, crash) = (cfg.starts[&0x10], cfg.starts[&0x30]);
+        assert_eq!(Some(vec![cfg.entry_index(), body, cfg.starts[&0x20], crash]), cfg.find_path(1, 0x31, true));
+        assert!(cfg.path_exists(0, 0x31, true));
+        assert_eq!(Some(vec![cfg.starts[&0x30], cfg.starts[&0x20]]), cfg.find_path(0x30, 0x20, true));
+
+        // The fall-through of the conditional jump was never taken.
+        assert_eq!(None, cfg.find_path(0, 2, true));
+        assert!(!cfg.path_exists(0, 2, true));
+        assert_eq!(Some(vec![cfg.entry_index(), cfg.starts[&2]]), cfg.find_path(0, 2, false));
+        assert!(cfg.path_exists(0, 2, false));
+        assert_eq!(None, cfg.find_path(0x20, 0, false));
+        assert!(!cfg.path_exists(0x20, 0, false));
+
+        assert_eq!(Some(vec![body]), cfg.find_path(0x11, 0x10, true));
+        assert!(cfg.path_exists(0x11, 0x10, true));
+        assert_eq!(None, cfg.find_path(0, 0x100, false));
+        assert!(!cfg.path_exists(0x100, 0, false));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::collections::BTreeMap;
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+/// The directed acyclic graph formed by collapsing every strongly connected component of a ControlFlowGraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condensation {
+    /// The positions of the blocks inside of each component, in topological order.
+    pub components: Vec<Vec<BlockId>>,
+    /// The component of every block, indexed by block position.
+    pub component_of: Vec<usize>,
+    /// The edges between components as `(source, target, count)`, with the counts summed across the member edges.
+    pub edges: Vec<(usize, usize, u64)>,
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Finds the strongly connected components of the graph with Tarjan's algorithm.
+    ///
+    /// Components are returned in topological order and the blocks of each component are in ascending order. A block
+    /// which isn't part of any cycle is its own singleton component.
+    pub fn sccs(&self) -> Vec<Vec<BlockId>> {
+        let count = self.blocks.len();
+        let mut index_of = vec![usize::MAX; count];
+        let mut low_link = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+
+        for root in 0..count {
+            if index_of[root] != usize::MAX {
+                continue;
+            }
+
+            // Each frame is a block and the position of the next edge to visit from it.
+            let mut frames = vec![(root, 0)];
+            index_of[root] = next_index;
+            low_link[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((block, next)) = frames.last_mut() {
+                let block = *block;
+                if let Some(edge) = self.blocks[block].edges.get(*next) {
+                    let edge = edge.target().index();
+                    *next += 1;
+                    if index_of[edge] == usize::MAX {
+                        index_of[edge] = next_index;
+                        low_link[edge] = next_index;
+                        next_index += 1;
+                        stack.push(edge);
+                        on_stack[edge] = true;
+                        frames.push((edge, 0));
+                    } else if on_stack[edge] {
+                        low_link[block] = low_link[block].min(index_of[edge]);
+                    }
+                    continue;
+                }
+
+                frames.pop();
+                if let Some((parent, _)) = frames.last() {
+                    low_link[*parent] = low_link[*parent].min(low_link[block]);
+                }
+                if low_link[block] == index_of[block] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(BlockId(member));
+                        if member == block {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+
+        // Tarjan's algorithm completes components in reverse topological order.
+        components.reverse();
+        components
+    }
+
+    /// Collapses every strongly connected component into a single node, producing a directed acyclic graph.
+    pub fn condensation(&self) -> Condensation {
+        let components = self.sccs();
+        let mut component_of = vec![0; self.blocks.len()];
+        for (component, members) in components.iter().enumerate() {
+            for member in members {
+                component_of[member.index()] = component;
+            }
+        }
+
+        let mut edges = BTreeMap::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            for edge in block.edges.iter() {
+                let (source, target) = (component_of[index], component_of[edge.target().index()]);
+                if source != target {
+                    let count: &mut u64 = edges.entry((source, target)).or_insert(0);
+                    *count = count.saturating_add(edge.count());
+                }
+            }
+        }
+
+        Condensation {
+            components,
+            component_of,
+            edges: edges.into_iter().map(|((source, target), count)| (source, target, count)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn loop_with_tail() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for _ in 0..3 {
+            cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalNotTaken, Some(10)))?;
+            cfg.execute(10, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(10)))?;
+        cfg.execute(20, BlockType::Instruction("INC".to_string(), None))?;
+
+        let (body, tail) = (cfg.starts[&10], cfg.starts[&20]);
+        assert_eq!(vec![vec![cfg.entry_index(), body], vec![tail]], cfg.sccs());
+
+        let condensation = cfg.condensation();
+        assert_eq!(2, condensation.components.len());
+        assert_eq!(vec![0, 0, 1], condensation.component_of);
+        assert_eq!(vec![(0, 1, 1)], condensation.edges);
+
+        Ok(())
+    }
+
+    #[test]
+    fn self_loop_is_singleton() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+
+        assert_eq!(vec![vec![BlockId(0)], vec![BlockId(1)]], cfg.sccs());
+        assert_eq!(vec![(0, 1, 1)], cfg.condensation().edges);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,550 @@
+//! A compact binary serialization of a ControlFlowGraph which doesn't depend on serde or the standard library.
+//!
+//! The bytes start with the magic bytes `CFGB` and a version byte, followed by a table of every distinct string, the
+//! traversal state, and the blocks in position order with their instructions, edges, and metadata. Every number is an
+//! unsigned LEB128 varint, every list is prefixed with its length, and strings are referred to by their position in
+//! the table, so the same graph always serializes to the same bytes.
+
+use alloc::collections::BTreeMap;
+use core::fmt;
+use crate::{BasicBlock, CfgCheckpoint, ControlFlowGraph, Edge};
+use crate::types::*;
+use crate::prelude::*;
+
+/// The bytes every serialized graph starts with.
+const MAGIC: &[u8; 4] = b"CFGB";
+/// The version of the layout which follows the magic bytes.
+const VERSION: u8 = 1;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Serializes the ControlFlowGraph into the compact binary format read by [from_bytes](Self::from_bytes).
+    ///
+    /// The blocks, their instructions, hits, sizes, edges, and metadata, the modules, the current block, the call
+    /// stack, the terminal instruction which halted execution, and the threads are serialized. Like with serde, the
+    /// behavior chosen through a CFGBuilder, the observer, and the recorded sequence aren't.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0);
+    /// cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+    ///
+    /// let bytes = cfg.to_bytes();
+    /// assert!(bytes.starts_with(b"CFGB"));
+    /// assert!(cfg.eq_with_counts(&ControlFlowGraph::from_bytes(&bytes)?));
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut strings = StringTable::default();
+        let mut body = Vec::new();
+
+        body.push(self.saturated as u8);
+        push_varint(&mut body, self.current_block.index() as u64);
+        push_addresses(&mut body, &self.call_stack);
+        push_optional(&mut body, self.terminated);
+        push_varint(&mut body, self.threads.len() as u64);
+        for (tid, cursor) in self.threads.iter() {
+            push_varint(&mut body, *tid);
+            push_varint(&mut body, cursor.current_block.index() as u64);
+            push_addresses(&mut body, &cursor.call_stack);
+            push_optional(&mut body, cursor.terminated);
+        }
+
+        push_varint(&mut body, self.blocks_discovered);
+        push_varint(&mut body, self.edges_discovered);
+        push_varint(&mut body, self.executed);
+        push_varint(&mut body, self.modules.len() as u64);
+        for module in self.modules.iter() {
+            push_varint(&mut body, strings.index(module.name().to_string()));
+            push_varint(&mut body, module.base());
+            push_varint(&mut body, module.size());
+        }
+        push_varint(&mut body, self.blocks.len() as u64);
+        for block in self.blocks.iter() {
+            push_varint(&mut body, block.start);
+            push_varint(&mut body, block.end);
+            push_varint(&mut body, block.hits);
+            push_varint(&mut body, block.discovered_at);
+            push_varint(&mut body, block.block.len() as u64);
+            for (pc, instruction) in block.block.iter() {
+                push_varint(&mut body, *pc);
+                push_varint(&mut body, block.instruction_hits.get(pc).copied().unwrap_or(0));
+                push_varint(&mut body, block.instruction_size(*pc).unwrap_or(1));
+                match instruction {
+                    BlockType::Instruction(name, operand) => {
+                        body.push(0);
+                        push_varint(&mut body, strings.index(name.to_string()));
+                        match operand {
+                            Some(operand) => {
+                                body.push(1);
+                                push_varint(&mut body, strings.index(operand.to_string()));
+                            }
+                            None => body.push(0),
+                        }
+                    }
+                    BlockType::Jump(name, success, jump_type, failure) => {
+                        body.push(1);
+                        push_varint(&mut body, strings.index(name.to_string()));
+                        push_varint(&mut body, *success);
+                        body.push(jump_type_tag(*jump_type));
+                        push_optional(&mut body, *failure);
+                    }
+                    BlockType::Switch(name, targets) => {
+                        body.push(2);
+                        push_varint(&mut body, strings.index(name.to_string()));
+                        push_addresses(&mut body, targets);
+                    }
+                    BlockType::Terminal(name) => {
+                        body.push(3);
+                        push_varint(&mut body, strings.index(name.to_string()));
+                    }
+                }
+            }
+            push_varint(&mut body, block.edges.len() as u64);
+            for edge in block.edges.iter() {
+                push_varint(&mut body, edge.target().index() as u64);
+                push_varint(&mut body, edge.count());
+                body.push(edge_kind_tag(edge.kind()));
+                body.push(edge.is_static() as u8);
+                push_varint(&mut body, edge.discovered_at());
+                push_varint(&mut body, edge.first_seen().map_or(0, |ordinal| ordinal.saturating_add(1)));
+                push_varint(&mut body, edge.last_seen().map_or(0, |ordinal| ordinal.saturating_add(1)));
+            }
+            push_varint(&mut body, block.metadata.len() as u64);
+            for (key, value) in block.metadata.iter() {
+                push_varint(&mut body, strings.index(key.clone()));
+                push_varint(&mut body, strings.index(value.clone()));
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(body.len() + 16);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        push_varint(&mut bytes, strings.texts.len() as u64);
+        for text in strings.texts.iter() {
+            push_varint(&mut bytes, text.len() as u64);
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+}
+
+impl<I: From<String>> ControlFlowGraph<I> {
+    /// Deserializes a ControlFlowGraph serialized by [to_bytes](Self::to_bytes).
+    ///
+    /// Bytes of another version are rejected with `CFGError::UnsupportedVersion`, and bytes which are cut short,
+    /// corrupted, describe an edge or current block which doesn't exist, or a block ending before it starts or holding
+    /// an instruction outside of its range with `CFGError::InvalidBytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CFGError> {
+        let mut reader = ByteReader { bytes, offset: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(reader.invalid("the bytes don't start with the magic bytes"));
+        }
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(CFGError::UnsupportedVersion { version });
+        }
+
+        let mut strings = Vec::new();
+        for _ in 0..reader.len()? {
+            let len = reader.len()?;
+            let text = core::str::from_utf8(reader.take(len)?).map_err(|_| reader.invalid("a string isn't UTF-8"))?;
+            strings.push(text.to_string());
+        }
+        let string = |reader: &mut ByteReader<'_>| -> Result<String, CFGError> {
+            let index = reader.usize()?;
+            strings.get(index).cloned().ok_or_else(|| reader.invalid("a string isn't in the table"))
+        };
+
+        let saturated = reader.byte()? != 0;
+        let current_block = reader.usize()?;
+        let call_stack = reader.addresses()?;
+        let terminated = reader.optional()?;
+        let mut threads = BTreeMap::new();
+        for _ in 0..reader.len()? {
+            let tid = reader.varint()?;
+            let current_block = BlockId(reader.usize()?);
+            let call_stack = reader.addresses()?;
+            threads.insert(tid, CfgCheckpoint { current_block, call_stack, previous_instruction: None, pending_fallthrough: None, terminated: reader.optional()? });
+        }
+
+        let blocks_discovered = reader.varint()?;
+        let edges_discovered = reader.varint()?;
+        let executed = reader.varint()?;
+        let mut modules = Vec::new();
+        for _ in 0..reader.len()? {
+            let name = string(&mut reader)?;
+            modules.push((reader.offset, name, reader.varint()?, reader.varint()?));
+        }
+        let mut blocks = Vec::new();
+        for _ in 0..reader.len()? {
+            let mut block = BasicBlock::new(reader.varint()?);
+            block.end = reader.varint()?;
+            if block.end < block.start {
+                return Err(reader.invalid("a block ends before it starts"));
+            }
+            block.hits = reader.varint()?;
+            block.discovered_at = reader.varint()?;
+            for _ in 0..reader.len()? {
+                let pc = reader.varint()?;
+                if !(block.start..=block.end).contains(&pc) {
+                    return Err(reader.invalid("an instruction lies outside of its block"));
+                }
+                let hits = reader.varint()?;
+                let size = reader.varint()?;
+                let tag = reader.byte()?;
+                let name = I::from(string(&mut reader)?);
+                let instruction = match tag {
+                    0 => {
+                        let operand = match reader.byte()? {
+                            0 => None,
+                            _ => Some(I::from(string(&mut reader)?)),
+                        };
+                        BlockType::Instruction(name, operand)
+                    }
+                    1 => {
+                        let success = reader.varint()?;
+                        let jump_type = jump_type_from_tag(reader.byte()?).ok_or_else(|| reader.invalid("unknown jump type"))?;
+                        let failure = reader.optional()?;
+                        BlockType::Jump(name, success, jump_type, failure)
+                    }
+                    2 => BlockType::Switch(name, reader.addresses()?),
+                    3 => BlockType::Terminal(name),
+                    _ => return Err(reader.invalid("unknown instruction type")),
+                };
+                block.exit |= matches!(instruction, BlockType::Terminal(_));
+                block.block.insert(pc, instruction);
+                block.instruction_hits.insert(pc, hits);
+                block.set_instruction_size(pc, size);
+            }
+            for _ in 0..reader.len()? {
+                let target = BlockId(reader.usize()?);
+                let count = reader.varint()?;
+                let kind = edge_kind_from_tag(reader.byte()?).ok_or_else(|| reader.invalid("unknown edge kind"))?;
+                let mut edge = Edge::new(target, count, kind);
+                if reader.byte()? != 0 {
+                    edge.mark_static();
+                }
+                edge.set_discovered_at(reader.varint()?);
+                let first_seen = reader.varint()?.checked_sub(1);
+                edge.set_seen(first_seen, reader.varint()?.checked_sub(1));
+                block.edges.push(edge);
+            }
+            for _ in 0..reader.len()? {
+                let key = string(&mut reader)?;
+                block.metadata.insert(key, string(&mut reader)?);
+            }
+            blocks.push(block);
+        }
+
+        if blocks.is_empty() {
+            return Err(reader.invalid("the graph has no entry block"));
+        }
+        if reader.offset != bytes.len() {
+            return Err(reader.invalid("there are bytes after the graph"));
+        }
+        let exists = |block: usize| block < blocks.len();
+        if !exists(current_block) || !threads.values().all(|cursor| exists(cursor.current_block.index()))
+            || !blocks.iter().all(|block| block.edges.iter().all(|edge| exists(edge.target().index()))) {
+            return Err(reader.invalid("a block which is referred to doesn't exist"));
+        }
+        let starts: BTreeMap<u64, BlockId> = blocks.iter().enumerate().map(|(index, block)| (block.start, BlockId(index))).collect();
+        if starts.len() != blocks.len() {
+            return Err(reader.invalid("two blocks start at the same address"));
+        }
+
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.blocks = blocks;
+        cfg.starts = starts;
+        cfg.rebuild_predecessors();
+        cfg.blocks_discovered = blocks_discovered;
+        cfg.edges_discovered = edges_discovered;
+        cfg.executed = executed;
+        cfg.current_block = BlockId(current_block);
+        cfg.call_stack = call_stack;
+        cfg.terminated = terminated;
+        cfg.threads = threads;
+        cfg.saturated = saturated;
+        for (offset, name, base, size) in modules {
+            cfg.add_module(name, base, size).map_err(|_| CFGError::InvalidBytes { offset, reason: "a module is invalid".to_string() })?;
+        }
+        Ok(cfg)
+    }
+}
+
+/// The distinct strings of a graph, in the order they were first seen.
+#[derive(Default)]
+struct StringTable {
+    texts: Vec<String>,
+    indices: BTreeMap<String, u64>,
+}
+
+impl StringTable {
+    /// Returns the position of the text in the table, adding it the first time it is seen.
+    fn index(&mut self, text: String) -> u64 {
+        if let Some(index) = self.indices.get(&text) {
+            return *index;
+        }
+        let index = self.texts.len() as u64;
+        self.texts.push(text.clone());
+        self.indices.insert(text, index);
+        index
+    }
+}
+
+/// Reads the fields of serialized bytes, keeping track of the offset for errors.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CFGError> {
+        let end = self.offset.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| self.invalid("the bytes end early"))?;
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, CFGError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, CFGError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.invalid("a number is longer than 64 bits"))
+    }
+
+    fn usize(&mut self) -> Result<usize, CFGError> {
+        let value = self.varint()?;
+        usize::try_from(value).map_err(|_| self.invalid("a number doesn't fit into a position"))
+    }
+
+    /// Reads the length of a list or string, which can't be longer than the bytes which are left as every element
+    /// takes at least one byte.
+    fn len(&mut self) -> Result<usize, CFGError> {
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.offset {
+            return Err(self.invalid("a length is longer than the bytes which are left"));
+        }
+        Ok(len)
+    }
+
+    fn addresses(&mut self) -> Result<Vec<u64>, CFGError> {
+        let len = self.len()?;
+        (0..len).map(|_| self.varint()).collect()
+    }
+
+    fn optional(&mut self) -> Result<Option<u64>, CFGError> {
+        match self.byte()? {
+            0 => Ok(None),
+            _ => self.varint().map(Some),
+        }
+    }
+
+    fn invalid(&self, reason: &str) -> CFGError {
+        CFGError::InvalidBytes { offset: self.offset, reason: reason.to_string() }
+    }
+}
+
+/// Appends the value as an unsigned LEB128 varint.
+pub(crate) fn push_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// Appends the number of addresses followed by every address.
+fn push_addresses(buffer: &mut Vec<u8>, addresses: &[u64]) {
+    push_varint(buffer, addresses.len() as u64);
+    for address in addresses {
+        push_varint(buffer, *address);
+    }
+}
+
+/// Appends a byte telling whether there is a value, followed by the value if there is one.
+fn push_optional(buffer: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            buffer.push(1);
+            push_varint(buffer, value);
+        }
+        None => buffer.push(0),
+    }
+}
+
+pub(crate) fn jump_type_tag(jump_type: JumpType) -> u8 {
+    match jump_type {
+        JumpType::UnconditionalJump => 0,
+        JumpType::ConditionalTaken => 1,
+        JumpType::ConditionalNotTaken => 2,
+        JumpType::Call => 3,
+        JumpType::Return => 4,
+        JumpType::Indirect => 5,
+    }
+}
+
+pub(crate) fn jump_type_from_tag(tag: u8) -> Option<JumpType> {
+    Some(match tag {
+        0 => JumpType::UnconditionalJump,
+        1 => JumpType::ConditionalTaken,
+        2 => JumpType::ConditionalNotTaken,
+        3 => JumpType::Call,
+        4 => JumpType::Return,
+        5 => JumpType::Indirect,
+        _ => return None,
+    })
+}
+
+pub(crate) fn edge_kind_tag(kind: EdgeKind) -> u8 {
+    match kind {
+        EdgeKind::Taken => 0,
+        EdgeKind::FallThrough => 1,
+        EdgeKind::Unconditional => 2,
+        EdgeKind::Call => 3,
+        EdgeKind::Return => 4,
+        EdgeKind::Unknown => 5,
+    }
+}
+
+pub(crate) fn edge_kind_from_tag(tag: u8) -> Option<EdgeKind> {
+    Some(match tag {
+        0 => EdgeKind::Taken,
+        1 => EdgeKind::FallThrough,
+        2 => EdgeKind::Unconditional,
+        3 => EdgeKind::Call,
+        4 => EdgeKind::Return,
+        5 => EdgeKind::Unknown,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BasicBlock, ControlFlowGraph};
+    use crate::types::*;
+
+    fn graph() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x400000);
+        cfg.preseed_edge(0x400040, 0x400050)?;
+        for lap in 0..3 {
+            cfg.execute_sized(0x400000, BlockType::Instruction("MOV".to_string(), Some("RAX, [RSP]".to_string())), 4)?;
+            let branch = if lap == 1 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute_sized(0x400004, BlockType::Jump("JZ".to_string(), 0x400020, branch, Some(0x400006)), 2)?;
+            let pc = if lap == 1 { 0x400020 } else { 0x400006 };
+            cfg.execute_switch(pc, "JMP".to_string(), vec![0x400000, 0x400040], if lap == 2 { 0x400040 } else { 0x400000 })?;
+        }
+        cfg.execute(0x400040, BlockType::Jump("CALL".to_string(), 0x401000, JumpType::Call, Some(0x400045)))?;
+        cfg.execute_tid(3, 0x402000, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None)).ok();
+        cfg.execute_tid(3, 0x402000, BlockType::Jump("JMP".to_string(), 0x400000, JumpType::Indirect, None))?;
+        cfg.annotate(cfg.entry_index(), "function", "_start")?;
+        cfg.add_module("app", 0x400000, 0x3000)?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn bytes_round_trip() -> Result<(), CFGError> {
+        let cfg = graph()?;
+        let bytes = cfg.to_bytes();
+        let decoded: ControlFlowGraph = ControlFlowGraph::from_bytes(&bytes)?;
+
+        assert!(cfg.eq_with_counts(&decoded));
+        assert_eq!(bytes, decoded.to_bytes());
+        assert_eq!(cfg.to_json(), decoded.to_json());
+        assert_eq!(cfg.to_string(), decoded.to_string());
+        assert_eq!(Some("_start"), decoded.entry().get_meta("function"));
+        assert_eq!(cfg.modules(), decoded.modules());
+        assert_eq!(Some(4), decoded.entry().instruction_size(0x400000));
+        assert_eq!(cfg.current_block_index(), decoded.current_block_index());
+        assert_eq!(cfg.thread_block_index(3), decoded.thread_block_index(3));
+        assert_eq!(cfg.edges().filter(|edge| edge.is_static()).count(), decoded.edges().filter(|edge| edge.is_static()).count());
+        assert_eq!(cfg.edges().map(|edge| edge.discovered_at()).collect::<Vec<_>>(), decoded.edges().map(|edge| edge.discovered_at()).collect::<Vec<_>>());
+        assert_eq!(cfg.edges().map(|edge| (edge.first_seen(), edge.last_seen())).collect::<Vec<_>>(), decoded.edges().map(|edge| (edge.first_seen(), edge.last_seen())).collect::<Vec<_>>());
+        assert_eq!(cfg.executed_count(), decoded.executed_count());
+        // Every repeated string is only stored once.
+        assert_eq!(1, bytes.windows(3).filter(|window| window == b"MOV").count());
+
+        let empty: ControlFlowGraph = ControlFlowGraph::new(0);
+        assert!(empty.eq_with_counts(&ControlFlowGraph::from_bytes(&empty.to_bytes())?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_bytes() -> Result<(), CFGError> {
+        let bytes = graph()?.to_bytes();
+
+        // The number of strings is the first varint after the header.
+        let mut corrupted = bytes.clone();
+        corrupted[5] = 0xff;
+        corrupted.splice(6..6, [0xff, 0xff, 0xff, 0x0f]);
+        let result = ControlFlowGraph::<String>::from_bytes(&corrupted);
+        assert!(matches!(&result, Err(CFGError::InvalidBytes { offset: 10, reason }) if reason == "a length is longer than the bytes which are left"));
+        assert_eq!("The serialized graph is invalid at byte 10: a length is longer than the bytes which are left.", result.err().unwrap().to_string());
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert!(matches!(ControlFlowGraph::<String>::from_bytes(&version), Err(CFGError::UnsupportedVersion { version: 2 })));
+        assert!(matches!(ControlFlowGraph::<String>::from_bytes(b"CFGA\x01"), Err(CFGError::InvalidBytes { .. })));
+
+        // No prefix and no single corrupted byte panics.
+        for len in 0..bytes.len() {
+            assert!(ControlFlowGraph::<String>::from_bytes(&bytes[..len]).is_err());
+        }
+        for position in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[position] ^= 0xa5;
+            // Whatever is decoded holds together well enough for the accessors of its blocks.
+            if let Ok(cfg) = ControlFlowGraph::<String>::from_bytes(&corrupted) {
+                assert!(cfg.blocks().all(|block| block.range().contains(&block.end()) && block.len_addresses() >= block.len() as u64));
+                let _ = cfg.to_string();
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn halted_round_trip() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute(0x10, BlockType::Terminal("HLT".to_string()))?;
+        cfg.execute_tid(1, 0x20, BlockType::Terminal("EXIT".to_string()))?;
+        let bytes = cfg.to_bytes();
+        let mut decoded: ControlFlowGraph = ControlFlowGraph::from_bytes(&bytes)?;
+        assert_eq!(Some(0x10), decoded.terminated_at());
+        assert_eq!(bytes, decoded.to_bytes());
+        assert!(matches!(decoded.execute(0x11, BlockType::Instruction("NOP".to_string(), None)), Err(CFGError::ExecutionAfterTerminal { terminal: 0x10, .. })));
+        assert!(matches!(decoded.execute_tid(1, 0x21, BlockType::Instruction("NOP".to_string(), None)), Err(CFGError::ExecutionAfterTerminal { terminal: 0x20, .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocks_outside_of_their_range() {
+        let mut inverted = BasicBlock::new(8);
+        inverted.end = 6;
+        let mut outside = BasicBlock::new(0);
+        outside.add_instruction(4, BlockType::Instruction("INC".to_string(), None));
+        outside.end = 2;
+        for (blocks, expected) in [
+            (vec![BasicBlock::new(0), inverted], "a block ends before it starts"),
+            (vec![outside], "an instruction lies outside of its block"),
+        ] {
+            let bytes = ControlFlowGraph::from_blocks(blocks, 0).to_bytes();
+            let result = ControlFlowGraph::<String>::from_bytes(&bytes);
+            assert!(matches!(&result, Err(CFGError::InvalidBytes { reason, .. }) if reason == expected), "{}", expected);
+        }
+    }
+}
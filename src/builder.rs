@@ -0,0 +1,228 @@
+use alloc::collections::BTreeSet;
+use core::fmt;
+use crate::ControlFlowGraph;
+use crate::types::*;
+use crate::prelude::*;
+
+/// Describes the recorded and the new instruction if they differ.
+type DescribeConflict<I> = fn(&BlockType<I>, &BlockType<I>) -> Option<(String, String)>;
+/// Replaces the payloads of an instruction with equal ones from the pool.
+type Intern<I> = fn(&mut BTreeSet<I>, BlockType<I>) -> BlockType<I>;
+
+/// The behavior of a ControlFlowGraph, chosen through a CFGBuilder.
+#[derive(Clone)]
+pub(crate) struct Config<I> {
+    /// Whether falling through into the start of a known block without a jump adds an edge to it
+    pub(crate) fall_through_edges: bool,
+    /// How an instruction which differs from the one recorded at its address is handled
+    pub(crate) conflict_policy: ConflictPolicy,
+    /// How a block which would start inside of an existing block is handled
+    pub(crate) overlap_policy: OverlapPolicy,
+    /// How an instruction which doesn't continue from the previous one without a jump is handled
+    pub(crate) discontinuity_policy: DiscontinuityPolicy,
+    /// How many bytes away from the end of the previous instruction the next one may be without being a discontinuity
+    pub(crate) discontinuity_threshold: u64,
+    /// Whether a conditional jump without a failure address falls through to the next instruction instead of being an error
+    pub(crate) infer_fallthrough: bool,
+    /// Describes the recorded and the new instruction if they differ, only known for payloads which can be compared and displayed
+    pub(crate) conflict: Option<DescribeConflict<I>>,
+    /// Replaces the payloads of a new instruction with equal ones from the pool, only known for payloads which can be ordered
+    pub(crate) intern: Option<Intern<I>>,
+}
+
+impl<I> Default for Config<I> {
+    fn default() -> Self {
+        Config { fall_through_edges: true, conflict_policy: ConflictPolicy::KeepFirst, overlap_policy: OverlapPolicy::Split, discontinuity_policy: DiscontinuityPolicy::Ignore, discontinuity_threshold: 0, infer_fallthrough: false, conflict: None, intern: None }
+    }
+}
+
+/// Configures and generates a ControlFlowGraph.
+///
+/// ```
+/// use ctrl_flow::{CFGBuilder, ControlFlowGraph};
+/// use ctrl_flow::types::ConflictPolicy;
+///
+/// let cfg: ControlFlowGraph = CFGBuilder::entry(0x2)
+///     .conflict_policy(ConflictPolicy::Error)
+///     .capacity(64)
+///     .build();
+/// assert_eq!(0x2, cfg.entry().start());
+/// ```
+pub struct CFGBuilder<I = String> {
+    entry_point: u64,
+    capacity: usize,
+    intern: bool,
+    config: Config<I>,
+}
+
+impl<I: Clone + Ord + fmt::Display> CFGBuilder<I> {
+    /// Starts configuring a ControlFlowGraph with the given entry point address and the same behavior as `ControlFlowGraph::new`.
+    pub fn entry(entry_point: u64) -> Self {
+        CFGBuilder { entry_point, capacity: 0, intern: false, config: Config::default() }
+    }
+
+    /// Sets whether equal instruction payloads share a single copy, which saves memory for payloads such as `Rc<str>`.
+    pub fn intern(mut self, intern: bool) -> Self {
+        self.intern = intern;
+        self
+    }
+
+    /// Sets whether falling through into the start of a known block without a jump adds an edge to it, enabled by default.
+    ///
+    /// When disabled the known block is still entered, which suits traces with gaps where the fall-through wasn't observed.
+    pub fn fall_through_edges(mut self, enabled: bool) -> Self {
+        self.config.fall_through_edges = enabled;
+        self
+    }
+
+    /// Sets how an instruction which differs from the one recorded at its address is handled, keeping the first by default.
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.config.conflict_policy = policy;
+        self
+    }
+
+    /// Sets how a block which would start inside of an existing block is handled, splitting the existing block by default.
+    pub fn overlap_policy(mut self, policy: OverlapPolicy) -> Self {
+        self.config.overlap_policy = policy;
+        self
+    }
+
+    /// Sets how an instruction which doesn't continue from where the previous one ended is handled when the previous one
+    /// wasn't a jump, ignoring such discontinuities by default.
+    pub fn discontinuity_policy(mut self, policy: DiscontinuityPolicy) -> Self {
+        self.config.discontinuity_policy = policy;
+        self
+    }
+
+    /// Sets how many bytes before or after the end of the previous instruction the next one may start without being a
+    /// discontinuity, zero by default.
+    ///
+    /// Instructions which are executed without their size are assumed to be a single byte long, so traces of those may
+    /// need a threshold covering the longest instruction.
+    pub fn discontinuity_threshold(mut self, threshold: u64) -> Self {
+        self.config.discontinuity_threshold = threshold;
+        self
+    }
+
+    /// Sets whether a conditional jump executed without a failure address falls through to the instruction after it
+    /// instead of being `CFGError::ExpectedFailureAddress`, disabled by default.
+    ///
+    /// With the size of the jump known from `execute_sized`, the fall-through address is right after the jump. Otherwise
+    /// a taken jump only adds its taken edge, and one which isn't taken adds its not-taken edge to the next instruction
+    /// which is executed after it. The fall-through address is recorded as the failure address of the jump once it is
+    /// known, so the jump shows up in `branch_stats` and `frontier_edges` like one executed with it.
+    pub fn infer_fallthrough(mut self, enabled: bool) -> Self {
+        self.config.infer_fallthrough = enabled;
+        self
+    }
+
+    /// Reserves room for the given number of BasicBlocks up front.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Generates the configured ControlFlowGraph.
+    pub fn build(mut self) -> ControlFlowGraph<I> {
+        self.config.conflict = Some(describe_conflict::<I>);
+        if self.intern {
+            self.config.intern = Some(intern_instruction::<I>);
+        }
+        let mut cfg = ControlFlowGraph::new(self.entry_point);
+        cfg.blocks.reserve(self.capacity);
+        cfg.config = self.config;
+        cfg
+    }
+}
+
+/// Returns the recorded and the new instruction as text if they conflict.
+pub(crate) fn describe_conflict<I: PartialEq + fmt::Display>(existing: &BlockType<I>, new: &BlockType<I>) -> Option<(String, String)> {
+    existing.conflicts_with(new).then(|| (existing.to_string(), new.to_string()))
+}
+
+/// Replaces every payload of the instruction with an equal one from the pool, adding those which aren't in it yet.
+fn intern_instruction<I: Clone + Ord>(pool: &mut BTreeSet<I>, instruction: BlockType<I>) -> BlockType<I> {
+    let intern = |payload: I| match pool.get(&payload) {
+        Some(existing) => existing.clone(),
+        None => {
+            pool.insert(payload.clone());
+            payload
+        }
+    };
+    instruction.map_payload(intern)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use crate::{CFGBuilder, ControlFlowGraph};
+    use crate::types::*;
+
+    #[test]
+    fn interning_shares_payloads() -> Result<(), CFGError> {
+        let run = |intern: bool| -> Result<bool, CFGError> {
+            let mut cfg = CFGBuilder::<Rc<str>>::entry(0).intern(intern).build();
+            cfg.execute(0, BlockType::Instruction(Rc::from("INC"), None))?;
+            cfg.execute(1, BlockType::Instruction(Rc::from("INC"), None))?;
+            match (&cfg.entry().block[&0], &cfg.entry().block[&1]) {
+                (BlockType::Instruction(first, _), BlockType::Instruction(second, _)) => Ok(Rc::ptr_eq(first, second)),
+                _ => unreachable!(),
+            }
+        };
+        assert!(run(true)?);
+        assert!(!run(false)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fall_through_edges_toggle() -> Result<(), CFGError> {
+        let run = |enabled: bool| -> Result<ControlFlowGraph, CFGError> {
+            let mut cfg = CFGBuilder::entry(0).fall_through_edges(enabled).build();
+            cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(1)))?;
+            cfg.execute(10, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(1)))?;
+            cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+            // Falls through into the block at 10 without a jump.
+            cfg.execute(10, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            Ok(cfg)
+        };
+
+        let enabled = run(true)?;
+        assert_eq!(vec![(enabled.starts[&10], 1)], enabled.blocks[enabled.starts[&1]].edge_counts());
+        let disabled = run(false)?;
+        assert!(disabled.blocks[disabled.starts[&1]].edges.is_empty());
+        assert_eq!(2, disabled.blocks[disabled.starts[&10]].hits());
+        assert_eq!(3, disabled.entry().hits());
+
+        Ok(())
+    }
+
+    #[test]
+    fn conflict_policies() -> Result<(), CFGError> {
+        let run = |policy: ConflictPolicy| -> Result<ControlFlowGraph, CFGError> {
+            let mut cfg = CFGBuilder::entry(0x40).conflict_policy(policy).build();
+            cfg.execute(0x40, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(0x41, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None))?;
+            cfg.execute(0x40, BlockType::Instruction("DEC".to_string(), None))?;
+            Ok(cfg)
+        };
+
+        let kept = run(ConflictPolicy::KeepFirst)?;
+        assert_eq!(BlockType::Instruction("INC".to_string(), None), kept.entry().block[&0x40]);
+        let overwritten = run(ConflictPolicy::Overwrite)?;
+        assert_eq!(BlockType::Instruction("DEC".to_string(), None), overwritten.entry().block[&0x40]);
+        assert_eq!(Some(2), overwritten.entry().instruction_hits(0x40));
+        assert!(matches!(run(ConflictPolicy::Error), Err(CFGError::InstructionConflict { pc: 0x40, existing, new }) if existing == "INC" && new == "DEC"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn capacity_is_reserved() {
+        let cfg: ControlFlowGraph = CFGBuilder::entry(0).capacity(1000).build();
+        assert!(cfg.blocks.capacity() >= 1000);
+        let cfg: ControlFlowGraph = CFGBuilder::entry(0).build();
+        assert!(cfg.blocks.capacity() < 1000);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::ControlFlowGraph;
+use crate::observer::CfgEvent;
+use crate::types::{BlockId, CFGError};
+use crate::prelude::*;
+
+/// The traversal state of a ControlFlowGraph, which can be restored to continue from where it was taken.
+///
+/// Only where execution is inside of the graph is captured, the blocks and edges recorded since are kept on restore.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CfgCheckpoint {
+    pub(crate) current_block: BlockId,
+    pub(crate) call_stack: Vec<u64>,
+    /// The address and exclusive end of the previous instruction, if it wasn't a jump
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) previous_instruction: Option<(u64, u64)>,
+    /// The address of the conditional jump whose fall-through is the next instruction, if it wasn't taken without one
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) pending_fallthrough: Option<u64>,
+    /// The address of the terminal instruction which halted execution, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) terminated: Option<u64>,
+}
+
+impl CfgCheckpoint {
+    /// Returns the position of the block which was current when the checkpoint was taken.
+    pub fn current_block_index(&self) -> BlockId {
+        self.current_block
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Captures the current block and the shadow call stack, such as when the emulator takes a snapshot.
+    pub fn checkpoint(&self) -> CfgCheckpoint {
+        CfgCheckpoint { current_block: self.current_block, call_stack: self.call_stack.clone(), previous_instruction: self.previous_instruction, pending_fallthrough: self.pending_fallthrough, terminated: self.terminated }
+    }
+
+    /// Continues from the given checkpoint, such as when the emulator restores a snapshot.
+    ///
+    /// Returns `CFGError::MissingBlock` if the block which was current no longer exists, leaving the graph unchanged.
+    pub fn restore(&mut self, checkpoint: CfgCheckpoint) -> Result<(), CFGError> {
+        if checkpoint.current_block.index() >= self.blocks.len() {
+            return Err(CFGError::MissingBlock { index: checkpoint.current_block.index() });
+        }
+        self.log_event(CfgEvent::Current { block: checkpoint.current_block });
+        self.current_block = checkpoint.current_block;
+        self.call_stack = checkpoint.call_stack;
+        self.previous_instruction = checkpoint.previous_instruction;
+        self.pending_fallthrough = checkpoint.pending_fallthrough;
+        self.terminated = checkpoint.terminated;
+        Ok(())
+    }
+
+    /// Continues from the block containing the given address, for when only the restored program counter is known.
+    ///
+    /// A new block is created at the address if no block contains it. The shadow call stack is left untouched, and the
+    /// next instruction is never a discontinuity nor the fall-through of a conditional jump, and a halt by a terminal
+    /// instruction is lifted.
+    pub fn set_current_block_by_address(&mut self, address: u64) {
+        self.previous_instruction = None;
+        self.pending_fallthrough = None;
+        self.terminated = None;
+        self.current_block = match self.find_block_containing(address) {
+            Some(index) => index,
+            None => self.create_block(address),
+        };
+        self.log_event(CfgEvent::Current { block: self.current_block });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn restore_after_diverging() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(1)))?;
+        cfg.execute(0x20, BlockType::Jump("JZ".to_string(), 0x30, JumpType::ConditionalTaken, Some(0x21)))?;
+        let checkpoint = cfg.checkpoint();
+        assert_eq!(cfg.starts[&0x30], checkpoint.current_block_index());
+
+        cfg.execute(0x30, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x40, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.restore(checkpoint)?;
+        cfg.execute(0x30, BlockType::Jump("JMP".to_string(), 0x50, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x50, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+
+        let (block_30, block_40, block_50, block_1) = (cfg.starts[&0x30], cfg.starts[&0x40], cfg.starts[&0x50], cfg.starts[&1]);
+        assert_eq!(vec![(block_40, 1), (block_50, 1)], cfg.blocks[block_30].edge_counts());
+        // The call stack was restored as well, so the second return found its return address.
+        assert_eq!(vec![(block_1, 1)], cfg.blocks[block_50].edge_counts());
+        assert_eq!(block_1, cfg.current_block_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn restore_missing_block() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(10)))?;
+        let checkpoint = cfg.checkpoint();
+
+        let mut other: ControlFlowGraph = ControlFlowGraph::new(0);
+        assert!(matches!(other.restore(checkpoint), Err(CFGError::MissingBlock { .. })));
+        assert_eq!(BlockId(0), other.current_block_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn current_block_by_address() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(2, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+
+        cfg.set_current_block_by_address(1);
+        assert_eq!(BlockId(0), cfg.current_block_index());
+        cfg.execute(2, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        assert_eq!(vec![(cfg.starts[&10], 1), (cfg.starts[&20], 1)], cfg.entry().edge_counts());
+
+        cfg.set_current_block_by_address(30);
+        assert_eq!(cfg.starts[&30], cfg.current_block_index());
+        assert_eq!(4, cfg.len());
+
+        Ok(())
+    }
+}
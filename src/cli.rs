@@ -0,0 +1,136 @@
+//! The logic behind the `ctrl-flow` binary, which converts a trace file into a rendered ControlFlowGraph.
+//!
+//! The binary only parses its arguments with [parse_args], opens the input, and hands both to [run], so everything it
+//! does can be driven from here as well.
+
+use std::io::{self, BufRead, Cursor, Read, Write};
+use std::path::PathBuf;
+use crate::ControlFlowGraph;
+use crate::import::parse_address;
+use crate::types::CFGError;
+
+/// The usage of the `ctrl-flow` binary, printed for `--help` and after invalid arguments.
+pub const USAGE: &str = "\
+usage: ctrl-flow [options] [trace]
+
+Reads a trace from the given file, or from stdin when it is missing or `-`, and writes the graph to stdout.
+
+options:
+    --format <dot|json|text>    the format the graph is written in, dot by default
+    --input <trace|qemu>        the format of the trace, the documented text format by default or a qemu exec log
+    --entry <address>           the entry point of the graph, the address of the first traced instruction by default
+    --prune-unreachable         removes the blocks which were never reached from the entry before writing
+    --help                      prints this message
+";
+
+/// The format the graph is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// The Graphviz DOT format of `to_dot`.
+    #[default]
+    Dot,
+    /// The JSON of `to_json`.
+    Json,
+    /// The listing of the blocks written by the `Display` implementation.
+    Text,
+}
+
+/// The format of the trace which is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputFormat {
+    /// The text format documented in the [ingest](crate::ingest) module.
+    #[default]
+    Trace,
+    /// The execution log written by `qemu -d exec,nochain`, see the [qemu](crate::qemu) module.
+    Qemu,
+}
+
+/// The options of a single conversion, as parsed from the arguments of the binary.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CliOptions {
+    /// The trace file to read, or stdin when `None`.
+    pub input: Option<PathBuf>,
+    /// The format of the trace.
+    pub input_format: InputFormat,
+    /// The format the graph is written in.
+    pub format: OutputFormat,
+    /// The entry point of the graph, or the address of the first traced instruction when `None`.
+    pub entry: Option<u64>,
+    /// Whether the blocks which were never reached from the entry are removed before writing.
+    pub prune_unreachable: bool,
+}
+
+/// Parses the arguments of the binary, without the name of the binary itself.
+///
+/// Returns `Ok(None)` when `--help` was passed, and a message describing the problem for invalid arguments.
+pub fn parse_args<A: IntoIterator<Item = String>>(args: A) -> Result<Option<CliOptions>, String> {
+    let mut options = CliOptions::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("missing value for `{}`", flag));
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--format" => options.format = match value("--format")?.as_str() {
+                "dot" => OutputFormat::Dot,
+                "json" => OutputFormat::Json,
+                "text" => OutputFormat::Text,
+                other => return Err(format!("unknown output format `{}`", other)),
+            },
+            "--input" => options.input_format = match value("--input")?.as_str() {
+                "trace" => InputFormat::Trace,
+                "qemu" => InputFormat::Qemu,
+                other => return Err(format!("unknown input format `{}`", other)),
+            },
+            "--entry" => options.entry = Some(parse_address(&value("--entry")?)?),
+            "--prune-unreachable" => options.prune_unreachable = true,
+            "-" => options.input = None,
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            _ if options.input.is_some() => return Err(format!("unexpected argument `{}`", arg)),
+            _ => options.input = Some(PathBuf::from(arg)),
+        }
+    }
+    Ok(Some(options))
+}
+
+/// Builds a ControlFlowGraph from the trace read from `reader` and writes it to `writer`, as described by the options.
+///
+/// The input of the options is ignored, the caller opens it and passes it as the reader.
+pub fn run<R: BufRead, W: Write>(options: &CliOptions, mut reader: R, mut writer: W) -> Result<(), CFGError> {
+    let parse_entry: fn(&str) -> Option<u64> = match options.input_format {
+        InputFormat::Trace => |line: &str| crate::ingest::parse_line(line).ok().flatten().map(|(pc, _)| pc),
+        InputFormat::Qemu => crate::qemu::parse_block_entry,
+    };
+    let (skipped, first) = read_until_entry(&mut reader, parse_entry)?;
+    let reader = Cursor::new(skipped).chain(reader);
+
+    let mut cfg = ControlFlowGraph::new(options.entry.or(first).unwrap_or(0));
+    match options.input_format {
+        InputFormat::Trace => cfg.ingest(reader).map(|_| ())?,
+        InputFormat::Qemu => crate::qemu::ingest_qemu_exec_log(&mut cfg, reader).map(|_| ())?,
+    }
+    if options.prune_unreachable {
+        cfg.prune_unreachable();
+    }
+
+    match options.format {
+        OutputFormat::Dot => write!(writer, "{}", cfg.to_dot())?,
+        OutputFormat::Json => write!(writer, "{}", cfg.to_json())?,
+        OutputFormat::Text => write!(writer, "{}", cfg)?,
+    }
+    Ok(writer.flush()?)
+}
+
+/// Reads lines until one of them has an address, returning the bytes read so far and the address, so that the entry
+/// point is known before the trace is ingested without reading all of it up front.
+fn read_until_entry<R: BufRead>(reader: &mut R, parse_entry: fn(&str) -> Option<u64>) -> io::Result<(Vec<u8>, Option<u64>)> {
+    let mut skipped = Vec::new();
+    loop {
+        let start = skipped.len();
+        if reader.read_until(b'\n', &mut skipped)? == 0 {
+            return Ok((skipped, None));
+        }
+        if let Some(address) = core::str::from_utf8(&skipped[start..]).ok().and_then(parse_entry) {
+            return Ok((skipped, Some(address)));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Coverage of a ControlFlowGraph against a known set of instruction addresses, and the branches it never flipped.
+
+use alloc::collections::BTreeSet;
+use core::fmt;
+use crate::{ControlFlowGraph, Edge};
+use crate::types::{BlockType, JumpType};
+use crate::prelude::*;
+
+/// The coverage of a ControlFlowGraph against a universe of instruction addresses, each list in ascending order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The addresses of the universe which were executed.
+    pub covered: Vec<u64>,
+    /// The addresses of the universe which were never executed.
+    pub missed: Vec<u64>,
+    /// The start addresses of blocks which were created but never had an instruction executed inside of them.
+    pub empty_blocks: Vec<u64>,
+}
+
+impl CoverageReport {
+    /// Returns the number of distinct addresses inside of the universe.
+    pub fn total(&self) -> usize {
+        self.covered.len() + self.missed.len()
+    }
+
+    /// Returns the percentage of the universe which was covered, an empty universe being fully covered.
+    pub fn percentage(&self) -> f64 {
+        match self.total() {
+            0 => 100.0,
+            total => self.covered.len() as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Covered {}/{} addresses ({:.2}%)", self.covered.len(), self.total(), self.percentage())?;
+        if !self.missed.is_empty() {
+            writeln!(f, "Missed: {}", hex_list(&self.missed))?;
+        }
+        if !self.empty_blocks.is_empty() {
+            writeln!(f, "Empty blocks: {}", hex_list(&self.empty_blocks))?;
+        }
+        Ok(())
+    }
+}
+
+/// A direction of a conditional jump which was never followed while the other one was, a branch seen but never flipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrontierEdge {
+    /// The address of the conditional jump.
+    pub pc: u64,
+    /// The address of the target which was never jumped to.
+    pub target: u64,
+    /// The number of times the other direction was followed.
+    pub sibling_count: u64,
+}
+
+/// Formats the addresses as a comma separated list of hex values.
+fn hex_list(addresses: &[u64]) -> String {
+    addresses.iter().map(|address| format!("{:#x}", address)).collect::<Vec<_>>().join(", ")
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Computes which addresses of the given universe were executed, such as every address of an assembler listing.
+    pub fn coverage(&self, universe: &[u64]) -> CoverageReport {
+        let executed: BTreeSet<u64> = self.blocks.iter().flat_map(|bb| bb.block.keys().copied()).collect();
+        let universe: BTreeSet<u64> = universe.iter().copied().collect();
+        let (covered, missed) = universe.into_iter().partition(|address| executed.contains(address));
+        let empty_blocks = self.starts.iter()
+            .filter(|(_, index)| self.blocks[**index].block.is_empty())
+            .map(|(start, _)| *start)
+            .collect();
+
+        CoverageReport { covered, missed, empty_blocks }
+    }
+
+    /// Returns the frontier of the graph, every conditional jump ending a block which only ever went one way, with
+    /// the direction it never went.
+    ///
+    /// The most executed branches come first, those executed equally often are ordered by the address of the jump.
+    pub fn frontier_edges(&self) -> Vec<FrontierEdge> {
+        let mut frontier = Vec::new();
+        for block in self.blocks.iter() {
+            let Some((&pc, BlockType::Jump(_, success, JumpType::ConditionalTaken | JumpType::ConditionalNotTaken, Some(failure)))) = block.block.iter().next_back() else { continue };
+            if success == failure {
+                continue;
+            }
+            let count = |address: &u64| self.starts.get(address).and_then(|index| block.edge_to(*index)).map(Edge::count);
+            match (count(success), count(failure)) {
+                (Some(0), Some(sibling_count)) if sibling_count > 0 => frontier.push(FrontierEdge { pc, target: *success, sibling_count }),
+                (Some(sibling_count), Some(0)) if sibling_count > 0 => frontier.push(FrontierEdge { pc, target: *failure, sibling_count }),
+                _ => {}
+            }
+        }
+        frontier.sort_by(|a, b| b.sibling_count.cmp(&a.sibling_count).then(a.pc.cmp(&b.pc)));
+        frontier
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrontierEdge;
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn seven_of_ten() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for pc in 0..4 {
+            cfg.execute(pc, BlockType::Instruction("INC".to_string(), None))?;
+        }
+        cfg.execute(4, BlockType::Jump("JZ".to_string(), 7, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(7, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(8, BlockType::Instruction("INC".to_string(), None))?;
+
+        let universe: Vec<u64> = (0..10).collect();
+        let report = cfg.coverage(&universe);
+        assert_eq!(vec![0, 1, 2, 3, 4, 7, 8], report.covered);
+        assert_eq!(vec![5, 6, 9], report.missed);
+        assert_eq!(vec![5], report.empty_blocks);
+        assert_eq!(70.0, report.percentage());
+        assert_eq!("Covered 7/10 addresses (70.00%)\nMissed: 0x5, 0x6, 0x9\nEmpty blocks: 0x5\n", report.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn frontier_until_flipped() -> Result<(), CFGError> {
+        // The first branch is always taken and the second one never is.
+        let mut cfg = ControlFlowGraph::new(0);
+        for _ in 0..3 {
+            cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(1)))?;
+            cfg.execute(0x10, BlockType::Jump("JNZ".to_string(), 0x30, JumpType::ConditionalNotTaken, Some(0x11)))?;
+            cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(1)))?;
+        assert_eq!(vec![
+            FrontierEdge { pc: 0, target: 1, sibling_count: 4 },
+            FrontierEdge { pc: 0x10, target: 0x30, sibling_count: 3 },
+        ], cfg.frontier_edges());
+
+        // Flipping the first branch once removes it from the frontier.
+        cfg.execute(0x10, BlockType::Jump("JNZ".to_string(), 0x30, JumpType::ConditionalNotTaken, Some(0x11)))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalNotTaken, Some(1)))?;
+        assert_eq!(vec![FrontierEdge { pc: 0x10, target: 0x30, sibling_count: 4 }], cfg.frontier_edges());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::types::*;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns every critical edge as the positions of its source and target, which is an edge whose source has more
+    /// than one successor and whose target has more than one predecessor.
+    ///
+    /// Code can't be placed on a critical edge at either end without also running on another edge, so instrumenting one
+    /// needs a block of its own, see [split_critical_edge](Self::split_critical_edge). The edges are ordered by the
+    /// position of their source and then in the order they were added.
+    pub fn critical_edges(&self) -> Vec<(BlockId, BlockId)> {
+        self.blocks.iter().enumerate()
+            .filter(|(_, block)| block.edges.len() > 1)
+            .flat_map(|(index, block)| block.edges.iter().map(move |edge| (BlockId(index), edge.target())))
+            .filter(|(_, target)| self.predecessors[target.index()].len() > 1)
+            .collect()
+    }
+
+    /// Inserts an empty block on the edge from `src` to `dst` and returns its position, so that the edge no longer is
+    /// critical.
+    ///
+    /// The edge from `src` leads to the new block instead, keeping its kind and count, and the new block has an
+    /// unconditional edge to `dst` with the same count. As it doesn't stand for any code, the new block starts just past
+    /// the highest block of the graph, or at the lowest address no block covers when the graph reaches the top of the
+    /// address space, which is a `CFGError::NoFreeAddress` if blocks cover every address. The address still belongs to
+    /// the new block afterwards, so executing an instruction there later lands in the trampoline. The split isn't
+    /// written to an event log.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0);
+    /// cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalNotTaken, Some(0x2)))?;
+    /// cfg.execute(0x2, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+    ///
+    /// let (src, dst) = cfg.critical_edges()[0];
+    /// let trampoline = cfg.split_critical_edge(src, dst)?;
+    /// assert!(cfg.critical_edges().is_empty());
+    /// assert_eq!(0x11, cfg.block(trampoline).unwrap().start());
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn split_critical_edge(&mut self, src: BlockId, dst: BlockId) -> Result<BlockId, CFGError> {
+        let source = self.blocks.get(src.index()).ok_or(CFGError::MissingBlock { index: src.index() })?;
+        let position = source.edges.iter().position(|edge| edge.target() == dst).ok_or(CFGError::MissingEdge { from: src, to: dst })?;
+        let count = source.edges[position].count();
+
+        let start = self.free_address().ok_or(CFGError::NoFreeAddress { from: src, to: dst })?;
+        let mut block = BasicBlock::new(start);
+        block.hits = count;
+        let synthetic = self.add_block(block);
+        self.notify_edge(synthetic, dst, count > 0);
+        self.blocks[src].edges[position].retarget(synthetic);
+        self.unlink(src, dst);
+        self.link(src, synthetic);
+        self.add_edge_count(synthetic, dst, count, EdgeKind::Unconditional);
+        Ok(synthetic)
+    }
+
+    /// Returns the address right after the highest byte of the graph, or the lowest address which no block covers if
+    /// there is none.
+    fn free_address(&self) -> Option<u64> {
+        let highest = self.blocks.iter().map(BasicBlock::last_byte).max().unwrap_or_default();
+        highest.checked_add(1).or_else(|| {
+            let mut free = 0;
+            for block in self.blocks_by_address() {
+                if free < block.start {
+                    return Some(free);
+                }
+                free = free.max(block.last_byte().checked_add(1)?);
+            }
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn diamond_with_loop() -> Result<(), CFGError> {
+        // The header at 0x10 branches to 0x20 or falls through to 0x13, which also jumps to 0x20, and 0x20 loops back
+        // to the header or leaves to 0x30.
+        let mut cfg = ControlFlowGraph::new(0x0);
+        cfg.execute(0x0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        for lap in 0..3 {
+            let branch = if lap == 1 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(0x10, BlockType::Jump("JZ".to_string(), 0x20, branch, Some(0x13)))?;
+            if lap != 1 {
+                cfg.execute(0x13, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+            }
+            let back = if lap == 2 { JumpType::ConditionalNotTaken } else { JumpType::ConditionalTaken };
+            cfg.execute(0x20, BlockType::Jump("JNZ".to_string(), 0x10, back, Some(0x22)))?;
+        }
+        let block = |address: u64| cfg.find_block_containing(address).unwrap();
+        let (entry, header, side, latch, exit) = (block(0x0), block(0x10), block(0x13), block(0x20), block(0x22));
+
+        assert_eq!((1, 2, 1, 2, 0), (cfg.out_degree(entry)?, cfg.out_degree(header)?, cfg.out_degree(side)?, cfg.out_degree(latch)?, cfg.out_degree(exit)?));
+        assert_eq!((0, 2, 1, 2, 1), (cfg.in_degree(entry)?, cfg.in_degree(header)?, cfg.in_degree(side)?, cfg.in_degree(latch)?, cfg.in_degree(exit)?));
+        assert!(matches!(cfg.in_degree(BlockId(9)), Err(CFGError::MissingBlock { index: 9 })));
+        // Leaving the header straight to the latch, and looping back from the latch to the header.
+        assert_eq!(vec![(header, latch), (latch, header)], cfg.critical_edges());
+
+        let trampoline = cfg.split_critical_edge(header, latch)?;
+        assert_eq!(vec![(latch, header)], cfg.critical_edges());
+        assert_eq!(0x23, cfg.block(trampoline).unwrap().start());
+        assert!(cfg.block(trampoline).unwrap().is_empty());
+        assert_eq!(vec![side, trampoline], cfg.successors(header)?);
+        assert_eq!(vec![side, trampoline], cfg.predecessors(latch)?);
+        assert_eq!(vec![header], cfg.predecessors(trampoline)?);
+        let edge = cfg.block(trampoline).unwrap().edge_to(latch).unwrap();
+        assert_eq!((1, EdgeKind::Unconditional), (edge.count(), edge.kind()));
+        assert_eq!(Some(EdgeKind::Taken), cfg.block(header).unwrap().edge_to(trampoline).map(|edge| edge.kind()));
+        assert!(cfg.verify().is_ok());
+
+        assert!(matches!(cfg.split_critical_edge(header, latch), Err(CFGError::MissingEdge { .. })));
+        let back = cfg.split_critical_edge(latch, header)?;
+        assert!(cfg.critical_edges().is_empty());
+        assert_eq!(0x24, cfg.block(back).unwrap().start());
+
+        // Executing the loop again keeps the degrees in step, such as when a jump splits a block.
+        cfg.execute(0x10, BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalNotTaken, Some(0x13)))?;
+        cfg.execute(0x13, BlockType::Jump("JMP".to_string(), 0x15, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x15, BlockType::Jump("JMP".to_string(), 0x14, JumpType::UnconditionalJump, None))?;
+        for index in (0..cfg.len()).map(BlockId::from) {
+            let scanned = cfg.blocks().enumerate().filter(|(_, bb)| bb.edge_to(index).is_some()).map(|(source, _)| BlockId::from(source)).collect::<Vec<_>>();
+            assert_eq!(scanned, cfg.predecessors(index)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn every_address_covered() -> Result<(), CFGError> {
+        // The jump covers every address below the top one, where its target starts.
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute_sized(0, BlockType::Jump("JMP".to_string(), u64::MAX, JumpType::UnconditionalJump, None), u64::MAX)?;
+        let (entry, top) = (cfg.entry_index(), cfg.find_block_containing(u64::MAX).unwrap());
+        let error = cfg.split_critical_edge(entry, top).unwrap_err();
+        assert!(matches!(error, CFGError::NoFreeAddress { from, to } if from == entry && to == top));
+        assert_eq!("The edge from the block 0 to the block 1 can't be split as blocks cover every address.", error.to_string());
+        assert_eq!(2, cfg.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Diagnostics about suspicious but valid shapes of a ControlFlowGraph, meant for checking recorded traces.
+
+use core::fmt;
+use crate::ControlFlowGraph;
+use crate::types::{BlockId, EdgeKind};
+use crate::prelude::*;
+
+/// The largest distance between the exclusive end of a block and the start of its fall-through successor which
+/// `diagnostics` doesn't report.
+pub const DEFAULT_GAP_THRESHOLD: u64 = 16;
+
+/// A suspicious shape of a ControlFlowGraph, identifying the offending block by its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgDiagnostic {
+    /// The block holds no instructions, such as the not-taken block of a conditional jump which was never executed.
+    EmptyBlock { block: BlockId, start: u64 },
+    /// The end address of the block is below the address of its last instruction.
+    EndBelowInstruction { block: BlockId, end: u64, address: u64 },
+    /// The block starts inside of the range of another block with a lower or equal start address.
+    OverlappingBlocks { block: BlockId, other: BlockId },
+    /// The fall-through successor of the block starts further after its exclusive end than the threshold allows.
+    Gap { block: BlockId, successor: BlockId, gap: u64 },
+}
+
+impl fmt::Display for CfgDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfgDiagnostic::EmptyBlock { block, start } => write!(f, "block {} at {:#x} has no instructions", block, start),
+            CfgDiagnostic::EndBelowInstruction { block, end, address } => write!(f, "block {} ends at {:#x} below its instruction at {:#x}", block, end, address),
+            CfgDiagnostic::OverlappingBlocks { block, other } => write!(f, "block {} starts inside of block {}", block, other),
+            CfgDiagnostic::Gap { block, successor, gap } => write!(f, "block {} falls through to block {} {:#x} after its end", block, successor, gap),
+        }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the diagnostics of the ControlFlowGraph, reporting gaps over [DEFAULT_GAP_THRESHOLD].
+    ///
+    /// See [diagnostics_with_gap_threshold](Self::diagnostics_with_gap_threshold) for what is reported.
+    pub fn diagnostics(&self) -> Vec<CfgDiagnostic> {
+        self.diagnostics_with_gap_threshold(DEFAULT_GAP_THRESHOLD)
+    }
+
+    /// Returns the diagnostics of the ControlFlowGraph, reporting fall-through gaps larger than the given threshold.
+    ///
+    /// Empty blocks, ends below the last instruction, and gaps are reported first in block order, followed by
+    /// overlapping blocks in address order. Unlike `verify`, none of these break an invariant of the graph.
+    pub fn diagnostics_with_gap_threshold(&self, gap_threshold: u64) -> Vec<CfgDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let index = BlockId(index);
+            match block.block.keys().next_back() {
+                None => diagnostics.push(CfgDiagnostic::EmptyBlock { block: index, start: block.start }),
+                Some(&address) if address > block.end => diagnostics.push(CfgDiagnostic::EndBelowInstruction { block: index, end: block.end, address }),
+                Some(_) => {}
+            }
+            for edge in block.edges.iter().filter(|edge| edge.kind() == EdgeKind::FallThrough) {
+                let Some(successor) = self.blocks.get(edge.target().index()) else { continue };
+                let gap = successor.start.saturating_sub(block.end_exclusive());
+                if gap > gap_threshold {
+                    diagnostics.push(CfgDiagnostic::Gap { block: index, successor: edge.target(), gap });
+                }
+            }
+        }
+
+        let mut by_start: Vec<usize> = (0..self.blocks.len()).collect();
+        by_start.sort_by_key(|index| self.blocks[*index].start);
+        // The block reaching furthest among those starting before the current one.
+        let mut furthest: Option<usize> = None;
+        for index in by_start {
+            let block = &self.blocks[index];
+            match furthest {
+                Some(other) if block.start <= self.blocks[other].last_byte() => {
+                    diagnostics.push(CfgDiagnostic::OverlappingBlocks { block: BlockId(index), other: BlockId(other) });
+                }
+                _ => {}
+            }
+            if furthest.is_none_or(|other| block.last_byte() > self.blocks[other].last_byte()) {
+                furthest = Some(index);
+            }
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BasicBlock;
+    use crate::types::*;
+
+    #[test]
+    fn clean_graph() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x10, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        assert!(cfg.diagnostics().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn every_diagnostic() -> Result<(), CFGError> {
+        // The not-taken block at 0x2 is created eagerly and never executed.
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(2)))?;
+        cfg.execute(0x10, BlockType::Instruction("INC".to_string(), None))?;
+        assert_eq!(vec![CfgDiagnostic::EmptyBlock { block: BlockId(1), start: 2 }], cfg.diagnostics());
+
+        // A block falling through far past its end, and a block ending below its instructions inside another one.
+        let mut entry = BasicBlock::new(0);
+        entry.add_instruction(0, BlockType::Instruction("INC".to_string(), None));
+        entry.add_instruction(4, BlockType::Instruction("INC".to_string(), None));
+        entry.add_edge(BlockId(1), true, EdgeKind::FallThrough);
+        let mut far = BasicBlock::new(0x40);
+        far.add_instruction(0x40, BlockType::Instruction("INC".to_string(), None));
+        let mut inner = BasicBlock::new(2);
+        inner.add_instruction(3, BlockType::Instruction("INC".to_string(), None));
+        inner.end = 2;
+        let cfg = ControlFlowGraph::from_blocks(vec![entry, far, inner], 0);
+
+        assert_eq!(vec![
+            CfgDiagnostic::Gap { block: BlockId(0), successor: BlockId(1), gap: 0x3b },
+            CfgDiagnostic::EndBelowInstruction { block: BlockId(2), end: 2, address: 3 },
+            CfgDiagnostic::OverlappingBlocks { block: BlockId(2), other: BlockId(0) },
+        ], cfg.diagnostics());
+        assert_eq!(2, cfg.diagnostics_with_gap_threshold(0x3b).len());
+        assert_eq!("block 0 falls through to block 1 0x3b after its end", cfg.diagnostics()[0].to_string());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Structural differences between two ControlFlowGraphs.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+use crate::ControlFlowGraph;
+use crate::prelude::*;
+
+/// A single difference between two ControlFlowGraphs, with blocks and edges identified by their start addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffEntry {
+    /// A block which is only inside of the original graph.
+    BlockRemoved(u64),
+    /// A block which is only inside of the other graph.
+    BlockAdded(u64),
+    /// An edge which is only inside of the original graph.
+    EdgeRemoved { from: u64, to: u64, count: u64 },
+    /// An edge which is only inside of the other graph.
+    EdgeAdded { from: u64, to: u64, count: u64 },
+    /// An edge inside of both graphs whose traversal counts differ.
+    EdgeCountChanged { from: u64, to: u64, before: u64, after: u64 },
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffEntry::BlockRemoved(start) => write!(f, "- block {:#x}", start),
+            DiffEntry::BlockAdded(start) => write!(f, "+ block {:#x}", start),
+            DiffEntry::EdgeRemoved { from, to, count } => write!(f, "- edge {:#x} -> {:#x} ({})", from, to, count),
+            DiffEntry::EdgeAdded { from, to, count } => write!(f, "+ edge {:#x} -> {:#x} ({})", from, to, count),
+            DiffEntry::EdgeCountChanged { from, to, before, after } => {
+                write!(f, "~ edge {:#x} -> {:#x} ({} -> {})", from, to, before, after)
+            }
+        }
+    }
+}
+
+/// The differences between two ControlFlowGraphs, with the block differences first and then the edge differences,
+/// each ordered by address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfgDiff {
+    entries: Vec<DiffEntry>,
+}
+
+impl CfgDiff {
+    /// Returns an iterator over the differences.
+    pub fn iter(&self) -> impl Iterator<Item=&DiffEntry> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of differences.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the graphs had no differences.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl IntoIterator for CfgDiff {
+    type Item = DiffEntry;
+    type IntoIter = alloc::vec::IntoIter<DiffEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CfgDiff {
+    type Item = &'a DiffEntry;
+    type IntoIter = core::slice::Iter<'a, DiffEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl fmt::Display for CfgDiff {
+    /// Formats one difference per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.entries.iter() {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Compares this ControlFlowGraph against another, matching blocks by their start addresses.
+    ///
+    /// Removed entries are only inside of this graph and added entries are only inside of `other`.
+    pub fn diff<J>(&self, other: &ControlFlowGraph<J>) -> CfgDiff {
+        let mut entries = Vec::new();
+
+        let (before, after): (BTreeSet<u64>, BTreeSet<u64>) = (self.starts.keys().copied().collect(), other.starts.keys().copied().collect());
+        entries.extend(before.difference(&after).map(|start| DiffEntry::BlockRemoved(*start)));
+        entries.extend(after.difference(&before).map(|start| DiffEntry::BlockAdded(*start)));
+
+        let (before, after) = (self.edge_map(), other.edge_map());
+        let pairs: BTreeSet<&(u64, u64)> = before.keys().chain(after.keys()).collect();
+        for &(from, to) in pairs {
+            match (before.get(&(from, to)), after.get(&(from, to))) {
+                (Some(&count), None) => entries.push(DiffEntry::EdgeRemoved { from, to, count }),
+                (None, Some(&count)) => entries.push(DiffEntry::EdgeAdded { from, to, count }),
+                (Some(&before), Some(&after)) if before != after => {
+                    entries.push(DiffEntry::EdgeCountChanged { from, to, before, after })
+                }
+                _ => {}
+            }
+        }
+
+        CfgDiff { entries }
+    }
+
+    /// Returns the traversal count of every edge keyed by the start addresses of its source and target blocks.
+    pub(crate) fn edge_map(&self) -> BTreeMap<(u64, u64), u64> {
+        self.blocks.iter()
+            .flat_map(|block| block.edges.iter().map(move |edge| ((block.start, self.blocks[edge.target()].start), edge.count())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    fn run(jump_type: JumpType) -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, jump_type, Some(6)))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn opposite_branches() -> Result<(), CFGError> {
+        let (taken, not_taken) = (run(JumpType::ConditionalTaken)?, run(JumpType::ConditionalNotTaken)?);
+        let diff = taken.diff(&not_taken);
+
+        let expected = vec![
+            DiffEntry::EdgeCountChanged { from: 2, to: 6, before: 0, after: 1 },
+            DiffEntry::EdgeCountChanged { from: 2, to: 9, before: 1, after: 0 },
+        ];
+        assert_eq!(expected, diff.iter().copied().collect::<Vec<_>>());
+        assert_eq!("~ edge 0x2 -> 0x6 (0 -> 1)\n~ edge 0x2 -> 0x9 (1 -> 0)\n", diff.to_string());
+        assert!(taken.diff(&taken).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn added_and_removed() -> Result<(), CFGError> {
+        let before = run(JumpType::ConditionalTaken)?;
+        let mut after = run(JumpType::ConditionalTaken)?;
+        after.execute(9, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+
+        let expected = vec![
+            DiffEntry::BlockAdded(20),
+            DiffEntry::EdgeAdded { from: 9, to: 20, count: 1 },
+        ];
+        assert_eq!(expected, before.diff(&after).into_iter().collect::<Vec<_>>());
+        let expected = vec![
+            DiffEntry::BlockRemoved(20),
+            DiffEntry::EdgeRemoved { from: 9, to: 20, count: 1 },
+        ];
+        assert_eq!(expected, after.diff(&before).into_iter().collect::<Vec<_>>());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::ControlFlowGraph;
+use crate::types::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns how an instruction is handled when it doesn't continue from where the previous one ended.
+    pub fn discontinuity_policy(&self) -> DiscontinuityPolicy {
+        self.config.discontinuity_policy
+    }
+
+    /// Checks whether the instruction at the given address continues from the previous instruction, if that wasn't a
+    /// jump, and follows the DiscontinuityPolicy if it doesn't.
+    ///
+    /// Returns the outcome of traversing the edge to the block at the address when execution restarted there.
+    pub(crate) fn follow_discontinuity(&mut self, program_counter: u64) -> Result<Option<ExecOutcome>, CFGError> {
+        let Some((previous, next)) = self.previous_instruction else {
+            return Ok(None);
+        };
+        if program_counter.abs_diff(next) <= self.config.discontinuity_threshold {
+            return Ok(None);
+        }
+        match self.config.discontinuity_policy {
+            DiscontinuityPolicy::Ignore => Ok(None),
+            DiscontinuityPolicy::Error => Err(CFGError::Discontinuity { from: previous, to: program_counter }),
+            DiscontinuityPolicy::Restart => {
+                let index = self.query_block_or_create(program_counter)?;
+                self.traverse_edge(index, EdgeKind::Unknown).map(Some)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CFGBuilder, ControlFlowGraph};
+    use crate::types::*;
+
+    /// Runs a trace which dropped the jump at 0x22 to 0x40 and the instruction at 0x40.
+    fn dropped_events(cfg: &mut ControlFlowGraph) -> Result<ExecOutcome, CFGError> {
+        cfg.execute(0x10, BlockType::Instruction("MOV".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x20, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x21, BlockType::Instruction("INC".to_string(), None))?;
+        let resumed = cfg.execute(0x41, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x42, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        Ok(resumed)
+    }
+
+    #[test]
+    fn error_policy() -> Result<(), CFGError> {
+        let mut ignored = ControlFlowGraph::new(0x10);
+        assert_eq!(DiscontinuityPolicy::Ignore, ignored.discontinuity_policy());
+        dropped_events(&mut ignored)?;
+        // The instructions after the dropped events end up in the block they weren't part of.
+        assert_eq!((0x20, 0x42), (ignored.blocks[1].start(), ignored.blocks[1].end()));
+
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x10).discontinuity_policy(DiscontinuityPolicy::Error).build();
+        let error = dropped_events(&mut cfg).unwrap_err();
+        assert!(matches!(error, CFGError::Discontinuity { from: 0x21, to: 0x41 }));
+        assert_eq!("The instruction at 0x41 does not continue from the instruction at 0x21 without a jump.", error.to_string());
+        assert_eq!((2, 0x21), (cfg.len(), cfg.blocks[cfg.current_block_index()].end()));
+
+        // Continuing from the known address of the resumed trace.
+        cfg.set_current_block_by_address(0x41);
+        cfg.execute(0x41, BlockType::Instruction("DEC".to_string(), None))?;
+        assert_eq!(3, cfg.len());
+
+        // Gaps within the threshold are part of the same block.
+        let mut sized: ControlFlowGraph = CFGBuilder::entry(0x10).discontinuity_policy(DiscontinuityPolicy::Error).discontinuity_threshold(0x20).build();
+        dropped_events(&mut sized)?;
+        assert_eq!(ignored.to_string(), sized.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn restart_policy() -> Result<(), CFGError> {
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x10).discontinuity_policy(DiscontinuityPolicy::Restart).build();
+        let resumed = dropped_events(&mut cfg)?;
+
+        let (block_20, block_41) = (cfg.starts[&0x20], cfg.starts[&0x41]);
+        assert_eq!(ExecOutcome::NewBlock(block_41), resumed);
+        assert_eq!((0x20, 0x21), (cfg.blocks[block_20].start(), cfg.blocks[block_20].end()));
+        assert_eq!(vec![(block_41, 1, EdgeKind::Unknown)], cfg.blocks[block_20].edges().map(|edge| (edge.target(), edge.count(), edge.kind())).collect::<Vec<_>>());
+        assert_eq!(Some(EdgeKind::Unconditional), cfg.blocks[block_41].edge_to(cfg.entry_index()).map(|edge| edge.kind()));
+
+        // The same discontinuity again traverses the same edge, and one into the middle of a block splits it.
+        dropped_events(&mut cfg)?;
+        cfg.execute(0x10, BlockType::Instruction("MOV".to_string(), None))?;
+        cfg.execute(0x42, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        assert_eq!(Some(2), cfg.blocks[block_20].edge_to(block_41).map(|edge| edge.count()));
+        assert_eq!(Some(EdgeKind::Unknown), cfg.entry().edge_to(cfg.starts[&0x42]).map(|edge| edge.kind()));
+
+        Ok(())
+    }
+}
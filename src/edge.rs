@@ -0,0 +1,377 @@
+use crate::{saturating_increase, BasicBlock, ControlFlowGraph};
+use crate::types::{BlockId, EdgeKind};
+use crate::prelude::*;
+
+/// An edge leaving a BasicBlock, with the position of the block it leads to, its traversal count and its kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edge {
+    target: BlockId,
+    count: u64,
+    kind: EdgeKind,
+    #[cfg_attr(feature = "serde", serde(default))]
+    is_static: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    discovered_at: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    first_seen: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    last_seen: Option<u64>,
+}
+
+impl Edge {
+    /// Generates an edge to the block at the given position.
+    pub(crate) fn new(target: BlockId, count: u64, kind: EdgeKind) -> Self {
+        Edge { target, count, kind, is_static: false, discovered_at: 0, first_seen: None, last_seen: None }
+    }
+
+    /// Returns the position of the block the edge leads to.
+    pub fn target(&self) -> BlockId {
+        self.target
+    }
+
+    /// Returns how many times the edge was traversed.
+    ///
+    /// A count of zero means the edge was observed as possible but never traversed, such as the direction a
+    /// conditional jump never took or an edge pre-seeded from static disassembly.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, EdgeKind, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0x10);
+    /// cfg.execute(0x10, BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalTaken, Some(0x11)))?;
+    ///
+    /// let counts: Vec<(EdgeKind, u64)> = cfg.entry().edges().map(|edge| (edge.kind(), edge.count())).collect();
+    /// assert_eq!(vec![(EdgeKind::FallThrough, 0), (EdgeKind::Taken, 1)], counts);
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns how control flowed along the edge, which is set by the instruction which first created it.
+    ///
+    /// An edge which was pre-seeded before any instruction created it is `Unconditional` until one does.
+    pub fn kind(&self) -> EdgeKind {
+        self.kind
+    }
+
+    /// Returns whether the edge was pre-seeded with `preseed_edge`, such as from static disassembly, whether or not it
+    /// was traversed since.
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// Returns how many edges the graph discovered before this one, counting the edges which were removed since.
+    ///
+    /// Graphs deserialized from before discovery ordinals were recorded have an ordinal of zero on every edge.
+    pub fn discovered_at(&self) -> u64 {
+        self.discovered_at
+    }
+
+    /// Returns the ordinal of the instruction which first traversed the edge, counting the instructions the graph
+    /// executed from one, or `None` if no instruction traversed it yet.
+    ///
+    /// Edges which were only counted without executing, such as the edge from the head of a split block to its tail or
+    /// edges imported from DOT, have no ordinals until an instruction traverses them.
+    pub fn first_seen(&self) -> Option<u64> {
+        self.first_seen
+    }
+
+    /// Returns the ordinal of the instruction which last traversed the edge, or `None` if no instruction traversed it yet.
+    pub fn last_seen(&self) -> Option<u64> {
+        self.last_seen
+    }
+
+    /// Records that the instruction with the given ordinal traversed the edge.
+    pub(crate) fn mark_seen(&mut self, ordinal: u64) {
+        self.first_seen.get_or_insert(ordinal);
+        self.last_seen = Some(ordinal);
+    }
+
+    /// Overwrites the first and last ordinals the edge was traversed at.
+    pub(crate) fn set_seen(&mut self, first_seen: Option<u64>, last_seen: Option<u64>) {
+        self.first_seen = first_seen;
+        self.last_seen = last_seen;
+    }
+
+    /// Overwrites the discovery ordinal of the edge.
+    pub(crate) fn set_discovered_at(&mut self, ordinal: u64) {
+        self.discovered_at = ordinal;
+    }
+
+    /// Marks the edge as pre-seeded.
+    pub(crate) fn mark_static(&mut self) {
+        self.is_static = true;
+    }
+
+    /// Records that an instruction created the edge with the given kind, which replaces the kind of a pre-seeded edge
+    /// no instruction created yet.
+    pub(crate) fn observe_kind(&mut self, kind: EdgeKind) {
+        if self.is_static && self.kind == EdgeKind::Unconditional {
+            self.kind = kind;
+        }
+    }
+
+    /// Points the edge at the block at the given position, such as after the blocks were compacted.
+    pub(crate) fn retarget(&mut self, target: BlockId) {
+        self.target = target;
+    }
+
+    /// Adds the given number of traversals to the count of the edge, returning whether the count saturated.
+    pub(crate) fn add_count(&mut self, count: u64) -> bool {
+        saturating_increase(&mut self.count, count)
+    }
+
+    /// Overwrites the count of the edge, such as to reset it between runs or to start a test close to `u64::MAX`.
+    pub(crate) fn set_count(&mut self, count: u64) {
+        self.count = count;
+    }
+}
+
+/// An edge of a ControlFlowGraph together with the BasicBlocks at both of its ends.
+pub struct EdgeRef<'a, I = String> {
+    source_index: BlockId,
+    target_index: BlockId,
+    source: &'a BasicBlock<I>,
+    target: &'a BasicBlock<I>,
+    count: u64,
+    kind: EdgeKind,
+    is_static: bool,
+    discovered_at: u64,
+    first_seen: Option<u64>,
+    last_seen: Option<u64>,
+}
+
+impl<'a, I> EdgeRef<'a, I> {
+    /// Returns the position of the block the edge leaves from.
+    pub fn source_index(&self) -> BlockId {
+        self.source_index
+    }
+
+    /// Returns the position of the block the edge leads to.
+    pub fn target_index(&self) -> BlockId {
+        self.target_index
+    }
+
+    /// Returns the start address of the block the edge leaves from.
+    pub fn source_start(&self) -> u64 {
+        self.source.start
+    }
+
+    /// Returns the start address of the block the edge leads to.
+    pub fn target_start(&self) -> u64 {
+        self.target.start
+    }
+
+    /// Returns the BasicBlock the edge leaves from.
+    pub fn source(&self) -> &'a BasicBlock<I> {
+        self.source
+    }
+
+    /// Returns the BasicBlock the edge leads to.
+    pub fn target(&self) -> &'a BasicBlock<I> {
+        self.target
+    }
+
+    /// Returns how many times the edge was traversed, zero meaning it was possible but never traversed.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    ///
+    /// let mut cfg: ControlFlowGraph = ControlFlowGraph::new(0x10);
+    /// cfg.preseed_edge(0x10, 0x40)?;
+    ///
+    /// let edge = cfg.edges().next().unwrap();
+    /// assert_eq!((0x40, 0), (edge.target_start(), edge.count()));
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns how control flowed along the edge.
+    pub fn kind(&self) -> EdgeKind {
+        self.kind
+    }
+
+    /// Returns whether the edge was pre-seeded, such as from static disassembly.
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// Returns how many edges the graph discovered before this one.
+    pub fn discovered_at(&self) -> u64 {
+        self.discovered_at
+    }
+
+    /// Returns the ordinal of the instruction which first traversed the edge, if any.
+    pub fn first_seen(&self) -> Option<u64> {
+        self.first_seen
+    }
+
+    /// Returns the ordinal of the instruction which last traversed the edge, if any.
+    pub fn last_seen(&self) -> Option<u64> {
+        self.last_seen
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns an iterator over every edge of the graph, ordered by the position of the source block and then by the
+    /// order the edges of that block were first added.
+    pub fn edges(&self) -> impl Iterator<Item = EdgeRef<'_, I>> {
+        self.blocks.iter().enumerate().flat_map(move |(index, source)| {
+            source.edges.iter().map(move |edge| EdgeRef {
+                source_index: BlockId(index),
+                target_index: edge.target,
+                source,
+                target: &self.blocks[edge.target],
+                count: edge.count,
+                kind: edge.kind,
+                is_static: edge.is_static,
+                discovered_at: edge.discovered_at,
+                first_seen: edge.first_seen,
+                last_seen: edge.last_seen,
+            })
+        })
+    }
+
+    /// Returns every edge whose first and last traversal span a range of instruction ordinals which overlaps the range
+    /// from `lo` to `hi`, inclusively, in the order of [edges](Self::edges).
+    ///
+    /// Instructions are counted from one in the order the graph executed them, so the edges which were only used while
+    /// starting up are those active up to the ordinal at the end of startup but not after it.
+    pub fn edges_active_between(&self, lo: u64, hi: u64) -> impl Iterator<Item = EdgeRef<'_, I>> {
+        self.edges().filter(move |edge| matches!((edge.first_seen, edge.last_seen), (Some(first), Some(last)) if first <= hi && lo <= last))
+    }
+
+    /// Returns the number of instructions the graph executed, which is the ordinal of the last one.
+    pub fn executed_count(&self) -> u64 {
+        self.executed
+    }
+
+    /// Returns the number of edges in the graph, including those which were never traversed.
+    pub fn edge_count(&self) -> usize {
+        self.blocks.iter().map(|block| block.edges.len()).sum()
+    }
+
+    /// Returns the number of edges in the graph which were traversed at least once.
+    pub fn traversed_edge_count(&self) -> usize {
+        self.blocks.iter().map(|block| block.edges.iter().filter(|edge| edge.count > 0).count()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::export::DotOptions;
+    use crate::types::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn conditional_jump_edges() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("A".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+
+        let edges: Vec<_> = cfg.edges().map(|edge| (edge.source_index(), edge.source_start(), edge.target_index(), edge.target_start(), edge.count())).collect();
+        assert_eq!(vec![(BlockId(0), 2, BlockId(1), 6, 0), (BlockId(0), 2, BlockId(2), 9, 1), (BlockId(2), 9, BlockId(0), 2, 1)], edges);
+
+        let mut total = 0;
+        let mut traversed = 0;
+        for block in cfg.blocks() {
+            for edge in block.edges() {
+                total += 1;
+                if edge.count() > 0 {
+                    traversed += 1;
+                }
+            }
+        }
+        assert_eq!(total, cfg.edge_count());
+        assert_eq!(traversed, cfg.traversed_edge_count());
+        assert_eq!((3, 2), (cfg.edge_count(), cfg.traversed_edge_count()));
+        assert_eq!(cfg.edge_count(), cfg.edges().count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn edge_kinds() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(2)))?;
+        cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x20, JumpType::ConditionalNotTaken, Some(0x12)))?;
+        cfg.execute(0x13, BlockType::Jump("CALL".to_string(), 0x30, JumpType::Call, Some(0x14)))?;
+        cfg.execute(0x31, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(0x15, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None))?;
+        cfg.execute_indirect(0x41, "JMP".to_string(), 0x50)?;
+        cfg.execute_switch(0x51, "JMP".to_string(), vec![0x60, 0x70], 0x70)?;
+        cfg.execute(0x71, BlockType::Jump("JMP".to_string(), 0x1f, JumpType::UnconditionalJump, None))?;
+        // Running into the start of the block of the untaken target of the second jump falls through into it.
+        cfg.execute(0x20, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x21, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        cfg.execute(2, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        // Jumping into the middle of a block splits it, the head falling through into the tail.
+        cfg.execute(4, BlockType::Jump("JMP".to_string(), 0x11, JumpType::UnconditionalJump, None))?;
+
+        let mut kinds: Vec<_> = cfg.edges().map(|edge| (edge.source_start(), edge.target_start(), edge.kind())).collect();
+        kinds.sort();
+        assert_eq!(vec![
+            (0, 2, EdgeKind::FallThrough),
+            (0, 0x10, EdgeKind::Taken),
+            (2, 0x11, EdgeKind::Unconditional),
+            (0x10, 0x11, EdgeKind::FallThrough),
+            (0x11, 0x12, EdgeKind::FallThrough),
+            (0x11, 0x20, EdgeKind::Taken),
+            (0x12, 0x30, EdgeKind::Call),
+            (0x14, 0x40, EdgeKind::Unconditional),
+            (0x1f, 0x20, EdgeKind::FallThrough),
+            (0x20, 2, EdgeKind::Unconditional),
+            (0x30, 0x14, EdgeKind::Return),
+            (0x40, 0x50, EdgeKind::Unconditional),
+            (0x50, 0x60, EdgeKind::Taken),
+            (0x50, 0x70, EdgeKind::Taken),
+            (0x70, 0x1f, EdgeKind::Unconditional),
+        ], kinds);
+        assert!(cfg.blocks().flat_map(|block| block.edges()).map(|edge| edge.kind()).eq(cfg.edges().map(|edge| edge.kind())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn active_edges() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        // Starting up runs straight into the loop.
+        cfg.execute(0, BlockType::Instruction("MOV".to_string(), None))?;
+        cfg.execute(1, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x10, BlockType::Instruction("MOV".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        let startup = cfg.executed_count();
+        assert_eq!(4, startup);
+
+        for taken in [true, true, true, false] {
+            let jump_type = if taken { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(0x20, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute(0x21, BlockType::Jump("JNZ".to_string(), 0x20, jump_type, Some(0x22)))?;
+        }
+
+        let seen = |lo, hi| cfg.edges_active_between(lo, hi).map(|edge| (edge.source_start(), edge.target_start(), edge.first_seen(), edge.last_seen())).collect::<Vec<_>>();
+        assert_eq!(vec![(0, 0x10, Some(2), Some(2)), (0x10, 0x20, Some(4), Some(4))], seen(1, startup));
+        // The edge which wasn't taken until the loop ended has no ordinals from when it was created.
+        assert_eq!(vec![(0x20, 0x22, Some(12), Some(12)), (0x20, 0x20, Some(6), Some(10))], seen(startup + 1, cfg.executed_count()));
+        assert_eq!(vec![(0x20, 0x20, Some(6), Some(10))], seen(7, 9));
+        assert!(seen(13, u64::MAX).is_empty());
+
+        let options = DotOptions { seen: true, ..DotOptions::default() };
+        assert!(cfg.to_dot_with_options(&options).contains("\"0x20\" -> \"0x20\" [label=\"3\", color=darkgreen, first_seen=6, last_seen=10];"));
+        assert!(!cfg.to_dot().contains("first_seen"));
+
+        cfg.reset_counts();
+        assert_eq!((0, 0), (cfg.executed_count(), cfg.edges_active_between(0, u64::MAX).count()));
+
+        Ok(())
+    }
+}
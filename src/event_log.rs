@@ -0,0 +1,593 @@
+//! An append-only log of the structural changes a ControlFlowGraph makes while executing, from which an identical
+//! graph can be rebuilt, such as after a crash or in another process.
+//!
+//! The log starts with the magic bytes `CFEL`, a version byte, and the entry point, followed by one record per change.
+//! Every record is a tag byte followed by its fields, with every number written as an unsigned LEB128 varint, every
+//! string as its length in bytes followed by its UTF-8, and every block named by its position.
+
+use core::fmt;
+use std::io::{self, BufReader, Read, Write};
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::binary::{edge_kind_from_tag, edge_kind_tag, jump_type_from_tag, jump_type_tag, push_varint};
+use crate::observer::CfgEvent;
+use crate::types::*;
+
+/// The bytes every event log starts with.
+const MAGIC: &[u8; 4] = b"CFEL";
+/// The version of the records which follow the magic bytes.
+const VERSION: u8 = 1;
+
+/// Writes every structural change of the ControlFlowGraph it is attached to as a compact binary record, see the
+/// [module](crate::event_log) for the format.
+///
+/// Writing stops at the first error, which is returned by `into_inner`. Every record is written with a single call to
+/// the writer, so wrapping it into a `BufWriter` saves a system call per record.
+///
+/// ```
+/// use ctrl_flow::{CfgEventLog, ControlFlowGraph};
+/// use ctrl_flow::types::{BlockType, JumpType};
+///
+/// let mut cfg = ControlFlowGraph::new(0);
+/// cfg.set_event_log(CfgEventLog::new(std::io::sink()));
+/// cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+/// assert_eq!(4, cfg.take_event_log().unwrap().records());
+/// # Ok::<(), ctrl_flow::types::CFGError>(())
+/// ```
+pub struct CfgEventLog<I = String> {
+    writer: Box<dyn Write + Send>,
+    /// Formats the payloads of instructions, only known for payloads which can be displayed
+    payload: fn(&I) -> String,
+    buffer: Vec<u8>,
+    records: u64,
+    error: Option<io::Error>,
+    /// The thread making the changes, or `None` for the graph itself
+    thread: Option<u64>,
+    /// The thread the last record written was made by, which lags behind `thread` until the next record
+    written_thread: Option<u64>,
+}
+
+impl<I: fmt::Display> CfgEventLog<I> {
+    /// Generates an event log writing to the given writer, with the payloads of instructions written as their text.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        CfgEventLog { writer: Box::new(writer), payload: |payload| payload.to_string(), buffer: Vec::new(), records: 0, error: None, thread: None, written_thread: None }
+    }
+}
+
+impl<I> CfgEventLog<I> {
+    /// Returns the number of records written, not counting the header.
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Flushes the writer and returns it, or the first error which stopped writing.
+    pub fn into_inner(mut self) -> io::Result<Box<dyn Write + Send>> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.writer.flush().map(|_| self.writer),
+        }
+    }
+
+    /// Writes the header naming the entry point of the graph the log was attached to.
+    fn write_header(&mut self, entry_point: u64) {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(MAGIC);
+        self.buffer.push(VERSION);
+        push_varint(&mut self.buffer, entry_point);
+        self.flush_buffer();
+    }
+
+    /// Writes the change as a record, unless writing already failed.
+    ///
+    /// A change of the thread is only written ahead of the next record made by another thread than the last one, so
+    /// that switching back and forth without changing anything doesn't grow the log.
+    pub(crate) fn write(&mut self, event: CfgEvent<'_, I>) {
+        if let CfgEvent::Thread { tid } = event {
+            self.thread = tid;
+            return;
+        }
+        if self.error.is_some() {
+            return;
+        }
+        if self.thread != self.written_thread {
+            self.buffer.clear();
+            self.buffer.push(9);
+            match self.thread {
+                Some(tid) => {
+                    self.buffer.push(1);
+                    push_varint(&mut self.buffer, tid);
+                }
+                None => self.buffer.push(0),
+            }
+            self.flush_buffer();
+            self.records += 1;
+            self.written_thread = self.thread;
+        }
+        let buffer = &mut self.buffer;
+        buffer.clear();
+        match event {
+            CfgEvent::Block { start } => {
+                buffer.push(0);
+                push_varint(buffer, start);
+            }
+            CfgEvent::Split { block, address } => {
+                buffer.push(1);
+                push_varints(buffer, &[block.index() as u64, address]);
+            }
+            CfgEvent::Instruction { block, pc, size, instruction } => {
+                buffer.push(2);
+                push_varints(buffer, &[block.index() as u64, pc, size]);
+                push_instruction(buffer, instruction, self.payload);
+            }
+            CfgEvent::Hit { block, pc, size } => {
+                buffer.push(3);
+                push_varints(buffer, &[block.index() as u64, pc, size]);
+            }
+            CfgEvent::Edge { from, to, kind, traversed, ordinal } => {
+                buffer.push(4);
+                push_varints(buffer, &[from.index() as u64, to.index() as u64]);
+                buffer.push(edge_kind_tag(kind));
+                buffer.push(traversed as u8);
+                if traversed {
+                    push_varint(buffer, ordinal);
+                }
+            }
+            CfgEvent::StaticEdge { from, to } => {
+                buffer.push(5);
+                push_varints(buffer, &[from.index() as u64, to.index() as u64]);
+            }
+            CfgEvent::Enter { block } => {
+                buffer.push(6);
+                push_varint(buffer, block.index() as u64);
+            }
+            CfgEvent::BlockHit { block } => {
+                buffer.push(7);
+                push_varint(buffer, block.index() as u64);
+            }
+            CfgEvent::Current { block } => {
+                buffer.push(8);
+                push_varint(buffer, block.index() as u64);
+            }
+            CfgEvent::Thread { .. } => unreachable!("thread changes are written ahead of the next record"),
+        }
+        self.flush_buffer();
+        self.records += 1;
+    }
+
+    fn flush_buffer(&mut self) {
+        if let Err(error) = self.writer.write_all(&self.buffer) {
+            self.error = Some(error);
+        }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Writes every structural change made from now on to the given event log, replacing the previous one.
+    ///
+    /// The log is replayed onto a new graph with the entry point of this one, so it should be attached before anything
+    /// is executed. Changes made by other means than executing, pre-seeding, and restoring a checkpoint, such as
+    /// removing, merging, or rebasing blocks, aren't logged.
+    pub fn set_event_log(&mut self, mut log: CfgEventLog<I>) {
+        log.write_header(self.entry().start);
+        self.event_log = Some(log);
+    }
+
+    /// Detaches the event log and returns it.
+    pub fn take_event_log(&mut self) -> Option<CfgEventLog<I>> {
+        self.event_log.take()
+    }
+}
+
+impl<I: From<String>> ControlFlowGraph<I> {
+    /// Rebuilds the ControlFlowGraph written to an event log by applying every record in order.
+    ///
+    /// A log which ends between two records is replayed up to its end, so the log of a crashed run still rebuilds
+    /// the graph as it was when the last record was written. A log ending inside of a record is rejected with
+    /// `CFGError::TruncatedEventLog`, and a record which doesn't apply to the graph with `CFGError::InvalidEventLog`.
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ctrl_flow::ControlFlowGraph;
+    ///
+    /// # fn replay() -> Result<(), ctrl_flow::types::CFGError> {
+    /// let cfg: ControlFlowGraph = ControlFlowGraph::replay_events(File::open("fuzz.cfel")?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replay_events<R: Read>(reader: R) -> Result<Self, CFGError> {
+        let mut reader = EventReader { reader: BufReader::new(reader), offset: 0, record: 0 };
+        let mut magic = [0; 4];
+        for byte in magic.iter_mut() {
+            *byte = reader.byte()?;
+        }
+        if &magic != MAGIC {
+            return Err(reader.invalid("the log doesn't start with the magic bytes"));
+        }
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(CFGError::UnsupportedVersion { version });
+        }
+        let mut cfg = ControlFlowGraph::new(reader.varint()?);
+        let mut thread = None;
+
+        while let Some(tag) = reader.next_record()? {
+            match tag {
+                0 => {
+                    let start = reader.varint()?;
+                    if cfg.starts.contains_key(&start) {
+                        return Err(reader.invalid("a block already starts at the address"));
+                    }
+                    cfg.add_block(BasicBlock::new(start));
+                }
+                1 => {
+                    let block = reader.block(&cfg)?;
+                    let address = reader.varint()?;
+                    if address <= cfg.blocks[block].start || address > cfg.blocks[block].end {
+                        return Err(reader.invalid("the split address isn't inside of the block"));
+                    }
+                    cfg.split_block(block, address);
+                }
+                2 => {
+                    let block = reader.block(&cfg)?;
+                    let (pc, size) = (reader.varint()?, reader.varint()?);
+                    let instruction = reader.instruction()?;
+                    let block = &mut cfg.blocks[block];
+                    match block.block.get_mut(&pc) {
+                        Some(existing) => *existing = instruction,
+                        None => {
+                            block.add_instruction(pc, instruction);
+                            cfg.executed = cfg.executed.saturating_add(1);
+                        }
+                    }
+                    block.set_instruction_size(pc, size);
+                }
+                3 => {
+                    let block = reader.block(&cfg)?;
+                    let (pc, size) = (reader.varint()?, reader.varint()?);
+                    let block = &mut cfg.blocks[block];
+                    let Some(hits) = block.instruction_hits.get_mut(&pc) else {
+                        return Err(reader.invalid("no instruction is recorded at the address"));
+                    };
+                    cfg.saturated |= crate::saturating_increase(hits, 1);
+                    block.set_instruction_size(pc, size);
+                    cfg.executed = cfg.executed.saturating_add(1);
+                }
+                4 => {
+                    let (from, to) = (reader.block(&cfg)?, reader.block(&cfg)?);
+                    let kind = edge_kind_from_tag(reader.byte()?).ok_or_else(|| reader.invalid("unknown edge kind"))?;
+                    let traversed = reader.byte()? != 0;
+                    // A fall-through is traversed before the instruction executing it is recorded, so the edge is
+                    // seen at its logged ordinal rather than at the number of instructions replayed so far.
+                    let replayed = cfg.executed;
+                    if traversed {
+                        cfg.executed = reader.varint()?;
+                    }
+                    cfg.add_edge(from, to, traversed, kind)?;
+                    cfg.executed = replayed;
+                }
+                5 => {
+                    let (from, to) = (reader.block(&cfg)?, reader.block(&cfg)?);
+                    cfg.blocks[from].mark_static_edge(to);
+                }
+                6 => {
+                    let block = reader.block(&cfg)?;
+                    cfg.enter_block(block);
+                }
+                7 => {
+                    let block = reader.block(&cfg)?;
+                    cfg.saturated |= crate::saturating_increase(&mut cfg.blocks[block].hits, 1);
+                }
+                8 => cfg.current_block = reader.block(&cfg)?,
+                9 => {
+                    let tid = reader.optional(EventReader::varint)?;
+                    cfg.switch_thread(thread, tid);
+                    thread = tid;
+                }
+                _ => return Err(reader.invalid("unknown record")),
+            }
+        }
+        // Every thread hands the graph back once it executed, even when the log ends before it did.
+        cfg.switch_thread(thread, None);
+        Ok(cfg)
+    }
+}
+
+/// Reads the fields of the records of an event log, keeping track of where the current record started.
+struct EventReader<R> {
+    reader: BufReader<R>,
+    /// The number of bytes read so far
+    offset: usize,
+    /// The offset the current record started at
+    record: usize,
+}
+
+impl<R: Read> EventReader<R> {
+    /// Reads the tag of the next record, or `None` at the end of the log.
+    fn next_record(&mut self) -> Result<Option<u8>, CFGError> {
+        self.record = self.offset;
+        let mut tag = [0];
+        match self.reader.read(&mut tag)? {
+            0 => Ok(None),
+            _ => {
+                self.offset += 1;
+                Ok(Some(tag[0]))
+            }
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, CFGError> {
+        let mut byte = [0];
+        match self.reader.read_exact(&mut byte) {
+            Ok(()) => {
+                self.offset += 1;
+                Ok(byte[0])
+            }
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Err(CFGError::TruncatedEventLog { offset: self.record }),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, CFGError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.invalid("a number is longer than 64 bits"))
+    }
+
+    fn usize(&mut self) -> Result<usize, CFGError> {
+        let value = self.varint()?;
+        usize::try_from(value).map_err(|_| self.invalid("a number doesn't fit into a position"))
+    }
+
+    /// Reads the position of a block which exists in the graph.
+    fn block<I>(&mut self, cfg: &ControlFlowGraph<I>) -> Result<BlockId, CFGError> {
+        let index = self.usize()?;
+        if index >= cfg.blocks.len() {
+            return Err(self.invalid("the block doesn't exist"));
+        }
+        Ok(BlockId(index))
+    }
+
+    fn string(&mut self) -> Result<String, CFGError> {
+        let len = self.usize()?;
+        let mut bytes = Vec::new();
+        for _ in 0..len {
+            bytes.push(self.byte()?);
+        }
+        String::from_utf8(bytes).map_err(|_| self.invalid("a string isn't UTF-8"))
+    }
+
+    fn optional<T>(&mut self, read: fn(&mut Self) -> Result<T, CFGError>) -> Result<Option<T>, CFGError> {
+        match self.byte()? {
+            0 => Ok(None),
+            _ => read(self).map(Some),
+        }
+    }
+
+    fn instruction<I: From<String>>(&mut self) -> Result<BlockType<I>, CFGError> {
+        let tag = self.byte()?;
+        let name = I::from(self.string()?);
+        match tag {
+            0 => Ok(BlockType::Instruction(name, self.optional(Self::string)?.map(I::from))),
+            1 => {
+                let success = self.varint()?;
+                let jump_type = jump_type_from_tag(self.byte()?).ok_or_else(|| self.invalid("unknown jump type"))?;
+                Ok(BlockType::Jump(name, success, jump_type, self.optional(Self::varint)?))
+            }
+            2 => {
+                let count = self.usize()?;
+                let targets = (0..count).map(|_| self.varint()).collect::<Result<_, _>>()?;
+                Ok(BlockType::Switch(name, targets))
+            }
+            3 => Ok(BlockType::Terminal(name)),
+            _ => Err(self.invalid("unknown instruction type")),
+        }
+    }
+
+    fn invalid(&self, reason: &str) -> CFGError {
+        CFGError::InvalidEventLog { offset: self.record, reason: reason.to_string() }
+    }
+}
+
+fn push_varints(buffer: &mut Vec<u8>, values: &[u64]) {
+    for value in values {
+        push_varint(buffer, *value);
+    }
+}
+
+fn push_string(buffer: &mut Vec<u8>, text: &str) {
+    push_varint(buffer, text.len() as u64);
+    buffer.extend_from_slice(text.as_bytes());
+}
+
+fn push_instruction<I>(buffer: &mut Vec<u8>, instruction: &BlockType<I>, payload: fn(&I) -> String) {
+    match instruction {
+        BlockType::Instruction(name, operand) => {
+            buffer.push(0);
+            push_string(buffer, &payload(name));
+            match operand {
+                Some(operand) => {
+                    buffer.push(1);
+                    push_string(buffer, &payload(operand));
+                }
+                None => buffer.push(0),
+            }
+        }
+        BlockType::Jump(name, success, jump_type, failure) => {
+            buffer.push(1);
+            push_string(buffer, &payload(name));
+            push_varint(buffer, *success);
+            buffer.push(jump_type_tag(*jump_type));
+            match failure {
+                Some(failure) => {
+                    buffer.push(1);
+                    push_varint(buffer, *failure);
+                }
+                None => buffer.push(0),
+            }
+        }
+        BlockType::Switch(name, targets) => {
+            buffer.push(2);
+            push_string(buffer, &payload(name));
+            push_varint(buffer, targets.len() as u64);
+            push_varints(buffer, targets);
+        }
+        BlockType::Terminal(name) => {
+            buffer.push(3);
+            push_string(buffer, &payload(name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use crate::{CfgEventLog, ControlFlowGraph};
+    use crate::types::*;
+
+    /// A writer whose bytes can still be read once the event log owns it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The start, ends, hits, instruction hits, and edges of a block.
+    type BlockSnapshot = (u64, u64, u64, u64, Vec<u64>, Vec<(BlockId, u64, EdgeKind, bool, Option<u64>, Option<u64>)>);
+
+    /// Everything about the blocks which execution decides, in the order the blocks were created.
+    fn snapshot(cfg: &ControlFlowGraph) -> Vec<BlockSnapshot> {
+        cfg.blocks().map(|block| (
+            block.start(),
+            block.end(),
+            block.end_exclusive(),
+            block.hits(),
+            block.instructions().map(|(pc, _)| block.instruction_hits(*pc).unwrap()).collect(),
+            block.edges().map(|edge| (edge.target(), edge.count(), edge.kind(), edge.is_static(), edge.first_seen(), edge.last_seen())).collect(),
+        )).collect()
+    }
+
+    fn logged(run: impl FnOnce(&mut ControlFlowGraph) -> Result<(), CFGError>) -> Result<(ControlFlowGraph, Vec<u8>), CFGError> {
+        let log = Shared::default();
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.set_event_log(CfgEventLog::new(log.clone()));
+        run(&mut cfg)?;
+        cfg.take_event_log().unwrap().into_inner()?;
+        let bytes = log.0.lock().unwrap().clone();
+        Ok((cfg, bytes))
+    }
+
+    #[test]
+    fn replay_round_trip() -> Result<(), CFGError> {
+        let (cfg, log) = logged(|cfg| {
+            for lap in 0..3 {
+                cfg.execute(0, BlockType::Instruction("LDAC".to_string(), Some("A".to_string())))?;
+                cfg.execute_sized(1, BlockType::Instruction("MOV".to_string(), None), 3)?;
+                let branch = if lap == 1 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+                cfg.execute(4, BlockType::Jump("JZ".to_string(), 0x20, branch, Some(5)))?;
+                let pc = if lap == 1 { 0x20 } else { 5 };
+                cfg.execute(pc, BlockType::Jump("CALL".to_string(), 0x40, JumpType::Call, Some(pc + 1)))?;
+                cfg.execute(0x40, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+                cfg.execute_switch(pc + 1, "SWITCH".to_string(), vec![0, 1], if lap == 2 { 1 } else { 0 })?;
+            }
+            // A second thread and a jump into the middle of the loop splitting the entry block.
+            cfg.execute_tid(7, 0x60, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+            cfg.execute_tid(7, 1, BlockType::Instruction("MOV".to_string(), None))?;
+            cfg.preseed_edge(0x40, 0x50)?;
+            cfg.set_current_block_by_address(0x20);
+            Ok(())
+        })?;
+
+        let replayed: ControlFlowGraph = ControlFlowGraph::replay_events(log.as_slice())?;
+        assert!(replayed.eq_with_counts(&cfg));
+        assert_eq!(snapshot(&cfg), snapshot(&replayed));
+        assert_eq!(cfg.to_string(), replayed.to_string());
+        assert_eq!(cfg.current_block_index(), replayed.current_block_index());
+        assert_eq!(cfg.executed_count(), replayed.executed_count());
+        assert_eq!(Some(cfg.starts[&1]), replayed.thread_block_index(7));
+
+        // Every prefix ending between two records replays as well.
+        let (_, first_lap) = logged(|cfg| cfg.execute(0, BlockType::Instruction("LDAC".to_string(), Some("A".to_string()))).map(|_| ()))?;
+        assert!(log.starts_with(&first_lap));
+        let replayed: ControlFlowGraph = ControlFlowGraph::replay_events(first_lap.as_slice())?;
+        assert_eq!(Some(1), replayed.entry().instruction_hits(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interleaved_threads_resume() -> Result<(), CFGError> {
+        let (mut cfg, log) = logged(|cfg| {
+            cfg.execute_tid(1, 0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(1)))?;
+            cfg.execute_tid(2, 0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalNotTaken, Some(1)))?;
+            Ok(())
+        })?;
+        let mut replayed: ControlFlowGraph = ControlFlowGraph::replay_events(log.as_slice())?;
+        assert_eq!((cfg.thread_block_index(1), cfg.thread_block_index(2)), (replayed.thread_block_index(1), replayed.thread_block_index(2)));
+        assert_eq!(cfg.current_block_index(), replayed.current_block_index());
+
+        // Resuming the trace after the replay carries on where each thread was, without edges between them.
+        for cfg in [&mut cfg, &mut replayed] {
+            cfg.execute_tid(2, 1, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            cfg.execute_tid(1, 0x10, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            cfg.execute_tid(1, 0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalNotTaken, Some(1)))?;
+            cfg.execute_tid(1, 1, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+        assert!(replayed.eq_with_counts(&cfg));
+        assert_eq!(snapshot(&cfg), snapshot(&replayed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn thread_changes_are_compact() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.set_event_log(CfgEventLog::new(io::sink()));
+        for pc in 0..3 {
+            cfg.execute_tid(1, pc, BlockType::Instruction("INC".to_string(), None))?;
+        }
+        // The thread is named once and starts in the entry, followed by one record per instruction.
+        assert_eq!(5, cfg.take_event_log().unwrap().records());
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_log() -> Result<(), CFGError> {
+        let (_, log) = logged(|cfg| cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x1000, JumpType::UnconditionalJump, None)).map(|_| ()))?;
+        // The header takes six bytes, followed by the instruction record starting with its tag, block, pc, and size.
+        assert_eq!(b"CFEL\x01\x00\x02\x00\x00\x01", &log[..10]);
+
+        let truncated = &log[..log.len() - 1];
+        let result = ControlFlowGraph::<String>::replay_events(truncated);
+        let Err(error @ CFGError::TruncatedEventLog { offset }) = result else { panic!("expected a truncated log") };
+        // The last record enters the jump target with its tag and position.
+        assert_eq!(log.len() - 2, offset);
+        assert_eq!(format!("The event log ends inside of the record at byte {}.", offset), error.to_string());
+        assert!(matches!(ControlFlowGraph::<String>::replay_events(&log[..3]), Err(CFGError::TruncatedEventLog { offset: 0 })));
+
+        let mut invalid = log.clone();
+        invalid[6] = 0xff;
+        assert!(matches!(ControlFlowGraph::<String>::replay_events(invalid.as_slice()), Err(CFGError::InvalidEventLog { offset: 6, .. })));
+        assert!(matches!(ControlFlowGraph::<String>::replay_events(&b"ELF\x7f\x01\x00"[..]), Err(CFGError::InvalidEventLog { offset: 0, .. })));
+
+        let mut version = log.clone();
+        version[4] = 2;
+        assert!(matches!(ControlFlowGraph::<String>::replay_events(version.as_slice()), Err(CFGError::UnsupportedVersion { version: 2 })));
+
+        Ok(())
+    }
+}
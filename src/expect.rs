@@ -0,0 +1,268 @@
+//! Comparison of a ControlFlowGraph against a concise description of the graph a test expects.
+//!
+//! ```
+//! use ctrl_flow::ControlFlowGraph;
+//! use ctrl_flow::expect::ExpectedCfg;
+//! use ctrl_flow::types::{BlockType, JumpType};
+//!
+//! let mut cfg = ControlFlowGraph::new(0x2);
+//! cfg.execute(0x3, BlockType::Instruction("INC".to_string(), None))?;
+//! cfg.execute(0x4, BlockType::Jump("JMP".to_string(), 0x9, JumpType::UnconditionalJump, None))?;
+//!
+//! let expected = ExpectedCfg::entry(0x2).insn(0x3, "INC").insn(0x4, "JMP").jmp_to_count(0x9, 1)
+//!     .block(0x9);
+//! assert_eq!(Ok(()), cfg.assert_matches(&expected));
+//!
+//! let wrong = ExpectedCfg::entry(0x2).insn(0x3, "INC").insn(0x4, "JMP").jmp_to_count(0x9, 2)
+//!     .block(0x9);
+//! let mismatches = cfg.assert_matches(&wrong).unwrap_err();
+//! assert_eq!("edge 0x2 -> 0x9 count expected 2 got 1", mismatches[0].to_string());
+//! # Ok::<(), ctrl_flow::types::CFGError>(())
+//! ```
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+use crate::ControlFlowGraph;
+use crate::types::BlockType;
+use crate::prelude::*;
+
+/// The blocks, instructions, and edges a ControlFlowGraph is expected to have, built up one block at a time.
+///
+/// The description is of the whole graph: every block and edge which isn't described is a mismatch, and so is every
+/// instruction of a described block which isn't listed. The end, the hits, and the edge counts are only compared when
+/// they were given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedCfg {
+    entry: u64,
+    blocks: BTreeMap<u64, ExpectedBlock>,
+    /// The start of the block which is described by the next calls
+    last: u64,
+}
+
+/// A block of an ExpectedCfg.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ExpectedBlock {
+    end: Option<u64>,
+    hits: Option<u64>,
+    instructions: BTreeMap<u64, String>,
+    /// The count of the edge to each start address, if it is compared
+    edges: BTreeMap<u64, Option<u64>>,
+}
+
+impl ExpectedCfg {
+    /// Starts the description with the entry block at the given address, which the next calls describe.
+    pub fn entry(start: u64) -> Self {
+        ExpectedCfg { entry: start, blocks: BTreeMap::from([(start, ExpectedBlock::default())]), last: start }
+    }
+
+    /// Adds a block starting at the given address, which the next calls describe.
+    ///
+    /// Describing a start address again continues the description of that block.
+    pub fn block(mut self, start: u64) -> Self {
+        self.blocks.entry(start).or_default();
+        self.last = start;
+        self
+    }
+
+    /// Expects the block to end at the given address, which is the address of its last instruction.
+    pub fn ends_at(mut self, end: u64) -> Self {
+        self.described().end = Some(end);
+        self
+    }
+
+    /// Expects the block to be entered the given number of times.
+    pub fn hits(mut self, hits: u64) -> Self {
+        self.described().hits = Some(hits);
+        self
+    }
+
+    /// Expects the block to hold an instruction at the given address, displayed as the given text or named by it.
+    ///
+    /// Naming only the instruction, such as `JZ` for `JZ 0x9 / 0x6 (taken)`, keeps descriptions of jumps short.
+    pub fn insn(mut self, address: u64, text: &str) -> Self {
+        self.described().instructions.insert(address, text.to_string());
+        self
+    }
+
+    /// Expects the block to have an edge to the block starting at the given address, however often it was traversed.
+    pub fn jmp_to(mut self, target: u64) -> Self {
+        self.described().edges.insert(target, None);
+        self
+    }
+
+    /// Expects the block to have an edge to the block starting at the given address which was traversed the given
+    /// number of times.
+    pub fn jmp_to_count(mut self, target: u64, count: u64) -> Self {
+        self.described().edges.insert(target, Some(count));
+        self
+    }
+
+    /// Returns the block which is being described.
+    fn described(&mut self) -> &mut ExpectedBlock {
+        self.blocks.get_mut(&self.last).unwrap()
+    }
+}
+
+/// A difference between a ControlFlowGraph and an ExpectedCfg, naming blocks by their start address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfgMismatch {
+    /// The graph has another entry.
+    Entry { expected: u64, found: u64 },
+    /// No block of the graph starts at the address.
+    MissingBlock { start: u64 },
+    /// The block of the graph wasn't described.
+    UnexpectedBlock { start: u64 },
+    /// The block ends at another address.
+    End { start: u64, expected: u64, found: u64 },
+    /// The block was entered another number of times.
+    Hits { start: u64, expected: u64, found: u64 },
+    /// The block doesn't hold an instruction at the address.
+    MissingInstruction { address: u64, expected: String },
+    /// The block holds an instruction at the address which wasn't described.
+    UnexpectedInstruction { address: u64, found: String },
+    /// The instruction at the address is another one.
+    Instruction { address: u64, expected: String, found: String },
+    /// The block has no edge to the block starting at `to`.
+    MissingEdge { from: u64, to: u64 },
+    /// The block has an edge which wasn't described.
+    UnexpectedEdge { from: u64, to: u64, count: u64 },
+    /// The edge was traversed another number of times.
+    EdgeCount { from: u64, to: u64, expected: u64, found: u64 },
+}
+
+impl fmt::Display for CfgMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfgMismatch::Entry { expected, found } => write!(f, "entry expected at {:#x} got {:#x}", expected, found),
+            CfgMismatch::MissingBlock { start } => write!(f, "missing block at {:#x}", start),
+            CfgMismatch::UnexpectedBlock { start } => write!(f, "unexpected block at {:#x}", start),
+            CfgMismatch::End { start, expected, found } => write!(f, "block {:#x} end expected {:#x} got {:#x}", start, expected, found),
+            CfgMismatch::Hits { start, expected, found } => write!(f, "block {:#x} hits expected {} got {}", start, expected, found),
+            CfgMismatch::MissingInstruction { address, expected } => write!(f, "missing instruction `{}` at {:#x}", expected, address),
+            CfgMismatch::UnexpectedInstruction { address, found } => write!(f, "unexpected instruction `{}` at {:#x}", found, address),
+            CfgMismatch::Instruction { address, expected, found } => write!(f, "instruction at {:#x} expected `{}` got `{}`", address, expected, found),
+            CfgMismatch::MissingEdge { from, to } => write!(f, "missing edge {:#x} -> {:#x}", from, to),
+            CfgMismatch::UnexpectedEdge { from, to, count } => write!(f, "unexpected edge {:#x} -> {:#x} with count {}", from, to, count),
+            CfgMismatch::EdgeCount { from, to, expected, found } => write!(f, "edge {:#x} -> {:#x} count expected {} got {}", from, to, expected, found),
+        }
+    }
+}
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Compares the ControlFlowGraph against the expected description, returning every mismatch in order of the start
+    /// address of the block it concerns.
+    ///
+    /// The mismatches of a block which is missing from the graph aren't reported beyond the missing block itself.
+    pub fn assert_matches(&self, expected: &ExpectedCfg) -> Result<(), Vec<CfgMismatch>> {
+        let mut mismatches = Vec::new();
+        if self.entry().start != expected.entry {
+            mismatches.push(CfgMismatch::Entry { expected: expected.entry, found: self.entry().start });
+        }
+
+        let starts: BTreeSet<u64> = self.starts.keys().chain(expected.blocks.keys()).copied().collect();
+        for start in starts {
+            let (block, described) = match (self.starts.get(&start), expected.blocks.get(&start)) {
+                (Some(&index), Some(described)) => (&self.blocks[index], described),
+                (Some(_), None) => {
+                    mismatches.push(CfgMismatch::UnexpectedBlock { start });
+                    continue;
+                }
+                (None, _) => {
+                    mismatches.push(CfgMismatch::MissingBlock { start });
+                    continue;
+                }
+            };
+            if let Some(end) = described.end.filter(|end| *end != block.end) {
+                mismatches.push(CfgMismatch::End { start, expected: end, found: block.end });
+            }
+            if let Some(hits) = described.hits.filter(|hits| *hits != block.hits) {
+                mismatches.push(CfgMismatch::Hits { start, expected: hits, found: block.hits });
+            }
+
+            let addresses: BTreeSet<u64> = block.block.keys().chain(described.instructions.keys()).copied().collect();
+            for address in addresses {
+                match (block.block.get(&address), described.instructions.get(&address)) {
+                    (Some(found), Some(expected)) if matches_text(found, expected) => {}
+                    (Some(found), Some(expected)) => mismatches.push(CfgMismatch::Instruction { address, expected: expected.clone(), found: found.to_string() }),
+                    (Some(found), None) => mismatches.push(CfgMismatch::UnexpectedInstruction { address, found: found.to_string() }),
+                    (None, Some(expected)) => mismatches.push(CfgMismatch::MissingInstruction { address, expected: expected.clone() }),
+                    (None, None) => {}
+                }
+            }
+
+            let edges: BTreeMap<u64, u64> = block.edges.iter().map(|edge| (self.blocks[edge.target()].start, edge.count())).collect();
+            let targets: BTreeSet<u64> = edges.keys().chain(described.edges.keys()).copied().collect();
+            for to in targets {
+                match (edges.get(&to), described.edges.get(&to)) {
+                    (Some(&found), Some(&Some(expected))) if found != expected => mismatches.push(CfgMismatch::EdgeCount { from: start, to, expected, found }),
+                    (Some(_), Some(_)) => {}
+                    (Some(&count), None) => mismatches.push(CfgMismatch::UnexpectedEdge { from: start, to, count }),
+                    (None, _) => mismatches.push(CfgMismatch::MissingEdge { from: start, to }),
+                }
+            }
+        }
+
+        if mismatches.is_empty() { Ok(()) } else { Err(mismatches) }
+    }
+}
+
+/// Returns whether the instruction is displayed as the text, or its name is.
+fn matches_text<I: fmt::Display>(instruction: &BlockType<I>, text: &str) -> bool {
+    let name = match instruction {
+        BlockType::Instruction(name, _) | BlockType::Jump(name, _, _, _) | BlockType::Switch(name, _) | BlockType::Terminal(name) => name,
+    };
+    instruction.to_string() == text || name.to_string() == text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    #[test]
+    fn matching_loop() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("DEC".to_string(), Some("R1".to_string())))?;
+        cfg.execute(1, BlockType::Jump("JNZ".to_string(), 0, JumpType::ConditionalTaken, Some(2)))?;
+        cfg.execute(0, BlockType::Instruction("DEC".to_string(), Some("R1".to_string())))?;
+        cfg.execute(1, BlockType::Jump("JNZ".to_string(), 0, JumpType::ConditionalNotTaken, Some(2)))?;
+
+        let expected = ExpectedCfg::entry(0).ends_at(1).hits(2).insn(0, "DEC R1").insn(1, "JNZ").jmp_to_count(0, 1).jmp_to_count(2, 1)
+            .block(2).hits(1);
+        assert_eq!(Ok(()), cfg.assert_matches(&expected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reported_mismatches() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(9, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        cfg.execute(4, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+
+        let expected = ExpectedCfg::entry(2).ends_at(5).insn(3, "DEC").insn(5, "NOP").jmp_to_count(9, 1).jmp_to(0x10)
+            .block(9).hits(1).insn(9, "JMP")
+            .block(0x10);
+        let mismatches: Vec<String> = cfg.assert_matches(&expected).unwrap_err().iter().map(CfgMismatch::to_string).collect();
+        assert_eq!(vec![
+            "block 0x2 end expected 0x5 got 0x4",
+            "instruction at 0x3 expected `DEC` got `INC`",
+            "unexpected instruction `JZ 0x9 / 0x6 (taken)` at 0x4",
+            "missing instruction `NOP` at 0x5",
+            "unexpected edge 0x2 -> 0x6 with count 0",
+            "edge 0x2 -> 0x9 count expected 1 got 2",
+            "missing edge 0x2 -> 0x10",
+            "unexpected block at 0x6",
+            "block 0x9 hits expected 1 got 2",
+            "unexpected edge 0x9 -> 0x2 with count 1",
+            "missing block at 0x10",
+        ], mismatches);
+
+        let other_entry = ExpectedCfg::entry(0).block(2);
+        assert_eq!(Some(&CfgMismatch::Entry { expected: 0, found: 2 }), cfg.assert_matches(&other_entry).unwrap_err().first());
+
+        Ok(())
+    }
+}
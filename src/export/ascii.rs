@@ -0,0 +1,220 @@
+use core::fmt::Write;
+use alloc::collections::BTreeMap;
+use crate::ControlFlowGraph;
+use crate::prelude::*;
+
+/// The number of blocks [render_ascii](ControlFlowGraph::render_ascii) draws before the drawing is cut off.
+const ASCII_BLOCK_LIMIT: usize = 50;
+
+/// An edge which isn't drawn straight down to the block below its source, and so is routed through a lane to the left
+/// of the blocks.
+///
+/// Rows are named by the position of their block in the drawing and a slot, which is zero for the row with the label of
+/// the block and one past the position of the edge in its source for the row the edge leaves from.
+struct Route {
+    source: usize,
+    slot: usize,
+    target: usize,
+    count: u64,
+    lane: usize,
+}
+
+impl Route {
+    /// Returns the topmost and the bottommost row the lane of the edge runs through.
+    fn span(&self) -> ((usize, usize), (usize, usize)) {
+        let departure = (self.source, self.slot);
+        let arrival = (self.target, 0);
+        (departure.min(arrival), departure.max(arrival))
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Draws the ControlFlowGraph as text, for terminals without Graphviz.
+    ///
+    /// The blocks are boxed address ranges from top to bottom in reverse post-order. An edge to the block right below
+    /// its source is drawn straight down with `|` and `v`, and every other edge runs through a lane to the left of the
+    /// blocks which leaves below its source with `+` and enters its target with `\->`, or `/->` from below. Counts are
+    /// written in brackets. Edges which don't fit into the given width, in characters, are written as `-> 0xADDR [count]`
+    /// next to their source instead. Only the first 50 blocks are drawn, followed by a note of how many were left out.
+    pub fn render_ascii(&self, width: usize) -> String {
+        let order = self.reverse_post_order(false, true);
+        let shown = &order[..order.len().min(ASCII_BLOCK_LIMIT)];
+        let mut positions = vec![None; self.blocks.len()];
+        for (position, block) in shown.iter().enumerate() {
+            positions[block.index()] = Some(position);
+        }
+
+        let labels: Vec<String> = shown.iter().map(|block| format!("{:#x} - {:#x}", self.blocks[*block].start, self.blocks[*block].end)).collect();
+        let box_width = labels.iter().map(String::len).max().unwrap_or(0) + 4;
+        let max_lanes = width.saturating_sub(box_width + 1) / 2;
+
+        let mut straight = vec![None; shown.len()];
+        let mut pending = Vec::new();
+        let mut fallbacks: Vec<Vec<(usize, String)>> = vec![Vec::new(); shown.len()];
+        for (position, block) in shown.iter().enumerate() {
+            for (slot, edge) in self.blocks[*block].edges.iter().enumerate() {
+                match positions[edge.target().index()] {
+                    Some(target) if target == position + 1 => straight[position] = Some(edge.count()),
+                    Some(target) => pending.push(Route { source: position, slot: slot + 1, target, count: edge.count(), lane: 0 }),
+                    None => fallbacks[position].push((slot, self.fallback(edge.target().index(), edge.count()))),
+                }
+            }
+        }
+
+        // Each lane holds the bottommost row it is taken up to, and edges take the innermost lane which is free.
+        pending.sort_by_key(|route| route.span());
+        let mut lanes: Vec<(usize, usize)> = Vec::new();
+        let mut routes = Vec::new();
+        for mut route in pending {
+            let (top, bottom) = route.span();
+            match lanes.iter().position(|taken| *taken < top) {
+                Some(lane) => route.lane = lane,
+                None if lanes.len() < max_lanes => {
+                    route.lane = lanes.len();
+                    lanes.push(bottom);
+                }
+                None => {
+                    let text = self.fallback(shown[route.target].index(), route.count);
+                    fallbacks[route.source].push((route.slot - 1, text));
+                    continue;
+                }
+            }
+            lanes[route.lane] = bottom;
+            routes.push(route);
+        }
+
+        let left = if lanes.is_empty() { 0 } else { 2 * lanes.len() + 1 };
+        let center = left + box_width / 2;
+        let border = format!("+{}+", "-".repeat(box_width - 2));
+        let mut grid: Vec<Vec<char>> = Vec::new();
+        let mut rows = BTreeMap::new();
+        for (position, label) in labels.iter().enumerate() {
+            grid.push(row(left, &border));
+            rows.insert((position, 0), grid.len());
+            let mut text = format!("| {:<width$} |", label, width = box_width - 4);
+            fallbacks[position].sort();
+            for (_, fallback) in fallbacks[position].iter() {
+                write!(text, " {}", fallback).unwrap();
+            }
+            grid.push(row(left, &text));
+            grid.push(row(left, &border));
+
+            let trunk = if straight[position].is_some() { "|" } else { "" };
+            let mut slots: Vec<usize> = routes.iter().filter(|route| route.source == position).map(|route| route.slot).collect();
+            slots.sort();
+            for slot in slots {
+                rows.insert((position, slot), grid.len());
+                grid.push(row(center, trunk));
+            }
+            if position + 1 < shown.len() {
+                match straight[position] {
+                    Some(count) => {
+                        grid.push(row(center, &format!("| [{}]", count)));
+                        grid.push(row(center, "v"));
+                    }
+                    None => grid.push(Vec::new()),
+                }
+            }
+        }
+
+        // Crossing lanes are drawn over the horizontal parts of edges, and the ends of each edge over both.
+        for route in routes.iter() {
+            let column = 2 * (lanes.len() - 1 - route.lane);
+            let (departure, arrival) = (rows[&(route.source, route.slot)], rows[&(route.target, 0)]);
+            for x in column + 1..left {
+                draw(&mut grid[departure], x, '-', false);
+                draw(&mut grid[arrival], x, if x + 1 == left { '>' } else { '-' }, false);
+            }
+            let count = format!("[{}]", route.count);
+            let start = if straight[route.source].is_some() && left + count.len() >= center { center + 2 } else { left };
+            for (x, c) in count.chars().enumerate() {
+                draw(&mut grid[departure], start + x, c, false);
+            }
+        }
+        for route in routes.iter() {
+            let column = 2 * (lanes.len() - 1 - route.lane);
+            let (departure, arrival) = (rows[&(route.source, route.slot)], rows[&(route.target, 0)]);
+            for row in grid[departure.min(arrival) + 1..departure.max(arrival)].iter_mut() {
+                draw(row, column, '|', true);
+            }
+        }
+        for route in routes.iter() {
+            let column = 2 * (lanes.len() - 1 - route.lane);
+            let (departure, arrival) = (rows[&(route.source, route.slot)], rows[&(route.target, 0)]);
+            draw(&mut grid[departure], column, '+', true);
+            draw(&mut grid[arrival], column, if arrival > departure { '\\' } else { '/' }, true);
+        }
+
+        let mut ascii = String::new();
+        for line in grid {
+            let line: String = line.into_iter().collect();
+            ascii.push_str(line.trim_end());
+            ascii.push('\n');
+        }
+        if order.len() > shown.len() {
+            writeln!(ascii, "... {} more blocks not drawn", order.len() - shown.len()).unwrap();
+        }
+        ascii
+    }
+
+    /// Returns the annotation of an edge to the block at the given index which couldn't be routed.
+    fn fallback(&self, target: usize, count: u64) -> String {
+        format!("-> {:#x} [{}]", self.blocks[target].start, count)
+    }
+}
+
+/// Returns a row of the drawing holding the text at the given column.
+fn row(column: usize, text: &str) -> Vec<char> {
+    let mut row = vec![' '; column];
+    row.extend(text.chars());
+    row
+}
+
+/// Draws the character at the given column of the row, over what is already drawn there if `over` is set.
+fn draw(row: &mut Vec<char>, column: usize, c: char, over: bool) {
+    if row.len() <= column {
+        row.resize(column + 1, ' ');
+    }
+    if over || row[column] == ' ' {
+        row[column] = c;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_ascii() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("A".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        assert_eq!(include_str!("testdata/conditional.ascii"), cfg.render_ascii(80));
+        // Without the room for a lane, the edge which isn't drawn straight down is written next to its source.
+        assert!(cfg.render_ascii(0).starts_with("+-----------+\n| 0x2 - 0x5 | -> 0x6 [0]\n+-----------+\n      | [1]\n      v\n"));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn loop_trace_ascii() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x1000);
+        cfg.ingest(include_str!("../../tests/testdata/loop.trace").as_bytes())?;
+        assert_eq!(include_str!("testdata/loop.ascii"), cfg.render_ascii(80));
+
+        // Graphs with more blocks than are drawn end in a note.
+        let mut chain = ControlFlowGraph::new(0);
+        for pc in 0..60 {
+            chain.execute(pc, BlockType::Jump("JMP".to_string(), pc + 1, JumpType::UnconditionalJump, None))?;
+        }
+        let drawing = chain.render_ascii(80);
+        assert!(drawing.ends_with("| 0x31 - 0x31 | -> 0x32 [1]\n+-------------+\n... 11 more blocks not drawn\n"));
+
+        Ok(())
+    }
+}
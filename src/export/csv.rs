@@ -0,0 +1,88 @@
+use core::fmt;
+use std::io::{self, Write};
+use crate::ControlFlowGraph;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Writes the edges as CSV rows of `src_start,dst_start,count`, sorted by the source and then the target.
+    ///
+    /// Addresses are written in hex with `hex`, otherwise in decimal.
+    pub fn write_edges_csv<W: Write>(&self, mut writer: W, hex: bool) -> io::Result<()> {
+        writeln!(writer, "src_start,dst_start,count")?;
+        for ((from, to), count) in self.edge_map() {
+            writeln!(writer, "{},{},{}", address(from, hex), address(to, hex), count)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the blocks as CSV rows of `start,end,instruction_count,first_instruction`, sorted by the start address.
+    ///
+    /// Addresses are written in hex with `hex`, otherwise in decimal.
+    pub fn write_blocks_csv<W: Write>(&self, mut writer: W, hex: bool) -> io::Result<()> {
+        writeln!(writer, "start,end,instruction_count,first_instruction")?;
+        for index in self.starts.values() {
+            let block = &self.blocks[*index];
+            let first = block.instructions().next().map(|(_, instruction)| quote(&instruction.to_string())).unwrap_or_default();
+            writeln!(writer, "{},{},{},{}", address(block.start, hex), address(block.end, hex), block.len(), first)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the address in hex or decimal.
+fn address(address: u64, hex: bool) -> String {
+    if hex { format!("{:#x}", address) } else { address.to_string() }
+}
+
+/// Quotes the field if it contains characters which are special to CSV.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    fn conditional_jump() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("LDAC".to_string(), Some("A, \"B\"".to_string())))?;
+        cfg.execute(4, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn edges_csv() -> Result<(), CFGError> {
+        let cfg = conditional_jump()?;
+        let mut csv = Vec::new();
+        cfg.write_edges_csv(&mut csv, false)?;
+        assert_eq!("src_start,dst_start,count\n2,6,0\n2,9,1\n", String::from_utf8(csv).unwrap());
+
+        let mut csv = Vec::new();
+        cfg.write_edges_csv(&mut csv, true)?;
+        assert_eq!("src_start,dst_start,count\n0x2,0x6,0\n0x2,0x9,1\n", String::from_utf8(csv).unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocks_csv() -> Result<(), CFGError> {
+        let cfg = conditional_jump()?;
+        let mut csv = Vec::new();
+        cfg.write_blocks_csv(&mut csv, true)?;
+        let expected = "\
+start,end,instruction_count,first_instruction
+0x2,0x5,3,\"LDAC A, \"\"B\"\"\"
+0x6,0x6,0,
+0x9,0xa,1,INC
+";
+        assert_eq!(expected, String::from_utf8(csv).unwrap());
+
+        Ok(())
+    }
+}
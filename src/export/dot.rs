@@ -0,0 +1,485 @@
+use core::fmt::{self, Write};
+use crate::ControlFlowGraph;
+use crate::types::{AddressFormat, EdgeKind};
+use crate::prelude::*;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Renders the ControlFlowGraph in the Graphviz DOT format.
+    ///
+    /// Each BasicBlock becomes a node labeled with its instructions in address order, along with the sizes of those
+    /// longer than a byte, and each edge is labeled with its traversal count. Edges are colored by their kind, those
+    /// which were never traversed are dashed, and the entry block is filled.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with_options(&DotOptions::default())
+    }
+
+    /// Renders the ControlFlowGraph in the Graphviz DOT format like [to_dot](Self::to_dot), with every address written
+    /// in the given format.
+    pub fn to_dot_with_format(&self, format: AddressFormat) -> String {
+        self.to_dot_with_options(&DotOptions { address_format: format, ..DotOptions::default() })
+    }
+
+    /// Renders the ControlFlowGraph in the Graphviz DOT format like [to_dot](Self::to_dot), with the blocks of every
+    /// function grouped into a `cluster_fn_<entry>` subgraph labeled with the entry address of the function.
+    ///
+    /// See [call_graph](Self::call_graph) for how blocks are assigned to functions, blocks which belong to no function
+    /// are left outside of the clusters.
+    pub fn to_dot_clustered(&self) -> String {
+        self.to_dot_with_options(&DotOptions { clustered: true, ..DotOptions::default() })
+    }
+
+    /// Renders the ControlFlowGraph in the Graphviz DOT format like [to_dot](Self::to_dot), with the value of the
+    /// given metadata key added to the label of every block which has it.
+    pub fn to_dot_with_meta(&self, key: &str) -> String {
+        self.to_dot_with_options(&DotOptions { meta_key: Some(key.to_string()), ..DotOptions::default() })
+    }
+
+    /// Renders the ControlFlowGraph in the Graphviz DOT format as described by the given DotOptions.
+    ///
+    /// The default DotOptions render exactly what [to_dot](Self::to_dot) does. Only the output of the default heat and
+    /// components options can be read back by `from_dot`, as their colors aren't colors of an edge kind and every block
+    /// is filled. The addresses in the labels are named by the Symbolizer of the graph or its modules, if any.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::export::{DotOptions, HeatScale, RankDir};
+    ///
+    /// let cfg = ControlFlowGraph::<String>::new(0);
+    /// let options = DotOptions { heat: Some(HeatScale::Log), rank_dir: Some(RankDir::LeftRight), ..DotOptions::default() };
+    /// assert!(cfg.to_dot_with_options(&options).contains("rankdir=LR;"));
+    /// ```
+    pub fn to_dot_with_options(&self, options: &DotOptions) -> String {
+        let format = options.address_format;
+        let address = |address: u64| format.address(address);
+        let label = |address: u64| escape(&self.symbolized(address, format).to_string());
+        let names = self.names();
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        if let Some(rank_dir) = options.rank_dir {
+            writeln!(dot, "    rankdir={};", rank_dir.as_str()).unwrap();
+        }
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        let max_hits = self.blocks.iter().map(|block| block.hits).max().unwrap_or(0);
+        let mut component_of = vec![0; self.blocks.len()];
+        if options.components {
+            for (component, blocks) in self.weakly_connected_components().into_iter().enumerate() {
+                blocks.into_iter().for_each(|block| component_of[block.index()] = component);
+            }
+        }
+        let mut nodes = Vec::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let mut text = format!("{} - {}\\l", label(block.start), label(block.end));
+            if let Some((key, value)) = options.meta_key.as_deref().and_then(|key| block.get_meta(key).map(|value| (key, value))) {
+                write!(text, "{}: {}\\l", escape(key), escape(value)).unwrap();
+            }
+            if options.instructions {
+                for (pc, instruction) in block.instructions() {
+                    // Instructions are a byte long unless they say otherwise.
+                    let size = match block.instruction_size(*pc) {
+                        Some(size) if size > 1 => format!(" ({} bytes)", size),
+                        _ => String::new(),
+                    };
+                    write!(text, "{}{}: {}\\l", label(*pc), size, escape(&instruction.display_with(format, names).to_string())).unwrap();
+                }
+            }
+
+            let style = match options.heat {
+                // The entry is marked by a double border instead, as every block is filled.
+                Some(scale) => {
+                    let border = if index == 0 { ", peripheries=2" } else { "" };
+                    format!(", style=filled, fillcolor=\"0.000 {:.3} 1.000\"{}", scale.heat(block.hits, max_hits), border)
+                }
+                None if options.components => {
+                    let border = if index == 0 { ", peripheries=2" } else { "" };
+                    format!(", style=filled, fillcolor={}{}", COMPONENT_COLORS[component_of[index] % COMPONENT_COLORS.len()], border)
+                }
+                None if index == 0 => ", style=filled, fillcolor=lightgrey".to_string(),
+                None => String::new(),
+            };
+            nodes.push(format!("\"{}\" [label=\"{}\"{}];", address(block.start), text, style));
+        }
+
+        if options.module_clusters {
+            let mut outside = vec![true; nodes.len()];
+            for (position, module) in self.modules.iter().enumerate() {
+                writeln!(dot, "    subgraph cluster_mod_{} {{", position).unwrap();
+                writeln!(dot, "        label=\"{}\";", escape(module.name())).unwrap();
+                for (index, _) in self.blocks.iter().enumerate().filter(|(_, block)| module.contains(block.start)) {
+                    writeln!(dot, "        {}", nodes[index]).unwrap();
+                    outside[index] = false;
+                }
+                writeln!(dot, "    }}").unwrap();
+            }
+            for (node, _) in nodes.iter().zip(outside).filter(|(_, outside)| *outside) {
+                writeln!(dot, "    {}", node).unwrap();
+            }
+        } else if options.clustered {
+            let function_of = self.function_of();
+            for (entry, blocks) in self.functions() {
+                writeln!(dot, "    subgraph cluster_fn_{} {{", address(entry)).unwrap();
+                writeln!(dot, "        label=\"{}\";", label(entry)).unwrap();
+                for block in blocks {
+                    writeln!(dot, "        {}", nodes[block.index()]).unwrap();
+                }
+                writeln!(dot, "    }}").unwrap();
+            }
+            for (node, _) in nodes.iter().zip(function_of).filter(|(_, function)| function.is_none()) {
+                writeln!(dot, "    {}", node).unwrap();
+            }
+        } else {
+            for node in nodes.iter() {
+                writeln!(dot, "    {}", node).unwrap();
+            }
+        }
+
+        let max_count = self.blocks.iter().flat_map(|block| block.edges.iter()).map(|edge| edge.count()).max().unwrap_or(0);
+        for block in self.blocks.iter() {
+            for edge in block.edges.iter().filter(|edge| options.zero_count_edges || edge.count() > 0) {
+                let target = self.blocks[edge.target()].start;
+                let style = if edge.count() == 0 { ", style=dashed" } else { "" };
+                let color = match options.heat {
+                    // Cold edges are blue and hot edges are red, drawn thicker the hotter they are.
+                    Some(scale) => {
+                        let heat = scale.heat(edge.count(), max_count);
+                        format!(", color=\"{:.3} 1.000 1.000\", penwidth={:.1}", (1.0 - heat) * 0.667, 1.0 + 3.0 * heat)
+                    }
+                    None => edge_color(edge.kind()).map(|color| format!(", color={}", color)).unwrap_or_default(),
+                };
+                let seen = match (options.seen, edge.first_seen(), edge.last_seen()) {
+                    (true, Some(first), Some(last)) => format!(", first_seen={}, last_seen={}", first, last),
+                    _ => String::new(),
+                };
+                writeln!(dot, "    \"{}\" -> \"{}\" [label=\"{}\"{}{}{}];", address(block.start), address(target), edge.count(), style, color, seen).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The options of [to_dot_with_options](ControlFlowGraph::to_dot_with_options), whose default renders the same as `to_dot`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DotOptions {
+    /// The format every address is written in.
+    pub address_format: AddressFormat,
+    /// The metadata key whose value is added to the label of every block which has it.
+    pub meta_key: Option<String>,
+    /// Whether the blocks of every function are grouped into a cluster, like `to_dot_clustered`.
+    pub clustered: bool,
+    /// Whether the blocks of every module are grouped into a `cluster_mod_<position>` subgraph labeled with its name,
+    /// which takes precedence over `clustered`.
+    pub module_clusters: bool,
+    /// Whether the labels of the blocks list their instructions, or only hold their address range.
+    pub instructions: bool,
+    /// Whether the edges which were never traversed are drawn.
+    pub zero_count_edges: bool,
+    /// The direction the graph is laid out in, left to Graphviz when `None`.
+    pub rank_dir: Option<RankDir>,
+    /// The scale blocks and edges are colored on by their hit and traversal counts, replacing the colors of the entry
+    /// and of the edge kinds. Blocks are filled from white to red and edges go from blue to red.
+    pub heat: Option<HeatScale>,
+    /// Whether the blocks of every weakly connected component are filled with a color of their own, which shows the
+    /// islands not connected to the entry. The entry is marked by a double border instead, and `heat` takes precedence.
+    pub components: bool,
+    /// Whether every traversed edge has `first_seen` and `last_seen` attributes holding the ordinals of the instructions
+    /// which first and last traversed it, which Graphviz ignores.
+    pub seen: bool,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        DotOptions { address_format: AddressFormat::Hex, meta_key: None, clustered: false, module_clusters: false, instructions: true, zero_count_edges: true, rank_dir: None, heat: None, components: false, seen: false }
+    }
+}
+
+/// How a count is placed on the heat scale between zero and the highest count of the graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeatScale {
+    /// The heat grows with the count itself.
+    Linear,
+    /// The heat grows with the logarithm of the count, which keeps counts far below the highest one visible.
+    Log,
+}
+
+impl HeatScale {
+    /// Returns the heat of the count between zero and one, relative to the highest count.
+    fn heat(self, count: u64, max: u64) -> f64 {
+        if max == 0 {
+            return 0.0;
+        }
+        match self {
+            HeatScale::Linear => count as f64 / max as f64,
+            HeatScale::Log => log2(count.saturating_add(1)) / log2(max.saturating_add(1)),
+        }
+    }
+}
+
+/// Approximates the base 2 logarithm of a non-zero value by interpolating linearly between powers of two, which is
+/// close enough for a color while not requiring the standard library.
+fn log2(value: u64) -> f64 {
+    let whole = value.ilog2();
+    whole as f64 + (value as f64 / (1u64 << whole) as f64 - 1.0)
+}
+
+/// The direction of the ranks of a DOT graph, its `rankdir` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RankDir {
+    /// From the top to the bottom, the default of Graphviz.
+    TopBottom,
+    /// From the left to the right.
+    LeftRight,
+    /// From the bottom to the top.
+    BottomTop,
+    /// From the right to the left.
+    RightLeft,
+}
+
+impl RankDir {
+    /// Returns the value of the `rankdir` attribute.
+    fn as_str(self) -> &'static str {
+        match self {
+            RankDir::TopBottom => "TB",
+            RankDir::LeftRight => "LR",
+            RankDir::BottomTop => "BT",
+            RankDir::RightLeft => "RL",
+        }
+    }
+}
+
+/// The color of the edges of each kind, unconditional edges keeping the default color.
+pub(crate) const EDGE_COLORS: [(EdgeKind, &str); 5] =
+    [(EdgeKind::Taken, "darkgreen"), (EdgeKind::FallThrough, "red"), (EdgeKind::Call, "blue"), (EdgeKind::Return, "purple"), (EdgeKind::Unknown, "orange")];
+
+/// The colors the blocks of each weakly connected component are filled with, repeating after the last one.
+const COMPONENT_COLORS: [&str; 8] = ["lightblue", "palegreen", "lightpink", "khaki", "lightsalmon", "plum", "paleturquoise", "wheat"];
+
+/// Returns the color of the edges of the given kind, if it isn't the default color.
+fn edge_color(kind: EdgeKind) -> Option<&'static str> {
+    EDGE_COLORS.iter().find(|(edge_kind, _)| *edge_kind == kind).map(|(_, color)| *color)
+}
+
+/// Escapes the characters which would otherwise terminate or alter a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_dot() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("\"quoted\"".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        let expected = "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    \"0x2\" [label=\"0x2 - 0x5\\l0x3: INC\\l0x4: LDAC \\\"quoted\\\"\\l0x5: JMP 0x9 / 0x6 (taken)\\l\", style=filled, fillcolor=lightgrey];
+    \"0x6\" [label=\"0x6 - 0x6\\l\"];
+    \"0x9\" [label=\"0x9 - 0xa\\l0xa: INC\\l\"];
+    \"0x2\" -> \"0x6\" [label=\"0\", style=dashed, color=red];
+    \"0x2\" -> \"0x9\" [label=\"1\", color=darkgreen];
+}
+";
+        assert_eq!(expected, cfg.to_dot());
+
+        Ok(())
+    }
+
+    #[test]
+    fn decimal_dot() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute(0x11, BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalTaken, Some(0x12)))?;
+
+        let expected = "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    \"16\" [label=\"16 - 17\\l17: JZ 32 / 18 (taken)\\l\", style=filled, fillcolor=lightgrey];
+    \"18\" [label=\"18 - 18\\l\"];
+    \"32\" [label=\"32 - 32\\l\"];
+    \"16\" -> \"18\" [label=\"0\", style=dashed, color=red];
+    \"16\" -> \"32\" [label=\"1\", color=darkgreen];
+}
+";
+        assert_eq!(expected, cfg.to_dot_with_format(AddressFormat::Decimal));
+        assert_eq!(cfg.to_dot(), cfg.to_dot_with_format(AddressFormat::Hex));
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_and_return_dot() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(2)))?;
+        cfg.execute(0x21, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(3, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+
+        let dot = cfg.to_dot();
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains("->")).collect();
+        assert_eq!(vec![
+            "    \"0x0\" -> \"0x20\" [label=\"1\", color=blue];",
+            "    \"0x20\" -> \"0x2\" [label=\"1\", color=purple];",
+            "    \"0x2\" -> \"0x30\" [label=\"1\"];",
+        ], edges);
+
+        Ok(())
+    }
+
+    #[test]
+    fn clustered_dot() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for _ in 0..3 {
+            cfg.execute(1, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(2)))?;
+            cfg.execute(0x21, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+            cfg.execute(3, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+
+        let expected = "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    subgraph cluster_fn_0x0 {
+        label=\"0x0\";
+        \"0x0\" [label=\"0x0 - 0x1\\l0x1: CALL 0x20 / 0x2 (call)\\l\", style=filled, fillcolor=lightgrey];
+        \"0x2\" [label=\"0x2 - 0x3\\l0x3: JMP 0x0 (unconditional)\\l\"];
+    }
+    subgraph cluster_fn_0x20 {
+        label=\"0x20\";
+        \"0x20\" [label=\"0x20 - 0x21\\l0x21: RET (return)\\l\"];
+    }
+    \"0x0\" -> \"0x20\" [label=\"3\", color=blue];
+    \"0x20\" -> \"0x2\" [label=\"3\", color=purple];
+    \"0x2\" -> \"0x0\" [label=\"3\"];
+}
+";
+        assert_eq!(expected, cfg.to_dot_clustered());
+        assert!(ControlFlowGraph::from_dot(&cfg.to_dot_clustered())?.eq_with_counts(&cfg));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dot_with_meta() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::UnconditionalJump, None))?;
+        cfg.annotate(BlockId(0), "function", "main")?;
+        cfg.annotate(BlockId(1), "function", "helper")?;
+        cfg.annotate(BlockId(1), "color", "red")?;
+        assert!(cfg.annotate(BlockId(2), "function", "missing").is_err());
+
+        let dot = cfg.to_dot_with_meta("function");
+        assert!(dot.contains("label=\"0x2 - 0x5\\lfunction: main\\l0x3: INC\\l"));
+        assert!(dot.contains("label=\"0x9 - 0x9\\lfunction: helper\\l\""));
+        assert!(!dot.contains("red"));
+        assert_eq!(cfg.to_dot(), cfg.to_dot_with_meta("missing"));
+
+        let block = cfg.block(BlockId(1)).unwrap();
+        assert_eq!(Some("red"), block.get_meta("color"));
+        assert_eq!(vec![("color", "red"), ("function", "helper")], block.meta_iter().collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn dot_with_options() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for lap in 0..3 {
+            cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+            let exit = if lap == 2 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(2, BlockType::Jump("JNZ".to_string(), 0x10, exit, Some(3)))?;
+            if lap < 2 {
+                cfg.execute(3, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            }
+        }
+        cfg.execute(0x10, BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalNotTaken, Some(0x11)))?;
+        assert_eq!(cfg.to_dot(), cfg.to_dot_with_options(&DotOptions::default()));
+
+        let heat = DotOptions { heat: Some(HeatScale::Linear), ..DotOptions::default() };
+        let expected = "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    \"0x0\" [label=\"0x0 - 0x2\\l0x1: INC\\l0x2: JNZ 0x10 / 0x3 (not taken)\\l\", style=filled, fillcolor=\"0.000 1.000 1.000\", peripheries=2];
+    \"0x3\" [label=\"0x3 - 0x3\\l0x3: JMP 0x0 (unconditional)\\l\", style=filled, fillcolor=\"0.000 0.667 1.000\"];
+    \"0x10\" [label=\"0x10 - 0x10\\l0x10: JZ 0x20 / 0x11 (not taken)\\l\", style=filled, fillcolor=\"0.000 0.333 1.000\"];
+    \"0x11\" [label=\"0x11 - 0x11\\l\", style=filled, fillcolor=\"0.000 0.333 1.000\"];
+    \"0x20\" [label=\"0x20 - 0x20\\l\", style=filled, fillcolor=\"0.000 0.000 1.000\"];
+    \"0x0\" -> \"0x3\" [label=\"2\", color=\"0.000 1.000 1.000\", penwidth=4.0];
+    \"0x0\" -> \"0x10\" [label=\"1\", color=\"0.334 1.000 1.000\", penwidth=2.5];
+    \"0x3\" -> \"0x0\" [label=\"2\", color=\"0.000 1.000 1.000\", penwidth=4.0];
+    \"0x10\" -> \"0x11\" [label=\"1\", color=\"0.334 1.000 1.000\", penwidth=2.5];
+    \"0x10\" -> \"0x20\" [label=\"0\", style=dashed, color=\"0.667 1.000 1.000\", penwidth=1.0];
+}
+";
+        assert_eq!(expected, cfg.to_dot_with_options(&heat));
+
+        let compact = DotOptions { instructions: false, zero_count_edges: false, rank_dir: Some(RankDir::LeftRight), heat: Some(HeatScale::Log), ..DotOptions::default() };
+        let expected = "\
+digraph cfg {
+    rankdir=LR;
+    node [shape=box, fontname=\"monospace\"];
+    \"0x0\" [label=\"0x0 - 0x2\\l\", style=filled, fillcolor=\"0.000 1.000 1.000\", peripheries=2];
+    \"0x3\" [label=\"0x3 - 0x3\\l\", style=filled, fillcolor=\"0.000 0.750 1.000\"];
+    \"0x10\" [label=\"0x10 - 0x10\\l\", style=filled, fillcolor=\"0.000 0.500 1.000\"];
+    \"0x11\" [label=\"0x11 - 0x11\\l\", style=filled, fillcolor=\"0.000 0.500 1.000\"];
+    \"0x20\" [label=\"0x20 - 0x20\\l\", style=filled, fillcolor=\"0.000 0.000 1.000\"];
+    \"0x0\" -> \"0x3\" [label=\"2\", color=\"0.000 1.000 1.000\", penwidth=4.0];
+    \"0x0\" -> \"0x10\" [label=\"1\", color=\"0.222 1.000 1.000\", penwidth=3.0];
+    \"0x3\" -> \"0x0\" [label=\"2\", color=\"0.000 1.000 1.000\", penwidth=4.0];
+    \"0x10\" -> \"0x11\" [label=\"1\", color=\"0.222 1.000 1.000\", penwidth=3.0];
+}
+";
+        assert_eq!(expected, cfg.to_dot_with_options(&compact));
+
+        Ok(())
+    }
+
+    #[test]
+    fn component_dot() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        let mut other = ControlFlowGraph::new(0);
+        other.set_current_block_by_address(0x20);
+        other.execute(0x20, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+        cfg.merge(&other)?;
+
+        let options = DotOptions { instructions: false, components: true, ..DotOptions::default() };
+        let expected = "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    \"0x0\" [label=\"0x0 - 0x0\\l\", style=filled, fillcolor=lightblue, peripheries=2];
+    \"0x10\" [label=\"0x10 - 0x10\\l\", style=filled, fillcolor=lightblue];
+    \"0x20\" [label=\"0x20 - 0x20\\l\", style=filled, fillcolor=palegreen];
+    \"0x30\" [label=\"0x30 - 0x30\\l\", style=filled, fillcolor=palegreen];
+    \"0x0\" -> \"0x10\" [label=\"1\"];
+    \"0x20\" -> \"0x30\" [label=\"1\"];
+}
+";
+        assert_eq!(expected, cfg.to_dot_with_options(&options));
+        let heat = DotOptions { heat: Some(HeatScale::Linear), ..options.clone() };
+        assert_eq!(cfg.to_dot_with_options(&DotOptions { components: false, ..heat.clone() }), cfg.to_dot_with_options(&heat));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dot_is_deterministic() -> Result<(), CFGError> {
+        let build = || -> Result<String, CFGError> {
+            let mut cfg = ControlFlowGraph::new(0);
+            for pc in (1..40).rev() {
+                cfg.execute(pc, BlockType::Instruction(format!("OP{}", pc), None))?;
+            }
+            cfg.execute(40, BlockType::Jump("JMP".to_string(), 20, JumpType::ConditionalNotTaken, Some(41)))?;
+            Ok(cfg.to_dot())
+        };
+        assert_eq!(build()?, build()?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+use crate::ControlFlowGraph;
+
+impl<I> ControlFlowGraph<I> {
+    /// Writes the executed blocks as a drcov coverage file, as loaded by Lighthouse and Lightkeeper.
+    ///
+    /// The file has a single module with the given name loaded at the given base, and one basic block record for every
+    /// block which was executed, sorted by address. A block spans from its start up to its exclusive end, so the size of
+    /// its last instruction is only accounted for when it was executed with `execute_sized`. Blocks below the module
+    /// base or beyond the reach of the 32 bit offsets of drcov are left out, and sizes which overflow 16 bits are
+    /// saturated.
+    pub fn write_drcov<W: Write>(&self, module_name: &str, module_base: u64, mut writer: W) -> io::Result<()> {
+        let records: Vec<(u32, u16)> = self.starts.values()
+            .map(|index| &self.blocks[*index])
+            .filter(|block| block.hits > 0 && !block.block.is_empty() && block.start >= module_base)
+            .filter_map(|block| {
+                let offset = u32::try_from(block.start - module_base).ok()?;
+                let size = u16::try_from(block.len_addresses()).unwrap_or(u16::MAX);
+                Some((offset, size))
+            })
+            .collect();
+        let module_end = records.iter().map(|(offset, size)| module_base.saturating_add(*offset as u64 + *size as u64)).max().unwrap_or(module_base);
+
+        writeln!(writer, "DRCOV VERSION: 2")?;
+        writeln!(writer, "DRCOV FLAVOR: ctrl-flow")?;
+        writeln!(writer, "Module Table: version 2, count 1")?;
+        writeln!(writer, "Columns: id, base, end, entry, checksum, timestamp, path")?;
+        writeln!(writer, " 0, {:#018x}, {:#018x}, 0x0000000000000000, 0x00000000, 0x00000000, {}", module_base, module_end, module_name)?;
+        writeln!(writer, "BB Table: {} bbs", records.len())?;
+        for (offset, size) in records {
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&size.to_le_bytes())?;
+            // Every block belongs to the only module.
+            writer.write_all(&0u16.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn drcov_round_trip() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x401000);
+        cfg.execute(0x401000, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x401004, BlockType::Jump("JZ".to_string(), 0x401020, JumpType::ConditionalTaken, Some(0x401008)))?;
+        cfg.execute(0x401020, BlockType::Jump("JMP".to_string(), 0x401000, JumpType::UnconditionalJump, None))?;
+
+        let mut bytes = Vec::new();
+        cfg.write_drcov("target.bin", 0x400000, &mut bytes)?;
+
+        let marker = b"BB Table: 2 bbs\n";
+        let table = bytes.windows(marker.len()).position(|window| window == marker).unwrap() + marker.len();
+        let header = String::from_utf8(bytes[..table].to_vec()).unwrap();
+        assert!(header.contains(" 0, 0x0000000000400000, 0x0000000000401021, 0x0000000000000000, 0x00000000, 0x00000000, target.bin\n"));
+
+        let records: Vec<(u32, u16, u16)> = bytes[table..].chunks(8).map(|record| (
+            u32::from_le_bytes(record[0..4].try_into().unwrap()),
+            u16::from_le_bytes(record[4..6].try_into().unwrap()),
+            u16::from_le_bytes(record[6..8].try_into().unwrap()),
+        )).collect();
+        assert_eq!(vec![(0x1000, 5, 0), (0x1020, 1, 0)], records);
+
+        Ok(())
+    }
+
+    #[test]
+    fn drcov_sized_blocks() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x401000);
+        cfg.execute_sized(0x401000, BlockType::Instruction("MOV".to_string(), None), 5)?;
+        cfg.execute_sized(0x401005, BlockType::Jump("JMP".to_string(), 0x401020, JumpType::UnconditionalJump, None), 2)?;
+        cfg.execute_sized(0x401020, BlockType::Instruction("RET".to_string(), None), 1)?;
+
+        let mut bytes = Vec::new();
+        cfg.write_drcov("target.bin", 0x400000, &mut bytes)?;
+        let sizes: Vec<u16> = bytes[bytes.len() - 16..].chunks(8).map(|record| u16::from_le_bytes(record[4..6].try_into().unwrap())).collect();
+        assert_eq!(vec![7, 1], sizes);
+
+        Ok(())
+    }
+}
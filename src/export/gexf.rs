@@ -0,0 +1,143 @@
+use core::fmt;
+use std::io::{self, Write};
+use crate::ControlFlowGraph;
+use crate::export::graphml::escape;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Writes the ControlFlowGraph as GEXF, the native format of Gephi.
+    ///
+    /// Each BasicBlock becomes a node identified by its start address, with attributes for its start address, its
+    /// instruction count, and its instructions in address order separated by newlines. Each edge is weighted by its
+    /// traversal count.
+    ///
+    /// When a sequence of entered blocks was recorded, the graph is dynamic and every block in it has the ordinal it
+    /// was first entered at as its start, so that the timeline of Gephi shows the graph growing. Blocks which were
+    /// never entered while recording have no start.
+    pub fn write_gexf<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut discovered = vec![None; self.blocks.len()];
+        let mut ordinal = 0;
+        for block in self.sequence.iter() {
+            if let Some(discovery @ None) = discovered.get_mut(block.index()) {
+                *discovery = Some(ordinal);
+                ordinal += 1;
+            }
+        }
+        let dynamic = !self.sequence.is_empty();
+
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">")?;
+        if dynamic {
+            writeln!(writer, "  <graph mode=\"dynamic\" defaultedgetype=\"directed\" timeformat=\"integer\">")?;
+        } else {
+            writeln!(writer, "  <graph mode=\"static\" defaultedgetype=\"directed\">")?;
+        }
+        writeln!(writer, "    <attributes class=\"node\">")?;
+        writeln!(writer, "      <attribute id=\"start\" title=\"start\" type=\"string\"/>")?;
+        writeln!(writer, "      <attribute id=\"instruction_count\" title=\"instruction_count\" type=\"integer\"/>")?;
+        writeln!(writer, "      <attribute id=\"instructions\" title=\"instructions\" type=\"string\"/>")?;
+        writeln!(writer, "    </attributes>")?;
+
+        writeln!(writer, "    <nodes>")?;
+        for (block, discovery) in self.blocks.iter().zip(discovered) {
+            let instructions: Vec<String> = block.instructions()
+                .map(|(address, instruction)| escape(&format!("{:#x}: {}", address, instruction)))
+                .collect();
+            match discovery {
+                Some(ordinal) => writeln!(writer, "      <node id=\"{:#x}\" label=\"{:#x}\" start=\"{}\">", block.start, block.start, ordinal)?,
+                None => writeln!(writer, "      <node id=\"{:#x}\" label=\"{:#x}\">", block.start, block.start)?,
+            }
+            writeln!(writer, "        <attvalues>")?;
+            writeln!(writer, "          <attvalue for=\"start\" value=\"{:#x}\"/>", block.start)?;
+            writeln!(writer, "          <attvalue for=\"instruction_count\" value=\"{}\"/>", block.len())?;
+            writeln!(writer, "          <attvalue for=\"instructions\" value=\"{}\"/>", instructions.join("&#10;"))?;
+            writeln!(writer, "        </attvalues>")?;
+            writeln!(writer, "      </node>")?;
+        }
+        writeln!(writer, "    </nodes>")?;
+
+        writeln!(writer, "    <edges>")?;
+        let edges = self.blocks.iter().flat_map(|block| block.edges.iter().map(move |edge| (block, edge)));
+        for (id, (block, edge)) in edges.enumerate() {
+            writeln!(writer, "      <edge id=\"{}\" source=\"{:#x}\" target=\"{:#x}\" weight=\"{}\"/>",
+                id, block.start, self.blocks[edge.target()].start, edge.count())?;
+        }
+        writeln!(writer, "    </edges>")?;
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</gexf>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_gexf() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("LDAC".to_string(), Some("<a & 'b'>".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+
+        let mut gexf = Vec::new();
+        cfg.write_gexf(&mut gexf)?;
+
+        let expected = "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">
+  <graph mode=\"static\" defaultedgetype=\"directed\">
+    <attributes class=\"node\">
+      <attribute id=\"start\" title=\"start\" type=\"string\"/>
+      <attribute id=\"instruction_count\" title=\"instruction_count\" type=\"integer\"/>
+      <attribute id=\"instructions\" title=\"instructions\" type=\"string\"/>
+    </attributes>
+    <nodes>
+      <node id=\"0x2\" label=\"0x2\">
+        <attvalues>
+          <attvalue for=\"start\" value=\"0x2\"/>
+          <attvalue for=\"instruction_count\" value=\"2\"/>
+          <attvalue for=\"instructions\" value=\"0x3: LDAC &lt;a &amp; &apos;b&apos;&gt;&#10;0x5: JZ 0x9 / 0x6 (taken)\"/>
+        </attvalues>
+      </node>
+      <node id=\"0x6\" label=\"0x6\">
+        <attvalues>
+          <attvalue for=\"start\" value=\"0x6\"/>
+          <attvalue for=\"instruction_count\" value=\"0\"/>
+          <attvalue for=\"instructions\" value=\"\"/>
+        </attvalues>
+      </node>
+      <node id=\"0x9\" label=\"0x9\">
+        <attvalues>
+          <attvalue for=\"start\" value=\"0x9\"/>
+          <attvalue for=\"instruction_count\" value=\"0\"/>
+          <attvalue for=\"instructions\" value=\"\"/>
+        </attvalues>
+      </node>
+    </nodes>
+    <edges>
+      <edge id=\"0\" source=\"0x2\" target=\"0x6\" weight=\"0\"/>
+      <edge id=\"1\" source=\"0x2\" target=\"0x9\" weight=\"1\"/>
+    </edges>
+  </graph>
+</gexf>
+";
+        assert_eq!(expected, String::from_utf8(gexf).unwrap());
+
+        // With a recorded sequence, every entered block starts at the ordinal it was discovered at.
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.record_sequence(true);
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(9, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+
+        let mut gexf = Vec::new();
+        cfg.write_gexf(&mut gexf)?;
+        let gexf = String::from_utf8(gexf).unwrap();
+        assert!(gexf.contains("  <graph mode=\"dynamic\" defaultedgetype=\"directed\" timeformat=\"integer\">\n"));
+        assert!(gexf.contains("<node id=\"0x2\" label=\"0x2\" start=\"0\">"));
+        assert!(gexf.contains("<node id=\"0x6\" label=\"0x6\">"));
+        assert!(gexf.contains("<node id=\"0x9\" label=\"0x9\" start=\"1\">"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use core::fmt;
+use std::io::{self, Write};
+use crate::ControlFlowGraph;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Writes the ControlFlowGraph as GraphML, which is read by yEd and Gephi.
+    ///
+    /// Each BasicBlock becomes a node identified by its start address, with data for its start and end address, its
+    /// instruction count, and its instructions in address order separated by newlines. Each edge has data for its
+    /// traversal count.
+    pub fn write_graphml<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        writeln!(writer, "  <key id=\"start\" for=\"node\" attr.name=\"start\" attr.type=\"string\"/>")?;
+        writeln!(writer, "  <key id=\"end\" for=\"node\" attr.name=\"end\" attr.type=\"string\"/>")?;
+        writeln!(writer, "  <key id=\"instruction_count\" for=\"node\" attr.name=\"instruction_count\" attr.type=\"int\"/>")?;
+        writeln!(writer, "  <key id=\"instructions\" for=\"node\" attr.name=\"instructions\" attr.type=\"string\"/>")?;
+        writeln!(writer, "  <key id=\"count\" for=\"edge\" attr.name=\"count\" attr.type=\"int\"/>")?;
+        writeln!(writer, "  <graph id=\"cfg\" edgedefault=\"directed\">")?;
+
+        for block in self.blocks.iter() {
+            let instructions: Vec<String> = block.instructions()
+                .map(|(address, instruction)| escape(&format!("{:#x}: {}", address, instruction)))
+                .collect();
+            writeln!(writer, "    <node id=\"{:#x}\">", block.start)?;
+            writeln!(writer, "      <data key=\"start\">{:#x}</data>", block.start)?;
+            writeln!(writer, "      <data key=\"end\">{:#x}</data>", block.end)?;
+            writeln!(writer, "      <data key=\"instruction_count\">{}</data>", block.len())?;
+            writeln!(writer, "      <data key=\"instructions\">{}</data>", instructions.join("&#10;"))?;
+            writeln!(writer, "    </node>")?;
+        }
+
+        for block in self.blocks.iter() {
+            for edge in block.edges.iter() {
+                writeln!(writer, "    <edge source=\"{:#x}\" target=\"{:#x}\">", block.start, self.blocks[edge.target()].start)?;
+                writeln!(writer, "      <data key=\"count\">{}</data>", edge.count())?;
+                writeln!(writer, "    </edge>")?;
+            }
+        }
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+}
+
+/// Escapes the characters which are special to XML.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_graphml() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("LDAC".to_string(), Some("<a & 'b'>".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+
+        let mut graphml = Vec::new();
+        cfg.write_graphml(&mut graphml)?;
+
+        let expected = "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">
+  <key id=\"start\" for=\"node\" attr.name=\"start\" attr.type=\"string\"/>
+  <key id=\"end\" for=\"node\" attr.name=\"end\" attr.type=\"string\"/>
+  <key id=\"instruction_count\" for=\"node\" attr.name=\"instruction_count\" attr.type=\"int\"/>
+  <key id=\"instructions\" for=\"node\" attr.name=\"instructions\" attr.type=\"string\"/>
+  <key id=\"count\" for=\"edge\" attr.name=\"count\" attr.type=\"int\"/>
+  <graph id=\"cfg\" edgedefault=\"directed\">
+    <node id=\"0x2\">
+      <data key=\"start\">0x2</data>
+      <data key=\"end\">0x5</data>
+      <data key=\"instruction_count\">2</data>
+      <data key=\"instructions\">0x3: LDAC &lt;a &amp; &apos;b&apos;&gt;&#10;0x5: JZ 0x9 / 0x6 (taken)</data>
+    </node>
+    <node id=\"0x6\">
+      <data key=\"start\">0x6</data>
+      <data key=\"end\">0x6</data>
+      <data key=\"instruction_count\">0</data>
+      <data key=\"instructions\"></data>
+    </node>
+    <node id=\"0x9\">
+      <data key=\"start\">0x9</data>
+      <data key=\"end\">0x9</data>
+      <data key=\"instruction_count\">0</data>
+      <data key=\"instructions\"></data>
+    </node>
+    <edge source=\"0x2\" target=\"0x6\">
+      <data key=\"count\">0</data>
+    </edge>
+    <edge source=\"0x2\" target=\"0x9\">
+      <data key=\"count\">1</data>
+    </edge>
+  </graph>
+</graphml>
+";
+        assert_eq!(expected, String::from_utf8(graphml).unwrap());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use core::fmt::{self, Write};
+use crate::ControlFlowGraph;
+use crate::types::AddressFormat;
+use crate::prelude::*;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Renders the ControlFlowGraph as JSON, without depending on serde.
+    ///
+    /// The output is deterministic, with blocks sorted by their start address and edges by their source and target.
+    /// Addresses are hex strings and edges refer to blocks by their start address. With a Symbolizer or modules, every
+    /// block they name has a `"symbol"` and the addresses inside of instructions are written as their names.
+    ///
+    /// ```text
+    /// {
+    ///   "entry": "0x2",
+    ///   "blocks": [
+    ///     {"start": "0x2", "end": "0x5", "instructions": {"0x3": "INC", "0x5": "JMP 0x9 / 0x6 (taken)"}}
+    ///   ],
+    ///   "edges": [
+    ///     {"from": "0x2", "to": "0x9", "count": 1}
+    ///   ]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> String {
+        self.to_json_with_format(AddressFormat::Hex)
+    }
+
+    /// Renders the ControlFlowGraph as JSON like [to_json](Self::to_json), with every address written in the given format.
+    ///
+    /// Decimal addresses are JSON numbers, except for the addresses keying the instructions of a block which are
+    /// decimal strings.
+    pub fn to_json_with_format(&self, format: AddressFormat) -> String {
+        let address = |address: u64| match format {
+            AddressFormat::Hex => quote(&format.address(address).to_string()),
+            AddressFormat::Decimal => address.to_string(),
+        };
+        let names = self.names();
+        let mut json = String::from("{\n");
+        writeln!(json, "  \"entry\": {},", address(self.entry().start)).unwrap();
+
+        json.push_str("  \"blocks\": [\n");
+        let blocks: Vec<String> = self.starts.values().map(|index| {
+            let block = &self.blocks[*index];
+            let instructions: Vec<String> = block.instructions()
+                .map(|(pc, instruction)| format!("{}: {}", quote(&format.address(*pc).to_string()), quote(&instruction.display_with(format, names).to_string())))
+                .collect();
+            let symbol = names.name(block.start, format)
+                .map(|symbol| format!(", \"symbol\": {}", quote(&symbol)))
+                .unwrap_or_default();
+            format!("    {{\"start\": {}, \"end\": {}{}, \"instructions\": {{{}}}}}", address(block.start), address(block.end), symbol, instructions.join(", "))
+        }).collect();
+        push_lines(&mut json, &blocks);
+        json.push_str("  ],\n");
+
+        json.push_str("  \"edges\": [\n");
+        let edges: Vec<String> = self.edge_map().into_iter()
+            .map(|((from, to), count)| format!("    {{\"from\": {}, \"to\": {}, \"count\": {}}}", address(from), address(to), count))
+            .collect();
+        push_lines(&mut json, &edges);
+        json.push_str("  ]\n}\n");
+        json
+    }
+}
+
+/// Pushes the elements of a JSON array, one per line.
+fn push_lines(json: &mut String, lines: &[String]) {
+    if !lines.is_empty() {
+        json.push_str(&lines.join(",\n"));
+        json.push('\n');
+    }
+}
+
+/// Quotes the text as a JSON string.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_json() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("\"A\\B\"".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        assert_eq!(include_str!("testdata/conditional.json"), cfg.to_json());
+
+        Ok(())
+    }
+
+    #[test]
+    fn decimal_json() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute(0x11, BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalTaken, Some(0x12)))?;
+
+        let expected = r#"{
+  "entry": 16,
+  "blocks": [
+    {"start": 16, "end": 17, "instructions": {"17": "JZ 32 / 18 (taken)"}},
+    {"start": 18, "end": 18, "instructions": {}},
+    {"start": 32, "end": 32, "instructions": {}}
+  ],
+  "edges": [
+    {"from": 16, "to": 18, "count": 0},
+    {"from": 16, "to": 32, "count": 1}
+  ]
+}
+"#;
+        assert_eq!(expected, cfg.to_json_with_format(AddressFormat::Decimal));
+        assert_eq!(cfg.to_json(), cfg.to_json_with_format(AddressFormat::Hex));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use core::fmt::{self, Write};
+use crate::ControlFlowGraph;
+use crate::prelude::*;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Renders the ControlFlowGraph as a Mermaid flowchart.
+    ///
+    /// Each BasicBlock becomes a node labeled with its address range and instructions in address order, and each edge
+    /// is labeled with its traversal count. Edges which were never traversed are dotted.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart TD\n");
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("{:#x} - {:#x}", block.start, block.end);
+            for (_, instruction) in block.instructions() {
+                write!(label, "<br/>{}", escape(&instruction.to_string())).unwrap();
+            }
+            writeln!(mermaid, "    B{}[\"{}\"]", index, label).unwrap();
+        }
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            for edge in block.edges.iter() {
+                let arrow = if edge.count() == 0 { "-.->" } else { "-->" };
+                writeln!(mermaid, "    B{} {}|{}| B{}", index, arrow, edge.count(), edge.target()).unwrap();
+            }
+        }
+
+        mermaid
+    }
+}
+
+/// Replaces the characters which Mermaid would interpret inside of a quoted label with entity codes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '&' => escaped.push_str("#amp;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_mermaid() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("#<\"x\">".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        let expected = "\
+flowchart TD
+    B0[\"0x2 - 0x5<br/>INC<br/>LDAC #35;#lt;#quot;x#quot;#gt;<br/>JMP 0x9 / 0x6 (taken)\"]
+    B1[\"0x6 - 0x6\"]
+    B2[\"0x9 - 0xa<br/>INC\"]
+    B0 -.->|0| B1
+    B0 -->|1| B2
+";
+        assert_eq!(expected, cfg.to_mermaid());
+
+        Ok(())
+    }
+}
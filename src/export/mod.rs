@@ -0,0 +1,19 @@
+//! Exporters which render a ControlFlowGraph into other formats.
+
+mod ascii;
+pub(crate) mod dot;
+mod mermaid;
+mod json;
+#[cfg(feature = "std")]
+mod csv;
+#[cfg(feature = "std")]
+mod drcov;
+#[cfg(feature = "std")]
+mod gexf;
+#[cfg(feature = "std")]
+mod graphml;
+mod text;
+#[cfg(feature = "petgraph")]
+mod petgraph;
+
+pub use dot::{DotOptions, HeatScale, RankDir};
@@ -0,0 +1,49 @@
+use ::petgraph::graph::{DiGraph, NodeIndex};
+use crate::{BasicBlock, ControlFlowGraph};
+
+impl<I> ControlFlowGraph<I> {
+    /// Converts the ControlFlowGraph into a petgraph DiGraph weighted by the BasicBlocks and the edge traversal counts.
+    ///
+    /// Node indices match the positions of the blocks inside of the ControlFlowGraph.
+    pub fn to_petgraph(&self) -> DiGraph<&BasicBlock<I>, u64> {
+        let edge_count = self.edge_count();
+        let mut graph = DiGraph::with_capacity(self.blocks.len(), edge_count);
+        for block in self.blocks.iter() {
+            graph.add_node(block);
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            for edge in block.edges.iter() {
+                graph.add_edge(NodeIndex::new(index), NodeIndex::new(edge.target().index()), edge.count());
+            }
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::petgraph::algo::{is_cyclic_directed, toposort};
+    use ::petgraph::graph::NodeIndex;
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn petgraph_interop() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+
+        let graph = cfg.to_petgraph();
+        assert_eq!(4, graph.node_count());
+        assert_eq!(3, graph.edge_count());
+        assert_eq!(9, graph[NodeIndex::new(2)].start);
+        assert!(!is_cyclic_directed(&graph));
+        assert_eq!(NodeIndex::new(0), toposort(&graph, None).unwrap()[0]);
+
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        assert!(is_cyclic_directed(&cfg.to_petgraph()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use core::fmt;
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::symbolize::{Names, SymbolizedAddress};
+use crate::types::AddressFormat;
+
+impl<I: fmt::Display> ControlFlowGraph<I> {
+    /// Writes the header and instructions of the BasicBlock at the given index, followed by its edges with the start
+    /// address of their target.
+    fn fmt_block(&self, index: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let block = &self.blocks[index];
+        block.fmt_instructions(index == 0, self.names(), f)?;
+        for edge in block.edges.iter() {
+            writeln!(f, "    -> {} ({})", self.symbolized(self.blocks[edge.target()].start, AddressFormat::Hex), edge.count())?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: fmt::Display> fmt::Display for ControlFlowGraph<I> {
+    /// Formats the ControlFlowGraph as a listing of its BasicBlocks in creation order, separated by blank lines.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for index in 0..self.blocks.len() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            self.fmt_block(index, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: fmt::Display> BasicBlock<I> {
+    /// Writes the address range of the BasicBlock followed by one line for each of its instructions in address order.
+    ///
+    /// Addresses are written as their names given by the Symbolizer or the modules of the graph, if any.
+    fn fmt_instructions(&self, entry: bool, names: Names<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address = |address: u64| SymbolizedAddress { address, format: AddressFormat::Hex, names };
+        let marker = if entry { " (entry)" } else { "" };
+        writeln!(f, "{} - {}{}:", address(self.start), address(self.end), marker)?;
+        for (pc, instruction) in self.instructions() {
+            writeln!(f, "    {}: {}", address(*pc), instruction.display_with(AddressFormat::Hex, names))?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: fmt::Display> fmt::Display for BasicBlock<I> {
+    /// Formats the BasicBlock as its address range and instructions, followed by its edges.
+    ///
+    /// A BasicBlock does not know the addresses of the blocks it jumps to, so edges name the index of their target.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_instructions(false, Names::default(), f)?;
+        for edge in self.edges.iter() {
+            writeln!(f, "    -> block {} ({})", edge.target(), edge.count())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn conditional_jump_listing() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("A".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        let expected = "\
+0x2 - 0x5 (entry):
+    0x3: INC
+    0x4: LDAC A
+    0x5: JZ 0x9 / 0x6 (taken)
+    -> 0x6 (0)
+    -> 0x9 (1)
+
+0x6 - 0x6:
+
+0x9 - 0xa:
+    0xa: INC
+";
+        assert_eq!(expected, cfg.to_string());
+
+        let block = "\
+0x2 - 0x5:
+    0x3: INC
+    0x4: LDAC A
+    0x5: JZ 0x9 / 0x6 (taken)
+    -> block 1 (0)
+    -> block 2 (1)
+";
+        assert_eq!(block, cfg.block(BlockId(0)).unwrap().to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_and_return_listing() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(1, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(2)))?;
+        cfg.execute(0x21, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(3, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+
+        let expected = "\
+0x0 - 0x1 (entry):
+    0x1: CALL 0x20 / 0x2 (call)
+    -> 0x20 (1)
+
+0x20 - 0x21:
+    0x21: RET (return)
+    -> 0x2 (1)
+
+0x2 - 0x3:
+    0x3: JMP 0x30 (unconditional)
+    -> 0x30 (1)
+
+0x30 - 0x30:
+";
+        assert_eq!(expected, cfg.to_string());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::ControlFlowGraph;
+use crate::observer::CfgEvent;
+use crate::types::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns whether conditional jumps without a failure address fall through to the next instruction.
+    pub fn infers_fallthrough(&self) -> bool {
+        self.config.infer_fallthrough
+    }
+
+    /// Returns the address of the conditional jump which wasn't taken and whose not-taken edge waits for the next
+    /// instruction to be executed, if any.
+    pub fn pending_fallthrough(&self) -> Option<u64> {
+        self.pending_fallthrough
+    }
+
+    /// Executes a conditional jump whose failure address isn't known, only traversing the edge to the success address
+    /// if it was taken and otherwise waiting for the next instruction.
+    pub(crate) fn defer_fallthrough(&mut self, program_counter: u64, success_address: u64, taken: bool) -> Result<ExecOutcome, CFGError> {
+        if taken {
+            let success_index = self.query_block_or_create(success_address)?;
+            self.traverse_edge(success_index, EdgeKind::Taken)
+        } else {
+            self.pending_fallthrough = Some(program_counter);
+            Ok(ExecOutcome::KnownInstruction)
+        }
+    }
+
+    /// Traverses the not-taken edge of the conditional jump waiting for the next instruction to the block at the given
+    /// address, which must lie after the jump, and records the address as the failure address of the jump.
+    ///
+    /// Returns the outcome of traversing the edge if a jump was waiting.
+    pub(crate) fn follow_fallthrough(&mut self, program_counter: u64) -> Result<Option<ExecOutcome>, CFGError> {
+        let Some(jump) = self.pending_fallthrough.take() else {
+            return Ok(None);
+        };
+        if program_counter <= jump {
+            return Err(CFGError::Discontinuity { from: jump, to: program_counter });
+        }
+        let index = self.query_block_or_create(program_counter)?;
+        let traversed = self.traverse_edge(index, EdgeKind::FallThrough)?;
+        self.resolve_failure(jump, program_counter);
+        Ok(Some(traversed))
+    }
+
+    /// Returns the failure address recorded with the conditional jump at the given address, if it was resolved before.
+    pub(crate) fn recorded_failure(&self, program_counter: u64) -> Option<u64> {
+        let block = self.find_block_containing(program_counter)?;
+        match self.blocks[block].block.get(&program_counter) {
+            Some(BlockType::Jump(_, _, _, failure_address)) => *failure_address,
+            _ => None,
+        }
+    }
+
+    /// Records the failure address of the conditional jump at the given address, which was executed without one.
+    fn resolve_failure(&mut self, jump: u64, failure_address: u64) {
+        let Some(block) = self.find_block_containing(jump) else { return };
+        let Some(mut instruction) = self.blocks[block].block.remove(&jump) else { return };
+        if let BlockType::Jump(_, _, _, failure @ None) = &mut instruction {
+            *failure = Some(failure_address);
+            let size = self.blocks[block].instruction_size(jump).unwrap_or(1);
+            self.log_event(CfgEvent::Instruction { block, pc: jump, size, instruction: &instruction });
+        }
+        self.blocks[block].block.insert(jump, instruction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CFGBuilder, ControlFlowGraph};
+    use crate::expect::ExpectedCfg;
+    use crate::types::*;
+
+    /// Runs a loop whose conditional jumps leave out their failure address.
+    fn countdown(cfg: &mut ControlFlowGraph) -> Result<(), CFGError> {
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalTaken, None))?;
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalNotTaken, None))?;
+        Ok(())
+    }
+
+    #[test]
+    fn deferred_fallthrough() -> Result<(), CFGError> {
+        let mut strict = ControlFlowGraph::new(0x10);
+        assert!(!strict.infers_fallthrough());
+        assert!(matches!(countdown(&mut strict), Err(CFGError::ExpectedFailureAddress { pc: 0x11, .. })));
+
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x10).infer_fallthrough(true).build();
+        countdown(&mut cfg)?;
+        assert_eq!((Some(0x11), 1), (cfg.pending_fallthrough(), cfg.len()));
+
+        // The next instruction resolves the not-taken edge, even when the jump is longer than a byte.
+        let outcome = cfg.execute(0x13, BlockType::Instruction("INC".to_string(), None))?;
+        assert_eq!((ExecOutcome::NewBlock(BlockId(1)), None), (outcome, cfg.pending_fallthrough()));
+        let expected = ExpectedCfg::entry(0x10).hits(2).insn(0x10, "DEC").insn(0x11, "JNZ").jmp_to_count(0x10, 1).jmp_to_count(0x13, 1)
+            .block(0x13).hits(1).insn(0x13, "INC");
+        assert_eq!(Ok(()), cfg.assert_matches(&expected));
+        assert_eq!(Some(EdgeKind::FallThrough), cfg.entry().edge_to(cfg.starts[&0x13]).map(|edge| edge.kind()));
+
+        // With known sizes the fall-through is right after the jump, and is created when the jump is taken.
+        let mut sized: ControlFlowGraph = CFGBuilder::entry(0x10).infer_fallthrough(true).build();
+        sized.execute_sized(0x10, BlockType::Instruction("DEC".to_string(), None), 1)?;
+        sized.execute_sized(0x11, BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalTaken, None), 2)?;
+        assert_eq!(Some(0), sized.entry().edge_to(sized.starts[&0x13]).map(|edge| edge.count()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inferred_failure_is_recorded() -> Result<(), CFGError> {
+        // A sized jump knows its failure address right away, so the way it never went is on the frontier.
+        let mut sized: ControlFlowGraph = CFGBuilder::entry(0x10).infer_fallthrough(true).conflict_policy(ConflictPolicy::Error).build();
+        for _ in 0..2 {
+            sized.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+            sized.execute_sized(0x11, BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalTaken, None), 2)?;
+        }
+        let jump = BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(0x13));
+        assert_eq!(Some(&jump), sized.entry().block.get(&0x11));
+        let frontier: Vec<(u64, u64, u64)> = sized.frontier_edges().iter().map(|edge| (edge.pc, edge.target, edge.sibling_count)).collect();
+        assert_eq!(vec![(0x11, 0x13, 2)], frontier);
+        let branches: Vec<(u64, u64, u64)> = sized.branch_stats().iter().map(|stat| (stat.pc, stat.taken, stat.not_taken)).collect();
+        assert_eq!(vec![(0x11, 2, 0)], branches);
+
+        // A deferred fall-through records the failure address once it is resolved, and executing the jump without it
+        // again doesn't conflict with the recorded one.
+        let mut deferred: ControlFlowGraph = CFGBuilder::entry(0x10).infer_fallthrough(true).conflict_policy(ConflictPolicy::Error).build();
+        countdown(&mut deferred)?;
+        assert_eq!(None, deferred.recorded_failure(0x11));
+        deferred.execute(0x13, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        assert_eq!(Some(0x13), deferred.recorded_failure(0x11));
+        countdown(&mut deferred)?;
+        assert_eq!(None, deferred.pending_fallthrough());
+        let branches: Vec<(u64, u64, u64)> = deferred.branch_stats().iter().map(|stat| (stat.pc, stat.taken, stat.not_taken)).collect();
+        assert_eq!(vec![(0x11, 2, 2)], branches);
+        assert!(deferred.frontier_edges().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unresolved_fallthrough() -> Result<(), CFGError> {
+        // A jump which is only ever taken never gets a not-taken edge.
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x10).infer_fallthrough(true).build();
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalTaken, None))?;
+        assert_eq!((1, 1, None), (cfg.len(), cfg.edge_count(), cfg.pending_fallthrough()));
+
+        // A trace which ends right after the jump wasn't taken leaves it waiting, and one which continues before it is
+        // a discontinuity.
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x10, JumpType::ConditionalNotTaken, None))?;
+        assert_eq!((1, Some(0x11)), (cfg.edge_count(), cfg.pending_fallthrough()));
+        let error = cfg.execute(0x8, BlockType::Instruction("INC".to_string(), None)).unwrap_err();
+        assert!(matches!(error, CFGError::Discontinuity { from: 0x11, to: 0x8 }));
+        assert_eq!((1, 1), (cfg.len(), cfg.edge_count()));
+
+        Ok(())
+    }
+}
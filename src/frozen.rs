@@ -0,0 +1,385 @@
+use alloc::sync::Arc;
+use core::fmt;
+use crate::{BasicBlock, ControlFlowGraph, Edge, Module, SymbolTable};
+use crate::analysis::{dominators_from, GraphLike};
+use crate::types::*;
+use crate::prelude::*;
+
+/// A read-only ControlFlowGraph for a long analysis phase, made by [finalize](ControlFlowGraph::finalize).
+///
+/// The instructions of every block are kept in a single sorted slice and every list is shrunk to its length, so a
+/// FrozenCfg takes less memory than a graph and is faster to walk. Cloning a FrozenCfg only counts another reference
+/// to the same blocks, and clones can be shared between threads.
+///
+/// ```
+/// use ctrl_flow::ControlFlowGraph;
+/// use ctrl_flow::types::{BlockType, JumpType};
+///
+/// let mut cfg = ControlFlowGraph::new(0x10);
+/// cfg.execute(0x10, BlockType::Instruction("INC".to_string(), None))?;
+/// cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+///
+/// let frozen = cfg.finalize();
+/// let shared = frozen.clone();
+/// std::thread::spawn(move || assert_eq!(Some(shared.entry_index()), shared.find_block_containing(0x11))).join().unwrap();
+/// assert_eq!(2, frozen.entry().hits());
+/// # Ok::<(), ctrl_flow::types::CFGError>(())
+/// ```
+///
+/// Nothing can be executed on a FrozenCfg,
+///
+/// ```compile_fail
+/// use ctrl_flow::ControlFlowGraph;
+/// use ctrl_flow::types::BlockType;
+///
+/// let cfg: ControlFlowGraph = ControlFlowGraph::new(0x10);
+/// let mut frozen = cfg.finalize();
+/// frozen.execute(0x10, BlockType::Instruction("INC".to_string(), None));
+/// ```
+pub struct FrozenCfg<I = String> {
+    graph: Arc<FrozenGraph<I>>,
+}
+
+/// The blocks of a FrozenCfg with everything needed to query them.
+struct FrozenGraph<I> {
+    blocks: Box<[FrozenBlock<I>]>,
+    /// The start address and position of every block, ordered by address
+    starts: Box<[(u64, BlockId)]>,
+    /// The positions of the blocks with an edge to each block in ascending order, indexed by block position
+    predecessors: Box<[Box<[BlockId]>]>,
+    modules: Box<[Module]>,
+    current_block: BlockId,
+    blocks_discovered: u64,
+    edges_discovered: u64,
+    executed: u64,
+    saturated: bool,
+    symbols: SymbolTable,
+}
+
+/// A BasicBlock of a FrozenCfg, with the same accessors.
+pub struct FrozenBlock<I = String> {
+    start: u64,
+    end: u64,
+    /// The instructions ordered by address
+    instructions: Box<[(u64, BlockType<I>)]>,
+    /// The number of times each instruction was executed, in the order of the instructions
+    instruction_hits: Box<[u64]>,
+    /// The size of each instruction which isn't a single byte long, ordered by address
+    sizes: Box<[(u64, u64)]>,
+    edges: Box<[Edge]>,
+    hits: u64,
+    metadata: Box<[(String, String)]>,
+    discovered_at: u64,
+    exit: bool,
+}
+
+impl<I> Clone for FrozenCfg<I> {
+    fn clone(&self) -> Self {
+        FrozenCfg { graph: Arc::clone(&self.graph) }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Compacts the ControlFlowGraph into a FrozenCfg once it won't change anymore, such as after ingesting a trace.
+    ///
+    /// Only the blocks, their edges, the modules, the symbols, and where execution was are kept. The observer, the
+    /// event log, and the symbolizer are dropped along with the call stack, the threads, and the recorded sequence.
+    pub fn finalize(self) -> FrozenCfg<I> {
+        let starts = self.starts.into_iter().collect();
+        let predecessors = self.predecessors.into_iter().map(Vec::into_boxed_slice).collect();
+        let blocks = self.blocks.into_iter().map(FrozenBlock::from).collect();
+        let graph = FrozenGraph {
+            blocks,
+            starts,
+            predecessors,
+            modules: self.modules.into_boxed_slice(),
+            current_block: self.current_block,
+            blocks_discovered: self.blocks_discovered,
+            edges_discovered: self.edges_discovered,
+            executed: self.executed,
+            saturated: self.saturated,
+            symbols: self.symbols,
+        };
+        FrozenCfg { graph: Arc::new(graph) }
+    }
+}
+
+impl<I> From<BasicBlock<I>> for FrozenBlock<I> {
+    fn from(block: BasicBlock<I>) -> Self {
+        let instruction_hits = block.block.keys().map(|address| block.instruction_hits.get(address).copied().unwrap_or(0)).collect();
+        FrozenBlock {
+            start: block.start,
+            end: block.end,
+            instructions: block.block.into_iter().collect(),
+            instruction_hits,
+            sizes: block.sizes.into_iter().collect(),
+            edges: block.edges.into_boxed_slice(),
+            hits: block.hits,
+            metadata: block.metadata.into_iter().collect(),
+            discovered_at: block.discovered_at,
+            exit: block.exit,
+        }
+    }
+}
+
+impl<I> GraphLike for FrozenCfg<I> {
+    fn block_count(&self) -> usize {
+        self.graph.blocks.len()
+    }
+
+    fn successor_ids(&self, block: BlockId, only_traversed: bool) -> impl Iterator<Item = BlockId> + '_ {
+        self.graph.blocks[block.0].edges.iter().filter(move |edge| edge.count() > 0 || !only_traversed).map(Edge::target)
+    }
+}
+
+impl<I> FrozenCfg<I> {
+    /// Returns the entry block.
+    pub fn entry(&self) -> &FrozenBlock<I> {
+        &self.graph.blocks[0]
+    }
+
+    /// Returns the position of the entry block.
+    pub fn entry_index(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    /// Returns the position of the block execution was in when the graph was finalized.
+    pub fn current_block_index(&self) -> BlockId {
+        self.graph.current_block
+    }
+
+    /// Returns the block at the given position, if there is one.
+    pub fn block(&self, index: BlockId) -> Option<&FrozenBlock<I>> {
+        self.graph.blocks.get(index.0)
+    }
+
+    /// Returns the number of blocks.
+    pub fn len(&self) -> usize {
+        self.graph.blocks.len()
+    }
+
+    /// Returns whether there are no blocks, which is never the case as there is always an entry block.
+    pub fn is_empty(&self) -> bool {
+        self.graph.blocks.is_empty()
+    }
+
+    /// Returns every block in the order they were created, like `ControlFlowGraph::blocks`.
+    pub fn blocks(&self) -> impl Iterator<Item=&FrozenBlock<I>> {
+        self.graph.blocks.iter()
+    }
+
+    /// Returns the modules of the address space in the order they were added, like `ControlFlowGraph::modules`.
+    pub fn modules(&self) -> &[Module] {
+        &self.graph.modules
+    }
+
+    /// Returns every edge of the graph with the position of its source, in the order of the blocks and their edges.
+    pub fn edges(&self) -> impl Iterator<Item=(BlockId, &Edge)> {
+        self.graph.blocks.iter().enumerate().flat_map(|(index, block)| block.edges.iter().map(move |edge| (BlockId(index), edge)))
+    }
+
+    /// Returns the position of the block whose instructions cover the given address, if any.
+    pub fn find_block_containing(&self, address: u64) -> Option<BlockId> {
+        let starts = &self.graph.starts;
+        let (_, index) = *starts.get(starts.partition_point(|(start, _)| *start <= address).checked_sub(1)?)?;
+        (address <= self.graph.blocks[index.0].last_byte()).then_some(index)
+    }
+
+    /// Returns the positions of the blocks which the block at the given position has an edge to, in the order they were added.
+    pub fn successors(&self, block_index: BlockId) -> Result<Vec<BlockId>, CFGError> {
+        let block = self.block(block_index).ok_or(CFGError::MissingBlock { index: block_index.0 })?;
+        Ok(block.edges.iter().map(Edge::target).collect())
+    }
+
+    /// Returns the positions of the blocks which have an edge to the block at the given position, in ascending order.
+    pub fn predecessors(&self, block_index: BlockId) -> Result<&[BlockId], CFGError> {
+        self.graph.predecessors.get(block_index.0).map(|predecessors| &predecessors[..]).ok_or(CFGError::MissingBlock { index: block_index.0 })
+    }
+
+    /// Computes the immediate dominator of every block like `ControlFlowGraph::dominators`.
+    pub fn dominators(&self) -> Result<Vec<Option<BlockId>>, CFGError> {
+        dominators_from(self, 0)
+    }
+}
+
+impl<I: Clone> FrozenCfg<I> {
+    /// Copies the blocks back into a ControlFlowGraph which can be executed on again, continuing from the block execution
+    /// was in when the graph was finalized.
+    pub fn thaw(&self) -> ControlFlowGraph<I> {
+        let blocks = self.graph.blocks.iter().map(|block| {
+            let mut thawed = BasicBlock::new(block.start);
+            thawed.end = block.end;
+            thawed.block = block.instructions.iter().cloned().collect();
+            thawed.instruction_hits = block.instructions.iter().map(|(address, _)| *address).zip(block.instruction_hits.iter().copied()).collect();
+            thawed.sizes = block.sizes.iter().copied().collect();
+            thawed.edges = block.edges.to_vec();
+            thawed.hits = block.hits;
+            thawed.metadata = block.metadata.iter().cloned().collect();
+            thawed.discovered_at = block.discovered_at;
+            thawed.exit = block.exit;
+            thawed
+        }).collect();
+        let mut cfg = ControlFlowGraph::from_blocks(blocks, self.graph.current_block.0);
+        cfg.blocks_discovered = self.graph.blocks_discovered;
+        cfg.edges_discovered = self.graph.edges_discovered;
+        cfg.executed = self.graph.executed;
+        cfg.saturated = self.graph.saturated;
+        cfg.modules = self.graph.modules.to_vec();
+        cfg.symbols = self.graph.symbols.clone();
+        cfg
+    }
+}
+
+impl<I: Clone + fmt::Display> FrozenCfg<I> {
+    /// Renders the graph in the Graphviz DOT format like `ControlFlowGraph::to_dot`, from a thawed copy.
+    pub fn to_dot(&self) -> String {
+        self.thaw().to_dot()
+    }
+
+    /// Renders the graph as JSON like `ControlFlowGraph::to_json`, from a thawed copy.
+    pub fn to_json(&self) -> String {
+        self.thaw().to_json()
+    }
+}
+
+impl<I> FrozenBlock<I> {
+    /// Returns the starting address of the block.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the address of the last instruction of the block.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Returns the address right after the last byte of the last instruction of the block, saturating at `u64::MAX`.
+    pub fn end_exclusive(&self) -> u64 {
+        self.end.saturating_add(self.instruction_size(self.end).unwrap_or(1))
+    }
+
+    /// Returns the address of the last byte of the last instruction of the block, saturating at `u64::MAX`.
+    fn last_byte(&self) -> u64 {
+        self.end.saturating_add(self.instruction_size(self.end).unwrap_or(1) - 1)
+    }
+
+    /// Returns the size in bytes of the instruction at the given address, if the block holds one there.
+    pub fn instruction_size(&self, address: u64) -> Option<u64> {
+        self.position(address)?;
+        Some(self.sizes.binary_search_by_key(&address, |(address, _)| *address).map_or(1, |position| self.sizes[position].1))
+    }
+
+    /// Returns the number of instructions in the block.
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Returns whether the block holds no instructions.
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Returns the value of the annotation with the given key, if the block has one.
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        let position = self.metadata.binary_search_by(|(existing, _)| existing.as_str().cmp(key)).ok()?;
+        Some(&self.metadata[position].1)
+    }
+
+    /// Returns the number of times execution entered the block.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of times the instruction at the given address was executed, if the block holds one there.
+    pub fn instruction_hits(&self, address: u64) -> Option<u64> {
+        self.position(address).map(|position| self.instruction_hits[position])
+    }
+
+    /// Returns the instructions of the block in address order.
+    pub fn instructions(&self) -> impl Iterator<Item=(&u64, &BlockType<I>)> {
+        self.instructions.iter().map(|(address, instruction)| (address, instruction))
+    }
+
+    /// Returns the edges leading out of the block.
+    pub fn edges(&self) -> impl Iterator<Item=&Edge> {
+        self.edges.iter()
+    }
+
+    /// Returns the edge to the block at the given position, if there is one.
+    pub fn edge_to(&self, target: BlockId) -> Option<&Edge> {
+        self.edges.iter().find(|edge| edge.target() == target)
+    }
+
+    /// Returns how many blocks the graph discovered before this one.
+    pub fn discovered_at(&self) -> u64 {
+        self.discovered_at
+    }
+
+    /// Returns whether the block ends with a terminal instruction, which exits the program.
+    pub fn is_exit(&self) -> bool {
+        self.exit
+    }
+
+    /// Returns the position of the instruction at the given address among the instructions.
+    fn position(&self, address: u64) -> Option<usize> {
+        self.instructions.binary_search_by_key(&address, |(address, _)| *address).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ControlFlowGraph, FrozenCfg, Symbol};
+    use crate::types::*;
+    use crate::prelude::*;
+
+    /// Collects the results of the read-only queries both a graph and a FrozenCfg answer.
+    macro_rules! queries {
+        ($cfg:expr) => {{
+            let cfg = &$cfg;
+            let blocks: Vec<_> = cfg.blocks().map(|block| {
+                let instructions: Vec<_> = block.instructions().map(|(address, instruction)| {
+                    (*address, instruction.to_string(), block.instruction_hits(*address), block.instruction_size(*address))
+                }).collect();
+                let edges: Vec<_> = block.edges().map(|edge| (edge.target(), edge.count(), edge.kind())).collect();
+                (block.start(), block.end(), block.end_exclusive(), block.hits(), block.get_meta("note").map(String::from), instructions, edges)
+            }).collect();
+            let containing: Vec<_> = (0..0x40).map(|address| cfg.find_block_containing(address)).collect();
+            let neighbors: Vec<_> = (0..cfg.len()).map(|index| {
+                (cfg.successors(BlockId(index)).unwrap(), cfg.predecessors(BlockId(index)).unwrap().to_vec())
+            }).collect();
+            (blocks, containing, neighbors, cfg.modules().to_vec(), cfg.dominators().unwrap(), cfg.to_dot(), cfg.to_json())
+        }};
+    }
+
+    #[test]
+    fn same_queries() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_sized(1, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(4)), 3)?;
+        cfg.execute(0x20, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x21, BlockType::Jump("JNZ".to_string(), 0x20, JumpType::ConditionalTaken, Some(0x22)))?;
+        cfg.execute(0x20, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x21, BlockType::Jump("JNZ".to_string(), 0x20, JumpType::ConditionalNotTaken, Some(0x22)))?;
+        cfg.execute(0x22, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(4, BlockType::Jump("JZ".to_string(), 0x30, JumpType::ConditionalTaken, Some(5)))?;
+        let entry = cfg.entry_index();
+        cfg.blocks[entry].set_meta("note", "start");
+        cfg.add_module("libc", 0x20, 0x10)?;
+
+        let before = queries!(cfg);
+        assert!(before.6.contains("libc+0x2"));
+        let frozen = cfg.finalize();
+        assert_eq!(before, queries!(frozen));
+        assert_eq!(before, queries!(frozen.thaw()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        fn shareable<T: Send + Sync + Clone>() {}
+        shareable::<FrozenCfg>();
+        shareable::<FrozenCfg<&'static str>>();
+        shareable::<FrozenCfg<Symbol>>();
+    }
+}
@@ -0,0 +1,384 @@
+//! Parsing of the formats written by the exporters back into a ControlFlowGraph.
+
+use alloc::collections::BTreeSet;
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::export::dot::EDGE_COLORS;
+use crate::types::*;
+use crate::prelude::*;
+
+/// A node or an edge statement of a DOT graph.
+enum Statement {
+    Node { id: u64, label: Option<String>, entry: bool },
+    Edge { from: u64, to: u64, count: u64, kind: EdgeKind },
+}
+
+impl ControlFlowGraph {
+    /// Reads a ControlFlowGraph from the subset of the Graphviz DOT format which `to_dot` writes, such as a hand-edited
+    /// expected graph, with addresses in hex or decimal.
+    ///
+    /// Every node is a block whose quoted id is its start address. Its label is optional, and otherwise starts with
+    /// the address range of the block followed by one `address: instruction` line for each instruction, where the
+    /// address of an instruction longer than a byte is followed by its size like `0x10 (4 bytes)`, any other
+    /// `key: value` line being metadata. The label of an edge is its traversal count, zero if it is missing, and its
+    /// color gives its kind, an edge without a color being unconditional. The filled node is the entry, or the first
+    /// node if none is filled, and edges may create the blocks they point to. The clusters written by
+    /// `to_dot_clustered` are flattened. Hit counts aren't part of DOT and start at zero, so the result compares equal
+    /// to the exported graph.
+    ///
+    /// Malformed statements produce a [CFGError::ParseError] with the line number, counting from one.
+    pub fn from_dot(dot: &str) -> Result<Self, CFGError> {
+        let mut statements = Vec::new();
+        let mut closed = false;
+        for (line_no, line) in dot.lines().enumerate() {
+            let line = line.trim();
+            let skipped = line.is_empty() || line.starts_with("//") || line.starts_with('#') || line.starts_with("digraph")
+                || line.starts_with("node ") || line.starts_with("edge ") || line.starts_with("graph ")
+                || line.starts_with("subgraph ") || line.starts_with("label=");
+            if skipped {
+                continue;
+            }
+            if line == "}" {
+                closed = true;
+                continue;
+            }
+            let statement = parse_statement(line).map_err(|reason| CFGError::ParseError(line_no + 1, reason))?;
+            statements.push((line_no + 1, statement));
+        }
+        if !closed {
+            return Err(CFGError::ParseError(dot.lines().count(), "missing closing `}`".to_string()));
+        }
+
+        let entry = statements.iter()
+            .filter_map(|(_, statement)| match statement {
+                Statement::Node { id, entry, .. } => Some((*id, *entry)),
+                Statement::Edge { .. } => None,
+            })
+            .reduce(|first, node| if !first.1 && node.1 { node } else { first })
+            .map(|(id, _)| id)
+            .ok_or_else(|| CFGError::ParseError(dot.lines().count(), "no nodes".to_string()))?;
+
+        let mut cfg = ControlFlowGraph::new(entry);
+        cfg.blocks[0].hits = 0;
+        let mut labeled = BTreeSet::new();
+        for (line_no, statement) in statements.iter() {
+            let Statement::Node { id, label, .. } = statement else { continue };
+            let index = cfg.starts.get(id).copied().unwrap_or_else(|| cfg.add_block(BasicBlock::new(*id)));
+            if let Some(label) = label {
+                if !labeled.insert(*id) {
+                    return Err(CFGError::ParseError(*line_no, format!("duplicate node `{:#x}`", id)));
+                }
+                let discovered_at = cfg.blocks[index].discovered_at;
+                cfg.blocks[index] = parse_label(*id, label).map_err(|reason| CFGError::ParseError(*line_no, reason))?;
+                cfg.blocks[index].discovered_at = discovered_at;
+            }
+        }
+        for (_, statement) in statements.iter() {
+            if let Statement::Edge { from, to, count, kind } = statement {
+                let from = cfg.starts.get(from).copied().unwrap_or_else(|| cfg.add_block(BasicBlock::new(*from)));
+                let to = cfg.starts.get(to).copied().unwrap_or_else(|| cfg.add_block(BasicBlock::new(*to)));
+                cfg.add_edge_count(from, to, *count, *kind);
+            }
+        }
+
+        Ok(cfg)
+    }
+}
+
+/// Parses a node or an edge statement.
+fn parse_statement(line: &str) -> Result<Statement, String> {
+    let line = line.strip_suffix(';').unwrap_or(line).trim_end();
+    let (id, rest) = parse_quoted(line)?;
+    let source = parse_address(&id)?;
+
+    if let Some(rest) = rest.trim_start().strip_prefix("->") {
+        let (target, rest) = parse_quoted(rest.trim_start())?;
+        let attributes = parse_attributes(rest)?;
+        let count = match attributes.iter().find(|(key, _)| key == "label") {
+            Some((_, label)) => label.parse().map_err(|_| format!("invalid edge count `{}`", label))?,
+            None => 0,
+        };
+        let kind = match attributes.iter().find(|(key, _)| key == "color") {
+            Some((_, color)) => EDGE_COLORS.iter().find(|(_, edge_color)| edge_color == color).map(|(kind, _)| *kind)
+                .ok_or_else(|| format!("unknown edge color `{}`", color))?,
+            None => EdgeKind::Unconditional,
+        };
+        return Ok(Statement::Edge { from: source, to: parse_address(&target)?, count, kind });
+    }
+
+    let attributes = parse_attributes(rest)?;
+    let label = attributes.iter().find(|(key, _)| key == "label").map(|(_, label)| label.clone());
+    let entry = attributes.iter().any(|(key, value)| key == "style" && value == "filled");
+    Ok(Statement::Node { id: source, label, entry })
+}
+
+/// Splits off a leading quoted string, keeping the escapes inside of it.
+fn parse_quoted(text: &str) -> Result<(String, &str), String> {
+    let Some(quoted) = text.strip_prefix('"') else {
+        return Err(format!("expected a quoted id in `{}`", text));
+    };
+    let mut escaped = false;
+    for (position, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok((quoted[..position].to_string(), &quoted[position + 1..])),
+            _ => {}
+        }
+    }
+    Err(format!("unterminated string in `{}`", text))
+}
+
+/// Parses an optional `[key=value, ...]` attribute list, the values being bare or quoted.
+fn parse_attributes(text: &str) -> Result<Vec<(String, String)>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(mut rest) = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) else {
+        return Err(format!("malformed attributes `{}`", text));
+    };
+
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(attributes);
+        }
+        let Some((key, value)) = rest.split_once('=') else {
+            return Err(format!("malformed attribute `{}`", rest));
+        };
+        let value = value.trim_start();
+        let (value, remainder) = if value.starts_with('"') {
+            parse_quoted(value)?
+        } else {
+            let end = value.find(',').unwrap_or(value.len());
+            (value[..end].trim_end().to_string(), &value[end..])
+        };
+        attributes.push((key.trim().to_string(), value));
+        rest = remainder.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest);
+    }
+}
+
+/// Parses the label of a node into the BasicBlock starting at the given address.
+fn parse_label(start: u64, label: &str) -> Result<BasicBlock<String>, String> {
+    let lines = unescape_lines(label);
+    let mut lines = lines.iter().filter(|line| !line.is_empty());
+    let range = lines.next().ok_or_else(|| "missing address range".to_string())?;
+    let (first, last) = range.split_once(" - ").ok_or_else(|| format!("malformed address range `{}`", range))?;
+    if parse_address(first)? != start {
+        return Err(format!("address range `{}` doesn't start at the node", range));
+    }
+
+    let mut block = BasicBlock::new(start);
+    for line in lines {
+        let (key, value) = line.split_once(": ").ok_or_else(|| format!("malformed label line `{}`", line))?;
+        let (address, size) = match key.strip_suffix(" bytes)").and_then(|key| key.split_once(" (")) {
+            Some((address, size)) => (address, Some(size)),
+            None => (key, None),
+        };
+        match parse_address(address) {
+            Ok(address) => {
+                block.add_instruction(address, parse_instruction(value)?);
+                block.instruction_hits.insert(address, 0);
+                if let Some(size) = size {
+                    let size = size.parse().map_err(|_| format!("malformed instruction size `{}`", size))?;
+                    block.set_instruction_size(address, size);
+                }
+            }
+            Err(_) => {
+                block.metadata.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    block.end = parse_address(last)?;
+    if block.end < start {
+        return Err(format!("address range `{}` ends before it starts", range));
+    }
+    if let Some(address) = block.block.keys().find(|address| !(start..=block.end).contains(*address)) {
+        return Err(format!("instruction at {:#x} lies outside of the address range `{}`", address, range));
+    }
+    Ok(block)
+}
+
+/// Removes the escapes of a DOT label and splits it into its `\l` terminated lines.
+fn unescape_lines(label: &str) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            lines.last_mut().unwrap().push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('l') | Some('n') => lines.push(String::new()),
+            Some(escaped) => lines.last_mut().unwrap().push(escaped),
+            None => {}
+        }
+    }
+    lines
+}
+
+/// Parses an instruction in the form its Display implementation writes it in, with addresses in hex or decimal.
+///
+/// Text which only looks like a jump or a switch, such as an operand in brackets, is an instruction with an operand.
+pub(crate) fn parse_instruction(text: &str) -> Result<BlockType, String> {
+    let (name, rest) = match text.split_once(' ') {
+        Some((name, rest)) => (name, rest.trim()),
+        None => (text, ""),
+    };
+    if name.is_empty() {
+        return Err("missing mnemonic".to_string());
+    }
+    let name = name.to_string();
+    if rest.is_empty() {
+        return Ok(BlockType::Instruction(name, None));
+    }
+
+    if let Some(targets) = rest.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let targets: Result<Vec<u64>, String> = targets.split(", ").map(parse_address).collect();
+        if let Ok(targets) = targets {
+            return Ok(BlockType::Switch(name, targets));
+        }
+    }
+
+    if rest == "(terminal)" {
+        return Ok(BlockType::Terminal(name));
+    }
+
+    if let Some((addresses, kind)) = rest.strip_suffix(')').and_then(|rest| rest.rsplit_once('(')) {
+        let jump_type = match kind {
+            "unconditional" => Some(JumpType::UnconditionalJump),
+            "taken" => Some(JumpType::ConditionalTaken),
+            "not taken" => Some(JumpType::ConditionalNotTaken),
+            "call" => Some(JumpType::Call),
+            "return" => Some(JumpType::Return),
+            "indirect" => Some(JumpType::Indirect),
+            _ => None,
+        };
+        let addresses = addresses.trim();
+        let jump = match (jump_type, addresses.split_once(" / ")) {
+            (Some(JumpType::Return), _) if addresses.is_empty() => Some(BlockType::Jump(name.clone(), 0, JumpType::Return, None)),
+            (Some(jump_type), Some((success, failure))) => match (parse_address(success), parse_address(failure)) {
+                (Ok(success), Ok(failure)) => Some(BlockType::Jump(name.clone(), success, jump_type, Some(failure))),
+                _ => None,
+            },
+            (Some(jump_type), None) => parse_address(addresses).ok().map(|success| BlockType::Jump(name.clone(), success, jump_type, None)),
+            (None, _) => None,
+        };
+        if let Some(jump) = jump {
+            return Ok(jump);
+        }
+    }
+
+    Ok(BlockType::Instruction(name, Some(rest.to_string())))
+}
+
+/// Parses a decimal or `0x` prefixed hex address.
+pub(crate) fn parse_address(token: &str) -> Result<u64, String> {
+    let parsed = match token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => token.parse(),
+    };
+    parsed.map_err(|_| format!("invalid address `{}`", token))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn dot_round_trip() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("\"quoted\" [x]".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(11)))?;
+        cfg.execute(0x21, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute_switch(11, "JMP".to_string(), vec![0x30, 0x40], 0x40)?;
+        cfg.execute(0x40, BlockType::Terminal("HLT".to_string()))?;
+        cfg.annotate(BlockId(0), "function", "main")?;
+
+        let restored = ControlFlowGraph::from_dot(&cfg.to_dot())?;
+        assert!(cfg == restored);
+        assert!(cfg.eq_with_counts(&restored));
+        assert_eq!(cfg.to_dot(), restored.to_dot());
+        assert_eq!(0, restored.entry().hits());
+        assert_eq!(cfg.exit_blocks(), restored.exit_blocks());
+
+        let decimal = ControlFlowGraph::from_dot(&cfg.to_dot_with_format(AddressFormat::Decimal))?;
+        assert!(cfg.eq_with_counts(&decimal));
+        let annotated = ControlFlowGraph::from_dot(&cfg.to_dot_with_meta("function"))?;
+        assert_eq!(Some("main"), annotated.entry().get_meta("function"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sized_round_trip() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute_sized(0, BlockType::Instruction("MOV".to_string(), Some("A, 1".to_string())), 4)?;
+        cfg.execute_sized(4, BlockType::Instruction("INC".to_string(), None), 1)?;
+        cfg.execute_sized(5, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None), 2)?;
+        cfg.execute_sized(0x10, BlockType::Terminal("HLT".to_string()), 1)?;
+        assert!(cfg.to_dot().contains("0x0 (4 bytes): MOV A, 1\\l0x4: INC\\l0x5 (2 bytes): JMP"));
+
+        let restored = ControlFlowGraph::from_dot(&cfg.to_dot())?;
+        assert!(cfg.eq_with_counts(&restored));
+        assert_eq!((Some(4), Some(1), Some(2)), (restored.entry().instruction_size(0), restored.entry().instruction_size(4), restored.entry().instruction_size(5)));
+        assert_eq!(7, restored.entry().end_exclusive());
+        assert_eq!(cfg.to_dot(), restored.to_dot());
+
+        let result = ControlFlowGraph::from_dot("digraph cfg {\n    \"0x0\" [label=\"0x0 - 0x0\\l0x0 (four bytes): INC\\l\"];\n}");
+        assert!(matches!(result, Err(CFGError::ParseError(2, message)) if message == "malformed instruction size `four`"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn hand_written_dot() -> Result<(), CFGError> {
+        let dot = "\
+digraph expected {
+    \"0x20\";
+    \"0x10\" [style=filled];
+    \"0x10\" -> \"0x20\" [label=\"3\"];
+    \"0x20\" -> \"0x30\";
+}
+";
+        let cfg = ControlFlowGraph::from_dot(dot)?;
+        assert_eq!(0x10, cfg.entry().start());
+        let starts: Vec<u64> = cfg.blocks().map(|bb| bb.start()).collect();
+        assert_eq!(vec![0x10, 0x20, 0x30], starts);
+        assert_eq!(vec![(BlockId(1), 3)], cfg.entry().edge_counts());
+        assert_eq!(vec![(BlockId(2), 0)], cfg.blocks[1].edge_counts());
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_dot() {
+        let cases = [
+            ("digraph cfg {\n    0x10;\n}\n", 2),
+            ("digraph cfg {\n    \"0x10\" [label=\"0x10 - 0x11\\l0x11 INC\\l\"];\n}\n", 2),
+            ("digraph cfg {\n    \"0x10\";\n    \"0x10\" -> \"0x20\" [label=\"many\"];\n}\n", 3),
+            ("digraph cfg {\n    \"0x10\";\n", 2),
+        ];
+        for (dot, line) in cases {
+            let result = ControlFlowGraph::from_dot(dot);
+            assert!(matches!(result, Err(CFGError::ParseError(l, _)) if l == line), "{:?}", dot);
+        }
+
+        // Address ranges which don't hold their instructions are rejected rather than breaking the block accessors.
+        let cases = [
+            ("0x10 - 0x4\\l", "address range `0x10 - 0x4` ends before it starts"),
+            ("0x10 - 0x11\\l0x12: INC\\l", "instruction at 0x12 lies outside of the address range `0x10 - 0x11`"),
+            ("0x10 - 0x11\\l0xf: INC\\l", "instruction at 0xf lies outside of the address range `0x10 - 0x11`"),
+        ];
+        for (label, reason) in cases {
+            let dot = format!("digraph cfg {{\n    \"0x20\";\n    \"0x10\" [label=\"{}\"];\n}}\n", label);
+            let result = ControlFlowGraph::from_dot(&dot);
+            assert!(matches!(&result, Err(CFGError::ParseError(3, message)) if message == reason), "{:?}", dot);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Ingestion of textual traces into a ControlFlowGraph.
+//!
+//! Each line of a trace is one executed instruction, starting with its program counter. Addresses may be written in
+//! decimal or in hex with a `0x` prefix. Blank lines and lines starting with `#` are skipped.
+//!
+//! ```text
+//! <pc> <mnemonic> [operand]                        an instruction, the operand being the rest of the line
+//! <pc> <mnemonic> -> <target>                      an unconditional jump
+//! <pc> <mnemonic> -> <target> taken <fallthrough>  a conditional jump which was taken
+//! <pc> <mnemonic> -> <target> nottaken <fallthrough>
+//!                                                  a conditional jump which was not taken
+//! <pc> <mnemonic> -> <target> call <return>        a call which returns to the given address
+//! <pc> <mnemonic> -> ret                           a return to the most recent call
+//! <pc> <mnemonic> -> halt                          a terminal instruction which halts the program
+//! ```
+
+use std::io::BufRead;
+use crate::ControlFlowGraph;
+use crate::import::parse_address;
+use crate::types::*;
+
+/// Statistics about a trace which was ingested into a ControlFlowGraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IngestStats {
+    /// The number of lines read from the trace, including skipped lines.
+    pub lines: usize,
+    /// The number of instructions executed on the graph.
+    pub instructions: usize,
+    /// The number of BasicBlocks which were created during ingestion.
+    pub blocks_created: usize,
+}
+
+impl ControlFlowGraph {
+    /// Reads a trace in the format documented in the [ingest](crate::ingest) module, executing each line on the ControlFlowGraph.
+    ///
+    /// Malformed lines produce a [CFGError::ParseError] with the line number, counting from one, and an instruction which
+    /// fails to execute a [CFGError::TraceLine] holding the line number and the error.
+    pub fn ingest<R: BufRead>(&mut self, reader: R) -> Result<IngestStats, CFGError> {
+        let mut stats = IngestStats::default();
+        let initial_blocks = self.blocks.len();
+
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            stats.lines += 1;
+            let record = parse_line(&line).map_err(|reason| CFGError::ParseError(line_no + 1, reason))?;
+            if let Some((program_counter, instruction)) = record {
+                self.execute(program_counter, instruction).map_err(|error| CFGError::TraceLine { line: line_no + 1, source: Box::new(error) })?;
+                stats.instructions += 1;
+            }
+        }
+
+        stats.blocks_created = self.blocks.len() - initial_blocks;
+        Ok(stats)
+    }
+}
+
+impl BlockType {
+    /// Parses one line of a trace in the format documented in the [ingest](crate::ingest) module into its program
+    /// counter and instruction, returning `None` for a blank line or a comment.
+    ///
+    /// A malformed line is a [CFGError::ParseError] on line 1 naming the bad token.
+    ///
+    /// ```
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let line = BlockType::try_parse_line("0x4008 JNZ -> 0x4010 taken 0x400a")?;
+    /// assert_eq!(Some((0x4008, BlockType::Jump("JNZ".to_string(), 0x4010, JumpType::ConditionalTaken, Some(0x400a)))), line);
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn try_parse_line(line: &str) -> Result<Option<(u64, BlockType)>, CFGError> {
+        parse_line(line).map_err(|reason| CFGError::ParseError(1, reason))
+    }
+}
+
+/// Parses one line of a trace, returning `None` for lines which are skipped.
+pub(crate) fn parse_line(line: &str) -> Result<Option<(u64, BlockType)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (pc, rest) = split_token(line);
+    let program_counter = parse_address(pc)?;
+    let (name, rest) = split_token(rest);
+    if name.is_empty() {
+        return Err("missing mnemonic".to_string());
+    }
+    let name = name.to_string();
+
+    let Some(jump) = rest.strip_prefix("->") else {
+        let operand = (!rest.is_empty()).then(|| rest.to_string());
+        return Ok(Some((program_counter, BlockType::Instruction(name, operand))));
+    };
+
+    let tokens: Vec<&str> = jump.split_whitespace().collect();
+    let instruction = match tokens.as_slice() {
+        ["ret"] => BlockType::Jump(name, 0, JumpType::Return, None),
+        ["halt"] => BlockType::Terminal(name),
+        [target] => BlockType::Jump(name, parse_address(target)?, JumpType::UnconditionalJump, None),
+        [target, kind, address] => {
+            let jump_type = match *kind {
+                "taken" => JumpType::ConditionalTaken,
+                "nottaken" => JumpType::ConditionalNotTaken,
+                "call" => JumpType::Call,
+                _ => return Err(format!("unknown jump kind `{}`", kind)),
+            };
+            BlockType::Jump(name, parse_address(target)?, jump_type, Some(parse_address(address)?))
+        }
+        _ => return Err(format!("malformed jump `{}`", jump.trim())),
+    };
+    Ok(Some((program_counter, instruction)))
+}
+
+/// Splits off the first whitespace separated token, returning it and the trimmed remainder.
+fn split_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim()),
+        None => (text, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn try_parse_line() -> Result<(), CFGError> {
+        assert_eq!(None, BlockType::try_parse_line("  # a comment")?);
+        assert_eq!(Some((16, BlockType::Instruction("LDAC".to_string(), Some("A, [0x20]".to_string())))), BlockType::try_parse_line("16 LDAC A, [0x20]")?);
+        assert_eq!(Some((0x20, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))), BlockType::try_parse_line("0x20 RET -> ret")?);
+        assert_eq!(Some((0x21, BlockType::Terminal("HLT".to_string()))), BlockType::try_parse_line("0x21 HLT -> halt")?);
+
+        for (line, reason) in [
+            ("-3 INC", "invalid address `-3`"),
+            ("0x5 JNZ -> 0x3 maybe 0x6", "unknown jump kind `maybe`"),
+            ("0x5 JNZ -> 0xg", "invalid address `0xg`"),
+            ("0x5", "missing mnemonic"),
+        ] {
+            let error = BlockType::try_parse_line(line).unwrap_err();
+            assert!(matches!(&error, CFGError::ParseError(1, message) if message == reason), "{}", error);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn ingest_matches_execute() -> Result<(), CFGError> {
+        let trace = "\
+# a loop which runs twice
+0x3 INC
+0x4 LDAC Some Operand
+0x5 JNZ -> 0x3 taken 0x6
+0x3 INC
+0x4 LDAC Some Operand
+0x5 JNZ -> 0x3 nottaken 0x6
+
+6 CALL -> 0x20 call 7
+0x20 RET -> ret
+7 JMP -> 0x2
+";
+        let mut ingested = ControlFlowGraph::new(2);
+        let stats = ingested.ingest(trace.as_bytes())?;
+        assert_eq!(11, stats.lines);
+        assert_eq!(9, stats.instructions);
+        assert_eq!(4, stats.blocks_created);
+
+        let mut executed = ControlFlowGraph::new(2);
+        for jump_type in [JumpType::ConditionalTaken, JumpType::ConditionalNotTaken] {
+            executed.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+            executed.execute(4, BlockType::Instruction("LDAC".to_string(), Some("Some Operand".to_string())))?;
+            executed.execute(5, BlockType::Jump("JNZ".to_string(), 3, jump_type, Some(6)))?;
+        }
+        executed.execute(6, BlockType::Jump("CALL".to_string(), 0x20, JumpType::Call, Some(7)))?;
+        executed.execute(0x20, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        executed.execute(7, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        assert_eq!(executed.to_dot(), ingested.to_dot());
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_lines() {
+        for (trace, line) in [("0x3 INC\nzz INC\n", 2), ("0x3\n", 1), ("0x5 JNZ -> 0x3 maybe 0x6\n", 1), ("0x5 JMP -> \n", 1)] {
+            let mut cfg = ControlFlowGraph::new(2);
+            let result = cfg.ingest(trace.as_bytes());
+            assert!(matches!(result, Err(CFGError::ParseError(l, _)) if l == line), "{:?}", trace);
+        }
+
+        let mut cfg = ControlFlowGraph::new(2);
+        let error = cfg.ingest("0x2 INC\n# returns too early\n0x3 RET -> ret\n".as_bytes()).unwrap_err();
+        assert!(matches!(&error, CFGError::TraceLine { line: 3, source } if matches!(**source, CFGError::ReturnWithoutCall { pc: 0x3 })), "{}", error);
+        assert!(error.to_string().starts_with("Failed to execute line 3 of the trace: "));
+    }
+}
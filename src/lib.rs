@@ -1,150 +1,956 @@
-use std::collections::HashMap;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+use core::ops::{Index, IndexMut, RangeInclusive};
+use crate::prelude::*;
 use crate::types::*;
+use crate::builder::Config;
+use crate::observer::CfgEvent;
 pub mod types;
+pub mod export;
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod ingest;
+#[cfg(feature = "qemu")]
+pub mod qemu;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod diff;
+mod merge;
+pub mod coverage;
+pub mod verify;
+pub mod diagnostics;
+pub mod expect;
+mod builder;
+mod checkpoint;
+mod threads;
+mod structure;
+mod mutate;
+mod critical;
+mod discontinuity;
+mod fallthrough;
+mod terminal;
+mod overlap;
+mod rebase;
+mod binary;
+mod preseed;
+mod sequence;
+mod symbolize;
+mod edge;
+mod symbols;
+mod modules;
+mod subgraph;
+mod observer;
+mod frozen;
+mod reversed;
+mod scale;
+#[cfg(feature = "std")]
+pub mod event_log;
+mod import;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(all(test, not(feature = "std")))]
+mod no_std_tests;
 
-pub struct ControlFlowGraph {
-    /// The indice of the current block
-    current_block: usize,
+pub use builder::CFGBuilder;
+pub use checkpoint::CfgCheckpoint;
+pub use edge::{Edge, EdgeRef};
+pub use symbols::{Symbol, SymbolTable};
+pub use modules::{Module, ModuleAddress};
+pub use observer::CfgObserver;
+pub use frozen::{FrozenBlock, FrozenCfg};
+pub use reversed::ReversedCfg;
+#[cfg(feature = "std")]
+pub use event_log::CfgEventLog;
+pub use symbolize::Symbolizer;
+#[cfg(feature = "std")]
+pub use sync::SyncCfg;
+
+/// The items of the standard prelude which come from alloc, so that they are in scope without the standard library.
+#[allow(unused_imports)]
+mod prelude {
+    pub(crate) use alloc::borrow::ToOwned;
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::format;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec;
+    pub(crate) use alloc::vec::Vec;
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlFlowGraph<I = String> {
+    /// The position of the current block
+    current_block: BlockId,
     /// The BasicBlocks found inside this given ControlFlowGraph
-    blocks: Vec<BasicBlock>
+    blocks: Vec<BasicBlock<I>>,
+    /// The position of each BasicBlock keyed by its start address, ordered so that containing blocks can be found
+    starts: BTreeMap<u64, BlockId>,
+    /// The positions of the blocks with an edge to each block in ascending order, indexed by block position
+    predecessors: Vec<Vec<BlockId>>,
+    /// The number of blocks discovered so far, which is the discovery ordinal of the next block
+    #[cfg_attr(feature = "serde", serde(default))]
+    blocks_discovered: u64,
+    /// The number of edges discovered so far, which is the discovery ordinal of the next edge
+    #[cfg_attr(feature = "serde", serde(default))]
+    edges_discovered: u64,
+    /// The number of instructions executed so far, which is the ordinal the edges traversed by the last one are marked with
+    #[cfg_attr(feature = "serde", serde(default))]
+    executed: u64,
+    /// The modules the address space is made of, in the order they were added
+    #[cfg_attr(feature = "serde", serde(default))]
+    modules: Vec<Module>,
+    /// The shadow call stack of return addresses pushed by calls and popped by returns
+    call_stack: Vec<u64>,
+    /// The address and exclusive end of the previous instruction if it wasn't a jump, which isn't serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    previous_instruction: Option<(u64, u64)>,
+    /// The address of the conditional jump which wasn't taken and whose fall-through is the next instruction, which
+    /// isn't serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pending_fallthrough: Option<u64>,
+    /// The address of the terminal instruction which halted execution, after which nothing is executed until the
+    /// current block is set again
+    #[cfg_attr(feature = "serde", serde(default))]
+    terminated: Option<u64>,
+    /// The current block and call stack of every thread executed through `execute_tid`, keyed by thread id
+    threads: BTreeMap<u64, CfgCheckpoint>,
+    /// The behavior chosen through a CFGBuilder, which isn't serialized and resets to the default
+    #[cfg_attr(feature = "serde", serde(skip))]
+    config: Config<I>,
+    /// The pool of payloads shared between instructions when interning
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pool: BTreeSet<I>,
+    /// The texts handed out as Symbols through `symbol`, which isn't serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    symbols: SymbolTable,
+    /// Notified of every new block and edge, which isn't serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observer: Option<Box<dyn CfgObserver>>,
+    /// Written every structural change to, which isn't serialized
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    event_log: Option<CfgEventLog<I>>,
+    /// Names addresses in the textual output, which isn't serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    symbolizer: Option<Box<dyn Symbolizer>>,
+    /// Whether a hit or traversal count stopped at its maximum instead of wrapping around
+    #[cfg_attr(feature = "serde", serde(default))]
+    saturated: bool,
+    /// Whether every block which is entered is appended to the sequence, which isn't serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    recording: bool,
+    /// The positions of the blocks entered while recording, in the order they were entered
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sequence: Vec<BlockId>
 }
 
-impl ControlFlowGraph {
+impl<I> ControlFlowGraph<I> {
     /// Generates a ControlFlowGraph, starting at the given entry point address.
-    fn new(entry_point: usize) -> Self {
-        ControlFlowGraph { current_block: 0, blocks: vec![BasicBlock::new(entry_point)] }
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0x2);
+    /// cfg.execute(0x3, BlockType::Instruction("INC".to_string(), None))?;
+    /// cfg.execute(0x4, BlockType::Jump("JMP".to_string(), 0x9, JumpType::UnconditionalJump, None))?;
+    /// cfg.execute(0xA, BlockType::Instruction("INC".to_string(), None))?;
+    ///
+    /// assert_eq!(2, cfg.blocks().count());
+    /// assert_eq!(1, cfg.entry().edges().count());
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn new(entry_point: u64) -> Self {
+        let mut entry = BasicBlock::new(entry_point);
+        entry.hits = 1;
+        ControlFlowGraph { current_block: BlockId(0), blocks: vec![entry], starts: BTreeMap::from([(entry_point, BlockId(0))]), predecessors: vec![Vec::new()], blocks_discovered: 1, edges_discovered: 0, executed: 0, modules: Vec::new(), call_stack: Vec::new(), previous_instruction: None, pending_fallthrough: None, terminated: None, threads: BTreeMap::new(), config: Config::default(), pool: BTreeSet::new(), symbols: SymbolTable::default(), observer: None, #[cfg(feature = "std")] event_log: None, symbolizer: None, saturated: false, recording: false, sequence: Vec::new() }
+    }
+
+    /// Generates a ControlFlowGraph from the given blocks without checking any of its invariants.
+    pub(crate) fn from_blocks(blocks: Vec<BasicBlock<I>>, current_block: usize) -> Self {
+        let starts = blocks.iter().enumerate().map(|(index, block)| (block.start, BlockId(index))).collect();
+        let predecessors = predecessor_index(&blocks);
+        let (blocks_discovered, edges_discovered) = (blocks.len() as u64, blocks.iter().map(|block| block.edges.len() as u64).sum());
+        ControlFlowGraph { current_block: BlockId(current_block), blocks, starts, predecessors, blocks_discovered, edges_discovered, executed: 0, modules: Vec::new(), call_stack: Vec::new(), previous_instruction: None, pending_fallthrough: None, terminated: None, threads: BTreeMap::new(), config: Config::default(), pool: BTreeSet::new(), symbols: SymbolTable::default(), observer: None, #[cfg(feature = "std")] event_log: None, symbolizer: None, saturated: false, recording: false, sequence: Vec::new() }
     }
 
-    /// Adds an edge to a BasicBlock, connecting src_block to dest_block.
-    fn add_edge(&mut self, src_block: usize, dest_block: usize, traversed: bool) -> Result<(), CFGError> {
-        let src_block = self.blocks.get_mut(src_block).ok_or(CFGError::MissingBlock)?;
-        src_block.add_edge(dest_block, traversed);
+    /// Overwrites every hit and traversal count of the graph, such as to start a test close to `u64::MAX`.
+    #[cfg(test)]
+    pub(crate) fn seed_counts(&mut self, count: u64) {
+        for block in self.blocks.iter_mut() {
+            block.hits = count;
+            block.instruction_hits.values_mut().for_each(|hits| *hits = count);
+            block.edges.iter_mut().for_each(|edge| edge.set_count(count));
+        }
+    }
+
+    /// Adds an edge of the given kind to a BasicBlock, connecting src_block to dest_block.
+    fn add_edge(&mut self, src_block: BlockId, dest_block: BlockId, traversed: bool, kind: EdgeKind) -> Result<(), CFGError> {
+        if src_block.0 >= self.blocks.len() {
+            return Err(CFGError::MissingBlock { index: src_block.0 });
+        }
+        self.log_event(CfgEvent::Edge { from: src_block, to: dest_block, kind, traversed, ordinal: self.executed });
+        let source = &mut self.blocks[src_block];
+        let previous = source.edge_to(dest_block).map(Edge::count);
+        self.saturated |= source.add_edge(dest_block, traversed, kind);
+        if traversed {
+            if let Some(edge) = source.edges.iter_mut().find(|edge| edge.target() == dest_block) {
+                edge.mark_seen(self.executed);
+            }
+        }
+        if previous.is_none() {
+            self.discover_edge(src_block, dest_block);
+        }
+        if previous.is_none() || (traversed && previous == Some(0)) {
+            self.notify_edge(src_block, dest_block, traversed);
+        }
         Ok(())
     }
 
-    /// Adds a BasicBlock to the ControlFlowGraph and returns the position of the BasicBlock.
-    fn add_block(&mut self, block: BasicBlock) -> usize {
+    /// Adds the given count to the edge from src_block to dest_block, creating it with the given kind if it doesn't exist.
+    pub(crate) fn add_edge_count(&mut self, src_block: BlockId, dest_block: BlockId, count: u64, kind: EdgeKind) {
+        let source = &mut self.blocks[src_block];
+        let created = source.edge_to(dest_block).is_none();
+        self.saturated |= source.add_edge_count(dest_block, count, kind);
+        if created {
+            self.discover_edge(src_block, dest_block);
+        }
+    }
+
+    /// Numbers the edge which was just added from src_block to dest_block with the next discovery ordinal and records
+    /// src_block as a predecessor of dest_block.
+    fn discover_edge(&mut self, src_block: BlockId, dest_block: BlockId) {
+        let ordinal = self.edges_discovered;
+        self.edges_discovered += 1;
+        if let Some(edge) = self.blocks[src_block].edges.iter_mut().find(|edge| edge.target() == dest_block) {
+            edge.set_discovered_at(ordinal);
+        }
+        self.link(src_block, dest_block);
+    }
+
+    /// Records src_block as a predecessor of dest_block, keeping the predecessors in ascending order.
+    fn link(&mut self, src_block: BlockId, dest_block: BlockId) {
+        let predecessors = &mut self.predecessors[dest_block.0];
+        if let Err(position) = predecessors.binary_search(&src_block) {
+            predecessors.insert(position, src_block);
+        }
+    }
+
+    /// Removes src_block from the predecessors of dest_block.
+    fn unlink(&mut self, src_block: BlockId, dest_block: BlockId) {
+        let predecessors = &mut self.predecessors[dest_block.0];
+        if let Ok(position) = predecessors.binary_search(&src_block) {
+            predecessors.remove(position);
+        }
+    }
+
+    /// Recomputes the predecessors of every block from the edges, after the edges were replaced wholesale.
+    pub(crate) fn rebuild_predecessors(&mut self) {
+        self.predecessors = predecessor_index(&self.blocks);
+    }
+
+    /// Adds a BasicBlock to the ControlFlowGraph, numbering it with the next discovery ordinal, and returns the position of the BasicBlock.
+    fn add_block(&mut self, mut block: BasicBlock<I>) -> BlockId {
+        block.discovered_at = self.blocks_discovered;
+        self.blocks_discovered += 1;
+        let id = BlockId(self.blocks.len());
+        self.starts.insert(block.start, id);
         self.blocks.push(block);
-        self.blocks.len() - 1
+        self.predecessors.push(Vec::new());
+        self.notify_block(id);
+        id
     }
 
     /// Searches for the block with the given start address and returns the position of it or creates a new one.
-    fn query_block_or_create(&mut self, address: usize) -> usize {
-        self.blocks.iter().position(|bb| bb.start == address).unwrap_or_else(|| { let new_block = BasicBlock::new(address); self.add_block(new_block) } )
+    ///
+    /// If the address lands inside of an existing block, that block is split and the position of the tail is returned,
+    /// unless the OverlapPolicy rejects the overlap with `CFGError::OverlappingBlock`.
+    fn query_block_or_create(&mut self, address: u64) -> Result<BlockId, CFGError> {
+        if let Some(&index) = self.starts.get(&address) {
+            return Ok(index);
+        }
+        if let Some(index) = self.overlapped_block(address) {
+            return match self.config.overlap_policy {
+                OverlapPolicy::Split => Ok(self.split_block(index, address)),
+                OverlapPolicy::Error => Err(self.overlap_error(index, address)),
+            };
+        }
+        Ok(self.create_block(address))
+    }
+
+    /// Creates an empty block at the given address, which must not be inside of an existing block, and returns its position.
+    fn create_block(&mut self, address: u64) -> BlockId {
+        self.log_event(CfgEvent::Block { start: address });
+        self.add_block(BasicBlock::new(address))
+    }
+
+    /// Splits the block at the given position into a head and a tail starting at the given address, returning the position of the tail.
+    ///
+    /// The tail takes over the outgoing edges of the original block and the head falls through into the tail. If the
+    /// current block is split, such as by a jump back into its own middle, the tail becomes current so that the jump
+    /// leaves from the tail and loops onto it.
+    fn split_block(&mut self, index: BlockId, address: u64) -> BlockId {
+        self.log_event(CfgEvent::Split { block: index, address });
+        let head = &mut self.blocks[index];
+        let tail_instructions = head.block.split_off(&address);
+        head.end = head.block.keys().next_back().copied().unwrap_or(head.start);
+
+        let mut tail = BasicBlock::new(address);
+        tail.end = tail_instructions.keys().next_back().copied().unwrap_or(address);
+        tail.block = tail_instructions;
+        tail.instruction_hits = head.instruction_hits.split_off(&address);
+        tail.sizes = head.sizes.split_off(&address);
+        tail.edges = core::mem::take(&mut head.edges);
+        // Every pass through the original block flowed through the split point.
+        tail.hits = head.hits;
+        tail.metadata = head.metadata.clone();
+        // The terminal instruction is the last one, so it ends up in the tail.
+        tail.exit = core::mem::take(&mut head.exit);
+
+        let fall_through = tail.hits;
+        let tail_index = self.add_block(tail);
+        // The tail leaves along the edges which the head left along before.
+        for target in self.blocks[tail_index].edges.iter().map(Edge::target).collect::<Vec<_>>() {
+            self.unlink(index, target);
+            self.link(tail_index, target);
+        }
+        self.blocks[index].edges.push(Edge::new(tail_index, fall_through, EdgeKind::FallThrough));
+        self.discover_edge(index, tail_index);
+        self.notify_edge(index, tail_index, fall_through > 0);
+
+        if self.current_block == index {
+            self.current_block = tail_index;
+        }
+        // The threads which aren't executing stay in the head, unless the last instruction they executed is in the tail.
+        for cursor in self.threads.values_mut() {
+            let last = cursor.previous_instruction.map(|(pc, _)| pc).or(cursor.pending_fallthrough).or(cursor.terminated);
+            if cursor.current_block == index && last.is_some_and(|pc| pc >= address) {
+                cursor.current_block = tail_index;
+            }
+        }
+        tail_index
+    }
+
+    /// Returns the entry BasicBlock, which is the block created for the entry point address.
+    pub fn entry(&self) -> &BasicBlock<I> {
+        &self.blocks[0]
+    }
+
+    /// Returns the position of the entry BasicBlock, which is always the first block.
+    pub fn entry_index(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    /// Returns the position of the BasicBlock which execution is currently inside of.
+    pub fn current_block_index(&self) -> BlockId {
+        self.current_block
+    }
+
+    /// Returns the BasicBlock at the given position, if it exists.
+    pub fn block(&self, index: BlockId) -> Option<&BasicBlock<I>> {
+        self.blocks.get(index.0)
+    }
+
+    /// Returns whether any hit or traversal count of the graph reached `u64::MAX` and stopped counting there, in which
+    /// case the counts are lower bounds rather than exact.
+    pub fn counts_saturated(&self) -> bool {
+        self.saturated
+    }
+
+    /// Returns how an instruction which differs from the one already recorded at its address is handled.
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.config.conflict_policy
+    }
+
+    /// Returns how a block which would start inside of an existing block is handled.
+    pub fn overlap_policy(&self) -> OverlapPolicy {
+        self.config.overlap_policy
+    }
+
+    /// Attaches the value to the BasicBlock at the given position under the given key.
+    ///
+    /// Annotations are copied to both halves when a block is later split.
+    pub fn annotate(&mut self, block_index: BlockId, key: impl Into<String>, value: impl Into<String>) -> Result<(), CFGError> {
+        let block = self.blocks.get_mut(block_index.0).ok_or(CFGError::MissingBlock { index: block_index.0 })?;
+        block.set_meta(key, value);
+        Ok(())
+    }
+
+    /// Returns the number of BasicBlocks inside the ControlFlowGraph.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns whether the ControlFlowGraph has no BasicBlocks, which is never the case as the entry block always exists.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
     }
 
     /// Returns an iterator over the BasicBlocks inside the ControlFlowGraph
-    pub fn blocks(&self) -> impl Iterator<Item=&BasicBlock> {
+    pub fn blocks(&self) -> impl Iterator<Item=&BasicBlock<I>> {
         self.blocks.iter()
     }
 
-    /// Executes the given BlockType on the ControlFlowGraph
-    pub fn execute(&mut self, program_counter: usize, instruction: BlockType) -> Result<(), CFGError> {
-        match instruction {
-            BlockType::Instruction(name, operand) => {
-                let curr_block = self.blocks.get_mut(self.current_block).ok_or(CFGError::MissingCurrentBlock)?;
-                assert!(program_counter >= curr_block.start, "Attempted to add an instruction behind the starting of the current block."); // TODO: Potentially generate an error-type?
-                if !curr_block.block.contains_key(&program_counter) {
-                    curr_block.add_instruction(program_counter, BlockType::Instruction(name, operand));
-                }
+    /// Returns an iterator over the BasicBlocks inside the ControlFlowGraph in order of their start address
+    pub fn blocks_by_address(&self) -> impl Iterator<Item=&BasicBlock<I>> {
+        self.starts.values().map(move |index| &self.blocks[*index])
+    }
+
+    /// Returns the position of the BasicBlock whose bytes from its start up to the last byte of its last instruction
+    /// contain the given address.
+    pub fn find_block_containing(&self, address: u64) -> Option<BlockId> {
+        let (_, &index) = self.starts.range(..=address).next_back()?;
+        (address <= self.blocks[index].last_byte()).then_some(index)
+    }
 
-                Ok(())
+    /// Keeps only the blocks whose position is marked in `keep`, compacting the positions of the remaining blocks.
+    ///
+    /// Edges to removed blocks and their entries in the recorded sequence are dropped, and the entry and current block
+    /// of the graph and every thread must be kept.
+    fn retain_blocks(&mut self, keep: &[bool]) {
+        let mut remap = vec![None; self.blocks.len()];
+        let mut next = 0;
+        for (index, kept) in keep.iter().enumerate() {
+            if *kept {
+                remap[index] = Some(BlockId(next));
+                next += 1;
             }
-            BlockType::Jump(name, success_address, jump_type, failure_address) => {
-                // Add the instruction to the current block, if we already haven't
-                let curr_block = self.blocks.get_mut(self.current_block).ok_or(CFGError::MissingCurrentBlock)?;
-                assert!(program_counter >= curr_block.start, "Attempted to add an instruction behind the starting of the current block.");
-                if !curr_block.block.contains_key(&program_counter) {
-                    // NOTE: Should check if this creating a copy or just using move semantics to use the same thing of memory...
-                    curr_block.add_instruction(program_counter, BlockType::Jump(name, success_address, jump_type, failure_address));
+        }
+
+        let blocks = core::mem::take(&mut self.blocks);
+        self.blocks = blocks.into_iter().zip(keep).filter(|(_, kept)| **kept).map(|(mut block, _)| {
+            block.remap_edges(&remap);
+            block
+        }).collect();
+        self.starts = self.blocks.iter().enumerate().map(|(index, block)| (block.start, BlockId(index))).collect();
+        self.rebuild_predecessors();
+        self.current_block = remap[self.current_block.0].expect("the current block is kept");
+        self.sequence.retain_mut(|block| match remap[block.0] {
+            Some(position) => {
+                *block = position;
+                true
+            }
+            None => false,
+        });
+        for cursor in self.threads.values_mut() {
+            cursor.current_block = remap[cursor.current_block.0].expect("the current block of every thread is kept");
+        }
+    }
+
+    /// Makes the block at the given position the current block, counting the entry into it.
+    fn enter_block(&mut self, index: BlockId) {
+        self.log_event(CfgEvent::Enter { block: index });
+        self.saturated |= saturating_increase(&mut self.blocks[index].hits, 1);
+        self.current_block = index;
+        if self.recording {
+            self.sequence.push(index);
+        }
+    }
+
+    /// Traverses the edge of the given kind from the current block to the block at the given position and enters it.
+    fn traverse_edge(&mut self, index: BlockId, kind: EdgeKind) -> Result<ExecOutcome, CFGError> {
+        let from = self.current_block;
+        self.add_edge(from, index, true, kind)?;
+        self.enter_block(index);
+
+        let traversed = self.blocks[from].edge_to(index).map(Edge::count);
+        Ok(if traversed == Some(1) { ExecOutcome::NewEdge { from, to: index } } else { ExecOutcome::ExistingEdgeIncremented { from, to: index } })
+    }
+
+    /// Returns the positions of the blocks which the block at the given position has an edge to, in the order they were added.
+    pub fn successors(&self, block_index: BlockId) -> Result<Vec<BlockId>, CFGError> {
+        let block = self.blocks.get(block_index.0).ok_or(CFGError::MissingBlock { index: block_index.0 })?;
+        Ok(block.edges.iter().map(Edge::target).collect())
+    }
+
+    /// Returns the positions of the blocks which have an edge to the block at the given position, in ascending order.
+    pub fn predecessors(&self, block_index: BlockId) -> Result<Vec<BlockId>, CFGError> {
+        self.predecessors.get(block_index.0).cloned().ok_or(CFGError::MissingBlock { index: block_index.0 })
+    }
+
+    /// Returns the number of edges leaving the block at the given position, whether or not they were traversed.
+    pub fn out_degree(&self, block_index: BlockId) -> Result<usize, CFGError> {
+        self.blocks.get(block_index.0).map(|block| block.edges.len()).ok_or(CFGError::MissingBlock { index: block_index.0 })
+    }
+
+    /// Returns the number of edges leading to the block at the given position, whether or not they were traversed.
+    ///
+    /// The predecessors of every block are kept up to date as edges are added, so this doesn't scan the graph.
+    pub fn in_degree(&self, block_index: BlockId) -> Result<usize, CFGError> {
+        self.predecessors.get(block_index.0).map(Vec::len).ok_or(CFGError::MissingBlock { index: block_index.0 })
+    }
+
+    /// Adds the instruction to the current block at the given address, if it isn't already recorded.
+    ///
+    /// Reaching the start of another block without a jump falls through into that block, which becomes the current block.
+    fn record_instruction(&mut self, program_counter: u64, instruction: BlockType<I>, size: u64) -> Result<ExecOutcome, CFGError> {
+        let mut outcome = ExecOutcome::KnownInstruction;
+        if let Some(&index) = self.starts.get(&program_counter) {
+            if index != self.current_block {
+                if self.config.fall_through_edges {
+                    outcome = self.traverse_edge(index, EdgeKind::FallThrough)?;
+                } else {
+                    self.enter_block(index);
                 }
-                match jump_type {
-                    JumpType::UnconditionalJump => {
-                        let success_index = self.query_block_or_create(success_address);
-                        self.add_edge(self.current_block, success_index, true)?;
-                        self.current_block = success_index;
-                        Ok(())
+            }
+        }
+
+        let block = self.current_block;
+        let curr_block = self.blocks.get_mut(block.0).ok_or(CFGError::MissingCurrentBlock { index: block.0 })?;
+        if program_counter < curr_block.start {
+            return Err(CFGError::AddressBeforeBlockStart { pc: program_counter, block, block_start: curr_block.start });
+        }
+        // Nothing follows the terminal instruction which ends an exit block.
+        if curr_block.exit && program_counter > curr_block.end {
+            return Err(CFGError::ExecutionAfterTerminal { pc: program_counter, terminal: curr_block.end });
+        }
+        if let Some(existing) = curr_block.block.get(&program_counter) {
+            match self.config.conflict_policy {
+                ConflictPolicy::KeepFirst => {}
+                policy => {
+                    if let Some((existing, new)) = self.config.conflict.and_then(|describe| describe(existing, &instruction)) {
+                        if policy == ConflictPolicy::Error {
+                            return Err(CFGError::InstructionConflict { pc: program_counter, existing, new });
+                        }
+                        self.log_event(CfgEvent::Instruction { block, pc: program_counter, size, instruction: &instruction });
+                        let instruction = self.intern(instruction);
+                        self.blocks[block].block.insert(program_counter, instruction);
                     }
-                    JumpType::ConditionalTaken => {
-                        // Failure address needs to be defined.
-                        let failure_address = failure_address.ok_or(CFGError::ExpectedFailureAddress)?;
+                }
+            }
+            self.log_event(CfgEvent::Hit { block, pc: program_counter, size });
+            let hits = self.blocks[block].instruction_hits.get_mut(&program_counter).unwrap();
+            self.saturated |= saturating_increase(hits, 1);
+        } else {
+            self.log_event(CfgEvent::Instruction { block, pc: program_counter, size, instruction: &instruction });
+            let instruction = self.intern(instruction);
+            self.blocks[block].add_instruction(program_counter, instruction);
+            outcome = outcome.or(ExecOutcome::NewInstruction);
+        }
+        self.blocks[block].set_instruction_size(program_counter, size);
 
-                        let failure_index = self.query_block_or_create(failure_address);
-                        self.add_edge(self.current_block, failure_index, false)?;
-                        let success_index = self.query_block_or_create(success_address);
-                        self.add_edge(self.current_block, success_index, true)?;
-                        self.current_block = success_index;
+        Ok(outcome)
+    }
 
-                        Ok(())
+    /// Replaces the payloads of the instruction with shared ones from the pool, if interning was enabled.
+    fn intern(&mut self, instruction: BlockType<I>) -> BlockType<I> {
+        match self.config.intern {
+            Some(intern) => intern(&mut self.pool, instruction),
+            None => instruction,
+        }
+    }
+
+    /// Records both possible targets of a conditional jump from the current block, counting only the one which was followed.
+    fn conditional_jump(&mut self, success_address: u64, failure_address: u64, taken: bool) -> Result<ExecOutcome, CFGError> {
+        // Resolve both targets before adding edges, as either lookup may split the current block.
+        let failure_index = self.query_block_or_create(failure_address)?;
+        let success_index = self.query_block_or_create(success_address)?;
+        self.add_edge(self.current_block, failure_index, false, EdgeKind::FallThrough)?;
+        self.add_edge(self.current_block, success_index, false, EdgeKind::Taken)?;
+        if taken {
+            self.traverse_edge(success_index, EdgeKind::Taken)
+        } else {
+            self.traverse_edge(failure_index, EdgeKind::FallThrough)
+        }
+    }
+
+    /// Executes an indirect jump at the given address which was observed to jump to the given target.
+    ///
+    /// This is shorthand for executing a `Jump` of `JumpType::Indirect` with the observed target as its success address.
+    pub fn execute_indirect(&mut self, program_counter: u64, instruction_name: I, observed_target: u64) -> Result<ExecOutcome, CFGError> {
+        self.execute(program_counter, BlockType::Jump(instruction_name, observed_target, JumpType::Indirect, None))
+    }
+
+    /// Executes a switch at the given address which jumped to the given target out of those listed in its table.
+    ///
+    /// Every target in the table gets a BasicBlock and an edge, with only the edge to the taken target being traversed.
+    pub fn execute_switch(&mut self, program_counter: u64, instruction_name: I, targets: Vec<u64>, taken_target: u64) -> Result<ExecOutcome, CFGError> {
+        if !targets.contains(&taken_target) {
+            return Err(CFGError::UnknownSwitchTarget { pc: program_counter, target: taken_target });
+        }
+        self.check_terminated(program_counter)?;
+        self.executed = self.executed.saturating_add(1);
+        let known_blocks = self.blocks.len();
+        let restarted = match self.follow_fallthrough(program_counter)? {
+            Some(traversed) => Some(traversed),
+            None => self.follow_discontinuity(program_counter)?,
+        };
+        let recorded = self.record_instruction(program_counter, BlockType::Switch(instruction_name, targets.clone()), 1)?;
+
+        // Resolve every target first, as creating one may split the block holding the switch.
+        let indices = targets.iter().map(|target| self.query_block_or_create(*target)).collect::<Result<Vec<BlockId>, CFGError>>()?;
+        for index in indices.iter() {
+            self.add_edge(self.current_block, *index, false, EdgeKind::Taken)?;
+        }
+        let taken_index = indices[targets.iter().position(|target| *target == taken_target).unwrap()];
+        let traversed = self.traverse_edge(taken_index, EdgeKind::Taken)?;
+        self.previous_instruction = None;
+
+        let outcome = restarted.map_or(recorded, |restarted| restarted.or(recorded));
+        Ok(self.jump_outcome(known_blocks, outcome.or(traversed)))
+    }
+
+    /// Reports the current block as new if it was created after the graph had the given number of blocks.
+    fn jump_outcome(&self, known_blocks: usize, outcome: ExecOutcome) -> ExecOutcome {
+        if self.current_block.0 >= known_blocks { ExecOutcome::NewBlock(self.current_block) } else { outcome }
+    }
+
+    /// Executes the given BlockType on the ControlFlowGraph, returning the most notable change it made.
+    ///
+    /// The instruction is assumed to be a single byte long, use `execute_sized` when instruction sizes are known.
+    pub fn execute(&mut self, program_counter: u64, instruction: BlockType<I>) -> Result<ExecOutcome, CFGError> {
+        self.execute_with_size(program_counter, instruction, None)
+    }
+
+    /// Executes the given BlockType on the ControlFlowGraph like `execute`, recording that it is `size` bytes long.
+    ///
+    /// The size of the last instruction of a block determines its exclusive end, which is used to find the block
+    /// containing an address and by the exports which report byte ranges. A size of zero is treated as one, and
+    /// executing the same address again with another size replaces it.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0x10);
+    /// cfg.execute_sized(0x10, BlockType::Instruction("MOV".to_string(), None), 3)?;
+    /// cfg.execute_sized(0x13, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None), 2)?;
+    ///
+    /// assert_eq!((0x13, 0x15), (cfg.entry().end(), cfg.entry().end_exclusive()));
+    /// assert_eq!(Some(cfg.entry_index()), cfg.find_block_containing(0x14));
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn execute_sized(&mut self, program_counter: u64, instruction: BlockType<I>, size: u64) -> Result<ExecOutcome, CFGError> {
+        self.execute_with_size(program_counter, instruction, Some(size))
+    }
+
+    /// Executes the given BlockType, which is a single byte long unless its size is known.
+    fn execute_with_size(&mut self, program_counter: u64, mut instruction: BlockType<I>, known_size: Option<u64>) -> Result<ExecOutcome, CFGError> {
+        self.check_terminated(program_counter)?;
+        self.executed = self.executed.saturating_add(1);
+        let size = known_size.unwrap_or(1);
+        let known_blocks = self.blocks.len();
+        let restarted = match self.follow_fallthrough(program_counter)? {
+            Some(traversed) => Some(traversed),
+            None => self.follow_discontinuity(program_counter)?,
+        };
+        let next = matches!(instruction, BlockType::Instruction(_, _)).then(|| (program_counter, program_counter.saturating_add(size.max(1))));
+        if let BlockType::Jump(_, _, JumpType::ConditionalTaken | JumpType::ConditionalNotTaken, failure_address @ None) = &mut instruction {
+            // An inferred failure address is recorded with the jump, as if it had been executed with it.
+            if self.config.infer_fallthrough {
+                *failure_address = known_size.map(|size| program_counter.saturating_add(size.max(1))).or_else(|| self.recorded_failure(program_counter));
+            }
+        }
+        let outcome = match instruction {
+            BlockType::Instruction(_, _) => self.record_instruction(program_counter, instruction, size),
+            BlockType::Terminal(_) => self.terminate(program_counter, instruction, size),
+            // The taken target of a switch can't be known from the instruction alone.
+            BlockType::Switch(_, _) => Err(CFGError::ExpectedSwitchTarget { pc: program_counter }),
+            BlockType::Jump(_, success_address, jump_type, failure_address) => {
+                // Add the instruction to the current block, if we already haven't
+                let recorded = self.record_instruction(program_counter, instruction, size)?;
+                let traversed = match jump_type {
+                    // An indirect jump is unconditional, with each observed target becoming its own edge.
+                    JumpType::UnconditionalJump | JumpType::Indirect => {
+                        let success_index = self.query_block_or_create(success_address)?;
+                        self.traverse_edge(success_index, EdgeKind::Unconditional)
                     }
-                    JumpType::ConditionalNotTaken => {
-                        // Failure address needs to be defined.
-                        let failure_address = failure_address.ok_or(CFGError::ExpectedFailureAddress)?;
+                    JumpType::ConditionalTaken | JumpType::ConditionalNotTaken => {
+                        let taken = matches!(jump_type, JumpType::ConditionalTaken);
+                        match failure_address {
+                            Some(failure_address) => self.conditional_jump(success_address, failure_address, taken),
+                            None if self.config.infer_fallthrough => self.defer_fallthrough(program_counter, success_address, taken),
+                            // Failure address needs to be defined.
+                            None => Err(CFGError::ExpectedFailureAddress { pc: program_counter, jump_type }),
+                        }
+                    }
+                    JumpType::Call => {
+                        // The failure address is where execution resumes once the callee returns.
+                        let failure_address = failure_address.ok_or(CFGError::ExpectedFailureAddress { pc: program_counter, jump_type })?;
+                        self.call_stack.push(failure_address);
 
-                        let failure_index = self.query_block_or_create(failure_address);
-                        self.add_edge(self.current_block, failure_index, true)?;
-                        let success_index = self.query_block_or_create(success_address);
-                        self.add_edge(self.current_block, success_index, false)?;
-                        self.current_block = failure_index;
+                        let success_index = self.query_block_or_create(success_address)?;
+                        self.traverse_edge(success_index, EdgeKind::Call)
+                    }
+                    JumpType::Return => {
+                        let return_address = self.call_stack.pop().ok_or(CFGError::ReturnWithoutCall { pc: program_counter })?;
 
-                        Ok(())
+                        let return_index = self.query_block_or_create(return_address)?;
+                        self.traverse_edge(return_index, EdgeKind::Return)
                     }
-                }
+                }?;
+                Ok(self.jump_outcome(known_blocks, recorded.or(traversed)))
+            }
+        }?;
+        self.previous_instruction = next;
+        Ok(match restarted {
+            Some(traversed) => self.jump_outcome(known_blocks, traversed.or(outcome)),
+            None => outcome,
+        })
+    }
+
+}
+
+impl<I: PartialEq + fmt::Display> ControlFlowGraph<I> {
+    /// Sets how an instruction which differs from the one already recorded at its address is handled from now on.
+    ///
+    /// Executions of the same jump which differ only in the direction of a conditional or the target of an indirect
+    /// jump or return are never a conflict.
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.config.conflict_policy = policy;
+        self.config.conflict = Some(builder::describe_conflict::<I>);
+    }
+}
+
+impl<I> Index<BlockId> for Vec<BasicBlock<I>> {
+    type Output = BasicBlock<I>;
+
+    fn index(&self, index: BlockId) -> &BasicBlock<I> {
+        &self[index.0]
+    }
+}
+
+impl<I> IndexMut<BlockId> for Vec<BasicBlock<I>> {
+    fn index_mut(&mut self, index: BlockId) -> &mut BasicBlock<I> {
+        &mut self[index.0]
+    }
+}
+
+/// Adds the amount to the count, stopping at `u64::MAX` instead of wrapping, and returns whether it had to stop.
+pub(crate) fn saturating_increase(count: &mut u64, amount: u64) -> bool {
+    let (sum, overflowed) = count.overflowing_add(amount);
+    *count = if overflowed { u64::MAX } else { sum };
+    overflowed
+}
+
+/// Returns the positions of the blocks with an edge to each block in ascending order, skipping edges to missing blocks.
+pub(crate) fn predecessor_index<I>(blocks: &[BasicBlock<I>]) -> Vec<Vec<BlockId>> {
+    let mut predecessors = vec![Vec::new(); blocks.len()];
+    for (index, block) in blocks.iter().enumerate() {
+        for edge in block.edges.iter() {
+            if let Some(predecessors) = predecessors.get_mut(edge.target().0) {
+                predecessors.push(BlockId(index));
             }
         }
     }
+    predecessors
+}
 
+impl<I> Default for ControlFlowGraph<I> {
+    /// Generates a ControlFlowGraph with an entry point address of zero.
+    fn default() -> Self {
+        ControlFlowGraph::new(0)
+    }
 }
 
 
-pub struct BasicBlock {
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BasicBlock<I = String> {
     /// The starting address of this basic block.
-    start: usize,
+    start: u64,
     /// The current end address of this basic block.
-    end: usize,
-    /// The mapping of each address to its respective BlockType.
-    block: HashMap<usize, BlockType>,
-    /// The edges for the given basic block which are indices to other BasicBlocks
-    edges: Vec<(usize, usize)>
+    end: u64,
+    /// The mapping of each address to its respective BlockType, ordered by address.
+    block: BTreeMap<u64, BlockType<I>>,
+    /// The edges for the given basic block which lead to other BasicBlocks, with their traversal counts and kinds
+    edges: Vec<Edge>,
+    /// The number of times execution has entered this basic block.
+    hits: u64,
+    /// The number of times each address inside of this basic block has been executed.
+    instruction_hits: BTreeMap<u64, u64>,
+    /// The size in bytes of each instruction which isn't a single byte long.
+    #[cfg_attr(feature = "serde", serde(default))]
+    sizes: BTreeMap<u64, u64>,
+    /// User defined annotations attached to this basic block.
+    metadata: BTreeMap<String, String>,
+    /// How many blocks the graph discovered before this one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    discovered_at: u64,
+    /// Whether this basic block ends with a terminal instruction, which exits the program.
+    #[cfg_attr(feature = "serde", serde(default))]
+    exit: bool
 }
 
-impl BasicBlock {
+impl<I> BasicBlock<I> {
     /// Generates a new BasicBlock with a given start address
-    fn new(start:usize) -> Self {
-        BasicBlock { start: start, end: start, block: HashMap::new(), edges: Vec::new() }
+    fn new(start:u64) -> Self {
+        BasicBlock { start, end: start, block: BTreeMap::new(), edges: Vec::new(), hits: 0, instruction_hits: BTreeMap::new(), sizes: BTreeMap::new(), metadata: BTreeMap::new(), discovered_at: 0, exit: false }
     }
 
-    /// Adds an instruction of BlockType to the given BasicBlock at the given address in the underlying HashMap.
-    fn add_instruction(&mut self, address:usize, instruction: BlockType) {
+    /// Adds an instruction of BlockType to the given BasicBlock at the given address in the underlying BTreeMap.
+    fn add_instruction(&mut self, address:u64, instruction: BlockType<I>) {
+        self.exit |= matches!(instruction, BlockType::Terminal(_));
         self.block.insert(address, instruction);
-        self.end = address;
+        self.instruction_hits.insert(address, 1);
+        // Instructions can be recorded out of order, such as when replaying a sorted coverage set, so the end is the
+        // highest address rather than the latest one.
+        self.end = self.end.max(address);
+    }
+
+    /// Records the size in bytes of the instruction at the given address, only storing sizes other than one.
+    fn set_instruction_size(&mut self, address: u64, size: u64) {
+        if size > 1 {
+            self.sizes.insert(address, size);
+        } else {
+            self.sizes.remove(&address);
+        }
+    }
+
+    /// Returns the starting address of this BasicBlock.
+    pub fn start(&self) -> u64 {
+        self.start
     }
 
-    /// Returns an iterator of the address/instruction pairs inside the underlying HashMap.
-    pub fn instructions(&self) -> impl Iterator<Item=(&usize, &BlockType)> {
+    /// Returns the address of the last instruction inside this BasicBlock, or the start if it has no instructions.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Returns the address right after the last byte of the last instruction inside this BasicBlock.
+    ///
+    /// Instructions executed without a size are a single byte long, so this is one past the end by default. A block
+    /// reaching the top of the address space has no such address, so this saturates at `u64::MAX`, see `range` instead.
+    pub fn end_exclusive(&self) -> u64 {
+        self.end.saturating_add(self.sizes.get(&self.end).copied().unwrap_or(1))
+    }
+
+    /// Returns the address of the last byte of the last instruction inside this BasicBlock, saturating at `u64::MAX`.
+    pub(crate) fn last_byte(&self) -> u64 {
+        self.end.saturating_add(self.sizes.get(&self.end).copied().unwrap_or(1) - 1)
+    }
+
+    /// Returns the size in bytes of the instruction at the given address, if this BasicBlock holds one there.
+    pub fn instruction_size(&self, address: u64) -> Option<u64> {
+        self.block.contains_key(&address).then(|| self.sizes.get(&address).copied().unwrap_or(1))
+    }
+
+    /// Returns the addresses spanned by this BasicBlock, from its start to the last byte of its last instruction.
+    pub fn range(&self) -> RangeInclusive<u64> {
+        self.start..=self.last_byte()
+    }
+
+    /// Returns the number of addresses spanned by this BasicBlock, which is at least its number of instructions.
+    ///
+    /// A block spanning the whole address space has one more address than fits, so this saturates at `u64::MAX`.
+    pub fn len_addresses(&self) -> u64 {
+        (self.last_byte() - self.start).saturating_add(1)
+    }
+
+    /// Returns the number of instructions inside this BasicBlock.
+    pub fn len(&self) -> usize {
+        self.block.len()
+    }
+
+    /// Returns whether this BasicBlock has no instructions, such as a block created for a branch which was never taken.
+    pub fn is_empty(&self) -> bool {
+        self.block.is_empty()
+    }
+
+    /// Returns whether this BasicBlock ends with a terminal instruction, which exits the program.
+    pub fn is_exit(&self) -> bool {
+        self.exit
+    }
+
+    /// Attaches the value to this BasicBlock under the given key, returning the value it replaced.
+    pub fn set_meta(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.metadata.insert(key.into(), value.into())
+    }
+
+    /// Returns the value attached to this BasicBlock under the given key.
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    /// Returns an iterator of the key/value annotations of this BasicBlock, in ascending key order.
+    pub fn meta_iter(&self) -> impl Iterator<Item=(&str, &str)> {
+        self.metadata.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the number of times execution has entered this BasicBlock.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns how many blocks the graph discovered before this one, counting the blocks which were removed since, so
+    /// the entry is discovered at zero.
+    ///
+    /// A tail split off of a block is discovered when it is split off. Graphs deserialized from before discovery
+    /// ordinals were recorded have an ordinal of zero on every block.
+    pub fn discovered_at(&self) -> u64 {
+        self.discovered_at
+    }
+
+    /// Returns the number of times the instruction at the given address was executed, if it was recorded in this BasicBlock.
+    pub fn instruction_hits(&self, address: u64) -> Option<u64> {
+        self.instruction_hits.get(&address).copied()
+    }
+
+    /// Returns an iterator of the address/instruction pairs inside the underlying BTreeMap, in ascending address order.
+    pub fn instructions(&self) -> impl Iterator<Item=(&u64, &BlockType<I>)> {
         self.block.iter()
     }
 
-    /// Returns an iterator of the edges/count pairs inside the underlying Vector.
-    pub fn edges(&self) -> impl Iterator<Item=&(usize, usize)> {
+    /// Returns an iterator of the edges inside the underlying Vector, in the order they were first added.
+    pub fn edges(&self) -> impl Iterator<Item=&Edge> {
         self.edges.iter()
     }
 
+    /// Returns the edge to the block at the given position, if this BasicBlock has one.
+    pub fn edge_to(&self, target: BlockId) -> Option<&Edge> {
+        self.edges.iter().find(|edge| edge.target() == target)
+    }
+
     /// Adds a new edge if it cannot find it, otherwise increments the edge counter depending on if it was traversed or not.
-    fn add_edge(&mut self, edge: usize, traversed: bool) {
-        if let Some((_, cnt)) = self.edges.iter_mut().find(|(e, _)| *e == edge) {
-            *cnt += traversed as usize;
+    ///
+    /// Returns whether the counter saturated.
+    fn add_edge(&mut self, edge: BlockId, traversed: bool, kind: EdgeKind) -> bool {
+        self.add_edge_count(edge, traversed as u64, kind)
+    }
+
+    /// Adds a new edge with the given count if it cannot find it, otherwise adds the count to the edge counter.
+    ///
+    /// An existing edge keeps the kind it was created with, unless it was pre-seeded without one. Returns whether the
+    /// counter saturated.
+    fn add_edge_count(&mut self, edge: BlockId, count: u64, kind: EdgeKind) -> bool {
+        if let Some(existing) = self.edges.iter_mut().find(|e| e.target() == edge) {
+            existing.observe_kind(kind);
+            existing.add_count(count)
         } else {
-            self.edges.push((edge, traversed as usize));
+            self.edges.push(Edge::new(edge, count, kind));
+            false
         }
     }
 
+    /// Points every edge at the new position of its target, dropping the edges whose target has none.
+    fn remap_edges(&mut self, remap: &[Option<BlockId>]) {
+        self.edges.retain_mut(|edge| match remap[edge.target().0] {
+            Some(target) => {
+                edge.retarget(target);
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Returns the position and traversal count of every edge, in the order they were first added.
+    #[cfg(test)]
+    pub(crate) fn edge_counts(&self) -> Vec<(BlockId, u64)> {
+        self.edges.iter().map(|edge| (edge.target(), edge.count())).collect()
+    }
+
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::expect::ExpectedCfg;
 
     #[test]
     fn unconditional_jump() -> Result<(), CFGError> {
@@ -153,9 +959,10 @@ mod tests {
         cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("SomeOperand".to_string())))?;
         cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::UnconditionalJump, None))?;
         cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
-        assert_eq!(2, cfg.blocks.len());
-        assert_eq!(1, cfg.blocks.get(0).unwrap().edges.len());
 
+        let expected = ExpectedCfg::entry(2).insn(3, "INC").insn(4, "LDAC SomeOperand").insn(5, "JMP").jmp_to_count(9, 1)
+            .block(9).insn(10, "INC");
+        assert_eq!(Ok(()), cfg.assert_matches(&expected));
 
         Ok(())
     }
@@ -167,11 +974,709 @@ mod tests {
         cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("SomeOperand".to_string())))?;
         cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
         cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        let expected = ExpectedCfg::entry(2).insn(3, "INC").insn(4, "LDAC SomeOperand").insn(5, "JMP 0x9 / 0x6 (taken)").jmp_to_count(6, 0).jmp_to_count(9, 1)
+            .block(6).hits(0)
+            .block(9).hits(1).insn(10, "INC");
+        assert_eq!(Ok(()), cfg.assert_matches(&expected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn backward_jump_splits_block() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("LDAC".to_string(), None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(2, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(3, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(2, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(3, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+
+        assert_eq!(2, cfg.blocks.len());
+        let (head, tail) = (&cfg.blocks[0], &cfg.blocks[1]);
+        assert_eq!((0, 0, 1), (head.start, head.end, head.block.len()));
+        assert_eq!((1, 3, 3), (tail.start, tail.end, tail.block.len()));
+        assert_eq!(vec![(BlockId(1), 1)], head.edge_counts());
+        assert_eq!(vec![(BlockId(1), 2)], tail.edge_counts());
+        assert_eq!(BlockId(1), cfg.current_block);
+
+        Ok(())
+    }
+
+    #[test]
+    fn jump_into_other_block_splits_it() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(2, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(11, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+
         assert_eq!(3, cfg.blocks.len());
-        assert_eq!(1, cfg.blocks.get(0).unwrap().edges.get(1).unwrap().1);
-        assert_eq!(0, cfg.blocks.get(0).unwrap().edges.get(0).unwrap().1);
+        assert_eq!(vec![(BlockId(2), 1)], cfg.blocks[0].edge_counts());
+        assert_eq!(vec![(BlockId(2), 1)], cfg.blocks[1].edge_counts());
+        assert_eq!(vec![(BlockId(1), 1)], cfg.blocks[2].edge_counts());
+        assert_eq!((1, 2), (cfg.blocks[2].start, cfg.blocks[2].end));
+        assert_eq!(BlockId(2), cfg.current_block);
+
+        Ok(())
+    }
+
+    #[test]
+    fn top_of_address_space() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(u64::MAX);
+        cfg.execute(u64::MAX, BlockType::Instruction("INC".to_string(), None))?;
+        assert_eq!(Some(cfg.entry_index()), cfg.find_block_containing(u64::MAX));
+        assert_eq!((u64::MAX..=u64::MAX, 1, u64::MAX), (cfg.entry().range(), cfg.entry().len_addresses(), cfg.entry().end_exclusive()));
+
+        let top = u64::MAX - 0x10;
+        let mut cfg = ControlFlowGraph::new(top);
+        cfg.execute(top, BlockType::Jump("JZ".to_string(), u64::MAX - 1, JumpType::ConditionalNotTaken, Some(top + 1)))?;
+        cfg.execute_sized(top + 1, BlockType::Jump("JMP".to_string(), u64::MAX - 1, JumpType::UnconditionalJump, None), 2)?;
+        cfg.execute_sized(u64::MAX - 1, BlockType::Instruction("INC".to_string(), None), 2)?;
+        let last = cfg.starts[&(u64::MAX - 1)];
+        assert_eq!((u64::MAX - 1..=u64::MAX, 2), (cfg.blocks[last].range(), cfg.blocks[last].len_addresses()));
+        assert_eq!(Some(last), cfg.find_block_containing(u64::MAX));
+        assert!(cfg.overlapping_blocks().is_empty() && cfg.diagnostics().is_empty());
+        #[cfg(feature = "std")]
+        {
+            let mut drcov = Vec::new();
+            cfg.write_drcov("top", top, &mut drcov)?;
+            assert!(String::from_utf8_lossy(&drcov).contains("0xffffffffffffffff"));
+        }
+
+        // Nothing is left above the graph, so the block splitting the critical edge goes below it.
+        let trampoline = cfg.split_critical_edge(cfg.entry_index(), last)?;
+        assert_eq!(0, cfg.blocks[trampoline].start());
+        assert_eq!(Some(last), cfg.finalize().find_block_containing(u64::MAX));
+
+        Ok(())
+    }
+
+    #[test]
+    fn addresses_above_32_bits() -> Result<(), CFGError> {
+        // Kernel addresses of a 64 bit target keep their upper half whatever the width of the host.
+        const KERNEL: u64 = 0xffff_8000_0000_0000;
+        let mut cfg = ControlFlowGraph::new(KERNEL);
+        cfg.execute_sized(KERNEL, BlockType::Jump("CALL".to_string(), KERNEL + 0x1_0000_0000, JumpType::Call, Some(KERNEL + 5)), 5)?;
+        cfg.execute(KERNEL + 0x1_0000_0000, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(KERNEL + 5, BlockType::Jump("JZ".to_string(), KERNEL, JumpType::ConditionalTaken, Some(KERNEL + 6)))?;
+
+        let callee = cfg.starts[&(KERNEL + 0x1_0000_0000)];
+        assert_eq!(Some(callee), cfg.find_block_containing(0xffff_8001_0000_0000));
+        assert_eq!(Some(cfg.entry_index()), cfg.find_block_containing(KERNEL + 4));
+        assert_eq!(None, cfg.find_block_containing(KERNEL + 0x1_0000));
+        assert!(cfg.to_string().contains("0xffff800100000000"));
+        assert!(cfg.to_json().contains("\"0xffff800000000005\""));
+
+        assert!(cfg.eq_with_counts(&ControlFlowGraph::from_bytes(&cfg.to_bytes())?));
+        assert_eq!(cfg.to_dot(), ControlFlowGraph::from_dot(&cfg.to_dot())?.to_dot());
+        cfg.rebase(KERNEL, 0x1000)?;
+        assert_eq!((0x1000, Some(BlockId(1))), (cfg.entry().start(), cfg.find_block_containing(0x1_0000_1000)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn many_blocks_loop() -> Result<(), CFGError> {
+        const BLOCKS: usize = 100_000;
+        let mut cfg = ControlFlowGraph::new(0);
+        for _ in 0..2 {
+            for i in 0..BLOCKS {
+                let start = i as u64 * 2;
+                let target = if i + 1 == BLOCKS { 0 } else { start + 2 };
+                cfg.execute(start, BlockType::Instruction("INC".to_string(), None))?;
+                cfg.execute(start + 1, BlockType::Jump("JMP".to_string(), target, JumpType::UnconditionalJump, None))?;
+            }
+        }
+
+        assert_eq!(BLOCKS, cfg.blocks.len());
+        assert!(cfg.blocks().all(|bb| bb.edge_counts() == vec![(cfg.starts[&((bb.start + 2) % (BLOCKS as u64 * 2))], 2)]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fall_through_into_known_block() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(11, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(5)))?;
+        for pc in 5..12 {
+            cfg.execute(pc, BlockType::Instruction("INC".to_string(), None))?;
+        }
+
+        let (straight, target) = (cfg.starts[&5], cfg.starts[&10]);
+        assert_eq!((5, 9, 5), (cfg.blocks[straight].start, cfg.blocks[straight].end, cfg.blocks[straight].len()));
+        assert_eq!(vec![(target, 1)], cfg.blocks[straight].edge_counts());
+        assert_eq!(2, cfg.blocks[target].len());
+        assert_eq!(2, cfg.blocks[target].hits());
+        assert_eq!(target, cfg.current_block);
+
+        Ok(())
+    }
+
+    #[test]
+    fn conditional_taken_then_not_taken() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalTaken, Some(2)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalNotTaken, Some(2)))?;
+
+        let (taken, not_taken) = (cfg.starts[&5], cfg.starts[&2]);
+        let mut edges = cfg.blocks[0].edge_counts();
+        edges.sort();
+        let mut expected = vec![(taken, 1), (not_taken, 1)];
+        expected.sort();
+        assert_eq!(expected, edges);
+        assert_eq!(not_taken, cfg.current_block);
+
+        Ok(())
+    }
+
+    #[test]
+    fn discovery_ordinals() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalTaken, Some(2)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalNotTaken, Some(2)))?;
+
+        // The fall through block of the jump is created before its target, and so are their edges.
+        let ordinals: Vec<_> = cfg.blocks().map(|block| (block.start(), block.discovered_at())).collect();
+        assert_eq!(vec![(0, 0), (2, 1), (5, 2)], ordinals);
+        let ordinals: Vec<_> = cfg.edges().map(|edge| (edge.source_start(), edge.target_start(), edge.discovered_at())).collect();
+        assert_eq!(vec![(0, 2, 0), (0, 5, 1), (5, 0, 2)], ordinals);
+
+        // Splitting the entry block discovers its tail and the edge into it.
+        cfg.execute(2, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+        let tail = cfg.starts[&1];
+        assert_eq!(3, cfg.blocks[tail].discovered_at());
+        assert_eq!(Some(3), cfg.entry().edge_to(tail).map(Edge::discovered_at));
+        assert_eq!(Some(4), cfg.blocks[cfg.starts[&2]].edge_to(tail).map(Edge::discovered_at));
+
+        // A merge keeps the earlier ordinal of the blocks and edges both graphs found.
+        let mut other = ControlFlowGraph::new(0);
+        other.preseed_block(5)?;
+        other.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        other.execute(1, BlockType::Jump("JZ".to_string(), 5, JumpType::ConditionalTaken, Some(2)))?;
+        assert_eq!((1, 2), (other.blocks[other.starts[&5]].discovered_at(), other.blocks[other.starts[&2]].discovered_at()));
+        other.merge(&cfg)?;
+        assert_eq!((1, 1), (other.blocks[other.starts[&5]].discovered_at(), other.blocks[other.starts[&2]].discovered_at()));
+        assert_eq!((4, 5), (other.blocks_discovered, other.edges_discovered));
+
+        Ok(())
+    }
+
+    #[test]
+    fn indirect_jump_targets() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for target in [0x10, 0x20, 0x10, 0x30, 0x20, 0x10] {
+            cfg.execute(0, BlockType::Instruction("LDR".to_string(), Some("R1".to_string())))?;
+            cfg.execute_indirect(1, "JMP".to_string(), target)?;
+            cfg.execute(target, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+
+        let expected = vec![(cfg.starts[&0x10], 3), (cfg.starts[&0x20], 2), (cfg.starts[&0x30], 1)];
+        assert_eq!(expected, cfg.blocks[0].edge_counts());
+        assert_eq!(Some(6), cfg.blocks[0].instruction_hits(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn switch_jump_table() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        let table = vec![0x10, 0x20, 0x30, 0x40];
+        for taken in [0x20, 0x40, 0x20] {
+            cfg.execute_switch(1, "SWITCH".to_string(), table.clone(), taken)?;
+            cfg.execute(taken, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+
+        let expected = vec![(cfg.starts[&0x10], 0), (cfg.starts[&0x20], 2), (cfg.starts[&0x30], 0), (cfg.starts[&0x40], 1)];
+        assert_eq!(expected, cfg.blocks[0].edge_counts());
+        assert_eq!(0, cfg.blocks[cfg.starts[&0x30]].hits());
+        assert_eq!(Some(&BlockType::Switch("SWITCH".to_string(), table.clone())), cfg.blocks[0].block.get(&1));
+        assert_eq!("SWITCH [0x10, 0x20, 0x30, 0x40]", cfg.blocks[0].block[&1].to_string());
+        assert!(cfg.to_dot().contains("\\l0x1: SWITCH [0x10, 0x20, 0x30, 0x40]\\l"));
+
+        assert!(matches!(cfg.execute_switch(1, "SWITCH".to_string(), table.clone(), 0x50), Err(CFGError::UnknownSwitchTarget { pc: 1, target: 0x50 })));
+        assert!(matches!(cfg.execute(1, BlockType::Switch("SWITCH".to_string(), table)), Err(CFGError::ExpectedSwitchTarget { pc: 1 })));
 
         Ok(())
     }
 
+    #[test]
+    fn conflict_policy_on_graph() -> Result<(), CFGError> {
+        let run = |policy: ConflictPolicy| -> Result<ControlFlowGraph, CFGError> {
+            let mut cfg = ControlFlowGraph::new(0x40);
+            cfg.set_conflict_policy(policy);
+            cfg.execute(0x40, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(0x41, BlockType::Jump("JNZ".to_string(), 0x40, JumpType::ConditionalTaken, Some(0x42)))?;
+            cfg.execute(0x40, BlockType::Instruction("DEC".to_string(), None))?;
+            // Only the direction differs, which never conflicts.
+            cfg.execute(0x41, BlockType::Jump("JNZ".to_string(), 0x40, JumpType::ConditionalNotTaken, Some(0x42)))?;
+            Ok(cfg)
+        };
+
+        let cfg: ControlFlowGraph = ControlFlowGraph::default();
+        assert_eq!(ConflictPolicy::KeepFirst, cfg.conflict_policy());
+
+        let kept = run(ConflictPolicy::KeepFirst)?;
+        assert_eq!(BlockType::Instruction("INC".to_string(), None), kept.blocks[0].block[&0x40]);
+
+        let overwritten = run(ConflictPolicy::Overwrite)?;
+        assert_eq!(ConflictPolicy::Overwrite, overwritten.conflict_policy());
+        assert_eq!(BlockType::Instruction("DEC".to_string(), None), overwritten.blocks[0].block[&0x40]);
+        assert_eq!(BlockType::Jump("JNZ".to_string(), 0x40, JumpType::ConditionalTaken, Some(0x42)), overwritten.blocks[0].block[&0x41]);
+
+        match run(ConflictPolicy::Error) {
+            Err(CFGError::InstructionConflict { pc, existing, new }) => {
+                assert_eq!((0x40, "INC", "DEC"), (pc, existing.as_str(), new.as_str()));
+            }
+            _ => panic!("expected an instruction conflict"),
+        }
+
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.set_conflict_policy(ConflictPolicy::Error);
+        for target in [0x10, 0x20] {
+            cfg.execute_indirect(0, "JMP".to_string(), target)?;
+            cfg.execute(target, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+        assert!(cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocks_in_address_order() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x20);
+        cfg.execute(0x21, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x22, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x12, BlockType::Jump("JZ".to_string(), 0x30, JumpType::ConditionalTaken, Some(0x13)))?;
+        cfg.execute(0x31, BlockType::Instruction("INC".to_string(), None))?;
+
+        let starts: Vec<u64> = cfg.blocks_by_address().map(|block| block.start()).collect();
+        assert_eq!(vec![0x10, 0x13, 0x20, 0x30], starts);
+
+        assert_eq!(Some(BlockId(0)), cfg.find_block_containing(0x20));
+        assert_eq!(Some(BlockId(0)), cfg.find_block_containing(0x21));
+        assert_eq!(Some(BlockId(0)), cfg.find_block_containing(0x22));
+        assert_eq!(Some(BlockId(1)), cfg.find_block_containing(0x12));
+        assert_eq!(None, cfg.find_block_containing(0x23));
+        assert_eq!(None, cfg.find_block_containing(0x14));
+        assert_eq!(None, cfg.find_block_containing(0x0f));
+        assert_eq!(Some(cfg.starts[&0x30]), cfg.find_block_containing(0x31));
+
+        Ok(())
+    }
+
+    #[test]
+    fn execute_outcomes() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        assert_eq!(ExecOutcome::NewInstruction, cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?);
+        assert_eq!(ExecOutcome::NewBlock(BlockId(2)), cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?);
+        // Recording the jump is new as well, but its new edge is more notable.
+        assert_eq!(ExecOutcome::NewEdge { from: BlockId(2), to: BlockId(0) }, cfg.execute(9, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?);
+        assert_eq!(ExecOutcome::KnownInstruction, cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?);
+        assert_eq!(ExecOutcome::NewEdge { from: BlockId(0), to: BlockId(1) }, cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalNotTaken, Some(6)))?);
+        assert_eq!(ExecOutcome::NewInstruction, cfg.execute(6, BlockType::Instruction("INC".to_string(), None))?);
+        // Falling through into the known block at 9 traverses a new edge.
+        assert_eq!(ExecOutcome::NewEdge { from: BlockId(1), to: BlockId(2) }, cfg.execute(9, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?);
+        assert_eq!(ExecOutcome::NewInstruction, cfg.execute(4, BlockType::Instruction("DEC".to_string(), None))?);
+        let outcome = cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        assert_eq!(ExecOutcome::ExistingEdgeIncremented { from: BlockId(0), to: BlockId(2) }, outcome);
+        assert!(!outcome.is_new());
+
+        // Jumping into the middle of a block creates its tail.
+        assert_eq!(ExecOutcome::NewBlock(BlockId(3)), cfg.execute(9, BlockType::Jump("JMP".to_string(), 4, JumpType::UnconditionalJump, None))?);
+        assert!(ExecOutcome::NewBlock(BlockId(3)).is_new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interleaved_conditional_counts() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        let mut state: u32 = 0x2545_f491;
+        let (mut taken, mut not_taken) = (0, 0);
+        for _ in 0..100 {
+            // A xorshift generator keeps the sequence reproducible without a dependency.
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let jump_type = if state & 1 == 1 { taken += 1; JumpType::ConditionalTaken } else { not_taken += 1; JumpType::ConditionalNotTaken };
+
+            cfg.execute(1, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute(2, BlockType::Jump("JNZ".to_string(), 1, jump_type, Some(3)))?;
+            if jump_type == JumpType::ConditionalNotTaken {
+                cfg.execute(3, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+                cfg.execute(0, BlockType::Instruction("LDAC".to_string(), None))?;
+            }
+        }
+
+        let site = cfg.starts[&1];
+        let mut edges = cfg.blocks[site].edge_counts();
+        edges.sort();
+        let mut expected = vec![(site, taken), (cfg.starts[&3], not_taken)];
+        expected.sort();
+        assert_eq!(expected, edges);
+        assert_eq!(100, taken + not_taken);
+        assert!(taken > 0 && not_taken > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_calls() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("CALL".to_string(), 10, JumpType::Call, Some(1)))?;
+        cfg.execute(10, BlockType::Jump("CALL".to_string(), 20, JumpType::Call, Some(11)))?;
+        cfg.execute(20, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(11, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+
+        let starts: Vec<u64> = cfg.blocks().map(|bb| bb.start).collect();
+        assert_eq!(vec![0, 10, 20, 11, 1], starts);
+        for index in 0..4 {
+            assert_eq!(vec![(BlockId(index + 1), 1)], cfg.blocks[index].edge_counts());
+        }
+        assert_eq!(BlockId(4), cfg.current_block);
+        assert!(cfg.call_stack.is_empty());
+
+        let result = cfg.execute(2, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None));
+        assert!(matches!(result, Err(CFGError::ReturnWithoutCall { pc: 2 })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_calls() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("CALL".to_string(), 10, JumpType::Call, Some(1)))?;
+        for depth in 0..3 {
+            cfg.execute(10, BlockType::Instruction("DEC".to_string(), None))?;
+            if depth < 2 {
+                cfg.execute(11, BlockType::Jump("JZ".to_string(), 14, JumpType::ConditionalNotTaken, Some(12)))?;
+                cfg.execute(12, BlockType::Jump("CALL".to_string(), 10, JumpType::Call, Some(13)))?;
+            } else {
+                cfg.execute(11, BlockType::Jump("JZ".to_string(), 14, JumpType::ConditionalTaken, Some(12)))?;
+            }
+        }
+        cfg.execute(14, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(13, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(13, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+
+        let index = |start: u64| cfg.starts[&start];
+        let count = |src: u64, dst: u64| cfg.blocks[index(src)].edges.iter().find(|edge| edge.target() == index(dst)).map(Edge::count);
+        assert_eq!(Some(1), count(0, 10));
+        assert_eq!(Some(2), count(12, 10));
+        assert_eq!(Some(1), count(14, 13));
+        assert_eq!(Some(1), count(13, 13));
+        assert_eq!(Some(1), count(13, 1));
+        assert_eq!(index(1), cfg.current_block);
+        assert!(cfg.call_stack.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn diamond_predecessors_and_successors() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(5)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(5)))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+
+        let (entry, left, right, join) = (cfg.entry_index(), cfg.starts[&5], cfg.starts[&10], cfg.starts[&20]);
+        assert_eq!(vec![left, right], cfg.successors(entry)?);
+        assert_eq!(vec![join], cfg.successors(left)?);
+        assert_eq!(vec![join], cfg.successors(right)?);
+        assert_eq!(vec![entry], cfg.successors(join)?);
+
+        assert_eq!(vec![join], cfg.predecessors(entry)?);
+        assert_eq!(vec![entry], cfg.predecessors(left)?);
+        assert_eq!(vec![entry], cfg.predecessors(right)?);
+        let mut join_predecessors = vec![left, right];
+        join_predecessors.sort();
+        assert_eq!(join_predecessors, cfg.predecessors(join)?);
+
+        assert!(matches!(cfg.successors(BlockId(4)), Err(CFGError::MissingBlock { index: 4 })));
+        assert!(matches!(cfg.predecessors(BlockId(4)), Err(CFGError::MissingBlock { index: 4 })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loop_hit_counts() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..10 {
+            cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+            cfg.execute(20, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(21, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        }
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalTaken, Some(20)))?;
+
+        let (header, body, exit) = (&cfg.blocks[cfg.starts[&10]], &cfg.blocks[cfg.starts[&20]], &cfg.blocks[cfg.starts[&30]]);
+        assert_eq!(1, cfg.entry().hits());
+        assert_eq!(11, header.hits());
+        assert_eq!(10, body.hits());
+        assert_eq!(1, exit.hits());
+        assert_eq!(Some(11), header.instruction_hits(10));
+        assert_eq!(Some(10), body.instruction_hits(21));
+        assert_eq!(None, body.instruction_hits(22));
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_block_keeps_hits() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("LDAC".to_string(), None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(2, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+
+        let (head, tail) = (&cfg.blocks[0], &cfg.blocks[1]);
+        assert_eq!((1, 2), (head.hits(), tail.hits()));
+        assert_eq!((Some(1), None), (head.instruction_hits(0), head.instruction_hits(1)));
+        assert_eq!((Some(2), Some(1)), (tail.instruction_hits(1), tail.instruction_hits(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn address_before_block_start() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::UnconditionalJump, None))?;
+
+        let result = cfg.execute(4, BlockType::Instruction("INC".to_string(), None));
+        assert!(matches!(result, Err(CFGError::AddressBeforeBlockStart { pc: 4, block: BlockId(1), block_start: 9 })));
+        let result = cfg.execute(8, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None));
+        assert!(matches!(result, Err(CFGError::AddressBeforeBlockStart { pc: 8, block: BlockId(1), block_start: 9 })));
+
+        // The rejected records leave the graph untouched, so execution can carry on.
+        assert_eq!(0, cfg.blocks[1].block.len());
+        assert_eq!(BlockId(1), cfg.current_block);
+        cfg.execute(9, BlockType::Instruction("INC".to_string(), None))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn instructions_in_address_order() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(7, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(3, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(5, BlockType::Instruction("LDAC".to_string(), None))?;
+
+        let addresses: Vec<u64> = cfg.entry().instructions().map(|(address, _)| *address).collect();
+        assert_eq!(vec![3, 5, 7], addresses);
+
+        Ok(())
+    }
+
+    #[test]
+    fn end_tracks_highest_address() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(5, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(3, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(7, BlockType::Instruction("LDAC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("INC".to_string(), None))?;
+
+        assert_eq!(7, cfg.entry().end());
+        assert_eq!(2..=7, cfg.entry().range());
+        assert_eq!(6, cfg.entry().len_addresses());
+        assert_eq!(Some(BlockId(0)), cfg.find_block_containing(7));
+
+        // Splitting in the middle leaves both halves with the right range.
+        cfg.execute(6, BlockType::Jump("JMP".to_string(), 4, JumpType::UnconditionalJump, None))?;
+        let tail = cfg.block(cfg.starts[&4]).unwrap();
+        assert_eq!((3, 4..=7), (cfg.entry().end(), tail.range()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sized_instructions() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute_sized(0x10, BlockType::Instruction("INC".to_string(), None), 1)?;
+        cfg.execute_sized(0x11, BlockType::Instruction("MOV".to_string(), None), 2)?;
+        cfg.execute_sized(0x13, BlockType::Jump("JNZ".to_string(), 0x40, JumpType::ConditionalNotTaken, Some(0x17)), 4)?;
+
+        assert_eq!((0x13, 0x17, 7), (cfg.entry().end(), cfg.entry().end_exclusive(), cfg.entry().len_addresses()));
+        assert_eq!([Some(1), Some(2), Some(4), None], [0x10, 0x11, 0x13, 0x14].map(|address| cfg.entry().instruction_size(address)));
+        assert_eq!(Some(BlockId(0)), cfg.find_block_containing(0x16));
+        // The not-taken block starts right after the last byte, and has no instructions to give it a size.
+        let not_taken = cfg.starts[&0x17];
+        assert_eq!(Some(not_taken), cfg.find_block_containing(0x17));
+        assert_eq!(0x18, cfg.blocks[not_taken].end_exclusive());
+
+        // Jumping back into the middle keeps the sizes with their instructions on both sides of the split.
+        cfg.execute_sized(0x17, BlockType::Jump("JMP".to_string(), 0x11, JumpType::UnconditionalJump, None), 2)?;
+        let tail = cfg.starts[&0x11];
+        assert_eq!((0x10, 0x11), (cfg.entry().end(), cfg.entry().end_exclusive()));
+        assert_eq!(0x11..=0x16, cfg.blocks[tail].range());
+        assert_eq!(Some(tail), cfg.find_block_containing(0x16));
+        assert_eq!(0x19, cfg.blocks[not_taken].end_exclusive());
+        assert!(cfg.verify().is_ok());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+
+        let json = serde_json::to_string(&cfg).unwrap();
+        let mut restored: ControlFlowGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(cfg.blocks.len(), restored.blocks.len());
+        assert_eq!(cfg.current_block, restored.current_block);
+        for (original, restored) in cfg.blocks().zip(restored.blocks()) {
+            assert_eq!(original.edges, restored.edges);
+        }
+        assert_eq!(cfg.predecessors, restored.predecessors);
+        assert_eq!(cfg.blocks().map(BasicBlock::discovered_at).collect::<Vec<_>>(), restored.blocks().map(BasicBlock::discovered_at).collect::<Vec<_>>());
+        assert_eq!((cfg.blocks_discovered, cfg.edges_discovered, cfg.executed), (restored.blocks_discovered, restored.edges_discovered, restored.executed));
+
+        for graph in [&mut cfg, &mut restored] {
+            graph.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+            graph.execute(11, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        }
+        assert_eq!(cfg.to_dot(), restored.to_dot());
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_accessors() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+
+        assert_eq!(3, cfg.len());
+        assert!(!cfg.is_empty());
+        assert_eq!(BlockId(0), cfg.entry_index());
+        assert_eq!(BlockId(2), cfg.current_block_index());
+        assert!(cfg.block(BlockId(3)).is_none());
+
+        let entry = cfg.block(cfg.entry_index()).unwrap();
+        assert_eq!((2, 5, 2), (entry.start(), entry.end(), entry.len()));
+        let not_taken = cfg.block(BlockId(1)).unwrap();
+        assert_eq!((6, 6), (not_taken.start(), not_taken.end()));
+        assert!(not_taken.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_payload() -> Result<(), CFGError> {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        enum Insn {
+            Inc,
+            Load(u32),
+            Jmp,
+        }
+
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction(Insn::Load(7), None))?;
+        cfg.execute(1, BlockType::Instruction(Insn::Inc, None))?;
+        cfg.execute(2, BlockType::Jump(Insn::Jmp, 0, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0, BlockType::Instruction(Insn::Load(7), None))?;
+
+        let instructions: Vec<&BlockType<Insn>> = cfg.entry().instructions().map(|(_, insn)| insn).collect();
+        assert_eq!(vec![&BlockType::Instruction(Insn::Load(7), None), &BlockType::Instruction(Insn::Inc, None)], instructions[..2]);
+        assert_eq!(vec![(BlockId(0), 1)], cfg.entry().edge_counts());
+        assert_eq!(2, cfg.entry().hits());
+
+        Ok(())
+    }
+
+    #[test]
+    fn default_entry() {
+        let cfg: ControlFlowGraph = ControlFlowGraph::default();
+        assert_eq!(1, cfg.blocks().count());
+        assert_eq!(0, cfg.entry().start);
+    }
+
+    #[test]
+    fn tight_self_loop() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        let mut outcomes = Vec::new();
+        for _ in 0..5 {
+            cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+            outcomes.push(cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?);
+        }
+
+        let entry = cfg.entry_index();
+        assert_eq!(ExecOutcome::NewEdge { from: entry, to: entry }, outcomes[0]);
+        assert_eq!(ExecOutcome::ExistingEdgeIncremented { from: entry, to: entry }, outcomes[4]);
+
+        assert_eq!(1, cfg.len());
+        assert_eq!(vec![(cfg.entry_index(), 5)], cfg.entry().edge_counts());
+        assert_eq!(cfg.entry_index(), cfg.current_block);
+        assert_eq!(Some(5), cfg.entry().instruction_hits(0x10));
+        assert_eq!(6, cfg.entry().hits());
+
+        Ok(())
+    }
+
+    #[test]
+    fn self_loop_into_middle() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute(0x10, BlockType::Instruction("MOV".to_string(), Some("5".to_string())))?;
+        for _ in 0..5 {
+            cfg.execute(0x11, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute(0x12, BlockType::Jump("JMP".to_string(), 0x11, JumpType::UnconditionalJump, None))?;
+        }
+
+        // The jump back into the middle splits off the loop body, which then loops onto itself.
+        assert_eq!(2, cfg.len());
+        let body = cfg.starts[&0x11];
+        assert_eq!((0x10, 0x10), (cfg.entry().start, cfg.entry().end));
+        assert_eq!((0x11, 0x12), (cfg.blocks[body].start, cfg.blocks[body].end));
+        assert_eq!(vec![(body, 1)], cfg.entry().edge_counts());
+        assert_eq!(vec![(body, 5)], cfg.blocks[body].edge_counts());
+        assert_eq!(body, cfg.current_block);
+        assert_eq!(Some(5), cfg.blocks[body].instruction_hits(0x11));
+        assert!(cfg.verify().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn saturating_counts() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        cfg.seed_counts(u64::MAX - 1);
+
+        // The first lap reaches the maximum exactly, which is still an exact count.
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        assert_eq!(vec![(cfg.entry_index(), u64::MAX)], cfg.entry().edge_counts());
+        assert!(!cfg.counts_saturated());
+
+        cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        assert_eq!(vec![(cfg.entry_index(), u64::MAX)], cfg.entry().edge_counts());
+        assert_eq!(u64::MAX, cfg.entry().hits());
+        assert_eq!(Some(u64::MAX), cfg.entry().instruction_hits(0x10));
+        assert!(cfg.counts_saturated());
+        assert!(cfg.metrics().counts_saturated);
+        assert!(cfg.metrics().to_string().ends_with("counts saturated: hit and traversal counts are lower bounds\n"));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,37 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+use ctrl_flow::cli::{self, USAGE};
+
+fn main() -> ExitCode {
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("ctrl-flow: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let stdout = io::stdout().lock();
+    let result = match &options.input {
+        Some(path) => match File::open(path) {
+            Ok(file) => cli::run(&options, BufReader::new(file), stdout),
+            Err(error) => {
+                eprintln!("ctrl-flow: failed to open {}: {}", path.display(), error);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => cli::run(&options, io::stdin().lock(), stdout),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("ctrl-flow: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+use crate::{saturating_increase, ControlFlowGraph, Module};
+use crate::types::{BlockId, CFGError, EdgeKind, OverlapPolicy};
+use crate::prelude::*;
+
+impl<I: Clone + PartialEq + fmt::Display> ControlFlowGraph<I> {
+    /// Merges another ControlFlowGraph into this one, such as one built from a separate run of the same program.
+    ///
+    /// Blocks are matched by their start addresses, splitting blocks of this graph where the other graph starts a block
+    /// in their middle. Instructions are unioned and the traversal and hit counts are summed, saturating at `u64::MAX`
+    /// like they do while executing, while every block and edge keeps the lower of its discovery ordinals in both
+    /// graphs and every edge spans the ordinals it was first and last seen at in either graph. The hits of a block
+    /// which this graph split count towards each of its pieces and the fall-throughs between them, so merging in either
+    /// order gives the same graph. The entry points must match, and an instruction which differs from the one recorded
+    /// at the same address is an error. Nothing is merged when an error is returned.
+    ///
+    /// When both graphs have modules, every address inside of a module of the other graph is matched by its offset into
+    /// the module of this graph with the same name, so that runs which loaded it at different bases merge.
+    pub fn merge(&mut self, other: &ControlFlowGraph<I>) -> Result<(), CFGError> {
+        let relocated;
+        let other = match self.relocated(other) {
+            Some(copy) => {
+                relocated = copy;
+                &relocated
+            }
+            None => other,
+        };
+        let (expected, found) = (self.entry().start, other.entry().start);
+        if expected != found {
+            return Err(CFGError::EntryMismatch { expected, found });
+        }
+
+        let recorded: BTreeMap<_, _> = self.blocks.iter().flat_map(|bb| bb.block.iter()).collect();
+        for (pc, instruction) in other.blocks.iter().flat_map(|bb| bb.block.iter()) {
+            match recorded.get(pc) {
+                Some(existing) if existing.conflicts_with(instruction) => {
+                    return Err(CFGError::InstructionConflict { pc: *pc, existing: existing.to_string(), new: instruction.to_string() });
+                }
+                _ => {}
+            }
+        }
+
+        // Blocks which would overlap are rejected up front, so that nothing is merged when one of them is.
+        if self.config.overlap_policy == OverlapPolicy::Error {
+            if let Some((index, start)) = other.blocks.iter().find_map(|block| self.overlapped_block(block.start).map(|index| (index, block.start))) {
+                return Err(self.overlap_error(index, start));
+            }
+        }
+        for block in other.blocks.iter() {
+            self.query_block_or_create(block.start)?;
+        }
+        for block in other.blocks.iter() {
+            let index = self.starts[&block.start];
+            self.blocks[index].discovered_at = self.blocks[index].discovered_at.min(block.discovered_at);
+            // Every pass through a block which this graph split flowed through each of its pieces, like a split does.
+            let pieces: Vec<BlockId> = self.starts.range(block.start..=block.end).map(|(_, index)| *index).collect();
+            for (position, &piece) in pieces.iter().enumerate() {
+                self.saturated |= saturating_increase(&mut self.blocks[piece].hits, block.hits);
+                if let Some(&next) = pieces.get(position + 1) {
+                    self.add_edge_count(piece, next, block.hits, EdgeKind::FallThrough);
+                }
+            }
+        }
+
+        for block in other.blocks.iter() {
+            for (pc, instruction) in block.block.iter() {
+                let index = self.containing_block(*pc);
+                let target = &mut self.blocks[index];
+                if !target.block.contains_key(pc) {
+                    target.add_instruction(*pc, instruction.clone());
+                    target.instruction_hits.insert(*pc, 0);
+                }
+                self.saturated |= saturating_increase(target.instruction_hits.get_mut(pc).unwrap(), block.instruction_hits[pc]);
+                target.set_instruction_size(*pc, block.instruction_size(*pc).unwrap_or(1));
+                target.end = target.end.max(*pc);
+            }
+
+            let index = self.starts[&block.start];
+            for (key, value) in block.metadata.iter() {
+                self.blocks[index].metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+
+            // The edges leave from wherever the last instruction of the other block ended up.
+            let source = self.containing_block(block.end);
+            for edge in block.edges.iter() {
+                let target = self.starts[&other.blocks[edge.target()].start];
+                self.add_edge_count(source, target, edge.count(), edge.kind());
+                if edge.is_static() {
+                    self.blocks[source].mark_static_edge(target);
+                }
+                if let Some(merged) = self.blocks[source].edges.iter_mut().find(|merged| merged.target() == target) {
+                    merged.set_discovered_at(merged.discovered_at().min(edge.discovered_at()));
+                    let first_seen = merged.first_seen().into_iter().chain(edge.first_seen()).min();
+                    merged.set_seen(first_seen, merged.last_seen().max(edge.last_seen()));
+                }
+            }
+        }
+        self.blocks_discovered = self.blocks_discovered.max(other.blocks_discovered);
+        self.edges_discovered = self.edges_discovered.max(other.edges_discovered);
+        self.executed = self.executed.max(other.executed);
+
+        Ok(())
+    }
+
+    /// Merges another ControlFlowGraph into this one like `merge`, with the counts of the other graph multiplied by
+    /// `weight` first, such as to count one workload double when combining profiles.
+    ///
+    /// The counts are scaled like `scaled` does, so a weight of zero adds the blocks, instructions and edges of the other
+    /// graph without adding to any count.
+    pub fn merge_weighted(&mut self, other: &ControlFlowGraph<I>, weight: f64) -> Result<(), CFGError> {
+        self.merge(&other.scaled(weight))
+    }
+
+    /// Copies the other graph with the addresses inside of its modules moved to the modules of this graph with the same
+    /// name, if both graphs have modules.
+    fn relocated(&self, other: &ControlFlowGraph<I>) -> Option<ControlFlowGraph<I>> {
+        if self.modules.is_empty() || other.modules.is_empty() {
+            return None;
+        }
+        let moves: Vec<(&Module, u64)> = other.modules.iter()
+            .filter_map(|module| self.modules.iter().find(|own| own.name() == module.name()).map(|own| (module, own.base())))
+            .collect();
+        let mut copy = ControlFlowGraph::from_blocks(other.blocks.clone(), other.current_block.index());
+        copy.relocate(|address| match moves.iter().find(|(module, _)| module.contains(address)) {
+            Some((module, base)) => address - module.base() + base,
+            None => address,
+        });
+        copy.blocks_discovered = other.blocks_discovered;
+        copy.edges_discovered = other.edges_discovered;
+        copy.executed = other.executed;
+        Some(copy)
+    }
+
+    /// Returns the position of the block with the greatest start address at or below the given address.
+    fn containing_block(&self, address: u64) -> BlockId {
+        self.starts.range(..=address).next_back().map(|(_, index)| *index).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    fn run(iterations: usize, exit: JumpType) -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        for _ in 0..iterations {
+            cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, JumpType::ConditionalNotTaken, Some(20)))?;
+            cfg.execute(20, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        }
+        cfg.execute(10, BlockType::Jump("JZ".to_string(), 30, exit, Some(20)))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn merged_counts_are_sums() -> Result<(), CFGError> {
+        let mut merged = run(3, JumpType::ConditionalTaken)?;
+        let mut other = run(2, JumpType::ConditionalNotTaken)?;
+        other.execute(20, BlockType::Jump("JMP".to_string(), 40, JumpType::UnconditionalJump, None))?;
+        merged.merge(&other)?;
+
+        let edges = merged.edge_map();
+        assert_eq!(2, edges[&(0, 10)]);
+        assert_eq!(6, edges[&(10, 20)]);
+        assert_eq!(1, edges[&(10, 30)]);
+        assert_eq!(5, edges[&(20, 10)]);
+        assert_eq!(1, edges[&(20, 40)]);
+        assert_eq!(5, merged.len());
+        assert_eq!(Some(7), merged.block(merged.starts[&10]).unwrap().instruction_hits(10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merged_counts_saturate() -> Result<(), CFGError> {
+        let mut merged = run(1, JumpType::ConditionalTaken)?;
+        merged.seed_counts(u64::MAX / 2 + 1);
+        let mut other = run(1, JumpType::ConditionalTaken)?;
+        other.seed_counts(u64::MAX / 2 + 1);
+        merged.merge(&other)?;
+
+        assert!(merged.edge_map().values().all(|count| *count == u64::MAX));
+        assert!(merged.blocks().all(|block| block.hits() == u64::MAX));
+        assert_eq!(Some(u64::MAX), merged.block(merged.starts[&10]).unwrap().instruction_hits(10));
+        assert!(merged.counts_saturated());
+        assert!(!run(1, JumpType::ConditionalTaken)?.counts_saturated());
+
+        Ok(())
+    }
+
+    #[test]
+    fn weighted_merge() -> Result<(), CFGError> {
+        let other = run(3, JumpType::ConditionalTaken)?;
+        let mut doubled = ControlFlowGraph::new(0);
+        doubled.merge_weighted(&other, 2.0)?;
+        assert!(doubled == other);
+        assert!(other.edge_map().iter().all(|(edge, count)| doubled.edge_map()[edge] == count * 2));
+        assert_eq!(Some(8), doubled.block(doubled.starts[&10]).unwrap().instruction_hits(10));
+
+        // A weight of zero only adds the structure.
+        let mut structure = ControlFlowGraph::new(0);
+        structure.merge_weighted(&other, 0.0)?;
+        assert!(structure == other);
+        assert!(structure.edge_map().values().all(|count| *count == 0));
+        structure.merge_weighted(&other, 1.0)?;
+        assert!(structure.eq_with_counts(&other));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_splits_blocks() -> Result<(), CFGError> {
+        let mut merged = ControlFlowGraph::new(0);
+        merged.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        merged.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        merged.execute(2, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        let mut other = ControlFlowGraph::new(0);
+        other.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        other.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        other.execute(2, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        other.execute(10, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+        merged.merge(&other)?;
+
+        let starts: Vec<u64> = merged.blocks().map(|bb| bb.start).collect();
+        assert_eq!(vec![0, 10, 1], starts);
+        assert_eq!((0, 0), (merged.blocks[0].start, merged.blocks[0].end));
+        assert_eq!((1, 2), (merged.blocks[2].start, merged.blocks[2].end));
+        let edges = merged.edge_map();
+        assert_eq!(3, edges.len());
+        assert_eq!(2, edges[&(0, 1)]);
+        assert_eq!(2, edges[&(1, 10)]);
+        assert_eq!(1, edges[&(10, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_order_doesnt_matter() -> Result<(), CFGError> {
+        // One graph splits the block at 0 by jumping back into it, the other keeps it whole.
+        let split = || -> Result<ControlFlowGraph, CFGError> {
+            let mut cfg = ControlFlowGraph::new(0);
+            cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(2, BlockType::Jump("JZ".to_string(), 1, JumpType::ConditionalTaken, Some(3)))?;
+            cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(2, BlockType::Jump("JZ".to_string(), 1, JumpType::ConditionalNotTaken, Some(3)))?;
+            Ok(cfg)
+        };
+        let whole = || -> Result<ControlFlowGraph, CFGError> {
+            let mut cfg = ControlFlowGraph::new(0);
+            cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(2, BlockType::Jump("JZ".to_string(), 1, JumpType::ConditionalNotTaken, Some(3)))?;
+            Ok(cfg)
+        };
+
+        let mut forward = split()?;
+        forward.merge(&whole()?)?;
+        let mut backward = whole()?;
+        backward.merge(&split()?)?;
+        assert!(forward.eq_with_counts(&backward));
+        let hits = |cfg: &ControlFlowGraph| cfg.blocks_by_address().map(|block| (block.start, block.hits())).collect::<Vec<_>>();
+        assert_eq!(hits(&forward), hits(&backward));
+        assert_eq!(vec![(0, 2), (1, 3), (3, 2)], hits(&forward));
+        assert_eq!(2, forward.edge_map()[&(0, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_errors() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        let mut conflicting = ControlFlowGraph::new(0);
+        conflicting.execute(0, BlockType::Instruction("DEC".to_string(), None))?;
+
+        let result = cfg.merge(&conflicting);
+        assert!(matches!(result, Err(CFGError::InstructionConflict { pc: 0, ref existing, ref new }) if existing == "INC" && new == "DEC"));
+        let result = cfg.merge(&ControlFlowGraph::new(4));
+        assert!(matches!(result, Err(CFGError::EntryMismatch { expected: 0, found: 4 })));
+        assert_eq!(1, cfg.entry().len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use crate::ControlFlowGraph;
+use crate::types::*;
+use crate::prelude::*;
+
+/// A module loaded into the address space of a trace, such as the main binary or a shared library, which covers the
+/// given number of bytes from its base address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Module {
+    name: String,
+    base: u64,
+    size: u64,
+}
+
+impl Module {
+    /// Returns the name of the module.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the address the module is loaded at.
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// Returns the number of bytes the module covers.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns whether the address lies inside of the module.
+    pub fn contains(&self, address: u64) -> bool {
+        address.checked_sub(self.base).is_some_and(|offset| offset < self.size)
+    }
+
+    /// Returns the last address inside of the module.
+    pub(crate) fn last(&self) -> u64 {
+        self.base + (self.size - 1)
+    }
+
+    /// Loads the module at the given base instead.
+    pub(crate) fn move_to(&mut self, base: u64) {
+        self.base = base;
+    }
+}
+
+/// An address given by the position of the module it lies in and its offset from the base of the module, which stays
+/// the same wherever the module is loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModuleAddress {
+    /// The position of the module among the modules of the graph.
+    pub module: usize,
+    /// The distance of the address from the base of the module.
+    pub offset: u64,
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Adds a module of `size` bytes loaded at `base` to the address space, returning its position among the modules.
+    ///
+    /// Addresses inside of a module which no Symbolizer names are written as the name of the module and their offset
+    /// into it in the textual output, such as `libfoo+0x1a2`. Returns `CFGError::InvalidModule` if the module is
+    /// empty, doesn't fit into the address range, overlaps another module, or has the name of another module.
+    pub fn add_module(&mut self, name: impl Into<String>, base: u64, size: u64) -> Result<usize, CFGError> {
+        let module = Module { name: name.into(), base, size };
+        let invalid = |reason: &str| Err(CFGError::InvalidModule { name: module.name.clone(), reason: reason.to_string() });
+        if size == 0 || base.checked_add(size - 1).is_none() {
+            return invalid("is empty or doesn't fit into the address range");
+        }
+        if let Some(other) = self.modules.iter().find(|other| other.name == module.name) {
+            return invalid(&format!("has the same name as the module at {:#x}", other.base));
+        }
+        if let Some(other) = self.modules.iter().find(|other| other.base <= module.last() && module.base <= other.last()) {
+            return invalid(&format!("overlaps the module {}", other.name));
+        }
+        self.modules.push(module);
+        Ok(self.modules.len() - 1)
+    }
+
+    /// Returns the modules of the address space in the order they were added.
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    /// Returns the position of the module with the given name, if there is one.
+    pub fn module_named(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.name == name)
+    }
+
+    /// Returns the address relative to the module it lies in, if it lies in one.
+    pub fn module_address(&self, address: u64) -> Option<ModuleAddress> {
+        let module = self.modules.iter().position(|module| module.contains(address))?;
+        Some(ModuleAddress { module, offset: address - self.modules[module].base })
+    }
+
+    /// Returns the start address of the block at the given position relative to the module it lies in, if it lies in one.
+    pub fn block_module_address(&self, block: BlockId) -> Option<ModuleAddress> {
+        self.module_address(self.blocks.get(block.0)?.start)
+    }
+
+    /// Returns the address the module-relative address is at where its module is loaded, if the module has it.
+    pub fn resolve_module_address(&self, address: ModuleAddress) -> Option<u64> {
+        let module = self.modules.get(address.module)?;
+        (address.offset < module.size).then(|| module.base + address.offset)
+    }
+
+    /// Returns the position of the block whose instructions cover the given offset into the module with the given name,
+    /// like [find_block_containing](Self::find_block_containing).
+    pub fn find_block_in_module(&self, name: &str, offset: u64) -> Option<BlockId> {
+        let module = self.module_named(name)?;
+        self.find_block_containing(self.resolve_module_address(ModuleAddress { module, offset })?)
+    }
+
+    /// Moves every address inside of the module with the given name to where it is loaded at the new base, like
+    /// [rebase](Self::rebase) does for the whole graph, such as when ASLR loaded only that module elsewhere.
+    ///
+    /// Addresses inside of the module are moved wherever they appear, so jumps and calls into the module from other
+    /// modules follow it. Returns `CFGError::UnknownModule` if there is no such module, and `CFGError::InvalidModule`
+    /// if it wouldn't fit into the address range or would overlap another module at the new base, leaving the graph
+    /// unchanged.
+    pub fn rebase_module(&mut self, name: &str, new_base: u64) -> Result<(), CFGError> {
+        let index = self.module_named(name).ok_or_else(|| CFGError::UnknownModule { name: name.to_string() })?;
+        let module = self.modules[index].clone();
+        let moved = Module { base: new_base, ..module.clone() };
+        let invalid = |reason: String| Err(CFGError::InvalidModule { name: name.to_string(), reason });
+        if new_base.checked_add(module.size - 1).is_none() {
+            return invalid(format!("doesn't fit into the address range at {:#x}", new_base));
+        }
+        if let Some(other) = self.modules.iter().enumerate().find(|(position, other)| *position != index && other.base <= moved.last() && moved.base <= other.last()) {
+            return invalid(format!("would overlap the module {} at {:#x}", other.1.name, new_base));
+        }
+
+        self.relocate(|address| if module.contains(address) { address - module.base + new_base } else { address });
+        self.modules[index].move_to(new_base);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ControlFlowGraph, ModuleAddress};
+    use crate::export::DotOptions;
+    use crate::types::*;
+    use crate::prelude::*;
+
+    /// Runs a call from the main binary into a library loaded at the given base, which returns to the binary.
+    fn run(library_base: u64) -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x40_1000);
+        cfg.add_module("app", 0x40_0000, 0x10_0000)?;
+        cfg.add_module("libfoo", library_base, 0x1000)?;
+        cfg.execute(0x40_1000, BlockType::Instruction("MOV".to_string(), None))?;
+        cfg.execute(0x40_1001, BlockType::Jump("CALL".to_string(), library_base + 0x1a2, JumpType::Call, Some(0x40_1006)))?;
+        cfg.execute(library_base + 0x1a2, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(library_base + 0x1a3, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(0x40_1006, BlockType::Instruction("HLT".to_string(), None))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn module_addresses() -> Result<(), CFGError> {
+        let mut cfg = run(0x7f00_0000)?;
+        assert!(matches!(cfg.add_module("libbar", 0x7f00_0800, 0x1000), Err(CFGError::InvalidModule { .. })));
+        assert!(matches!(cfg.add_module("libfoo", 0x1000, 0x10), Err(CFGError::InvalidModule { .. })));
+        assert_eq!("The module libbar overlaps the module libfoo.", cfg.add_module("libbar", 0x7f00_0fff, 1).unwrap_err().to_string());
+        assert_eq!(2, cfg.modules().len());
+
+        let callee = cfg.find_block_in_module("libfoo", 0x1a3).unwrap();
+        assert_eq!(0x7f00_01a2, cfg.blocks[callee].start());
+        assert_eq!(Some(ModuleAddress { module: 1, offset: 0x1a2 }), cfg.block_module_address(callee));
+        assert_eq!(Some(0x7f00_01a2), cfg.resolve_module_address(ModuleAddress { module: 1, offset: 0x1a2 }));
+        assert_eq!((None, None), (cfg.module_address(0x7f00_1000), cfg.find_block_in_module("libfoo", 0x1000)));
+
+        let listing = cfg.to_string();
+        assert!(listing.starts_with("app+0x1000 - app+0x1001 (entry):\n    app+0x1000: MOV\n    app+0x1001: CALL libfoo+0x1a2 / app+0x1006 (call)\n"));
+        assert!(listing.contains("\n    -> libfoo+0x1a2 (1)\n"));
+
+        // Every module is a cluster, with the call edge between them.
+        let dot = cfg.to_dot_with_options(&DotOptions { module_clusters: true, ..DotOptions::default() });
+        assert!(dot.contains("    subgraph cluster_mod_1 {\n        label=\"libfoo\";\n        \"0x7f0001a2\" [label=\"libfoo+0x1a2 - libfoo+0x1a3\\l"));
+        assert!(dot.contains("\n    \"0x401000\" -> \"0x7f0001a2\" [label=\"1\", color=blue];\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rebase_and_merge_modules() -> Result<(), CFGError> {
+        let mut cfg = run(0x7f00_0000)?;
+        let other = run(0x7e00_0000)?;
+        assert!(!cfg.eq_with_counts(&other));
+
+        // Merging matches the library of both runs, despite being loaded at different bases.
+        cfg.merge(&other)?;
+        assert_eq!(3, cfg.len());
+        let callee = cfg.find_block_in_module("libfoo", 0x1a2).unwrap();
+        assert_eq!((2, None), (cfg.blocks[callee].hits(), cfg.find_block_containing(0x7e00_01a2)));
+
+        cfg.rebase_module("libfoo", 0x7e00_0000)?;
+        let mut expected = run(0x7e00_0000)?;
+        expected.merge(&other)?;
+        assert!(cfg.eq_with_counts(&expected));
+        assert_eq!(0x7e00_0000, cfg.modules()[1].base());
+
+        // The return address of a call into the moved library still leads back to the binary.
+        cfg.execute(0x40_1007, BlockType::Jump("CALL".to_string(), 0x7e00_01a2, JumpType::Call, Some(0x40_100c)))?;
+        cfg.rebase_module("libfoo", 0x7d00_0000)?;
+        cfg.execute(0x7d00_01a2, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x7d00_01a3, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        assert_eq!(0x40_100c, cfg.blocks[cfg.current_block_index()].start());
+
+        assert!(matches!(cfg.rebase_module("libbar", 0), Err(CFGError::UnknownModule { .. })));
+        assert!(matches!(cfg.rebase_module("libfoo", 0x40_0800), Err(CFGError::InvalidModule { .. })));
+        assert!(matches!(cfg.rebase_module("libfoo", u64::MAX), Err(CFGError::InvalidModule { .. })));
+
+        Ok(())
+    }
+}
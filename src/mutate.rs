@@ -0,0 +1,261 @@
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::types::{BlockId, CFGError};
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Detaches the BasicBlock at the given position and returns it, dropping every edge to it.
+    ///
+    /// The positions of the blocks after it shift down by one, and every edge is rewritten to match. The edges of the
+    /// returned block still name the positions from before the removal. The entry and the current block of the graph
+    /// or any thread can't be removed.
+    pub fn remove_block(&mut self, block_index: BlockId) -> Result<BasicBlock<I>, CFGError> {
+        if block_index.index() >= self.blocks.len() {
+            return Err(CFGError::MissingBlock { index: block_index.index() });
+        }
+        if self.block_in_use(block_index) {
+            return Err(CFGError::BlockInUse { block: block_index });
+        }
+
+        let start = self.blocks[block_index].start;
+        let removed = core::mem::replace(&mut self.blocks[block_index], BasicBlock::new(start));
+        let mut keep = vec![true; self.blocks.len()];
+        keep[block_index.index()] = false;
+        self.retain_blocks(&keep);
+        Ok(removed)
+    }
+
+    /// Merges the BasicBlock at the given position with its successor, when it has exactly one outgoing edge and is
+    /// the only predecessor of the successor.
+    ///
+    /// The successor must start after the block ends, its instructions are appended and its outgoing edges replace the
+    /// edge between them. The merged block keeps the position, start, and hits of the block at the given position, and
+    /// is an exit block if the successor was one. The positions after the successor shift down by one.
+    pub fn merge_with_successor(&mut self, block_index: BlockId) -> Result<(), CFGError> {
+        let block = self.blocks.get(block_index.index()).ok_or(CFGError::MissingBlock { index: block_index.index() })?;
+        let successor = match block.edges.as_slice() {
+            [edge] => edge.target(),
+            _ => return Err(CFGError::CannotMergeBlock { block: block_index }),
+        };
+        let predecessors = self.predecessors(successor)?;
+        if successor == BlockId(0) || successor == block_index || predecessors != [block_index] || self.blocks[successor].start <= block.end {
+            return Err(CFGError::CannotMergeBlock { block: block_index });
+        }
+
+        // Execution which was inside of the successor is now inside of the merged block.
+        if self.current_block == successor {
+            self.current_block = block_index;
+        }
+        for cursor in self.threads.values_mut() {
+            if cursor.current_block == successor {
+                cursor.current_block = block_index;
+            }
+        }
+
+        let start = self.blocks[successor].start;
+        let absorbed = core::mem::replace(&mut self.blocks[successor], BasicBlock::new(start));
+        let merged = &mut self.blocks[block_index];
+        merged.end = absorbed.end;
+        merged.exit = absorbed.exit;
+        merged.block.extend(absorbed.block);
+        merged.instruction_hits.extend(absorbed.instruction_hits);
+        merged.sizes.extend(absorbed.sizes);
+        merged.edges = absorbed.edges;
+        for (key, value) in absorbed.metadata {
+            merged.metadata.entry(key).or_insert(value);
+        }
+
+        let mut keep = vec![true; self.blocks.len()];
+        keep[successor.index()] = false;
+        self.retain_blocks(&keep);
+        Ok(())
+    }
+
+    /// Removes the edge from the block at position `src` to the block at position `dst`, returning whether there was one.
+    pub fn remove_edge(&mut self, src: BlockId, dst: BlockId) -> Result<bool, CFGError> {
+        for index in [src, dst] {
+            if index.index() >= self.blocks.len() {
+                return Err(CFGError::MissingBlock { index: index.index() });
+            }
+        }
+        let Some(position) = self.blocks[src].edges.iter().position(|edge| edge.target() == dst) else {
+            return Ok(false);
+        };
+        self.blocks[src].edges.remove(position);
+        self.unlink(src, dst);
+        Ok(true)
+    }
+
+    /// Keeps only the edges for which the closure returns true, given the positions of their source and target and their
+    /// traversal count.
+    pub fn retain_edges(&mut self, mut f: impl FnMut(BlockId, BlockId, u64) -> bool) {
+        let mut removed = Vec::new();
+        for (index, block) in self.blocks.iter_mut().enumerate() {
+            block.edges.retain(|edge| {
+                let keep = f(BlockId(index), edge.target(), edge.count());
+                if !keep {
+                    removed.push((BlockId(index), edge.target()));
+                }
+                keep
+            });
+        }
+        for (src, dst) in removed {
+            self.unlink(src, dst);
+        }
+    }
+
+    /// Zeroes every hit and traversal count while keeping the blocks, instructions, and edges, so that the next run
+    /// counts from scratch.
+    ///
+    /// Execution starts over at the entry like it does in a new graph, which counts as entering the entry once. The call
+    /// stack, the threads, and the recorded sequence are cleared, and instructions are counted from one again for the
+    /// ordinals edges are first and last seen at.
+    pub fn reset_counts(&mut self) {
+        for block in self.blocks.iter_mut() {
+            block.hits = 0;
+            block.instruction_hits.values_mut().for_each(|hits| *hits = 0);
+            block.edges.iter_mut().for_each(|edge| {
+                edge.set_count(0);
+                edge.set_seen(None, None);
+            });
+        }
+        self.blocks[0].hits = 1;
+        self.executed = 0;
+        self.current_block = BlockId(0);
+        self.call_stack.clear();
+        self.previous_instruction = None;
+        self.pending_fallthrough = None;
+        self.terminated = None;
+        self.threads.clear();
+        self.sequence.clear();
+        self.saturated = false;
+    }
+
+    /// Returns whether the block at the given position is the entry or the current block of the graph or any thread.
+    fn block_in_use(&self, block_index: BlockId) -> bool {
+        block_index == BlockId(0) || block_index == self.current_block || self.threads.values().any(|cursor| cursor.current_block == block_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    fn chain() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        run(&mut cfg)?;
+        Ok(cfg)
+    }
+
+    fn run(cfg: &mut ControlFlowGraph) -> Result<(), CFGError> {
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalNotTaken, Some(10)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(11, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute(21, BlockType::Jump("JMP".to_string(), 30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(30, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        Ok(())
+    }
+
+    #[test]
+    fn reset_and_rerun() -> Result<(), CFGError> {
+        let mut cfg = chain()?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, JumpType::ConditionalTaken, Some(10)))?;
+        cfg.reset_counts();
+        assert!(cfg.blocks().skip(1).all(|block| block.hits() == 0 && block.edges().all(|edge| edge.count() == 0)));
+        assert!(cfg.blocks().all(|block| block.instructions().all(|(pc, _)| block.instruction_hits(*pc) == Some(0))));
+        assert_eq!((1, BlockId(0), 5), (cfg.entry().hits(), cfg.current_block_index(), cfg.edge_count()));
+
+        run(&mut cfg)?;
+        let fresh = chain()?;
+        assert!(cfg.eq_with_counts(&fresh));
+        for (block, fresh_block) in cfg.blocks().zip(fresh.blocks()) {
+            assert_eq!(fresh_block.hits(), block.hits());
+            assert!(block.instructions().all(|(pc, _)| block.instruction_hits(*pc) == fresh_block.instruction_hits(*pc)));
+        }
+        assert_eq!(fresh.current_block_index(), cfg.current_block_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_and_retain_edges() -> Result<(), CFGError> {
+        let mut cfg = chain()?;
+        let (block_10, block_20, block_30) = (cfg.starts[&10], cfg.starts[&20], cfg.starts[&30]);
+        assert!(cfg.remove_edge(BlockId(0), block_20)?);
+        assert!(!cfg.remove_edge(BlockId(0), block_20)?);
+        assert!(!cfg.remove_edge(block_30, block_10)?);
+        assert!(matches!(cfg.remove_edge(BlockId(0), BlockId(9)), Err(CFGError::MissingBlock { index: 9 })));
+        assert_eq!(vec![block_10], cfg.predecessors(block_20)?);
+        assert_eq!(1, cfg.in_degree(block_20)?);
+
+        // The edges leaving 20 and the edge from 30 back to the entry, which is the only one traversed twice, are dropped.
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(30, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        cfg.retain_edges(|src, _, count| src != block_20 && count < 2);
+        assert_eq!(vec![(block_10, 1), (block_30, 1)], cfg.entry().edge_counts());
+        assert!(cfg.successors(block_20)?.is_empty() && cfg.successors(block_30)?.is_empty());
+        assert_eq!(vec![block_10], cfg.predecessors(block_20)?);
+        assert_eq!(vec![BlockId(0)], cfg.predecessors(block_30)?);
+        assert!(cfg.predecessors(BlockId(0))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_middle_block() -> Result<(), CFGError> {
+        let mut cfg = chain()?;
+        let removed = cfg.remove_block(cfg.starts[&10])?;
+        assert_eq!(10, removed.start());
+        assert_eq!(3, cfg.len());
+
+        let (block_20, block_30) = (cfg.starts[&20], cfg.starts[&30]);
+        assert_eq!((BlockId(1), BlockId(2)), (block_20, block_30));
+        assert_eq!(vec![(block_20, 0)], cfg.entry().edge_counts());
+        assert_eq!(vec![(block_30, 1)], cfg.blocks[block_20].edge_counts());
+        assert_eq!(vec![(BlockId(0), 1)], cfg.blocks[block_30].edge_counts());
+        assert!(cfg.verify().is_ok());
+
+        assert!(matches!(cfg.remove_block(BlockId(0)), Err(CFGError::BlockInUse { block: BlockId(0) })));
+        assert!(matches!(cfg.remove_block(BlockId(3)), Err(CFGError::MissingBlock { index: 3 })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_chain() -> Result<(), CFGError> {
+        let mut cfg = chain()?;
+        let (block_10, block_20) = (cfg.starts[&10], cfg.starts[&20]);
+        // The block at 20 has two predecessors, the entry and the block at 10.
+        assert!(matches!(cfg.merge_with_successor(block_10), Err(CFGError::CannotMergeBlock { .. })));
+        assert!(matches!(cfg.merge_with_successor(BlockId(0)), Err(CFGError::CannotMergeBlock { block: BlockId(0) })));
+
+        cfg.merge_with_successor(block_20)?;
+        assert_eq!(3, cfg.len());
+        let merged = cfg.block(cfg.starts[&20]).unwrap();
+        assert_eq!((20, 30), (merged.start(), merged.end()));
+        assert_eq!(vec![20, 21, 30], merged.instructions().map(|(pc, _)| *pc).collect::<Vec<_>>());
+        assert_eq!(vec![(BlockId(0), 1)], merged.edge_counts());
+        assert_eq!(None, cfg.starts.get(&30));
+        assert_eq!(vec![(cfg.starts[&10], 1), (cfg.starts[&20], 0)], cfg.entry().edge_counts());
+        assert_eq!(BlockId(0), cfg.current_block_index());
+        assert!(cfg.verify().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_into_exit() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x10, BlockType::Terminal("HLT".to_string()))?;
+        cfg.merge_with_successor(BlockId(0))?;
+
+        assert_eq!(vec![BlockId(0)], cfg.exit_blocks());
+        cfg.set_current_block_by_address(0);
+        let result = cfg.execute(0x11, BlockType::Instruction("INC".to_string(), None));
+        assert!(matches!(result, Err(CFGError::ExecutionAfterTerminal { pc: 0x11, terminal: 0x10 })));
+
+        Ok(())
+    }
+}
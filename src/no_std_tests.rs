@@ -0,0 +1,46 @@
+//! Exercises the API which remains without the `std` feature, only naming items from core and alloc.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::ControlFlowGraph;
+use crate::types::*;
+
+fn name(text: &str) -> String {
+    text.to_string()
+}
+
+#[test]
+fn execute_without_std() -> Result<(), CFGError> {
+    let mut cfg = ControlFlowGraph::new(0);
+    for jump_type in [JumpType::ConditionalTaken, JumpType::ConditionalNotTaken] {
+        cfg.execute(0, BlockType::Instruction(name("CMP"), Some(name("R1"))))?;
+        cfg.execute(1, BlockType::Jump(name("JZ"), 20, jump_type, Some(10)))?;
+        let next = if jump_type == JumpType::ConditionalTaken { 20 } else { 10 };
+        cfg.execute(next, BlockType::Jump(name("CALL"), 0x40, JumpType::Call, Some(next + 1)))?;
+        cfg.execute(0x40, BlockType::Jump(name("RET"), 0, JumpType::Return, None))?;
+        cfg.execute(next + 1, BlockType::Jump(name("JMP"), 0, JumpType::UnconditionalJump, None))?;
+    }
+
+    let starts: Vec<u64> = cfg.blocks_by_address().map(|block| block.start()).collect();
+    assert_eq!(vec![0, 10, 11, 20, 21, 0x40], starts);
+    assert_eq!(6, cfg.reachable_from_entry(true).len());
+    assert!(!cfg.is_acyclic());
+    assert!(cfg.verify().is_ok());
+    assert!(cfg.to_dot().starts_with("digraph cfg {"));
+    assert!(cfg.to_json().contains("\"entry\""));
+    assert!(format!("{}", cfg).starts_with("0x0 - 0x1 (entry):"));
+
+    Ok(())
+}
+
+#[test]
+fn errors_display_without_std() {
+    let error = CFGError::AddressBeforeBlockStart { pc: 4, block: BlockId(1), block_start: 9 };
+    assert_eq!("Attempted to add an instruction at 0x4 behind the start 0x9 of the current block 1.", format!("{}", error));
+
+    let mut cfg = ControlFlowGraph::new(0);
+    let result = cfg.execute(0, BlockType::Jump(name("RET"), 0, JumpType::Return, None));
+    assert!(matches!(result, Err(CFGError::ReturnWithoutCall { pc: 0 })));
+}
@@ -0,0 +1,139 @@
+use crate::ControlFlowGraph;
+use crate::types::{BlockId, BlockType, EdgeKind};
+use crate::prelude::*;
+
+/// Receives the growth of a ControlFlowGraph as it happens, such as for a fuzzer reacting to new coverage.
+///
+/// An observer is only given addresses, never the graph itself, so it can't change the graph while being notified.
+pub trait CfgObserver: Send {
+    /// Called after a BasicBlock starting at the given address was created.
+    fn on_new_block(&mut self, _start: u64) {}
+
+    /// Called after an edge between the blocks starting at the given addresses was created, and once more with
+    /// `traversed` set when an edge which was created without being traversed is traversed for the first time.
+    fn on_new_edge(&mut self, _from: u64, _to: u64, _traversed: bool) {}
+}
+
+/// A structural change made by a ControlFlowGraph, as written to its CfgEventLog.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) enum CfgEvent<'a, I> {
+    /// A new block was created at an address no block contains.
+    Block { start: u64 },
+    /// The block at the position was split at the address, as done by `split_block`.
+    Split { block: BlockId, address: u64 },
+    /// The instruction was recorded at the address of the block, or replaced the one recorded there.
+    Instruction { block: BlockId, pc: u64, size: u64, instruction: &'a BlockType<I> },
+    /// The instruction already recorded at the address of the block was executed again.
+    Hit { block: BlockId, pc: u64, size: u64 },
+    /// An edge was added or, when traversed, counted by the instruction with the ordinal.
+    Edge { from: BlockId, to: BlockId, kind: EdgeKind, traversed: bool, ordinal: u64 },
+    /// The edge was marked as static.
+    StaticEdge { from: BlockId, to: BlockId },
+    /// The block was entered, counting its hits and making it current.
+    Enter { block: BlockId },
+    /// The hits of the block were counted without entering it, such as for the first block of a thread.
+    BlockHit { block: BlockId },
+    /// The block became current without being entered.
+    Current { block: BlockId },
+    /// The changes which follow are made by the thread with the id, or by the graph itself for `None`.
+    Thread { tid: Option<u64> },
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Notifies the given observer of every block and edge created from now on, replacing the previous observer.
+    pub fn set_observer(&mut self, observer: Box<dyn CfgObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Removes the observer and returns it.
+    pub fn take_observer(&mut self) -> Option<Box<dyn CfgObserver>> {
+        self.observer.take()
+    }
+
+    /// Notifies the observer of the block at the given position, which was just created.
+    pub(crate) fn notify_block(&mut self, block: BlockId) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_new_block(self.blocks[block].start);
+        }
+    }
+
+    /// Notifies the observer of the edge between the blocks at the given positions, which was just created or traversed
+    /// for the first time.
+    pub(crate) fn notify_edge(&mut self, from: BlockId, to: BlockId, traversed: bool) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_new_edge(self.blocks[from].start, self.blocks[to].start, traversed);
+        }
+    }
+
+    /// Writes the change to the event log, if one is attached.
+    #[cfg(feature = "std")]
+    pub(crate) fn log_event(&mut self, event: CfgEvent<'_, I>) {
+        if let Some(log) = self.event_log.as_mut() {
+            log.write(event);
+        }
+    }
+
+    /// Does nothing, as an event log can only be attached with the standard library.
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn log_event(&mut self, _event: CfgEvent<'_, I>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use crate::ControlFlowGraph;
+    use crate::observer::CfgObserver;
+    use crate::types::*;
+
+    #[derive(Default)]
+    struct Events {
+        blocks: Vec<u64>,
+        edges: Vec<(u64, u64, bool)>,
+    }
+
+    struct Recorder(Arc<Mutex<Events>>);
+
+    impl CfgObserver for Recorder {
+        fn on_new_block(&mut self, start: u64) {
+            self.0.lock().unwrap().blocks.push(start);
+        }
+
+        fn on_new_edge(&mut self, from: u64, to: u64, traversed: bool) {
+            self.0.lock().unwrap().edges.push((from, to, traversed));
+        }
+    }
+
+    #[test]
+    fn conditional_jump_events() -> Result<(), CFGError> {
+        let events = Arc::new(Mutex::new(Events::default()));
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.set_observer(Box::new(Recorder(events.clone())));
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(4, BlockType::Instruction("LDAC".to_string(), Some("A".to_string())))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+
+        {
+            let events = events.lock().unwrap();
+            assert_eq!(vec![6, 9], events.blocks);
+            assert_eq!(vec![(2, 6, false), (2, 9, false), (2, 9, true)], events.edges);
+        }
+
+        // Taking the same branch again isn't new, while finally taking the other one is.
+        cfg.execute(11, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        cfg.execute(10, BlockType::Jump("JMP".to_string(), 2, JumpType::UnconditionalJump, None))?;
+        cfg.execute(5, BlockType::Jump("JZ".to_string(), 9, JumpType::ConditionalNotTaken, Some(6)))?;
+        {
+            let events = events.lock().unwrap();
+            assert_eq!(2, events.blocks.len());
+            assert_eq!(vec![(9, 2, true), (2, 6, true)], events.edges[3..]);
+        }
+
+        assert!(cfg.take_observer().is_some());
+        cfg.execute(6, BlockType::Jump("JMP".to_string(), 0x20, JumpType::UnconditionalJump, None))?;
+        assert_eq!(2, events.lock().unwrap().blocks.len());
+
+        Ok(())
+    }
+}
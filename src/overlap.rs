@@ -0,0 +1,83 @@
+use crate::ControlFlowGraph;
+use crate::types::*;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns every pair of blocks whose address ranges overlap, as the position of the block starting first and the
+    /// position of the block starting inside of it, ordered by the start address of the latter.
+    ///
+    /// Blocks which are found through `execute` never overlap in their `[start, end]` ranges, but graphs put together
+    /// otherwise may, and the last instruction of a block may be sized to run past the start of the next block. Both
+    /// are reported, as the bytes of the ranges are compared.
+    pub fn overlapping_blocks(&self) -> Vec<(BlockId, BlockId)> {
+        let mut overlaps = Vec::new();
+        // The blocks starting before the current one which might still cover it, as the starts are walked in order.
+        let mut open: Vec<BlockId> = Vec::new();
+        for (&start, &index) in self.starts.iter() {
+            open.retain(|earlier| start <= self.blocks[*earlier].last_byte());
+            overlaps.extend(open.iter().map(|earlier| (*earlier, index)));
+            open.push(index);
+        }
+        overlaps
+    }
+
+    /// Returns the position of the block whose `[start, end]` range holds the address past its start, if there is one.
+    pub(crate) fn overlapped_block(&self, address: u64) -> Option<BlockId> {
+        let (&start, &index) = self.starts.range(..=address).next_back()?;
+        (start != address && address <= self.blocks[index].end).then_some(index)
+    }
+
+    /// Returns the error for a block which would start at the address inside of the block at the given position.
+    pub(crate) fn overlap_error(&self, index: BlockId, address: u64) -> CFGError {
+        let existing = &self.blocks[index];
+        CFGError::OverlappingBlock { new_start: address, existing_start: existing.start, existing_end: existing.end }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CFGBuilder, ControlFlowGraph};
+    use crate::types::*;
+
+    fn run(cfg: &mut ControlFlowGraph) -> Result<ExecOutcome, CFGError> {
+        cfg.execute(0x10, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x11, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x12, BlockType::Jump("JMP".to_string(), 0x11, JumpType::UnconditionalJump, None))
+    }
+
+    #[test]
+    fn split_policy() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        assert_eq!(OverlapPolicy::Split, cfg.overlap_policy());
+        run(&mut cfg)?;
+        assert_eq!(vec![(0x10, 0x10), (0x11, 0x12)], cfg.blocks().map(|block| (block.start(), block.end())).collect::<Vec<_>>());
+        assert!(cfg.overlapping_blocks().is_empty());
+
+        // An instruction sized to run past the start of the next block is an overlap of the bytes.
+        let mut sized = ControlFlowGraph::new(0x20);
+        sized.execute_sized(0x20, BlockType::Jump("JZ".to_string(), 0x22, JumpType::ConditionalTaken, Some(0x24)), 4)?;
+        assert_eq!(vec![(BlockId(0), sized.find_block_containing(0x22).unwrap())], sized.overlapping_blocks());
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_policy() -> Result<(), CFGError> {
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x10).overlap_policy(OverlapPolicy::Error).build();
+        let error = run(&mut cfg).unwrap_err();
+        assert!(matches!(error, CFGError::OverlappingBlock { new_start: 0x11, existing_start: 0x10, existing_end: 0x12 }));
+        assert_eq!("A block starting at 0x11 would overlap the block from 0x10 to 0x12.", error.to_string());
+        assert_eq!(1, cfg.len());
+        assert!(matches!(cfg.preseed_block(0x12), Err(CFGError::OverlappingBlock { new_start: 0x12, .. })));
+        assert_eq!(cfg.entry_index(), cfg.preseed_block(0x10)?);
+
+        // A run which started a block inside of one of this graph isn't merged at all.
+        let mut other = ControlFlowGraph::new(0x10);
+        run(&mut other)?;
+        let before = cfg.to_json();
+        assert!(matches!(cfg.merge(&other), Err(CFGError::OverlappingBlock { new_start: 0x11, .. })));
+        assert_eq!(before, cfg.to_json());
+
+        Ok(())
+    }
+}
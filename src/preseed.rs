@@ -0,0 +1,55 @@
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::observer::CfgEvent;
+use crate::types::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Creates a BasicBlock starting at the given address before it is executed, such as a function start known from
+    /// static disassembly, returning its position.
+    ///
+    /// An address inside of an existing block splits it like a jump into its middle would, or is rejected with
+    /// `CFGError::OverlappingBlock` by `OverlapPolicy::Error`. The position of an existing block starting at the
+    /// address is returned as is.
+    pub fn preseed_block(&mut self, start: u64) -> Result<BlockId, CFGError> {
+        self.query_block_or_create(start)
+    }
+
+    /// Creates an edge between the blocks starting at the given addresses before it is executed, such as a branch known
+    /// from static disassembly, creating the blocks as `preseed_block` does.
+    ///
+    /// The edge is marked as static and starts with a count of zero, so once the trace ran, a static edge with a count
+    /// of zero was possible but never executed, while an edge which isn't static was only found by executing. The edge
+    /// is `Unconditional` until an instruction creates it with its actual kind. An existing edge is only marked as
+    /// static.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0);
+    /// cfg.preseed_edge(0, 0x10)?;
+    /// cfg.preseed_edge(0, 0x20)?;
+    /// cfg.execute(1, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+    ///
+    /// let never_executed: Vec<u64> = cfg.edges().filter(|edge| edge.is_static() && edge.count() == 0).map(|edge| edge.target_start()).collect();
+    /// assert_eq!(vec![0x20], never_executed);
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn preseed_edge(&mut self, src_start: u64, dst_start: u64) -> Result<(), CFGError> {
+        let source = self.preseed_block(src_start)?;
+        let target = self.preseed_block(dst_start)?;
+        // Creating the target splits the source if it starts inside of it, which keeps the source at its position.
+        self.add_edge(source, target, false, EdgeKind::Unconditional)?;
+        self.log_event(CfgEvent::StaticEdge { from: source, to: target });
+        self.blocks[source].mark_static_edge(target);
+        Ok(())
+    }
+}
+
+impl<I> BasicBlock<I> {
+    /// Marks the edge to the block at the given position as pre-seeded, if this BasicBlock has one.
+    pub(crate) fn mark_static_edge(&mut self, target: BlockId) {
+        if let Some(edge) = self.edges.iter_mut().find(|edge| edge.target() == target) {
+            edge.mark_static();
+        }
+    }
+}
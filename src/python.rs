@@ -0,0 +1,209 @@
+//! Python bindings of the ControlFlowGraph through pyo3, with the `python` feature.
+//!
+//! The extension module is named `ctrl_flow` and holds a `ControlFlowGraph` class along with the `CfgError` exception,
+//! which is raised for every CFGError with the same message.
+//!
+//! ```text
+//! import ctrl_flow
+//!
+//! cfg = ctrl_flow.ControlFlowGraph(0x401000)
+//! cfg.execute(0x401000, "MOV", "RAX, 1")
+//! cfg.execute_jump(0x401004, "JZ", 0x401020, "taken", 0x401006)
+//! for block in cfg.blocks():
+//!     print(hex(block["start"]), block["hits"], block["edges"])
+//! ```
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::{BasicBlock, ControlFlowGraph};
+use crate::types::*;
+
+create_exception!(ctrl_flow, CfgError, PyException, "Raised for every error of a ControlFlowGraph, with the message of the CFGError.");
+
+impl From<CFGError> for PyErr {
+    fn from(error: CFGError) -> PyErr {
+        CfgError::new_err(error.to_string())
+    }
+}
+
+/// A ControlFlowGraph of instructions named by strings, as seen from Python.
+#[pyclass(name = "ControlFlowGraph", unsendable)]
+pub struct PyControlFlowGraph {
+    cfg: ControlFlowGraph,
+}
+
+#[pymethods]
+impl PyControlFlowGraph {
+    /// Generates a ControlFlowGraph, starting at the given entry point address.
+    #[new]
+    fn new(entry_point: u64) -> Self {
+        PyControlFlowGraph { cfg: ControlFlowGraph::new(entry_point) }
+    }
+
+    /// Executes an instruction which isn't a jump at the given address.
+    #[pyo3(signature = (pc, name, operand=None))]
+    fn execute(&mut self, pc: u64, name: String, operand: Option<String>) -> PyResult<()> {
+        self.cfg.execute(pc, BlockType::Instruction(name, operand))?;
+        Ok(())
+    }
+
+    /// Executes a jump at the given address, whose kind is one of `unconditional`, `taken`, `not_taken`, `call`,
+    /// `return`, and `indirect`.
+    #[pyo3(signature = (pc, name, target, kind, fallthrough=None))]
+    fn execute_jump(&mut self, pc: u64, name: String, target: u64, kind: &str, fallthrough: Option<u64>) -> PyResult<()> {
+        let kind = parse_jump_kind(kind).map_err(PyValueError::new_err)?;
+        self.cfg.execute(pc, BlockType::Jump(name, target, kind, fallthrough))?;
+        Ok(())
+    }
+
+    /// Executes a terminal instruction at the given address, which halts the program.
+    fn execute_terminal(&mut self, pc: u64, name: String) -> PyResult<()> {
+        self.cfg.execute(pc, BlockType::Terminal(name))?;
+        Ok(())
+    }
+
+    /// Returns every block as a dict, in the order they were created with the entry block first.
+    fn blocks<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.cfg.blocks().map(|block| BlockRecord::new(&self.cfg, block).into_dict(py)).collect()
+    }
+
+    /// Renders the graph in the Graphviz DOT format.
+    fn to_dot(&self) -> String {
+        self.cfg.to_dot()
+    }
+
+    /// Renders the graph as JSON.
+    fn to_json(&self) -> String {
+        self.cfg.to_json()
+    }
+
+    fn __len__(&self) -> usize {
+        self.cfg.len()
+    }
+
+    fn __str__(&self) -> String {
+        self.cfg.to_string()
+    }
+}
+
+/// The `ctrl_flow` Python module.
+#[pymodule]
+fn ctrl_flow(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyControlFlowGraph>()?;
+    module.add("CfgError", module.py().get_type::<CfgError>())?;
+    Ok(())
+}
+
+/// Parses the kind of a jump passed from Python, which is named like the JumpType is displayed with underscores instead
+/// of spaces, such as `unconditional`, `taken`, `not_taken`, `call`, `return`, and `indirect`.
+fn parse_jump_kind(kind: &str) -> Result<JumpType, String> {
+    let kinds = [JumpType::UnconditionalJump, JumpType::ConditionalTaken, JumpType::ConditionalNotTaken, JumpType::Call, JumpType::Return, JumpType::Indirect];
+    kinds.into_iter()
+        .find(|jump_type| jump_type.to_string().replace(' ', "_") == kind)
+        .ok_or_else(|| format!("unknown jump kind `{}`", kind))
+}
+
+/// Returns the name an EdgeKind is passed to Python with.
+fn edge_kind_name(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::Taken => "taken",
+        EdgeKind::FallThrough => "fall_through",
+        EdgeKind::Unconditional => "unconditional",
+        EdgeKind::Call => "call",
+        EdgeKind::Return => "return",
+        EdgeKind::Unknown => "unknown",
+    }
+}
+
+/// The contents of a BasicBlock which are handed to Python as a dict, naming other blocks by their start address.
+#[derive(Debug, PartialEq, Eq)]
+struct BlockRecord {
+    start: u64,
+    end: u64,
+    hits: u64,
+    instructions: Vec<(u64, String)>,
+    edges: Vec<EdgeRecord>,
+}
+
+/// An edge of a BlockRecord, which becomes a dict with the start address of its target.
+#[derive(Debug, PartialEq, Eq)]
+struct EdgeRecord {
+    target: u64,
+    count: u64,
+    kind: &'static str,
+}
+
+impl BlockRecord {
+    /// Collects the contents of the given block of the graph.
+    fn new(cfg: &ControlFlowGraph, block: &BasicBlock) -> Self {
+        BlockRecord {
+            start: block.start(),
+            end: block.end(),
+            hits: block.hits(),
+            instructions: block.instructions().map(|(pc, instruction)| (*pc, instruction.to_string())).collect(),
+            edges: block.edges().map(|edge| EdgeRecord {
+                target: cfg.blocks[edge.target()].start(),
+                count: edge.count(),
+                kind: edge_kind_name(edge.kind()),
+            }).collect(),
+        }
+    }
+
+    /// Converts the record into a dict with the keys `start`, `end`, `hits`, `instructions` as a list of address and
+    /// text pairs, and `edges` as a list of dicts with the keys `target`, `count`, and `kind`.
+    fn into_dict(self, py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("start", self.start)?;
+        dict.set_item("end", self.end)?;
+        dict.set_item("hits", self.hits)?;
+        dict.set_item("instructions", self.instructions)?;
+        let edges = self.edges.into_iter().map(|edge| {
+            let dict = PyDict::new(py);
+            dict.set_item("target", edge.target)?;
+            dict.set_item("count", edge.count)?;
+            dict.set_item("kind", edge.kind)?;
+            Ok(dict)
+        }).collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("edges", edges)?;
+        Ok(dict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump_kinds() {
+        assert_eq!(Ok(JumpType::ConditionalNotTaken), parse_jump_kind("not_taken"));
+        assert_eq!(Ok(JumpType::UnconditionalJump), parse_jump_kind("unconditional"));
+        assert_eq!(Ok(JumpType::Return), parse_jump_kind("return"));
+        assert_eq!(Err("unknown jump kind `not taken`".to_string()), parse_jump_kind("not taken"));
+        assert_eq!(Err("unknown jump kind `jmp`".to_string()), parse_jump_kind("jmp"));
+    }
+
+    #[test]
+    fn block_records() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        cfg.execute(0x10, BlockType::Instruction("MOV".to_string(), Some("A, 1".to_string())))?;
+        cfg.execute(0x11, BlockType::Jump("JZ".to_string(), 0x20, parse_jump_kind("taken").unwrap(), Some(0x13)))?;
+
+        let records: Vec<_> = cfg.blocks().map(|block| BlockRecord::new(&cfg, block)).collect();
+        assert_eq!(BlockRecord {
+            start: 0x10,
+            end: 0x11,
+            hits: 1,
+            instructions: vec![(0x10, "MOV A, 1".to_string()), (0x11, "JZ 0x20 / 0x13 (taken)".to_string())],
+            edges: vec![
+                EdgeRecord { target: 0x13, count: 0, kind: "fall_through" },
+                EdgeRecord { target: 0x20, count: 1, kind: "taken" },
+            ],
+        }, records[0]);
+        assert_eq!(vec![0x10, 0x13, 0x20], records.iter().map(|record| record.start).collect::<Vec<_>>());
+        assert!(records[1].edges.is_empty() && records[1].instructions.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! Ingestion of the execution logs which QEMU writes with `-d exec,nochain`.
+//!
+//! Every time QEMU enters a translation block it logs a line with the guest address of the block as the second field
+//! inside of the brackets, followed by the cpu state and optionally a symbol, which differ between QEMU versions.
+//!
+//! ```text
+//! Trace 0: 0x7f5bd4000100 [00000000/0000000000400080/00000000/ff000000] _start
+//! Trace 0x7f5bd4000100 [00000000/0x00400080/0x40c00000]
+//! ```
+//!
+//! The instructions inside of a block aren't logged, so every block holds a single placeholder instruction at its
+//! start, an indirect jump named [PLACEHOLDER] whose targets are the blocks entered after it. With `nochain`, QEMU
+//! logs every block entry, so consecutive lines are consecutive blocks of the execution.
+
+use std::io::BufRead;
+use crate::ControlFlowGraph;
+use crate::types::*;
+
+/// The name of the instruction recorded at the start of every block entered by an execution log.
+pub const PLACEHOLDER: &str = "tb";
+
+/// Statistics about an execution log which was ingested into a ControlFlowGraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QemuLogStats {
+    /// The number of lines read from the log, including skipped lines.
+    pub lines: usize,
+    /// The number of block entries found in the log.
+    pub block_entries: usize,
+    /// The number of lines which aren't block entries and were skipped, such as other `-d` output.
+    pub skipped: usize,
+    /// The number of BasicBlocks which were created during ingestion.
+    pub blocks_created: usize,
+}
+
+/// Reads an execution log written by QEMU with `-d exec,nochain`, entering a block keyed by the guest address of every
+/// logged block entry.
+///
+/// Consecutive block entries become an unconditional transition from the earlier block to the later one. The first
+/// entry continues from the current block of the graph, so a graph created with the address of the first logged block
+/// as its entry point starts in it. Lines which aren't block entries are skipped and counted.
+pub fn ingest_qemu_exec_log<R: BufRead>(cfg: &mut ControlFlowGraph, reader: R) -> Result<QemuLogStats, CFGError> {
+    let mut stats = QemuLogStats::default();
+    let initial_blocks = cfg.blocks.len();
+
+    for line in reader.lines() {
+        let line = line?;
+        stats.lines += 1;
+        let Some(address) = parse_block_entry(&line) else {
+            stats.skipped += 1;
+            continue;
+        };
+        stats.block_entries += 1;
+        // The current block is always the one entered by the previous line, which starts at its logged address.
+        let current = cfg.blocks[cfg.current_block].start;
+        if stats.block_entries > 1 || address != current {
+            cfg.execute_indirect(current, PLACEHOLDER.to_string(), address)?;
+        }
+    }
+
+    stats.blocks_created = cfg.blocks.len() - initial_blocks;
+    Ok(stats)
+}
+
+/// Parses the guest address out of a `Trace` line, returning `None` for any other line.
+pub(crate) fn parse_block_entry(line: &str) -> Option<u64> {
+    let rest = line.trim().strip_prefix("Trace ")?;
+    let (_, state) = rest.split_once('[')?;
+    let (state, _) = state.split_once(']')?;
+    let address = state.split('/').nth(1)?.trim();
+    let address = address.strip_prefix("0x").unwrap_or(address);
+    u64::from_str_radix(address, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_log() -> Result<(), CFGError> {
+        // A loop from 0x400090 to 0x4000a0 which runs twice before exiting to 0x4000b0, which loops onto itself once.
+        let log = "\
+QEMU 8.2.0 monitor - type 'help' for more information
+Trace 0: 0x7f5bd4000100 [00000000/0000000000400080/00000000/ff000000] _start
+Trace 0: 0x7f5bd4000240 [00000000/0000000000400090/00000000/ff000000] loop
+Trace 0: 0x7f5bd4000380 [00000000/00000000004000a0/00000000/ff000000]
+Linking TBs 0x7f5bd4000240 index 0 -> 0x7f5bd4000380
+Trace 0: 0x7f5bd4000240 [00000000/0000000000400090/00000000/ff000000] loop
+Trace 0x7f5bd4000380 [00000000/0x004000a0/0x40c00000]
+Trace 0: 0x7f5bd40004c0 [00000000/00000000004000b0/00000000/ff000000] exit
+Trace 0: 0x7f5bd40004c0 [00000000/00000000004000b0/00000000/ff000000] exit
+Trace 0: 0x7f5bd4000600 [garbled
+";
+        let mut cfg = ControlFlowGraph::new(0x400080);
+        let stats = ingest_qemu_exec_log(&mut cfg, log.as_bytes())?;
+        assert_eq!(QemuLogStats { lines: 10, block_entries: 7, skipped: 3, blocks_created: 3 }, stats);
+
+        let edges: Vec<_> = cfg.edges().map(|edge| (edge.source_start(), edge.target_start(), edge.count(), edge.kind())).collect();
+        assert_eq!(vec![
+            (0x400080, 0x400090, 1, EdgeKind::Unconditional),
+            (0x400090, 0x4000a0, 2, EdgeKind::Unconditional),
+            (0x4000a0, 0x400090, 1, EdgeKind::Unconditional),
+            (0x4000a0, 0x4000b0, 1, EdgeKind::Unconditional),
+            (0x4000b0, 0x4000b0, 1, EdgeKind::Unconditional),
+        ], edges);
+        assert_eq!(Some(2), cfg.block(cfg.starts[&0x400090]).unwrap().instruction_hits(0x400090));
+
+        // Another log continues from the block the previous one ended in.
+        let stats = ingest_qemu_exec_log(&mut cfg, "Trace 0: 0x7f5bd4000100 [00000000/0000000000400080/00000000/ff000000]\n".as_bytes())?;
+        assert_eq!((1, 0), (stats.block_entries, stats.blocks_created));
+        assert_eq!(Some(1), cfg.block(cfg.starts[&0x4000b0]).unwrap().edge_to(cfg.entry_index()).map(|edge| edge.count()));
+
+        Ok(())
+    }
+}
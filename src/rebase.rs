@@ -0,0 +1,193 @@
+use crate::ControlFlowGraph;
+use crate::types::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Moves every address of the graph from the given old base to the new one, such as when a module was loaded at a
+    /// different base by ASLR, so that graphs of separate runs can be merged and compared.
+    ///
+    /// The start and end of every block, the addresses of its instructions, the targets and failure addresses of its
+    /// jumps and switches, the return addresses on the call stacks of the graph and every thread, the previous
+    /// instruction execution continues from, the conditional jump waiting for its fall-through, the terminal
+    /// instruction which halted execution, and the bases of the modules are all moved by the distance between the
+    /// bases. Addresses below the old base move as well. Returns `CFGError::RebaseOverflow` if an address would move
+    /// past either end of the address range, leaving the graph unchanged.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0x7f00_1000);
+    /// cfg.execute(0x7f00_1004, BlockType::Jump("JMP".to_string(), 0x7f00_1020, JumpType::UnconditionalJump, None))?;
+    /// cfg.rebase(0x7f00_0000, 0x40_0000)?;
+    /// assert_eq!((0x40_1000, 0x40_1004), (cfg.entry().start(), cfg.entry().end()));
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn rebase(&mut self, old_base: u64, new_base: u64) -> Result<(), CFGError> {
+        // Moving preserves the order of the addresses, so only the lowest and highest one can leave the address range.
+        let mut lowest = u64::MAX;
+        let mut highest = 0;
+        let mut bound = |address: u64| {
+            lowest = lowest.min(address);
+            highest = highest.max(address);
+        };
+        self.blocks.iter().flat_map(|block| block.block.keys()).for_each(|pc| bound(*pc));
+        self.for_each_address(|address| bound(*address));
+        self.modules.iter().for_each(|module| {
+            bound(module.base());
+            bound(module.last());
+        });
+        let overflow = |address| CFGError::RebaseOverflow { address, old_base, new_base };
+        let shift = |address: u64| if new_base >= old_base { address.checked_add(new_base - old_base) } else { address.checked_sub(old_base - new_base) };
+        if lowest <= highest {
+            shift(lowest).ok_or(overflow(lowest))?;
+            shift(highest).ok_or(overflow(highest))?;
+        }
+
+        self.relocate(|address| shift(address).unwrap());
+        self.modules.iter_mut().for_each(|module| module.move_to(shift(module.base()).unwrap()));
+        Ok(())
+    }
+
+    /// Moves every address of the graph which is moved by a rebase to where the given function maps it, which must keep
+    /// the blocks from overlapping.
+    pub(crate) fn relocate(&mut self, shift: impl Fn(u64) -> u64) {
+        self.for_each_address(|address| *address = shift(*address));
+        for block in self.blocks.iter_mut() {
+            block.block = core::mem::take(&mut block.block).into_iter().map(|(pc, instruction)| (shift(pc), instruction)).collect();
+            block.instruction_hits = core::mem::take(&mut block.instruction_hits).into_iter().map(|(pc, hits)| (shift(pc), hits)).collect();
+            block.sizes = core::mem::take(&mut block.sizes).into_iter().map(|(pc, size)| (shift(pc), size)).collect();
+        }
+        self.starts = self.blocks.iter().enumerate().map(|(index, block)| (block.start, BlockId(index))).collect();
+    }
+
+    /// Moves every address of the graph so that the entry starts at zero, see [rebase](Self::rebase).
+    ///
+    /// Returns `CFGError::RebaseOverflow` if any address is below the entry, leaving the graph unchanged.
+    pub fn normalize_to_entry(&mut self) -> Result<(), CFGError> {
+        self.rebase(self.entry().start, 0)
+    }
+
+    /// Calls the given function with every address of the graph which is moved by a rebase, other than the addresses
+    /// the instructions of every block are keyed by.
+    fn for_each_address(&mut self, mut visit: impl FnMut(&mut u64)) {
+        for block in self.blocks.iter_mut() {
+            visit(&mut block.start);
+            visit(&mut block.end);
+            for instruction in block.block.values_mut() {
+                match instruction {
+                    // The target recorded for a return is a placeholder, the return address comes from the call stack.
+                    BlockType::Jump(_, _, JumpType::Return, failure) => failure.iter_mut().for_each(&mut visit),
+                    BlockType::Jump(_, target, _, failure) => {
+                        visit(target);
+                        failure.iter_mut().for_each(&mut visit);
+                    }
+                    BlockType::Switch(_, targets) => targets.iter_mut().for_each(&mut visit),
+                    BlockType::Instruction(_, _) | BlockType::Terminal(_) => {}
+                }
+            }
+        }
+        self.call_stack.iter_mut().for_each(&mut visit);
+        for (address, end) in self.previous_instruction.iter_mut() {
+            visit(address);
+            visit(end);
+        }
+        self.pending_fallthrough.iter_mut().for_each(&mut visit);
+        self.terminated.iter_mut().for_each(&mut visit);
+        for cursor in self.threads.values_mut() {
+            cursor.call_stack.iter_mut().for_each(&mut visit);
+            for (address, end) in cursor.previous_instruction.iter_mut() {
+                visit(address);
+                visit(end);
+            }
+            cursor.pending_fallthrough.iter_mut().for_each(&mut visit);
+            cursor.terminated.iter_mut().for_each(&mut visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CFGBuilder, ControlFlowGraph};
+    use crate::types::*;
+
+    /// Runs a few kinds of jumps at the given base, stopping inside of a call so that a return address is on the stack.
+    fn run(base: u64) -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(base);
+        cfg.execute_sized(base, BlockType::Instruction("MOV".to_string(), None), 4)?;
+        cfg.execute(base + 4, BlockType::Jump("JZ".to_string(), base + 0x20, JumpType::ConditionalTaken, Some(base + 5)))?;
+        cfg.execute_switch(base + 0x20, "JMP".to_string(), vec![base + 0x30, base + 0x40], base + 0x30)?;
+        cfg.execute(base + 0x30, BlockType::Jump("CALL".to_string(), base + 0x100, JumpType::Call, Some(base + 0x31)))?;
+        cfg.execute_tid(1, base + 0x40, BlockType::Instruction("NOP".to_string(), None))?;
+        cfg.execute(base + 0x100, BlockType::Instruction("INC".to_string(), None))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn rebase_matches_graph_built_there() -> Result<(), CFGError> {
+        let mut cfg = run(0x7f12_3400_0000)?;
+        cfg.rebase(0x7f12_3400_0000, 0x40_0000)?;
+        let mut expected = run(0x40_0000)?;
+        assert!(cfg.eq_with_counts(&expected));
+        assert_eq!(cfg.to_dot(), expected.to_dot());
+        assert_eq!(Some(BlockId(0)), cfg.find_block_containing(0x40_0003));
+
+        // The rebased return address leads back to the block after the call.
+        for graph in [&mut cfg, &mut expected] {
+            graph.execute(0x40_0101, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        }
+        assert!(cfg.eq_with_counts(&expected));
+        assert!(cfg.verify().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn execution_continues_after_rebase() -> Result<(), CFGError> {
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x1000).discontinuity_policy(DiscontinuityPolicy::Error).build();
+        cfg.execute(0x1000, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_tid(1, 0x1000, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.rebase(0x1000, 0x2000)?;
+
+        // Both the graph and the thread continue right after the moved instruction.
+        cfg.execute(0x2001, BlockType::Instruction("DEC".to_string(), None))?;
+        cfg.execute_tid(1, 0x2001, BlockType::Instruction("DEC".to_string(), None))?;
+        assert_eq!((1, 0x2001), (cfg.len(), cfg.entry().end()));
+
+        // A jump which wasn't taken still falls through to the instruction after it.
+        let mut cfg: ControlFlowGraph = CFGBuilder::entry(0x1000).infer_fallthrough(true).build();
+        cfg.execute(0x1000, BlockType::Jump("JZ".to_string(), 0x1010, JumpType::ConditionalNotTaken, None))?;
+        cfg.execute_tid(1, 0x1000, BlockType::Jump("JZ".to_string(), 0x1010, JumpType::ConditionalNotTaken, None))?;
+        cfg.rebase(0x1000, 0x100)?;
+        cfg.execute(0x101, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_tid(1, 0x101, BlockType::Instruction("INC".to_string(), None))?;
+        assert_eq!(Some(&2), cfg.edge_map().get(&(0x100, 0x101)));
+
+        // A halt is reported where the terminal instruction was moved to.
+        let mut cfg = ControlFlowGraph::new(0x1000);
+        cfg.execute(0x1000, BlockType::Terminal("HLT".to_string()))?;
+        cfg.execute_tid(1, 0x1000, BlockType::Terminal("HLT".to_string()))?;
+        cfg.rebase(0x1000, 0x100)?;
+        assert_eq!(Some(0x100), cfg.terminated_at());
+        assert!(matches!(cfg.execute(0x101, BlockType::Instruction("INC".to_string(), None)), Err(CFGError::ExecutionAfterTerminal { pc: 0x101, terminal: 0x100 })));
+        assert!(matches!(cfg.execute_tid(1, 0x101, BlockType::Instruction("INC".to_string(), None)), Err(CFGError::ExecutionAfterTerminal { terminal: 0x100, .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_and_overflow() -> Result<(), CFGError> {
+        let mut cfg = run(0x1000)?;
+        cfg.normalize_to_entry()?;
+        assert!(cfg.eq_with_counts(&run(0)?));
+
+        // Rebasing below zero or past the end of the address range fails without touching the graph.
+        let mut cfg = run(0x1000)?;
+        assert!(matches!(cfg.rebase(0x1000, 0x800), Ok(())));
+        assert!(matches!(cfg.rebase(0x1000, 0), Err(CFGError::RebaseOverflow { address: 0x800, old_base: 0x1000, new_base: 0 })));
+        // The highest address is the end of the instruction executed last.
+        assert!(matches!(cfg.rebase(0, u64::MAX - 0x800), Err(CFGError::RebaseOverflow { address: 0x901, .. })));
+        assert!(cfg.eq_with_counts(&run(0x800)?));
+
+        Ok(())
+    }
+}
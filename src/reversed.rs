@@ -0,0 +1,183 @@
+use crate::{BasicBlock, ControlFlowGraph, Edge};
+use crate::analysis::{breadth_first_order, dominators_from, reachable_mask, GraphLike};
+use crate::types::*;
+use crate::prelude::*;
+
+/// A view of a ControlFlowGraph with every edge reversed, for backward analyses such as slicing, made by
+/// [reversed](ControlFlowGraph::reversed).
+///
+/// The successors of a block in the view are its predecessors in the graph and the other way around, while the blocks
+/// themselves are those of the graph, which isn't copied.
+pub struct ReversedCfg<'a, I = String> {
+    graph: &'a ControlFlowGraph<I>,
+}
+
+impl<I> Clone for ReversedCfg<'_, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I> Copy for ReversedCfg<'_, I> {}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns a view of the graph with every edge reversed.
+    pub fn reversed(&self) -> ReversedCfg<'_, I> {
+        ReversedCfg { graph: self }
+    }
+}
+
+impl<I> GraphLike for ReversedCfg<'_, I> {
+    fn block_count(&self) -> usize {
+        self.graph.blocks.len()
+    }
+
+    fn successor_ids(&self, block: BlockId, only_traversed: bool) -> impl Iterator<Item = BlockId> + '_ {
+        self.graph.predecessors[block.0].iter().copied()
+            .filter(move |source| !only_traversed || self.graph.blocks[*source].edge_to(block).is_some_and(|edge| edge.count() > 0))
+    }
+}
+
+impl<'a, I> ReversedCfg<'a, I> {
+    /// Returns the graph the view reverses.
+    pub fn graph(&self) -> &'a ControlFlowGraph<I> {
+        self.graph
+    }
+
+    /// Returns the block at the given position of the graph, if there is one.
+    pub fn block(&self, index: BlockId) -> Option<&'a BasicBlock<I>> {
+        self.graph.blocks.get(index.0)
+    }
+
+    /// Returns every block of the graph in the order they were created.
+    pub fn blocks(&self) -> impl Iterator<Item = &'a BasicBlock<I>> {
+        self.graph.blocks.iter()
+    }
+
+    /// Returns the number of blocks.
+    pub fn len(&self) -> usize {
+        self.graph.blocks.len()
+    }
+
+    /// Returns whether there are no blocks, which is never the case as there is always an entry block.
+    pub fn is_empty(&self) -> bool {
+        self.graph.blocks.is_empty()
+    }
+
+    /// Returns the position of the block whose instructions cover the given address, if any.
+    pub fn find_block_containing(&self, address: u64) -> Option<BlockId> {
+        self.graph.find_block_containing(address)
+    }
+
+    /// Returns the positions of the blocks which have an edge to the block at the given position in the graph, in
+    /// ascending order.
+    pub fn successors(&self, block_index: BlockId) -> Result<Vec<BlockId>, CFGError> {
+        self.graph.predecessors(block_index)
+    }
+
+    /// Returns the positions of the blocks which the block at the given position has an edge to in the graph, in the
+    /// order those edges were added.
+    pub fn predecessors(&self, block_index: BlockId) -> Result<Vec<BlockId>, CFGError> {
+        let block = self.block(block_index).ok_or(CFGError::MissingBlock { index: block_index.0 })?;
+        Ok(block.edges.iter().map(Edge::target).collect())
+    }
+
+    /// Returns the blocks reachable from the given block in the view in the order a breadth-first search visits them,
+    /// which are the blocks that can reach it in the graph with the nearest first.
+    ///
+    /// With `only_traversed`, edges which were never traversed are not followed.
+    pub fn breadth_first(&self, root: BlockId, only_traversed: bool) -> Result<Vec<BlockId>, CFGError> {
+        self.block(root).ok_or(CFGError::MissingBlock { index: root.0 })?;
+        Ok(breadth_first_order(self, root.0, only_traversed))
+    }
+
+    /// Returns the positions of the blocks reachable from the given block in the view in ascending order, including the
+    /// block itself, such as the backward slice of a crashing block.
+    pub fn reachable_from(&self, root: BlockId, only_traversed: bool) -> Result<Vec<BlockId>, CFGError> {
+        self.block(root).ok_or(CFGError::MissingBlock { index: root.0 })?;
+        Ok(reachable_mask(self, root.0, only_traversed).into_iter().enumerate().filter_map(|(index, reachable)| reachable.then_some(BlockId(index))).collect())
+    }
+
+    /// Computes the immediate dominator of every block of the view rooted at the given block, indexed by block position,
+    /// which are the immediate post-dominators of the graph with the block as its only exit.
+    ///
+    /// The root is its own immediate dominator and blocks which can't reach it in the graph are `None`.
+    pub fn dominators_from(&self, root: BlockId) -> Result<Vec<Option<BlockId>>, CFGError> {
+        dominators_from(self, root.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+    use crate::prelude::*;
+
+    /// Runs a loop which branches to one of two blocks rejoining at its end, then leaves the loop.
+    fn diamond_loop() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(0x20)))?;
+        cfg.execute(0x10, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x30, BlockType::Jump("JNZ".to_string(), 0, JumpType::ConditionalTaken, Some(0x31)))?;
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalNotTaken, Some(0x20)))?;
+        cfg.execute(0x20, BlockType::Jump("JMP".to_string(), 0x30, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x30, BlockType::Jump("JNZ".to_string(), 0, JumpType::ConditionalNotTaken, Some(0x31)))?;
+        cfg.execute(0x31, BlockType::Instruction("HLT".to_string(), None))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn breadth_first_backwards() -> Result<(), CFGError> {
+        let cfg = diamond_loop()?;
+        let reversed = cfg.reversed();
+        let exit = cfg.find_block_containing(0x31).unwrap();
+
+        // The blocks which reach the exit, searched by hand from the edges of the graph.
+        let mut expected = vec![exit];
+        let mut position = 0;
+        while position < expected.len() {
+            let target = expected[position];
+            for (index, block) in cfg.blocks().enumerate() {
+                if block.edge_to(target).is_some() && !expected.contains(&BlockId(index)) {
+                    expected.push(BlockId(index));
+                }
+            }
+            position += 1;
+        }
+        assert_eq!(expected, reversed.breadth_first(exit, false)?);
+        assert_eq!(expected.iter().copied().collect::<BTreeSet<_>>(), reversed.reachable_from(exit, false)?.into_iter().collect());
+
+        for index in 0..cfg.len() {
+            let index = BlockId(index);
+            assert_eq!(cfg.predecessors(index)?, reversed.successors(index)?);
+            assert_eq!(cfg.successors(index)?, reversed.predecessors(index)?);
+            assert_eq!(cfg.block(index).map(|block| block.start()), reversed.block(index).map(|block| block.start()));
+        }
+        assert!(matches!(reversed.breadth_first(BlockId(cfg.len()), false), Err(CFGError::MissingBlock { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn post_dominators_of_view() -> Result<(), CFGError> {
+        let cfg = diamond_loop()?;
+        let reversed = cfg.reversed();
+        let exit = cfg.find_block_containing(0x31).unwrap();
+        let post_dominators = cfg.post_dominators()?;
+        for (index, dominator) in reversed.dominators_from(exit)?.into_iter().enumerate() {
+            if index != exit.index() {
+                assert_eq!(post_dominators[index], dominator);
+            }
+        }
+
+        // The untaken edge of the not-taken jump leading nowhere is still an edge of the view.
+        let mut partial = ControlFlowGraph::new(0);
+        partial.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalTaken, Some(0x20)))?;
+        let failure = partial.find_block_containing(0x20).unwrap();
+        assert_eq!(vec![failure, partial.entry_index()], partial.reversed().breadth_first(failure, false)?);
+        assert_eq!(vec![failure], partial.reversed().breadth_first(failure, true)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::ControlFlowGraph;
+
+impl<I: Clone> ControlFlowGraph<I> {
+    /// Returns a copy of the graph with every hit and traversal count multiplied by `factor`, such as to weigh one
+    /// workload more than others before merging them.
+    ///
+    /// The counts are rounded to the nearest integer and saturate at `u64::MAX`, which `counts_saturated` reports on the
+    /// copy. A factor of zero, a negative one, or NaN zeroes every count while keeping the blocks, instructions and edges,
+    /// and an edge whose count is rounded to zero forgets when it was seen like after `reset_counts`. The copy keeps the
+    /// modules, the symbols and the configuration of the graph and continues from its current block, but not the call
+    /// stack, the threads, or the recorded sequence.
+    pub fn scaled(&self, factor: f64) -> ControlFlowGraph<I> {
+        let mut copy = ControlFlowGraph::from_blocks(self.blocks.clone(), self.current_block.index());
+        copy.blocks_discovered = self.blocks_discovered;
+        copy.edges_discovered = self.edges_discovered;
+        copy.executed = self.executed;
+        copy.modules = self.modules.clone();
+        copy.config = self.config.clone();
+        copy.symbols = self.symbols.clone();
+        let mut saturated = self.saturated;
+        for block in copy.blocks.iter_mut() {
+            saturated |= scale(&mut block.hits, factor);
+            for hits in block.instruction_hits.values_mut() {
+                saturated |= scale(hits, factor);
+            }
+            for edge in block.edges.iter_mut() {
+                let mut count = edge.count();
+                saturated |= scale(&mut count, factor);
+                edge.set_count(count);
+                if count == 0 {
+                    edge.set_seen(None, None);
+                }
+            }
+        }
+        copy.saturated = saturated;
+        copy
+    }
+}
+
+/// Multiplies the count by the factor and rounds it to the nearest integer, returning whether it saturated.
+fn scale(count: &mut u64, factor: f64) -> bool {
+    let product = *count as f64 * factor;
+    // Casting a float to an integer saturates at both ends and turns NaN into zero.
+    *count = (product + 0.5) as u64;
+    product >= u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    /// Runs a loop at 0x10 three times before leaving it for 0x20.
+    fn looped() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        for jump_type in [JumpType::ConditionalTaken, JumpType::ConditionalTaken, JumpType::ConditionalNotTaken] {
+            cfg.execute(0x10, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute(0x11, BlockType::Jump("JNZ".to_string(), 0x10, jump_type, Some(0x20)))?;
+        }
+        cfg.execute(0x20, BlockType::Terminal("HLT".to_string()))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn scaled_counts() -> Result<(), CFGError> {
+        let cfg = looped()?;
+        let body = cfg.starts[&0x10];
+
+        let halved = cfg.scaled(0.5);
+        assert!(halved == cfg);
+        // 2 loops back halve to 1, while 3 hits round half up to 2.
+        assert_eq!((1, 1), (halved.edge_map()[&(0x10, 0x10)], halved.edge_map()[&(0x10, 0x20)]));
+        assert_eq!((2, 2), (halved.blocks[body].hits(), halved.blocks[body].instruction_hits[&0x11]));
+        assert!(!halved.counts_saturated());
+
+        let saturated = cfg.scaled(f64::MAX);
+        assert_eq!(u64::MAX, saturated.edge_map()[&(0x10, 0x10)]);
+        assert!(saturated.counts_saturated());
+
+        Ok(())
+    }
+
+    #[test]
+    fn zero_factor_keeps_structure() -> Result<(), CFGError> {
+        let cfg = looped()?;
+        for factor in [0.0, -1.0, f64::NAN] {
+            let zeroed = cfg.scaled(factor);
+            assert!(zeroed == cfg);
+            assert!(zeroed.edge_map().values().all(|&count| count == 0));
+            assert!(zeroed.blocks().all(|block| block.hits() == 0 && block.edges().all(|edge| edge.first_seen().is_none())));
+            assert_eq!(cfg.exit_blocks(), zeroed.exit_blocks());
+        }
+
+        Ok(())
+    }
+}
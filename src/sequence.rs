@@ -0,0 +1,89 @@
+use alloc::collections::BTreeSet;
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+
+impl<I> ControlFlowGraph<I> {
+    /// Starts or stops recording the sequence of blocks which execution enters, such as to replay a run or to measure
+    /// path coverage.
+    ///
+    /// Starting to record appends the current block, and from then on every block entered by `execute` is appended,
+    /// including a block looping onto itself. The blocks entered by every thread are appended to the same sequence.
+    /// Stopping keeps what was recorded so far.
+    ///
+    /// ```
+    /// use ctrl_flow::ControlFlowGraph;
+    /// use ctrl_flow::types::{BlockId, BlockType, JumpType};
+    ///
+    /// let mut cfg = ControlFlowGraph::new(0);
+    /// cfg.record_sequence(true);
+    /// cfg.execute(1, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+    /// assert_eq!(&[BlockId::from(0), BlockId::from(1)], cfg.sequence());
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn record_sequence(&mut self, enabled: bool) {
+        if enabled && !self.recording {
+            self.sequence.push(self.current_block);
+        }
+        self.recording = enabled;
+    }
+
+    /// Returns whether the sequence of entered blocks is being recorded.
+    pub fn is_recording_sequence(&self) -> bool {
+        self.recording
+    }
+
+    /// Returns the positions of the blocks entered while recording, in the order they were entered.
+    ///
+    /// Splitting a block leaves the earlier entries with the position of its head, while removing blocks drops their
+    /// entries and compacts the positions of the others like they do for the blocks.
+    pub fn sequence(&self) -> &[BlockId] {
+        &self.sequence
+    }
+
+    /// Empties the recorded sequence to bound its memory, continuing from the current block if still recording.
+    pub fn clear_sequence(&mut self) {
+        self.sequence.clear();
+        if self.recording {
+            self.sequence.push(self.current_block);
+        }
+    }
+
+    /// Returns every distinct pair of blocks which were entered one after the other in the recorded sequence, the
+    /// path coverage of length two.
+    pub fn sequence_pairs(&self) -> BTreeSet<(BlockId, BlockId)> {
+        self.sequence.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn loop_sequence() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.record_sequence(true);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        for lap in 0..3 {
+            let exit = if lap == 2 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            cfg.execute(0x10, BlockType::Jump("JNZ".to_string(), 0x20, exit, Some(0x11)))?;
+            if lap < 2 {
+                cfg.execute(0x11, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+            }
+        }
+        cfg.record_sequence(false);
+        cfg.execute(0x20, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+
+        let (entry, body, latch, exit) = (cfg.entry_index(), cfg.starts[&0x10], cfg.starts[&0x11], cfg.starts[&0x20]);
+        assert_eq!(&[entry, body, latch, body, latch, body, exit], cfg.sequence());
+        assert_eq!(BTreeSet::from([(entry, body), (body, latch), (latch, body), (body, exit)]), cfg.sequence_pairs());
+
+        cfg.record_sequence(true);
+        cfg.clear_sequence();
+        assert_eq!(&[entry], cfg.sequence());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use core::hash::{Hash, Hasher};
+use crate::ControlFlowGraph;
+
+impl<I: PartialEq> ControlFlowGraph<I> {
+    /// Returns whether both graphs have the same structure, comparing the traversal counts of their edges as well.
+    ///
+    /// See the `PartialEq` implementation for what makes up the structure.
+    pub fn eq_with_counts(&self, other: &ControlFlowGraph<I>) -> bool {
+        self.same_blocks(other) && self.edge_map() == other.edge_map()
+    }
+
+    /// Returns whether both graphs have blocks at the same addresses, ending at the same addresses, holding the same instructions.
+    fn same_blocks(&self, other: &ControlFlowGraph<I>) -> bool {
+        self.starts.len() == other.starts.len() && self.blocks_by_address().zip(other.blocks_by_address()).all(|(block, other_block)| {
+            block.start == other_block.start && block.end_exclusive() == other_block.end_exclusive() && block.end == other_block.end && block.block.len() == other_block.block.len()
+                && block.block.iter().zip(other_block.block.iter()).all(|((pc, instruction), (other_pc, other_instruction))| {
+                    pc == other_pc && !instruction.conflicts_with(other_instruction)
+                })
+        })
+    }
+}
+
+impl<I: PartialEq> PartialEq for ControlFlowGraph<I> {
+    /// Compares the structure of both graphs, regardless of the order their blocks were created in.
+    ///
+    /// The structure is made up of the block start and end addresses, the instructions of every block, and the edges
+    /// keyed by the start addresses of their source and target. Traversal and hit counts, metadata, and what may differ
+    /// between executions of the same jump are ignored, use `eq_with_counts` to compare the edge counts as well.
+    fn eq(&self, other: &Self) -> bool {
+        self.same_blocks(other) && self.edge_map().keys().eq(other.edge_map().keys())
+    }
+}
+
+impl<I: Hash> ControlFlowGraph<I> {
+    /// Returns a hash of the structure of the graph, which is equal for graphs which compare equal.
+    ///
+    /// The hash doesn't depend on the order blocks were created in, nor on the platform or compiler version.
+    pub fn structural_hash(&self) -> u64 {
+        self.hash_structure(false)
+    }
+
+    /// Returns a hash of the structure of the graph including the edge counts, which is equal for graphs for which
+    /// `eq_with_counts` holds.
+    pub fn structural_hash_with_counts(&self) -> u64 {
+        self.hash_structure(true)
+    }
+
+    fn hash_structure(&self, counts: bool) -> u64 {
+        let mut hasher = Fnv1a::default();
+        for &index in self.starts.values() {
+            let block = &self.blocks[index];
+            block.start.hash(&mut hasher);
+            block.end.hash(&mut hasher);
+            block.end_exclusive().hash(&mut hasher);
+            block.block.len().hash(&mut hasher);
+            for (pc, instruction) in block.block.iter() {
+                pc.hash(&mut hasher);
+                instruction.hash_shape(&mut hasher);
+            }
+        }
+        for ((from, to), count) in self.edge_map() {
+            (from, to).hash(&mut hasher);
+            if counts {
+                count.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// The 64 bit FNV-1a hash, which unlike the hasher of the standard library is specified to never change.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    fn run(targets: &[u64]) -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for target in targets {
+            cfg.execute(0, BlockType::Instruction("LDR".to_string(), Some("R1".to_string())))?;
+            cfg.execute_indirect(1, "JMP".to_string(), *target)?;
+            cfg.execute(*target, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute(*target + 1, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+        Ok(cfg)
+    }
+
+    #[test]
+    fn equal_regardless_of_order() -> Result<(), CFGError> {
+        let first = run(&[10, 20])?;
+        let second = run(&[20, 10])?;
+        // The blocks were created in a different order.
+        assert_ne!(first.blocks().map(|block| block.start()).collect::<Vec<_>>(), second.blocks().map(|block| block.start()).collect::<Vec<_>>());
+
+        assert!(first == second);
+        assert!(first.eq_with_counts(&second));
+        assert_eq!(first.structural_hash(), second.structural_hash());
+        assert_eq!(first.structural_hash_with_counts(), second.structural_hash_with_counts());
+
+        // The conditional jump is recorded with the direction it first went in, which doesn't affect the structure.
+        let conditional = |first: JumpType, second: JumpType| -> Result<ControlFlowGraph, CFGError> {
+            let mut cfg = ControlFlowGraph::new(0);
+            for jump_type in [first, second] {
+                cfg.execute(0, BlockType::Jump("JZ".to_string(), 20, jump_type, Some(10)))?;
+                let next = if jump_type == JumpType::ConditionalTaken { 20 } else { 10 };
+                cfg.execute(next, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            }
+            Ok(cfg)
+        };
+        let taken_first = conditional(JumpType::ConditionalTaken, JumpType::ConditionalNotTaken)?;
+        let not_taken_first = conditional(JumpType::ConditionalNotTaken, JumpType::ConditionalTaken)?;
+        assert!(taken_first == not_taken_first);
+        assert_eq!(taken_first.structural_hash(), not_taken_first.structural_hash());
+
+        Ok(())
+    }
+
+    #[test]
+    fn counts_are_optional() -> Result<(), CFGError> {
+        let once = run(&[10, 20])?;
+        let twice = run(&[10, 20, 10])?;
+
+        assert!(once == twice);
+        assert!(!once.eq_with_counts(&twice));
+        assert_eq!(once.structural_hash(), twice.structural_hash());
+        assert_ne!(once.structural_hash_with_counts(), twice.structural_hash_with_counts());
+
+        let different = run(&[10])?;
+        assert!(once != different);
+        assert_ne!(once.structural_hash(), different.structural_hash());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use crate::ControlFlowGraph;
+use crate::types::{BlockId, CFGError};
+use crate::prelude::*;
+
+impl<I: Clone> ControlFlowGraph<I> {
+    /// Copies the part of the graph reachable from the given block into a new ControlFlowGraph, such as the blocks of
+    /// a single function.
+    ///
+    /// Every edge is followed whether or not it was traversed. With `max_depth`, only blocks at most that many edges
+    /// away from the root are copied. The root becomes the entry and current block of the new graph, followed by the
+    /// other copied blocks in their original order, and edges to blocks which weren't copied are dropped. The copy
+    /// keeps the behavior of this graph but starts with an empty call stack, no threads, no observer or event log, and no symbolizer.
+    pub fn subgraph_from(&self, root: BlockId, max_depth: Option<usize>) -> Result<ControlFlowGraph<I>, CFGError> {
+        if root.index() >= self.blocks.len() {
+            return Err(CFGError::MissingBlock { index: root.index() });
+        }
+
+        let mut reachable = vec![false; self.blocks.len()];
+        reachable[root.index()] = true;
+        let mut worklist = VecDeque::from([(root, 0)]);
+        while let Some((block, depth)) = worklist.pop_front() {
+            if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                continue;
+            }
+            for edge in self.blocks[block].edges.iter() {
+                if !reachable[edge.target().index()] {
+                    reachable[edge.target().index()] = true;
+                    worklist.push_back((edge.target(), depth + 1));
+                }
+            }
+        }
+
+        let order: Vec<usize> = core::iter::once(root.index())
+            .chain((0..self.blocks.len()).filter(|index| reachable[*index] && *index != root.index()))
+            .collect();
+        let mut remap = vec![None; self.blocks.len()];
+        for (position, index) in order.iter().enumerate() {
+            remap[*index] = Some(BlockId(position));
+        }
+
+        let blocks: Vec<_> = order.iter().map(|index| {
+            let mut block = self.blocks[*index].clone();
+            block.remap_edges(&remap);
+            block
+        }).collect();
+        let starts: BTreeMap<u64, BlockId> = blocks.iter().enumerate().map(|(index, block)| (block.start, BlockId(index))).collect();
+        let predecessors = crate::predecessor_index(&blocks);
+
+        Ok(ControlFlowGraph {
+            current_block: BlockId(0),
+            blocks,
+            starts,
+            predecessors,
+            blocks_discovered: self.blocks_discovered,
+            edges_discovered: self.edges_discovered,
+            executed: self.executed,
+            modules: self.modules.clone(),
+            call_stack: Vec::new(),
+            previous_instruction: None,
+            pending_fallthrough: None,
+            terminated: None,
+            threads: BTreeMap::new(),
+            config: self.config.clone(),
+            pool: self.pool.clone(),
+            symbols: self.symbols.clone(),
+            observer: None,
+            #[cfg(feature = "std")]
+            event_log: None,
+            symbolizer: None,
+            saturated: self.saturated,
+            recording: false,
+            sequence: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    fn chain() -> Result<ControlFlowGraph, CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        cfg.execute(10, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(11, BlockType::Jump("JMP".to_string(), 20, JumpType::UnconditionalJump, None))?;
+        cfg.execute(20, BlockType::Jump("JZ".to_string(), 40, JumpType::ConditionalNotTaken, Some(30)))?;
+        cfg.execute(30, BlockType::Jump("JMP".to_string(), 10, JumpType::UnconditionalJump, None))?;
+        Ok(cfg)
+    }
+
+    #[test]
+    fn subgraph_of_chain() -> Result<(), CFGError> {
+        let cfg = chain()?;
+        let sub = cfg.subgraph_from(cfg.starts[&10], None)?;
+
+        let starts: Vec<u64> = sub.blocks().map(|bb| bb.start()).collect();
+        assert_eq!(vec![10, 20, 30, 40], starts);
+        assert_eq!(None, sub.find_block_containing(0));
+        assert_eq!(BlockId(0), sub.current_block_index());
+        assert_eq!(cfg.blocks[cfg.starts[&10]].hits(), sub.entry().hits());
+        assert_eq!(vec![(BlockId(1), 1)], sub.blocks[0].edge_counts());
+        assert_eq!(vec![(BlockId(2), 1), (BlockId(3), 0)], sub.blocks[1].edge_counts());
+        assert_eq!(vec![(BlockId(0), 1)], sub.blocks[2].edge_counts());
+        assert!(sub.verify().is_ok());
+
+        let shallow = cfg.subgraph_from(cfg.starts[&10], Some(1))?;
+        let starts: Vec<u64> = shallow.blocks().map(|bb| bb.start()).collect();
+        assert_eq!(vec![10, 20], starts);
+        assert!(shallow.blocks[1].edges.is_empty());
+
+        assert!(matches!(cfg.subgraph_from(BlockId(5), None), Err(CFGError::MissingBlock { index: 5 })));
+
+        Ok(())
+    }
+}
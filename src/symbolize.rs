@@ -0,0 +1,139 @@
+use core::fmt;
+use crate::{ControlFlowGraph, Module};
+use crate::types::AddressFormat;
+use crate::prelude::*;
+
+/// Names addresses in the textual output of a ControlFlowGraph, such as `main+0x12` instead of `0x401012`.
+///
+/// Any closure from an address to an optional name is a Symbolizer, and addresses it returns `None` for are written in
+/// the AddressFormat of the output as before.
+///
+/// ```
+/// use ctrl_flow::ControlFlowGraph;
+/// use ctrl_flow::types::{BlockType, JumpType};
+///
+/// let mut cfg = ControlFlowGraph::new(0x401000);
+/// cfg.set_symbolizer(Box::new(|address: u64| (address == 0x401000).then(|| "main".to_string())));
+/// cfg.execute(0x401000, BlockType::Jump("JMP".to_string(), 0x401000, JumpType::UnconditionalJump, None))?;
+/// assert!(cfg.to_string().starts_with("main - main (entry):\n    main: JMP main (unconditional)\n"));
+/// # Ok::<(), ctrl_flow::types::CFGError>(())
+/// ```
+pub trait Symbolizer: Send {
+    /// Returns the name of the given address, or `None` to write the address itself.
+    fn symbolize(&self, _address: u64) -> Option<String> {
+        None
+    }
+}
+
+impl<F: Fn(u64) -> Option<String> + Send> Symbolizer for F {
+    fn symbolize(&self, address: u64) -> Option<String> {
+        self(address)
+    }
+}
+
+/// What addresses are named by in the textual output, the Symbolizer and the modules of a graph.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Names<'a> {
+    pub(crate) symbolizer: Option<&'a dyn Symbolizer>,
+    pub(crate) modules: &'a [Module],
+}
+
+impl Names<'_> {
+    /// Returns the name the Symbolizer gives the address, or otherwise the name of its module with the offset into it
+    /// written in the given format, such as `libfoo+0x1a2`.
+    pub(crate) fn name(&self, address: u64, format: AddressFormat) -> Option<String> {
+        if let Some(name) = self.symbolizer.and_then(|symbolizer| symbolizer.symbolize(address)) {
+            return Some(name);
+        }
+        let module = self.modules.iter().find(|module| module.contains(address))?;
+        Some(match address - module.base() {
+            0 => module.name().to_string(),
+            offset => format!("{}+{}", module.name(), format.address(offset)),
+        })
+    }
+}
+
+/// An address which is displayed as its name, or in its AddressFormat without one.
+pub(crate) struct SymbolizedAddress<'a> {
+    pub(crate) address: u64,
+    pub(crate) format: AddressFormat,
+    pub(crate) names: Names<'a>,
+}
+
+impl fmt::Display for SymbolizedAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.names.name(self.address, self.format) {
+            Some(name) => f.write_str(&name),
+            None => self.format.address(self.address).fmt(f),
+        }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Names addresses with the given Symbolizer in the `Display` listing, the DOT labels, and the instructions of the
+    /// JSON from now on, replacing the previous Symbolizer.
+    ///
+    /// Only the labels are symbolized, the addresses identifying blocks and edges are still written as addresses.
+    pub fn set_symbolizer(&mut self, symbolizer: Box<dyn Symbolizer>) {
+        self.symbolizer = Some(symbolizer);
+    }
+
+    /// Removes the Symbolizer and returns it.
+    pub fn take_symbolizer(&mut self) -> Option<Box<dyn Symbolizer>> {
+        self.symbolizer.take()
+    }
+
+    /// Returns what addresses are named by in the textual output.
+    pub(crate) fn names(&self) -> Names<'_> {
+        Names { symbolizer: self.symbolizer.as_deref(), modules: &self.modules }
+    }
+
+    /// Returns the address wrapped so that it is displayed in the given format or as its name.
+    pub(crate) fn symbolized(&self, address: u64, format: AddressFormat) -> SymbolizedAddress<'_> {
+        SymbolizedAddress { address, format, names: self.names() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn closure_symbolizer() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x401000);
+        cfg.execute(0x401000, BlockType::Jump("CALL".to_string(), 0x402000, JumpType::Call, Some(0x401005)))?;
+        cfg.execute(0x402000, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        let unsymbolized = (cfg.to_string(), cfg.to_dot(), cfg.to_json());
+
+        cfg.set_symbolizer(Box::new(|address: u64| match address {
+            0x401000 => Some("main".to_string()),
+            0x402000 => Some("<init>".to_string()),
+            _ => None,
+        }));
+        let expected = "\
+main - main (entry):
+    main: CALL <init> / 0x401005 (call)
+    -> <init> (1)
+
+<init> - <init>:
+    <init>: RET (return)
+    -> 0x401005 (1)
+
+0x401005 - 0x401005:
+";
+        assert_eq!(expected, cfg.to_string());
+
+        let dot = cfg.to_dot();
+        assert!(dot.contains("    \"0x401000\" [label=\"main - main\\lmain: CALL <init> / 0x401005 (call)\\l\", style=filled, fillcolor=lightgrey];\n"));
+        assert!(dot.contains("    \"0x402000\" [label=\"<init> - <init>\\l<init>: RET (return)\\l\"];\n"));
+        assert!(dot.contains("    \"0x401000\" -> \"0x402000\""));
+        let json = cfg.to_json();
+        assert!(json.contains("{\"start\": \"0x401000\", \"end\": \"0x401000\", \"symbol\": \"main\", \"instructions\": {\"0x401000\": \"CALL <init> / 0x401005 (call)\"}}"));
+
+        assert!(cfg.take_symbolizer().is_some());
+        assert_eq!(unsymbolized, (cfg.to_string(), cfg.to_dot(), cfg.to_json()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use crate::ControlFlowGraph;
+use crate::prelude::*;
+
+/// An interned string handed out by `ControlFlowGraph::symbol`, which is cheap to clone and can be used as the payload
+/// of a BlockType.
+///
+/// Every Symbol with the same text from the same graph shares a single allocation and id. Symbols are compared by their
+/// text, as their ids are only meaningful to the graph which handed them out.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    id: usize,
+    text: Arc<str>,
+}
+
+impl Symbol {
+    /// Returns the id of the Symbol, its position in the order the graph first saw its text.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns the text of the Symbol.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.text.cmp(&other.text)
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// The mnemonics and operands interned by a ControlFlowGraph, so a trace which repeats the same text millions of times
+/// only stores it once.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    texts: Vec<Arc<str>>,
+    ids: BTreeMap<Arc<str>, usize>,
+}
+
+impl SymbolTable {
+    /// Returns the Symbol for the given text, only allocating the first time the text is seen.
+    fn intern(&mut self, text: &str) -> Symbol {
+        if let Some((text, id)) = self.ids.get_key_value(text) {
+            return Symbol { id: *id, text: text.clone() };
+        }
+        let id = self.texts.len();
+        let text: Arc<str> = Arc::from(text);
+        self.texts.push(text.clone());
+        self.ids.insert(text.clone(), id);
+        Symbol { id, text }
+    }
+
+    /// Returns the text of the Symbol with the given id, if it was handed out.
+    pub fn resolve(&self, id: usize) -> Option<&str> {
+        self.texts.get(id).map(|text| &**text)
+    }
+
+    /// Returns the number of distinct texts which were interned.
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+
+    /// Returns whether nothing was interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+}
+
+impl ControlFlowGraph<Symbol> {
+    /// Interns the given text into the SymbolTable of the graph, returning the Symbol to execute instructions with.
+    ///
+    /// ```
+    /// use ctrl_flow::{ControlFlowGraph, Symbol};
+    /// use ctrl_flow::types::BlockType;
+    ///
+    /// let mut cfg: ControlFlowGraph<Symbol> = ControlFlowGraph::new(0);
+    /// let (add, r1, r2) = (cfg.symbol("ADD"), cfg.symbol("R1"), cfg.symbol("R2"));
+    /// cfg.execute(0, BlockType::Instruction(add.clone(), Some(r1)))?;
+    /// cfg.execute(1, BlockType::Instruction(add, Some(r2)))?;
+    /// assert_eq!(3, cfg.symbols().len());
+    /// assert_eq!(Some("ADD"), cfg.resolve(0));
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn symbol(&mut self, text: &str) -> Symbol {
+        self.symbols.intern(text)
+    }
+
+    /// Returns the text of the Symbol with the given id, if the graph handed it out.
+    pub fn resolve(&self, id: usize) -> Option<&str> {
+        self.symbols.resolve(id)
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the texts interned through `symbol`, which is empty unless the payloads are Symbols.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use crate::{ControlFlowGraph, Symbol};
+    use crate::types::*;
+
+    std::thread_local! {
+        /// The number of allocations made by the calling thread.
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts the allocations of every thread on its own, so that tests running at the same time don't interfere.
+    struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    fn allocations(run: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        run();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    #[test]
+    fn equal_text_shares_id() -> Result<(), CFGError> {
+        let mut cfg: ControlFlowGraph<Symbol> = ControlFlowGraph::new(0);
+        let instruction = BlockType::Instruction(cfg.symbol("ADD"), Some(cfg.symbol("R1")));
+        cfg.execute(0, instruction)?;
+        let instruction = BlockType::Instruction(cfg.symbol("ADD"), Some(cfg.symbol("R2")));
+        cfg.execute(1, instruction)?;
+
+        match (&cfg.entry().block[&0], &cfg.entry().block[&1]) {
+            (BlockType::Instruction(first, Some(first_operand)), BlockType::Instruction(second, Some(second_operand))) => {
+                assert_eq!(first.id(), second.id());
+                assert_ne!(first_operand.id(), second_operand.id());
+                assert_eq!(Some("ADD"), cfg.resolve(first.id()));
+                assert_eq!(Some("R2"), cfg.resolve(second_operand.id()));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(None, cfg.resolve(3));
+        assert_eq!("0x0 - 0x1 (entry):\n    0x0: ADD R1\n    0x1: ADD R2\n", cfg.to_string());
+
+        // The graph can move to another thread, and a thawed copy resolves the same ids.
+        let cfg = std::thread::spawn(move || cfg).join().unwrap();
+        assert_eq!(Some("R1"), cfg.finalize().thaw().resolve(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn long_trace_allocates_once() -> Result<(), CFGError> {
+        const INSTRUCTIONS: u64 = 100_000;
+        let mut strings = ControlFlowGraph::new(0);
+        let with_strings = allocations(|| {
+            for pc in 0..INSTRUCTIONS {
+                strings.execute(pc, BlockType::Instruction("ADD".to_string(), Some("R1".to_string()))).unwrap();
+            }
+        });
+        let mut symbols: ControlFlowGraph<Symbol> = ControlFlowGraph::new(0);
+        let with_symbols = allocations(|| {
+            for pc in 0..INSTRUCTIONS {
+                let instruction = BlockType::Instruction(symbols.symbol("ADD"), Some(symbols.symbol("R1")));
+                symbols.execute(pc, instruction).unwrap();
+            }
+        });
+
+        // Both graphs store the instructions alike, but the symbols only allocate their two texts once.
+        assert_eq!(2, symbols.symbols().len());
+        assert!(with_symbols + 2 * INSTRUCTIONS as usize <= with_strings + 8, "{} allocations with symbols, {} with strings", with_symbols, with_strings);
+        let add = symbols.resolve(0).unwrap().as_ptr();
+        assert!(symbols.entry().instructions().all(|(_, instruction)| match instruction {
+            BlockType::Instruction(name, _) => name.as_str().as_ptr() == add,
+            _ => false,
+        }));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use std::cell::Cell;
+use std::iter;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use crate::ControlFlowGraph;
+use crate::types::*;
+
+/// The id which the next thread to execute on any SyncCfg is given.
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The most executions the apply thread carries out before letting go of the graph, so that `lock` isn't starved.
+const BATCH: usize = 256;
+
+/// The result of an execution, or what it panicked with.
+type Reply = thread::Result<Result<ExecOutcome, CFGError>>;
+
+std::thread_local! {
+    /// The id of the calling thread, handed out the first time it executes on any SyncCfg.
+    static THREAD_ID: Cell<Option<u64>> = const { Cell::new(None) };
+    /// The channel the calling thread receives the results of its executions from, as it waits for each one before
+    /// queueing the next.
+    static REPLIES: (SyncSender<Reply>, Receiver<Reply>) = mpsc::sync_channel(1);
+}
+
+/// An execution queued up for the apply thread.
+struct Request<I> {
+    tid: u64,
+    program_counter: u64,
+    instruction: BlockType<I>,
+    reply: SyncSender<Reply>,
+}
+
+/// A ControlFlowGraph which can be fed from several producer threads at once, such as one thread per emulated core.
+///
+/// Every thread executes with its own current block and call stack like `execute_tid`, so the events of different
+/// threads never create edges between each other. The producers queue their executions up for a single apply thread
+/// which owns the graph and applies them in batches, so they never contend on a lock with each other, but each
+/// producer waits for its own execution to be applied to return its result. The executions of all producers are
+/// still applied one after the other, in the order they were queued up.
+///
+/// ```
+/// use std::thread;
+/// use ctrl_flow::{ControlFlowGraph, SyncCfg};
+/// use ctrl_flow::types::{BlockType, JumpType};
+///
+/// let cfg = SyncCfg::new(ControlFlowGraph::new(0));
+/// thread::scope(|scope| {
+///     for _ in 0..2 {
+///         scope.spawn(|| cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None)));
+///     }
+/// });
+/// let cfg = cfg.into_inner();
+/// assert_eq!(2, cfg.edges().next().unwrap().count());
+/// ```
+pub struct SyncCfg<I = String> {
+    cfg: Arc<Mutex<ControlFlowGraph<I>>>,
+    /// Where the executions are queued up for the apply thread
+    requests: Sender<Request<I>>,
+    /// The thread applying the queued up executions, which stops once the queue is closed
+    apply: JoinHandle<()>,
+}
+
+impl<I: Send + 'static> SyncCfg<I> {
+    /// Wraps the ControlFlowGraph so that it can be shared between threads, starting the thread which applies the
+    /// executions.
+    pub fn new(cfg: ControlFlowGraph<I>) -> Self {
+        let cfg = Arc::new(Mutex::new(cfg));
+        let (requests, queue) = mpsc::channel();
+        let graph = Arc::clone(&cfg);
+        let apply = thread::spawn(move || apply(&graph, queue));
+        SyncCfg { cfg, requests, apply }
+    }
+
+    /// Executes the given BlockType on the graph as the calling thread, returning the same results as `execute`.
+    ///
+    /// Each thread is given an id the first time it executes, so mixing this with `execute_tid` should use ids which
+    /// don't collide with them, such as by only using one of the two on a graph.
+    pub fn execute(&self, program_counter: u64, instruction: BlockType<I>) -> Result<ExecOutcome, CFGError> {
+        let tid = THREAD_ID.with(|id| {
+            let tid = id.get().unwrap_or_else(|| NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+            id.set(Some(tid));
+            tid
+        });
+        self.execute_tid(tid, program_counter, instruction)
+    }
+
+    /// Executes the given BlockType on the graph as the given thread, such as the index of the core which executed it.
+    ///
+    /// A panic while executing, such as inside of an observer, is resumed on the calling thread.
+    pub fn execute_tid(&self, tid: u64, program_counter: u64, instruction: BlockType<I>) -> Result<ExecOutcome, CFGError> {
+        let reply = REPLIES.with(|(reply, replies)| {
+            let request = Request { tid, program_counter, instruction, reply: reply.clone() };
+            self.requests.send(request).expect("the apply thread runs as long as the queue is open");
+            replies.recv().expect("the apply thread replies to every execution")
+        });
+        reply.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Locks the graph, such as to inspect it while the producers keep running.
+    ///
+    /// Every execution which returned was applied, while those of producers still waiting aren't applied until the
+    /// lock is let go of.
+    pub fn lock(&self) -> MutexGuard<'_, ControlFlowGraph<I>> {
+        lock(&self.cfg)
+    }
+
+    /// Returns the ControlFlowGraph once every producer is done with it, stopping the apply thread.
+    pub fn into_inner(self) -> ControlFlowGraph<I> {
+        let SyncCfg { cfg, requests, apply } = self;
+        drop(requests);
+        // The apply thread catches the panics of executions, so it only ends once the queue is closed.
+        let _ = apply.join();
+        let cfg = Arc::try_unwrap(cfg).unwrap_or_else(|_| unreachable!("the apply thread has let go of the graph once it ended"));
+        cfg.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<I: Send + 'static> From<ControlFlowGraph<I>> for SyncCfg<I> {
+    fn from(cfg: ControlFlowGraph<I>) -> Self {
+        SyncCfg::new(cfg)
+    }
+}
+
+/// Locks the graph, which is still usable after a panic as executing never leaves it half updated.
+fn lock<I>(cfg: &Mutex<ControlFlowGraph<I>>) -> MutexGuard<'_, ControlFlowGraph<I>> {
+    cfg.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Applies the queued up executions until the queue is closed, replying to each producer with its result.
+fn apply<I>(cfg: &Mutex<ControlFlowGraph<I>>, queue: Receiver<Request<I>>) {
+    while let Ok(first) = queue.recv() {
+        let mut cfg = lock(cfg);
+        for Request { tid, program_counter, instruction, reply } in iter::once(first).chain(queue.try_iter().take(BATCH - 1)) {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| cfg.execute_tid(tid, program_counter, instruction)));
+            // A producer only stops waiting for its reply by panicking itself, which doesn't concern anyone else.
+            let _ = reply.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+    use crate::{CfgObserver, ControlFlowGraph, SyncCfg};
+    use crate::types::*;
+
+    /// Runs a loop whose body branches on the lap, exiting after the given number of laps.
+    fn run_laps(execute: &mut dyn FnMut(u64, BlockType) -> Result<ExecOutcome, CFGError>, laps: usize) -> Result<(), CFGError> {
+        for lap in 0..laps {
+            execute(0, BlockType::Instruction("INC".to_string(), None))?;
+            let branch = if lap % 3 == 0 { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            execute(1, BlockType::Jump("JZ".to_string(), 0x10, branch, Some(2)))?;
+            let pc = if lap % 3 == 0 { 0x10 } else { 2 };
+            let exit = if lap + 1 == laps { JumpType::ConditionalTaken } else { JumpType::ConditionalNotTaken };
+            execute(pc, BlockType::Jump("JNZ".to_string(), 0x20, exit, Some(pc + 1)))?;
+            if lap + 1 < laps {
+                execute(pc + 1, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn four_producers() -> Result<(), CFGError> {
+        const THREADS: u64 = 4;
+        const LAPS: usize = 2000;
+        let cfg = SyncCfg::new(ControlFlowGraph::new(0));
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| run_laps(&mut |pc, instruction| cfg.execute(pc, instruction), LAPS).unwrap());
+            }
+        });
+        let cfg = cfg.into_inner();
+        assert_eq!(THREADS as usize, cfg.threads().count());
+        assert!(cfg.verify().is_ok());
+
+        // Every lap traverses three edges except for the last one, however the threads were interleaved.
+        let total: u64 = cfg.edges().map(|edge| edge.count()).sum();
+        assert_eq!(THREADS * (3 * LAPS as u64 - 1), total);
+        let mut sequential = ControlFlowGraph::new(0);
+        for tid in 0..THREADS {
+            run_laps(&mut |pc, instruction| sequential.execute_tid(tid, pc, instruction), LAPS)?;
+        }
+        assert!(cfg.eq_with_counts(&sequential));
+
+        Ok(())
+    }
+
+    /// Panics when a block is created at 0x40.
+    struct PanicAt40;
+
+    impl CfgObserver for PanicAt40 {
+        fn on_new_block(&mut self, start: u64) {
+            assert_ne!(0x40, start);
+        }
+    }
+
+    #[test]
+    fn panic_reaches_producer() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.set_observer(Box::new(PanicAt40));
+        let cfg = SyncCfg::new(cfg);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| cfg.execute(0, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None))));
+        assert!(result.is_err());
+
+        // The apply thread carries on with the executions after the panic.
+        cfg.execute_tid(1, 0x20, BlockType::Instruction("INC".to_string(), None))?;
+        let block = cfg.lock().find_block_containing(0x20).unwrap();
+        assert_eq!(Some(1), cfg.into_inner().blocks[block].instruction_hits(0x20));
+
+        Ok(())
+    }
+}
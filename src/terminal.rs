@@ -0,0 +1,110 @@
+use crate::ControlFlowGraph;
+use crate::types::*;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Returns the positions of the blocks which end with a terminal instruction, in ascending order.
+    pub fn exit_blocks(&self) -> Vec<BlockId> {
+        self.blocks.iter().enumerate().filter(|(_, block)| block.exit).map(|(index, _)| BlockId(index)).collect()
+    }
+
+    /// Returns the address of the terminal instruction which halted execution, if any.
+    ///
+    /// Executing anything else afterwards is rejected with `CFGError::ExecutionAfterTerminal`, until execution is
+    /// continued from elsewhere with `set_current_block_by_address` or `restore`.
+    pub fn terminated_at(&self) -> Option<u64> {
+        self.terminated
+    }
+
+    /// Rejects the instruction at the given address if a terminal instruction halted execution.
+    pub(crate) fn check_terminated(&self, program_counter: u64) -> Result<(), CFGError> {
+        match self.terminated {
+            Some(terminal) => Err(CFGError::ExecutionAfterTerminal { pc: program_counter, terminal }),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the terminal instruction at the given address in the current block, which becomes an exit block unless
+    /// another instruction is kept at the address, and halts execution.
+    pub(crate) fn terminate(&mut self, program_counter: u64, instruction: BlockType<I>, size: u64) -> Result<ExecOutcome, CFGError> {
+        let outcome = self.record_instruction(program_counter, instruction, size)?;
+        let block = &mut self.blocks[self.current_block];
+        block.exit |= matches!(block.block.get(&program_counter), Some(BlockType::Terminal(_)));
+        self.terminated = Some(program_counter);
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    /// Runs a program which calls a function and halts once it returns.
+    fn program(cfg: &mut ControlFlowGraph) -> Result<ExecOutcome, CFGError> {
+        cfg.execute(0x10, BlockType::Jump("CALL".to_string(), 0x40, JumpType::Call, Some(0x11)))?;
+        cfg.execute(0x40, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x41, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None))?;
+        cfg.execute(0x11, BlockType::Instruction("MOV".to_string(), None))?;
+        cfg.execute(0x12, BlockType::Terminal("HLT".to_string()))
+    }
+
+    #[test]
+    fn normal_exit() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        assert_eq!(ExecOutcome::NewInstruction, program(&mut cfg)?);
+
+        let exit = cfg.starts[&0x11];
+        assert_eq!(vec![exit], cfg.exit_blocks());
+        assert!(cfg.blocks[exit].is_exit() && !cfg.entry().is_exit());
+        assert_eq!((Some(0x12), 0x12), (cfg.terminated_at(), cfg.blocks[exit].end()));
+        assert_eq!(Some(&BlockType::Terminal("HLT".to_string())), cfg.blocks[exit].block.get(&0x12));
+        assert_eq!(Some(exit), cfg.post_dominators()?[cfg.starts[&0x40].index()]);
+
+        // Running the program again from the entry halts at the same exit.
+        cfg.set_current_block_by_address(0x10);
+        assert_eq!(None, cfg.terminated_at());
+        assert_eq!(ExecOutcome::KnownInstruction, program(&mut cfg)?);
+        assert_eq!((vec![exit], 2), (cfg.exit_blocks(), cfg.blocks[exit].hits()));
+
+        // A jump into the middle of the exit block moves the exit into the tail.
+        cfg.set_current_block_by_address(0x50);
+        cfg.execute(0x50, BlockType::Jump("JMP".to_string(), 0x12, JumpType::UnconditionalJump, None))?;
+        cfg.execute(0x12, BlockType::Terminal("HLT".to_string()))?;
+        let tail = cfg.starts[&0x12];
+        assert_eq!(vec![tail], cfg.exit_blocks());
+        assert!(!cfg.blocks[exit].is_exit());
+        assert_eq!(Some(tail), cfg.post_dominators()?[exit.index()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn execution_after_terminal() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0x10);
+        program(&mut cfg)?;
+
+        let error = cfg.execute(0x13, BlockType::Instruction("NOP".to_string(), None)).unwrap_err();
+        assert!(matches!(error, CFGError::ExecutionAfterTerminal { pc: 0x13, terminal: 0x12 }));
+        assert_eq!("The instruction at 0x13 was executed after the terminal instruction at 0x12 halted execution.", error.to_string());
+        assert!(matches!(cfg.execute(0x10, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None)), Err(CFGError::ExecutionAfterTerminal { pc: 0x10, .. })));
+        assert!(matches!(cfg.execute_switch(0x10, "JMP".to_string(), vec![0x40], 0x40), Err(CFGError::ExecutionAfterTerminal { .. })));
+        // Nothing was recorded past the halt.
+        assert_eq!((3, 0x12), (cfg.len(), cfg.blocks[cfg.starts[&0x11]].end()));
+
+        // Continuing inside of the exit block still can't run past its terminal instruction.
+        cfg.set_current_block_by_address(0x11);
+        cfg.execute(0x11, BlockType::Instruction("MOV".to_string(), None))?;
+        let error = cfg.execute(0x13, BlockType::Instruction("NOP".to_string(), None)).unwrap_err();
+        assert!(matches!(error, CFGError::ExecutionAfterTerminal { pc: 0x13, terminal: 0x12 }));
+
+        // Every thread halts on its own.
+        cfg.set_current_block_by_address(0x10);
+        cfg.execute_tid(1, 0x60, BlockType::Terminal("EXIT".to_string()))?;
+        assert!(matches!(cfg.execute_tid(1, 0x61, BlockType::Instruction("NOP".to_string(), None)), Err(CFGError::ExecutionAfterTerminal { pc: 0x61, terminal: 0x60 })));
+        program(&mut cfg)?;
+        assert_eq!(vec![cfg.starts[&0x11], cfg.starts[&0x60]], cfg.exit_blocks());
+
+        Ok(())
+    }
+}
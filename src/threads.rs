@@ -0,0 +1,179 @@
+use crate::{saturating_increase, CfgCheckpoint, ControlFlowGraph};
+use crate::observer::CfgEvent;
+use crate::types::*;
+use crate::prelude::*;
+
+impl<I> ControlFlowGraph<I> {
+    /// Executes the given BlockType on the ControlFlowGraph as the given thread.
+    ///
+    /// Blocks and edges are shared between all threads, but every thread has its own current block and call stack, so
+    /// interleaved events of different threads never create edges between each other. The first event of a thread
+    /// continues from the block containing its address, which is created if no block contains it. The current block
+    /// and call stack used by `execute` are left untouched. A thread whose block is split while another one executes
+    /// carries on from the half holding the last instruction it executed.
+    pub fn execute_tid(&mut self, tid: u64, program_counter: u64, instruction: BlockType<I>) -> Result<ExecOutcome, CFGError> {
+        let (mut cursor, started) = match self.threads.remove(&tid) {
+            Some(cursor) => (cursor, false),
+            None => (self.thread_start(program_counter), true),
+        };
+        self.log_event(CfgEvent::Thread { tid: Some(tid) });
+        self.swap_cursor(&mut cursor);
+        if started {
+            self.log_event(CfgEvent::Current { block: self.current_block });
+        }
+        // The cursor of the graph is parked under the id of the executing thread, so that splits move it like the
+        // cursors of the other threads.
+        self.threads.insert(tid, cursor);
+        let result = self.execute(program_counter, instruction);
+        let mut cursor = self.threads.remove(&tid).unwrap();
+        self.swap_cursor(&mut cursor);
+        self.threads.insert(tid, cursor);
+        self.log_event(CfgEvent::Thread { tid: None });
+        result
+    }
+
+    /// Hands the graph over from the thread executing so far to the given one, the graph itself being `None`, in the
+    /// same way as `execute_tid` does around an execution. A thread which never executed starts in the entry.
+    #[cfg(feature = "std")]
+    pub(crate) fn switch_thread(&mut self, from: Option<u64>, to: Option<u64>) {
+        if from == to {
+            return;
+        }
+        // The cursor of the graph is parked under the id of the executing thread.
+        if let Some(tid) = from {
+            let mut cursor = self.threads.remove(&tid).unwrap();
+            self.swap_cursor(&mut cursor);
+            self.threads.insert(tid, cursor);
+        }
+        if let Some(tid) = to {
+            let fresh = || CfgCheckpoint { current_block: BlockId(0), call_stack: Vec::new(), previous_instruction: None, pending_fallthrough: None, terminated: None };
+            let mut cursor = self.threads.remove(&tid).unwrap_or_else(fresh);
+            self.swap_cursor(&mut cursor);
+            self.threads.insert(tid, cursor);
+        }
+    }
+
+    /// Returns the ids of every thread executed through `execute_tid` in ascending order.
+    pub fn threads(&self) -> impl Iterator<Item = u64> + '_ {
+        self.threads.keys().copied()
+    }
+
+    /// Returns the position of the current block of the given thread.
+    pub fn thread_block_index(&self, tid: u64) -> Option<BlockId> {
+        self.threads.get(&tid).map(|cursor| cursor.current_block)
+    }
+
+    /// Finds or creates the block a new thread starts in, counting the entry into a block created for it.
+    fn thread_start(&mut self, program_counter: u64) -> CfgCheckpoint {
+        let current_block = match self.find_block_containing(program_counter) {
+            Some(index) => index,
+            None => {
+                let index = self.create_block(program_counter);
+                self.log_event(CfgEvent::BlockHit { block: index });
+                self.saturated |= saturating_increase(&mut self.blocks[index].hits, 1);
+                index
+            }
+        };
+        CfgCheckpoint { current_block, call_stack: Vec::new(), previous_instruction: None, pending_fallthrough: None, terminated: None }
+    }
+
+    /// Exchanges the current block, call stack, previous instruction, pending fall-through, and halt of the graph with those of the given cursor.
+    fn swap_cursor(&mut self, cursor: &mut CfgCheckpoint) {
+        core::mem::swap(&mut self.current_block, &mut cursor.current_block);
+        core::mem::swap(&mut self.call_stack, &mut cursor.call_stack);
+        core::mem::swap(&mut self.previous_instruction, &mut cursor.previous_instruction);
+        core::mem::swap(&mut self.pending_fallthrough, &mut cursor.pending_fallthrough);
+        core::mem::swap(&mut self.terminated, &mut cursor.terminated);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ControlFlowGraph;
+    use crate::types::*;
+
+    #[test]
+    fn interleaved_threads() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        for _ in 0..3 {
+            cfg.execute_tid(1, 0, BlockType::Instruction("LDAC".to_string(), None))?;
+            cfg.execute_tid(2, 0, BlockType::Instruction("LDAC".to_string(), None))?;
+            cfg.execute_tid(1, 1, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalTaken, Some(2)))?;
+            cfg.execute_tid(2, 1, BlockType::Jump("JZ".to_string(), 10, JumpType::ConditionalNotTaken, Some(2)))?;
+            cfg.execute_tid(2, 2, BlockType::Instruction("INC".to_string(), None))?;
+            cfg.execute_tid(1, 10, BlockType::Instruction("DEC".to_string(), None))?;
+            cfg.execute_tid(2, 3, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+            cfg.execute_tid(1, 11, BlockType::Jump("JMP".to_string(), 0, JumpType::UnconditionalJump, None))?;
+        }
+
+        let (taken, not_taken) = (cfg.starts[&10], cfg.starts[&2]);
+        assert_eq!(vec![(not_taken, 3), (taken, 3)], cfg.entry().edge_counts());
+        assert_eq!(vec![(BlockId(0), 3)], cfg.blocks[taken].edge_counts());
+        assert_eq!(vec![(BlockId(0), 3)], cfg.blocks[not_taken].edge_counts());
+        assert_eq!(vec![1, 2], cfg.threads().collect::<Vec<_>>());
+        assert_eq!((Some(BlockId(0)), Some(BlockId(0)), None), (cfg.thread_block_index(1), cfg.thread_block_index(2), cfg.thread_block_index(3)));
+        assert_eq!(BlockId(0), cfg.current_block_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn thread_starting_elsewhere() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute_tid(1, 0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_tid(1, 1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_tid(1, 2, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_tid(2, 0x40, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+        // The block of the first thread was split behind it, so it carries on in the tail without a bogus edge.
+        cfg.execute_tid(1, 3, BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None))?;
+
+        let (tail, other) = (cfg.starts[&1], cfg.starts[&0x40]);
+        assert_eq!(2, cfg.blocks[other].hits());
+        assert_eq!(vec![(tail, 1)], cfg.entry().edge_counts());
+        assert_eq!(vec![(other, 1)], cfg.blocks[tail].edge_counts());
+        assert_eq!(vec![(tail, 1)], cfg.blocks[other].edge_counts());
+        assert_eq!(3, cfg.blocks[tail].end());
+        assert!(cfg.verify().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_moves_parked_cursors() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        // The first thread has only entered the block at 0x10, while the second one is past 0x12.
+        cfg.execute_tid(1, 0x20, BlockType::Jump("JMP".to_string(), 0x10, JumpType::UnconditionalJump, None))?;
+        cfg.execute_tid(2, 0x10, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute_tid(2, 0x12, BlockType::Instruction("INC".to_string(), None))?;
+        // A third thread splits both the block at 0x10 and the block the graph itself is parked in.
+        cfg.execute_tid(3, 0x30, BlockType::Jump("JZ".to_string(), 0x12, JumpType::ConditionalNotTaken, Some(0x31)))?;
+        cfg.execute_tid(3, 0x31, BlockType::Jump("JMP".to_string(), 1, JumpType::UnconditionalJump, None))?;
+
+        let (head, tail) = (cfg.starts[&0x10], cfg.starts[&0x12]);
+        assert_eq!((Some(head), Some(tail)), (cfg.thread_block_index(1), cfg.thread_block_index(2)));
+        assert_eq!(cfg.starts[&1], cfg.current_block_index());
+        cfg.execute(2, BlockType::Instruction("INC".to_string(), None))?;
+        assert_eq!(Some(1), cfg.entry().edge_to(cfg.starts[&1]).map(|edge| edge.count()));
+        assert_eq!(2, cfg.blocks[cfg.starts[&1]].end());
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_thread_stays_in_its_block() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Jump("JZ".to_string(), 0x10, JumpType::ConditionalNotTaken, Some(1)))?;
+        cfg.execute(1, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(0x12, BlockType::Instruction("INC".to_string(), None))?;
+
+        // The block at 0x10 was never entered, so the instruction ends up in the current block.
+        let (current, untaken) = (cfg.starts[&1], cfg.starts[&0x10]);
+        assert_eq!(current, cfg.current_block_index());
+        assert_eq!(Some(1), cfg.blocks[current].instruction_hits(0x12));
+        assert_eq!((0, 0), (cfg.blocks[untaken].hits(), cfg.blocks[untaken].len()));
+
+        Ok(())
+    }
+}
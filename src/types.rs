@@ -1,24 +1,755 @@
+use alloc::borrow::Cow;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+#[cfg(feature = "std")]
 use thiserror::Error;
+use crate::import::parse_address;
+use crate::symbolize::{Names, SymbolizedAddress};
+use crate::prelude::*;
 
 
-#[derive(Clone, Copy)]
+/// The position of a BasicBlock inside of its ControlFlowGraph, kept apart from addresses so that the two can't be mixed up.
+///
+/// Positions are handed out in the order blocks are created, the entry block always being the first.
+///
+/// ```
+/// use ctrl_flow::ControlFlowGraph;
+///
+/// let cfg: ControlFlowGraph = ControlFlowGraph::new(0x401000);
+/// assert_eq!(0x401000, cfg.block(cfg.entry_index()).unwrap().start());
+/// assert_eq!(Some(cfg.entry_index()), cfg.find_block_containing(0x401000));
+/// ```
+///
+/// An address can't be passed where a BlockId is expected,
+///
+/// ```compile_fail
+/// use ctrl_flow::ControlFlowGraph;
+///
+/// let cfg: ControlFlowGraph = ControlFlowGraph::new(0x401000);
+/// let entry = cfg.block(0x401000);
+/// ```
+///
+/// nor can a BlockId be passed where an address is expected.
+///
+/// ```compile_fail
+/// use ctrl_flow::ControlFlowGraph;
+///
+/// let cfg: ControlFlowGraph = ControlFlowGraph::new(0x401000);
+/// let entry = cfg.find_block_containing(cfg.entry_index());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct BlockId(pub(crate) usize);
+
+impl BlockId {
+    /// Returns the position as a plain index, such as for indexing a vector of per block results.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for BlockId {
+    fn from(index: usize) -> Self {
+        BlockId(index)
+    }
+}
+
+impl From<BlockId> for usize {
+    fn from(id: BlockId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum JumpType {
     UnconditionalJump,
     ConditionalTaken,
-    ConditionalNotTaken
+    ConditionalNotTaken,
+    /// Jumps to the success address and pushes the failure address as the return address.
+    Call,
+    /// Returns to the most recently pushed return address, the success address is ignored.
+    Return,
+    /// Jumps to a target which was computed at run-time, the success address being the target observed this time.
+    Indirect
+}
+
+/// How control flowed along an edge, set by the kind of instruction which created the edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeKind {
+    /// The success address of a conditional jump, or a target of a switch.
+    Taken,
+    /// The failure address of a conditional jump, or execution running into the start of another block.
+    FallThrough,
+    /// The target of an unconditional or indirect jump.
+    Unconditional,
+    /// The callee of a call.
+    Call,
+    /// The return address a return went back to.
+    Return,
+    /// A discontinuity in the trace which wasn't explained by a jump, followed by `DiscontinuityPolicy::Restart`.
+    Unknown
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An executed instruction, generic over the payload which names it and its operand.
+///
+/// The payload defaults to a `String`, but can be any type such as an already decoded instruction or a compact opcode.
+pub enum BlockType<I = String> {
+    Instruction(I, Option<I>),
+    Jump(I, u64, JumpType, Option<u64>),
+    /// A computed jump through a table, with the name of the instruction and every target listed in the table.
+    Switch(I, Vec<u64>),
+    /// An instruction which halts the program, such as `HLT` or `EXIT`, after which nothing else is executed.
+    Terminal(I)
+}
+
+impl<I> BlockType<I> {
+    /// Converts every payload of the instruction with the given function, keeping its addresses and jump type.
+    pub fn map_payload<J>(self, mut f: impl FnMut(I) -> J) -> BlockType<J> {
+        match self {
+            BlockType::Instruction(name, operand) => BlockType::Instruction(f(name), operand.map(f)),
+            BlockType::Jump(name, success_address, jump_type, failure_address) => BlockType::Jump(f(name), success_address, jump_type, failure_address),
+            BlockType::Switch(name, targets) => BlockType::Switch(f(name), targets),
+            BlockType::Terminal(name) => BlockType::Terminal(f(name)),
+        }
+    }
+}
+
+/// Copies static names into the default String payload, for decoders whose tables hold `&'static str` mnemonics.
+impl From<BlockType<&'static str>> for BlockType {
+    fn from(instruction: BlockType<&'static str>) -> Self {
+        instruction.map_payload(String::from)
+    }
+}
+
+/// Borrows static names as a `Cow<'static, str>` payload without allocating, so that a graph can hold both static
+/// mnemonics and owned operands.
+impl From<BlockType<&'static str>> for BlockType<Cow<'static, str>> {
+    fn from(instruction: BlockType<&'static str>) -> Self {
+        instruction.map_payload(Cow::Borrowed)
+    }
+}
+
+/// Wraps owned names into a `Cow<'static, str>` payload.
+impl From<BlockType> for BlockType<Cow<'static, str>> {
+    fn from(instruction: BlockType) -> Self {
+        instruction.map_payload(Cow::Owned)
+    }
+}
+
+impl<I: PartialEq> BlockType<I> {
+    /// Returns whether the other instruction recorded at the same address is a different instruction.
+    ///
+    /// What may change between executions of the same jump is ignored, which is the direction of a conditional jump
+    /// and the target of an indirect jump or a return.
+    pub(crate) fn conflicts_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BlockType::Jump(name, target, jump_type, failure), BlockType::Jump(other_name, other_target, other_type, other_failure)) => {
+                let conditional = |jump_type: &JumpType| matches!(jump_type, JumpType::ConditionalTaken | JumpType::ConditionalNotTaken);
+                let same_type = jump_type == other_type || (conditional(jump_type) && conditional(other_type));
+                let same_target = target == other_target || matches!(jump_type, JumpType::Indirect | JumpType::Return);
+                name != other_name || !same_type || !same_target || failure != other_failure
+            }
+            _ => self != other,
+        }
+    }
+}
+
+impl BlockType {
+    /// Generates a jump whose target is the last word of its operand, such as `0x4010` in `0x4010` or `short 0x4010`.
+    ///
+    /// The target may be written in decimal or in hex with a `0x` prefix. A return has no target, so its operand is
+    /// ignored. An operand without a valid target is a [CFGError::ParseError] on line 1 naming the token.
+    ///
+    /// ```
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let jump = BlockType::jump_from_operand("JNZ", "0x4010", JumpType::ConditionalTaken, Some(0x4002))?;
+    /// assert_eq!(BlockType::Jump("JNZ".to_string(), 0x4010, JumpType::ConditionalTaken, Some(0x4002)), jump);
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn jump_from_operand(name: &str, operand: &str, jump_type: JumpType, fallthrough: Option<u64>) -> Result<BlockType, CFGError> {
+        let target = match jump_type {
+            JumpType::Return => 0,
+            _ => {
+                let token = operand.split_whitespace().next_back().ok_or_else(|| CFGError::ParseError(1, format!("missing jump target in `{}`", operand)))?;
+                parse_address(token).map_err(|reason| CFGError::ParseError(1, reason))?
+            }
+        };
+        Ok(BlockType::Jump(name.to_string(), target, jump_type, fallthrough))
+    }
+}
+
+impl<I> BlockType<I> {
+    /// Generates an instruction without an operand.
+    ///
+    /// ```
+    /// use ctrl_flow::types::BlockType;
+    ///
+    /// let instruction: BlockType = BlockType::insn("INC");
+    /// assert_eq!(BlockType::Instruction("INC".to_string(), None), instruction);
+    /// ```
+    pub fn insn(name: impl Into<I>) -> Self {
+        BlockType::Instruction(name.into(), None)
+    }
+
+    /// Generates an instruction with the given operand.
+    ///
+    /// ```
+    /// use ctrl_flow::types::BlockType;
+    ///
+    /// let instruction: BlockType = BlockType::insn_with("LDAC", "X");
+    /// assert_eq!(BlockType::Instruction("LDAC".to_string(), Some("X".to_string())), instruction);
+    /// ```
+    pub fn insn_with(name: impl Into<I>, operand: impl Into<I>) -> Self {
+        BlockType::Instruction(name.into(), Some(operand.into()))
+    }
+
+    /// Starts building a jump with the given name, naming each of its addresses instead of passing them in order.
+    ///
+    /// The jump is unconditional unless another kind is chosen, and `build` checks that the addresses the kind needs
+    /// were given.
+    ///
+    /// ```
+    /// use ctrl_flow::types::{BlockType, JumpType};
+    ///
+    /// let jump: BlockType = BlockType::jump("JNZ").to(9).conditional_taken().fallthrough(6).build()?;
+    /// assert_eq!(BlockType::Jump("JNZ".to_string(), 9, JumpType::ConditionalTaken, Some(6)), jump);
+    ///
+    /// let jump: BlockType = BlockType::jump("JMP").to(0x40).build()?;
+    /// assert_eq!(BlockType::Jump("JMP".to_string(), 0x40, JumpType::UnconditionalJump, None), jump);
+    /// # Ok::<(), ctrl_flow::types::CFGError>(())
+    /// ```
+    pub fn jump(name: impl Into<I>) -> JumpBuilder<I> {
+        JumpBuilder { name: name.into(), target: None, jump_type: JumpType::UnconditionalJump, fallthrough: None }
+    }
+}
+
+/// Builds a `BlockType::Jump`, started by `BlockType::jump`.
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct JumpBuilder<I = String> {
+    name: I,
+    target: Option<u64>,
+    jump_type: JumpType,
+    fallthrough: Option<u64>,
+}
+
+impl<I> JumpBuilder<I> {
+    /// Sets the address the jump goes to when it's taken, the callee of a call, or the target observed for an
+    /// indirect jump.
+    pub fn to(mut self, target: u64) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets the address of the instruction following the jump, which a conditional jump goes to when it's not taken
+    /// and a call returns to.
+    pub fn fallthrough(mut self, address: u64) -> Self {
+        self.fallthrough = Some(address);
+        self
+    }
+
+    /// Sets the kind of the jump.
+    pub fn kind(mut self, jump_type: JumpType) -> Self {
+        self.jump_type = jump_type;
+        self
+    }
+
+    /// Makes the jump an unconditional one, which is the default.
+    pub fn unconditional(self) -> Self {
+        self.kind(JumpType::UnconditionalJump)
+    }
+
+    /// Makes the jump a conditional one which was taken this time.
+    pub fn conditional_taken(self) -> Self {
+        self.kind(JumpType::ConditionalTaken)
+    }
+
+    /// Makes the jump a conditional one which wasn't taken this time.
+    pub fn conditional_not_taken(self) -> Self {
+        self.kind(JumpType::ConditionalNotTaken)
+    }
+
+    /// Makes the jump a call.
+    pub fn call(self) -> Self {
+        self.kind(JumpType::Call)
+    }
+
+    /// Makes the jump a return, which needs no target.
+    pub fn ret(self) -> Self {
+        self.kind(JumpType::Return)
+    }
+
+    /// Makes the jump an indirect one.
+    pub fn indirect(self) -> Self {
+        self.kind(JumpType::Indirect)
+    }
+
+    /// Returns the jump, or `CFGError::IncompleteJump` if it lacks the target every jump but a return needs, or the
+    /// fallthrough address a conditional jump or a call needs.
+    ///
+    /// A conditional jump whose fallthrough is left for the graph to infer, see `CFGBuilder::infer_fallthrough`, can
+    /// still be written out as a `BlockType::Jump` without one.
+    ///
+    /// ```
+    /// use ctrl_flow::types::{BlockType, CFGError};
+    ///
+    /// let error = BlockType::<String>::jump("JZ").to(9).conditional_not_taken().build().unwrap_err();
+    /// assert!(matches!(error, CFGError::IncompleteJump { missing: "a fallthrough address", .. }));
+    /// ```
+    pub fn build(self) -> Result<BlockType<I>, CFGError> {
+        let missing = match self.jump_type {
+            JumpType::Return => None,
+            _ if self.target.is_none() => Some("a target"),
+            JumpType::ConditionalTaken | JumpType::ConditionalNotTaken | JumpType::Call if self.fallthrough.is_none() => Some("a fallthrough address"),
+            _ => None,
+        };
+        match missing {
+            Some(missing) => Err(CFGError::IncompleteJump { jump_type: self.jump_type, missing }),
+            None => Ok(BlockType::Jump(self.name, self.target.unwrap_or(0), self.jump_type, self.fallthrough)),
+        }
+    }
+}
+
+impl<I: Hash> BlockType<I> {
+    /// Hashes the instruction such that instructions which don't conflict with each other hash the same.
+    pub(crate) fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        match self {
+            BlockType::Instruction(name, operand) => {
+                0u8.hash(state);
+                name.hash(state);
+                operand.hash(state);
+            }
+            BlockType::Jump(name, target, jump_type, failure) => {
+                1u8.hash(state);
+                name.hash(state);
+                match jump_type {
+                    JumpType::ConditionalTaken | JumpType::ConditionalNotTaken => "conditional".hash(state),
+                    JumpType::Indirect | JumpType::Return => jump_type.to_string().hash(state),
+                    _ => {
+                        jump_type.to_string().hash(state);
+                        target.hash(state);
+                    }
+                }
+                failure.hash(state);
+            }
+            BlockType::Switch(name, targets) => {
+                2u8.hash(state);
+                name.hash(state);
+                targets.hash(state);
+            }
+            BlockType::Terminal(name) => {
+                3u8.hash(state);
+                name.hash(state);
+            }
+        }
+    }
+}
+
+impl fmt::Display for JumpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            JumpType::UnconditionalJump => "unconditional",
+            JumpType::ConditionalTaken => "taken",
+            JumpType::ConditionalNotTaken => "not taken",
+            JumpType::Call => "call",
+            JumpType::Return => "return",
+            JumpType::Indirect => "indirect",
+        };
+        f.write_str(name)
+    }
 }
 
-pub enum BlockType {
-    Instruction(String, Option<String>),
-    Jump(String, usize, JumpType, Option<usize>)
+/// How addresses are written by the Display implementation of BlockType and by the exporters which accept it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressFormat {
+    /// Lowercase hex with a `0x` prefix, such as `0x401000`.
+    #[default]
+    Hex,
+    /// Plain decimal, such as `4198400`.
+    Decimal,
 }
 
-#[derive(Error, Debug)]
+impl AddressFormat {
+    /// Returns the address wrapped so that it is displayed in this format.
+    pub fn address(self, address: u64) -> FormattedAddress {
+        FormattedAddress { address, format: self }
+    }
+}
+
+/// An address which is displayed in the AddressFormat it was created with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormattedAddress {
+    address: u64,
+    format: AddressFormat,
+}
+
+impl fmt::Display for FormattedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format {
+            AddressFormat::Hex => write!(f, "{:#x}", self.address),
+            AddressFormat::Decimal => write!(f, "{}", self.address),
+        }
+    }
+}
+
+impl<I> BlockType<I> {
+    /// Returns the instruction wrapped so that its addresses are displayed in the given format.
+    pub fn display(&self, format: AddressFormat) -> DisplayBlockType<'_, I> {
+        self.display_with(format, Names::default())
+    }
+
+    /// Returns the instruction wrapped so that its addresses are displayed as their names given by the Symbolizer, or
+    /// in the given format without one.
+    pub(crate) fn display_with<'a>(&'a self, format: AddressFormat, names: Names<'a>) -> DisplayBlockType<'a, I> {
+        DisplayBlockType { instruction: self, format, names }
+    }
+}
+
+/// A BlockType which is displayed with its addresses in the AddressFormat it was created with.
+pub struct DisplayBlockType<'a, I> {
+    instruction: &'a BlockType<I>,
+    format: AddressFormat,
+    names: Names<'a>,
+}
+
+impl<I: fmt::Display> fmt::Display for DisplayBlockType<'_, I> {
+    /// Formats the instruction as its name followed by its operand, or by its jump target and failure address and the jump type,
+    /// or by `(terminal)` for a terminal instruction.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address = |address: &u64| SymbolizedAddress { address: *address, format: self.format, names: self.names };
+        match self.instruction {
+            BlockType::Instruction(name, Some(operand)) => write!(f, "{} {}", name, operand),
+            BlockType::Instruction(name, None) => write!(f, "{}", name),
+            BlockType::Jump(name, _, JumpType::Return, _) => write!(f, "{} ({})", name, JumpType::Return),
+            BlockType::Jump(name, success_address, jump_type, Some(failure_address)) => {
+                write!(f, "{} {} / {} ({})", name, address(success_address), address(failure_address), jump_type)
+            }
+            BlockType::Jump(name, success_address, jump_type, None) => write!(f, "{} {} ({})", name, address(success_address), jump_type),
+            BlockType::Switch(name, targets) => {
+                write!(f, "{} [", name)?;
+                for (index, target) in targets.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { ", " };
+                    write!(f, "{}{}", separator, address(target))?;
+                }
+                write!(f, "]")
+            }
+            BlockType::Terminal(name) => write!(f, "{} (terminal)", name),
+        }
+    }
+}
+
+impl<I: fmt::Display> fmt::Display for BlockType<I> {
+    /// Formats the instruction like [display](BlockType::display) with its addresses in hex.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(AddressFormat::Hex).fmt(f)
+    }
+}
+
+/// What executing an instruction changed in the ControlFlowGraph, reporting only the most notable change.
+///
+/// From most to least notable, a jump may create the block it enters, traverse an edge for the first time, or
+/// traverse an edge again, while the instruction itself may be recorded for the first time or already be known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExecOutcome {
+    /// The instruction was already recorded at its address and no edge was traversed.
+    KnownInstruction,
+    /// An edge which was traversed before was traversed again.
+    ExistingEdgeIncremented { from: BlockId, to: BlockId },
+    /// The instruction was recorded at its address for the first time.
+    NewInstruction,
+    /// An edge was traversed for the first time, from and to the given block positions.
+    NewEdge { from: BlockId, to: BlockId },
+    /// The block at the given position was created and entered.
+    NewBlock(BlockId),
+}
+
+impl ExecOutcome {
+    /// Returns whether the execution discovered anything new, such as new coverage for a fuzzer.
+    pub fn is_new(&self) -> bool {
+        matches!(self, ExecOutcome::NewInstruction | ExecOutcome::NewEdge { .. } | ExecOutcome::NewBlock(_))
+    }
+
+    /// Returns the more notable of the two outcomes.
+    pub(crate) fn or(self, other: ExecOutcome) -> ExecOutcome {
+        let rank = |outcome: &ExecOutcome| match outcome {
+            ExecOutcome::KnownInstruction => 0,
+            ExecOutcome::ExistingEdgeIncremented { .. } => 1,
+            ExecOutcome::NewInstruction => 2,
+            ExecOutcome::NewEdge { .. } => 3,
+            ExecOutcome::NewBlock(_) => 4,
+        };
+        if rank(&other) > rank(&self) { other } else { self }
+    }
+}
+
+/// How an instruction is handled when it differs from the instruction already recorded at its address.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConflictPolicy {
+    /// The first recorded instruction is kept and the new one is ignored.
+    #[default]
+    KeepFirst,
+    /// The new instruction is rejected with `CFGError::InstructionConflict`.
+    Error,
+    /// The new instruction replaces the recorded one.
+    Overwrite
+}
+
+/// How a block which would start inside of the `[start, end]` range of an existing block is handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// The existing block is split at the start of the new block, so that the tail becomes the new block.
+    #[default]
+    Split,
+    /// The block isn't created and `CFGError::OverlappingBlock` is returned.
+    Error
+}
+
+/// How an instruction which doesn't continue from where the previous instruction ended is handled, when the previous
+/// instruction wasn't a jump.
+///
+/// Such a discontinuity happens when the traced program was interrupted or the tracer dropped events, and would
+/// otherwise append unrelated code to the current block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiscontinuityPolicy {
+    /// The instruction is appended to the current block as usual.
+    #[default]
+    Ignore,
+    /// The instruction is rejected with `CFGError::Discontinuity`.
+    Error,
+    /// The current block ends, and execution continues in the block at the address of the instruction through an
+    /// edge of `EdgeKind::Unknown`.
+    Restart
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[non_exhaustive]
 pub enum CFGError {
-    #[error("There was an attempt to find a BasicBlock which doesn't exist.")]
-    MissingBlock,
-    #[error("The current block does not exist inside the BasicBlocks.")]
-    MissingCurrentBlock,
-    #[error("A failure address was expected for a conditional jump and it was not provided.")]
-    ExpectedFailureAddress,
-}
\ No newline at end of file
+    MissingBlock { index: usize },
+    MissingCurrentBlock { index: usize },
+    ExpectedFailureAddress { pc: u64, jump_type: JumpType },
+    AddressBeforeBlockStart { pc: u64, block: BlockId, block_start: u64 },
+    ReturnWithoutCall { pc: u64 },
+    ParseError(usize, String),
+    EntryMismatch { expected: u64, found: u64 },
+    InstructionConflict { pc: u64, existing: String, new: String },
+    ExpectedSwitchTarget { pc: u64 },
+    UnknownSwitchTarget { pc: u64, target: u64 },
+    BlockInUse { block: BlockId },
+    CannotMergeBlock { block: BlockId },
+    MissingEdge { from: BlockId, to: BlockId },
+    OverlappingBlock { new_start: u64, existing_start: u64, existing_end: u64 },
+    Discontinuity { from: u64, to: u64 },
+    RebaseOverflow { address: u64, old_base: u64, new_base: u64 },
+    TruncatedEventLog { offset: usize },
+    InvalidEventLog { offset: usize, reason: String },
+    UnsupportedVersion { version: u8 },
+    InvalidBytes { offset: usize, reason: String },
+    InvalidModule { name: String, reason: String },
+    UnknownModule { name: String },
+    ExecutionAfterTerminal { pc: u64, terminal: u64 },
+    IncompleteJump { jump_type: JumpType, missing: &'static str },
+    NoFreeAddress { from: BlockId, to: BlockId },
+    /// The line of a trace, counting from one, whose instruction failed to execute with the source.
+    TraceLine { line: usize, source: Box<CFGError> },
+    #[cfg(feature = "std")]
+    Io(#[cfg_attr(feature = "std", from)] std::io::Error),
+}
+
+impl fmt::Display for CFGError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CFGError::MissingBlock { index } => write!(f, "There was an attempt to find the BasicBlock {} which doesn't exist.", index),
+            CFGError::MissingCurrentBlock { index } => write!(f, "The current block {} does not exist inside the BasicBlocks.", index),
+            CFGError::ExpectedFailureAddress { pc, jump_type } => {
+                write!(f, "A failure address was expected for the {} jump at {:#x} and it was not provided.", jump_type, pc)
+            }
+            CFGError::AddressBeforeBlockStart { pc, block, block_start } => {
+                write!(f, "Attempted to add an instruction at {:#x} behind the start {:#x} of the current block {}.", pc, block_start, block)
+            }
+            CFGError::ReturnWithoutCall { pc } => write!(f, "The return at {:#x} was executed without a matching call on the call stack.", pc),
+            CFGError::ParseError(line, reason) => write!(f, "Failed to parse line {} of the input: {}.", line, reason),
+            CFGError::EntryMismatch { expected, found } => write!(f, "The entry point {:#x} does not match the entry point {:#x}.", found, expected),
+            CFGError::InstructionConflict { pc, existing, new } => {
+                write!(f, "The instruction `{}` at {:#x} conflicts with the instruction `{}` already recorded there.", new, pc, existing)
+            }
+            CFGError::ExpectedSwitchTarget { pc } => write!(f, "The switch at {:#x} was executed without the target it took, use execute_switch instead.", pc),
+            CFGError::UnknownSwitchTarget { pc, target } => {
+                write!(f, "The switch at {:#x} took the target {:#x} which is not listed in its table.", pc, target)
+            }
+            CFGError::BlockInUse { block } => {
+                write!(f, "The block {} is the entry or the current block of the graph or a thread and cannot be removed.", block)
+            }
+            CFGError::CannotMergeBlock { block } => {
+                write!(f, "The block {} does not have a single successor following it which has it as its only predecessor.", block)
+            }
+            CFGError::MissingEdge { from, to } => write!(f, "The block {} does not have an edge to the block {}.", from, to),
+            CFGError::OverlappingBlock { new_start, existing_start, existing_end } => {
+                write!(f, "A block starting at {:#x} would overlap the block from {:#x} to {:#x}.", new_start, existing_start, existing_end)
+            }
+            CFGError::Discontinuity { from, to } => write!(f, "The instruction at {:#x} does not continue from the instruction at {:#x} without a jump.", to, from),
+            CFGError::RebaseOverflow { address, old_base, new_base } => {
+                write!(f, "Rebasing the address {:#x} from {:#x} to {:#x} does not fit into an address.", address, old_base, new_base)
+            }
+            CFGError::TruncatedEventLog { offset } => write!(f, "The event log ends inside of the record at byte {}.", offset),
+            CFGError::InvalidEventLog { offset, reason } => write!(f, "The record at byte {} of the event log is invalid: {}.", offset, reason),
+            CFGError::UnsupportedVersion { version } => write!(f, "The version {} of the serialized data is not supported.", version),
+            CFGError::InvalidBytes { offset, reason } => write!(f, "The serialized graph is invalid at byte {}: {}.", offset, reason),
+            CFGError::InvalidModule { name, reason } => write!(f, "The module {} {}.", name, reason),
+            CFGError::UnknownModule { name } => write!(f, "There is no module named {}.", name),
+            CFGError::ExecutionAfterTerminal { pc, terminal } => {
+                write!(f, "The instruction at {:#x} was executed after the terminal instruction at {:#x} halted execution.", pc, terminal)
+            }
+            CFGError::IncompleteJump { jump_type, missing } => write!(f, "The {} jump was built without {}.", jump_type, missing),
+            CFGError::NoFreeAddress { from, to } => {
+                write!(f, "The edge from the block {} to the block {} can't be split as blocks cover every address.", from, to)
+            }
+            CFGError::TraceLine { line, source } => write!(f, "Failed to execute line {} of the trace: {}", line, source),
+            #[cfg(feature = "std")]
+            CFGError::Io(error) => write!(f, "An I/O error occurred: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ControlFlowGraph;
+
+    #[test]
+    fn block_type_display() {
+        let instructions = [
+            (BlockType::Instruction("INC".to_string(), None), "INC", "INC"),
+            (BlockType::Instruction("LDAC".to_string(), Some("A".to_string())), "LDAC A", "LDAC A"),
+            (BlockType::Jump("JMP".to_string(), 0x401000, JumpType::UnconditionalJump, None), "JMP 0x401000 (unconditional)", "JMP 4198400 (unconditional)"),
+            (BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalTaken, Some(0x11)), "JZ 0x20 / 0x11 (taken)", "JZ 32 / 17 (taken)"),
+            (BlockType::Jump("JZ".to_string(), 0x20, JumpType::ConditionalNotTaken, Some(0x11)), "JZ 0x20 / 0x11 (not taken)", "JZ 32 / 17 (not taken)"),
+            (BlockType::Jump("CALL".to_string(), 0x40, JumpType::Call, Some(0x12)), "CALL 0x40 / 0x12 (call)", "CALL 64 / 18 (call)"),
+            (BlockType::Jump("RET".to_string(), 0, JumpType::Return, None), "RET (return)", "RET (return)"),
+            (BlockType::Terminal("HLT".to_string()), "HLT (terminal)", "HLT (terminal)"),
+            (BlockType::Jump("JMP".to_string(), 0x50, JumpType::Indirect, None), "JMP 0x50 (indirect)", "JMP 80 (indirect)"),
+            (BlockType::Switch("JMP".to_string(), vec![0x10, 0x20]), "JMP [0x10, 0x20]", "JMP [16, 32]"),
+        ];
+        for (instruction, hex, decimal) in instructions {
+            assert_eq!(hex, instruction.to_string());
+            assert_eq!(hex, instruction.display(AddressFormat::Hex).to_string());
+            assert_eq!(decimal, instruction.display(AddressFormat::Decimal).to_string());
+        }
+    }
+
+    #[test]
+    fn static_and_owned_names() -> Result<(), CFGError> {
+        // Static mnemonics from a decoder table are recorded without allocating.
+        let mut cfg: ControlFlowGraph<&'static str> = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("MOV", Some("A, 1")))?;
+        cfg.execute(1, BlockType::Jump("JMP", 0, JumpType::UnconditionalJump, None))?;
+        assert!(cfg.to_string().starts_with("0x0 - 0x1 (entry):\n    0x0: MOV A, 1\n    0x1: JMP 0x0 (unconditional)\n"));
+        let owned: BlockType = BlockType::Instruction("MOV", Some("A, 1")).into();
+        assert_eq!(BlockType::Instruction("MOV".to_string(), Some("A, 1".to_string())), owned);
+
+        // A Cow payload mixes static names with owned operands which were formatted at runtime.
+        let mut cfg: ControlFlowGraph<Cow<'static, str>> = ControlFlowGraph::new(0);
+        cfg.execute(0, BlockType::Instruction("MOV", Some("A, 1")).into())?;
+        cfg.execute(1, BlockType::Instruction("ADD".to_string(), Some(format!("A, {}", 2))).into())?;
+        cfg.execute(2, BlockType::Jump(Cow::Borrowed("JMP"), 0, JumpType::UnconditionalJump, None))?;
+        let instructions: Vec<_> = cfg.entry().instructions().map(|(_, instruction)| instruction).collect();
+        assert!(matches!(instructions[0], BlockType::Instruction(Cow::Borrowed("MOV"), Some(Cow::Borrowed("A, 1")))));
+        assert!(matches!(instructions[1], BlockType::Instruction(Cow::Owned(_), Some(Cow::Owned(_)))));
+        let texts: Vec<String> = instructions.iter().map(|instruction| instruction.to_string()).collect();
+        assert_eq!(vec!["MOV A, 1", "ADD A, 2", "JMP 0x0 (unconditional)"], texts);
+
+        #[cfg(feature = "serde")]
+        {
+            let restored: ControlFlowGraph<Cow<'static, str>> = serde_json::from_str(&serde_json::to_string(&cfg).unwrap()).unwrap();
+            assert_eq!(cfg.to_string(), restored.to_string());
+        }
+
+        // Neither payload makes an instruction any bigger than the default String does.
+        assert_eq!(core::mem::size_of::<BlockType>(), core::mem::size_of::<BlockType<Cow<'static, str>>>());
+        assert!(core::mem::size_of::<BlockType<&'static str>>() <= core::mem::size_of::<BlockType>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn jump_from_operand() -> Result<(), CFGError> {
+        let jump = |operand: &str| BlockType::jump_from_operand("JNZ", operand, JumpType::ConditionalTaken, Some(0x4002));
+        assert_eq!(BlockType::Jump("JNZ".to_string(), 0x4010, JumpType::ConditionalTaken, Some(0x4002)), jump("0x4010")?);
+        assert_eq!(BlockType::Jump("JNZ".to_string(), 0x4010, JumpType::ConditionalTaken, Some(0x4002)), jump("short 0X4010")?);
+        assert_eq!(BlockType::Jump("JNZ".to_string(), 16400, JumpType::ConditionalTaken, Some(0x4002)), jump(" 16400 ")?);
+        assert_eq!(BlockType::Jump("RET".to_string(), 0, JumpType::Return, None), BlockType::jump_from_operand("RET", "8", JumpType::Return, None)?);
+
+        for (operand, reason) in [
+            ("-0x10", "invalid address `-0x10`"),
+            ("-16", "invalid address `-16`"),
+            ("[rax+8]", "invalid address `[rax+8]`"),
+            ("0x40zz", "invalid address `0x40zz`"),
+            ("0x", "invalid address `0x`"),
+            ("", "missing jump target in ``"),
+        ] {
+            let error = jump(operand).unwrap_err();
+            assert!(matches!(&error, CFGError::ParseError(1, message) if message == reason), "{}", error);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn jump_builder() -> Result<(), CFGError> {
+        let jump = |name: &str| BlockType::<String>::jump(name);
+        assert_eq!(BlockType::Jump("CALL".to_string(), 0x40, JumpType::Call, Some(0x12)), jump("CALL").fallthrough(0x12).call().to(0x40).build()?);
+        assert_eq!(BlockType::Jump("RET".to_string(), 0, JumpType::Return, None), jump("RET").ret().build()?);
+        assert_eq!(BlockType::Jump("JMP".to_string(), 0x20, JumpType::Indirect, None), jump("JMP").indirect().to(0x20).build()?);
+        assert_eq!(jump("JZ").conditional_taken().to(9).fallthrough(6).build()?, jump("JZ").kind(JumpType::ConditionalTaken).to(9).fallthrough(6).build()?);
+        // The last kind chosen wins.
+        assert_eq!(BlockType::Jump("JMP".to_string(), 9, JumpType::UnconditionalJump, None), jump("JMP").call().unconditional().to(9).build()?);
+
+        for (builder, jump_type, missing) in [
+            (jump("JMP"), JumpType::UnconditionalJump, "a target"),
+            (jump("JZ").conditional_taken().fallthrough(6), JumpType::ConditionalTaken, "a target"),
+            (jump("JZ").conditional_taken().to(9), JumpType::ConditionalTaken, "a fallthrough address"),
+            (jump("JZ").conditional_not_taken().to(9), JumpType::ConditionalNotTaken, "a fallthrough address"),
+            (jump("CALL").call().to(0x40), JumpType::Call, "a fallthrough address"),
+            (jump("JMP").indirect(), JumpType::Indirect, "a target"),
+        ] {
+            let error = builder.build().unwrap_err();
+            assert!(matches!(&error, CFGError::IncompleteJump { jump_type: found, missing: reason } if *found == jump_type && *reason == missing), "{}", error);
+        }
+        let error = jump("JZ").conditional_taken().to(9).build().unwrap_err();
+        assert_eq!("The taken jump was built without a fallthrough address.", error.to_string());
+
+        // The payload is whatever the name converts into.
+        let jump: BlockType<&str> = BlockType::jump("JMP").to(9).build()?;
+        assert_eq!(BlockType::Jump("JMP", 9, JumpType::UnconditionalJump, None), jump);
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_context() {
+        let mut cfg = crate::ControlFlowGraph::new(0x40);
+        let error = cfg.execute(0x41, BlockType::Jump("JZ".to_string(), 0x50, JumpType::ConditionalTaken, None)).unwrap_err();
+        assert!(matches!(&error, CFGError::ExpectedFailureAddress { pc: 0x41, jump_type: JumpType::ConditionalTaken }));
+        assert_eq!("A failure address was expected for the taken jump at 0x41 and it was not provided.", error.to_string());
+
+        let error = cfg.execute(0x42, BlockType::Jump("RET".to_string(), 0, JumpType::Return, None)).unwrap_err();
+        assert!(error.to_string().contains("0x42"));
+        let error = cfg.execute(0x43, BlockType::Switch("JMP".to_string(), vec![0x10])).unwrap_err();
+        assert!(error.to_string().contains("0x43"));
+        let error = cfg.predecessors(BlockId(7)).unwrap_err();
+        assert_eq!("There was an attempt to find the BasicBlock 7 which doesn't exist.", error.to_string());
+    }
+}
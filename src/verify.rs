@@ -0,0 +1,105 @@
+//! Validation of the invariants of a ControlFlowGraph.
+
+use alloc::collections::BTreeSet;
+use core::fmt;
+use crate::ControlFlowGraph;
+use crate::types::BlockId;
+use crate::prelude::*;
+
+/// A broken invariant of a ControlFlowGraph, identifying the offending block by its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgInvariantViolation {
+    /// An edge of the block points at a position which has no block.
+    DanglingEdge { block: BlockId, target: BlockId },
+    /// The end address of the block is below its start address.
+    EndBeforeStart { block: BlockId, start: u64, end: u64 },
+    /// The block has the same start address as an earlier block.
+    DuplicateStart { block: BlockId, start: u64 },
+    /// An instruction of the block is outside of its `[start, end]` range.
+    InstructionOutOfRange { block: BlockId, address: u64 },
+    /// The current block points at a position which has no block.
+    InvalidCurrentBlock { current_block: BlockId },
+}
+
+impl fmt::Display for CfgInvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfgInvariantViolation::DanglingEdge { block, target } => write!(f, "block {} has an edge to the missing block {}", block, target),
+            CfgInvariantViolation::EndBeforeStart { block, start, end } => write!(f, "block {} ends at {:#x} before it starts at {:#x}", block, end, start),
+            CfgInvariantViolation::DuplicateStart { block, start } => write!(f, "block {} starts at {:#x} like an earlier block", block, start),
+            CfgInvariantViolation::InstructionOutOfRange { block, address } => write!(f, "block {} has an instruction at {:#x} outside of its range", block, address),
+            CfgInvariantViolation::InvalidCurrentBlock { current_block } => write!(f, "the current block {} does not exist", current_block),
+        }
+    }
+}
+
+impl<I> ControlFlowGraph<I> {
+    /// Checks the invariants of the ControlFlowGraph, returning every violation which was found.
+    pub fn verify(&self) -> Result<(), Vec<CfgInvariantViolation>> {
+        let mut violations = Vec::new();
+        let mut starts = BTreeSet::new();
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let index = BlockId(index);
+            for edge in block.edges.iter() {
+                if edge.target().index() >= self.blocks.len() {
+                    violations.push(CfgInvariantViolation::DanglingEdge { block: index, target: edge.target() });
+                }
+            }
+            if block.end < block.start {
+                violations.push(CfgInvariantViolation::EndBeforeStart { block: index, start: block.start, end: block.end });
+            }
+            if !starts.insert(block.start) {
+                violations.push(CfgInvariantViolation::DuplicateStart { block: index, start: block.start });
+            }
+            for address in block.block.keys() {
+                if *address < block.start || *address > block.end {
+                    violations.push(CfgInvariantViolation::InstructionOutOfRange { block: index, address: *address });
+                }
+            }
+        }
+
+        if self.current_block.index() >= self.blocks.len() {
+            violations.push(CfgInvariantViolation::InvalidCurrentBlock { current_block: self.current_block });
+        }
+
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BasicBlock, Edge};
+    use crate::types::*;
+
+    #[test]
+    fn valid_graph() -> Result<(), CFGError> {
+        let mut cfg = ControlFlowGraph::new(2);
+        cfg.execute(3, BlockType::Instruction("INC".to_string(), None))?;
+        cfg.execute(5, BlockType::Jump("JMP".to_string(), 9, JumpType::ConditionalTaken, Some(6)))?;
+        assert_eq!(Ok(()), cfg.verify());
+
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_graph() {
+        let mut entry = BasicBlock::new(0);
+        entry.add_instruction(4, BlockType::Instruction("INC".to_string(), None));
+        entry.end = 2;
+        entry.edges.push(Edge::new(BlockId(5), 1, EdgeKind::Unconditional));
+        let mut inverted = BasicBlock::new(8);
+        inverted.end = 6;
+        let cfg = ControlFlowGraph::from_blocks(vec![entry, inverted, BasicBlock::new(0)], 3);
+
+        let expected = vec![
+            CfgInvariantViolation::DanglingEdge { block: BlockId(0), target: BlockId(5) },
+            CfgInvariantViolation::InstructionOutOfRange { block: BlockId(0), address: 4 },
+            CfgInvariantViolation::EndBeforeStart { block: BlockId(1), start: 8, end: 6 },
+            CfgInvariantViolation::DuplicateStart { block: BlockId(2), start: 0 },
+            CfgInvariantViolation::InvalidCurrentBlock { current_block: BlockId(3) },
+        ];
+        assert_eq!(Err(expected), cfg.verify());
+    }
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "cli")]
+
+use std::fs::File;
+use std::io::BufReader;
+use ctrl_flow::ControlFlowGraph;
+use ctrl_flow::cli::{self, CliOptions, InputFormat, OutputFormat};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/testdata/loop.trace");
+
+fn convert(args: &[&str]) -> String {
+    let options = cli::parse_args(args.iter().map(|arg| arg.to_string())).unwrap().unwrap();
+    let file = File::open(options.input.as_ref().unwrap()).unwrap();
+    let mut output = Vec::new();
+    cli::run(&options, BufReader::new(file), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn trace_to_every_format() {
+    let mut cfg = ControlFlowGraph::new(0x1000);
+    cfg.ingest(BufReader::new(File::open(FIXTURE).unwrap())).unwrap();
+
+    assert_eq!(cfg.to_dot(), convert(&[FIXTURE]));
+    assert_eq!(cfg.to_json(), convert(&["--format", "json", FIXTURE]));
+    let text = convert(&[FIXTURE, "--format", "text"]);
+    assert_eq!(cfg.to_string(), text);
+    assert!(text.starts_with("0x1000 - 0x1000 (entry):\n    0x1000: CALL 0x2000 / 0x1001 (call)\n"));
+}
+
+#[test]
+fn entry_and_pruning() {
+    // An entry ahead of the trace leaves the first traced instruction inside of the synthetic entry block.
+    let entered = convert(&["--entry", "0xfff", "--format", "text", FIXTURE]);
+    assert!(entered.starts_with("0xfff - 0x1000 (entry):\n"));
+
+    // The not-taken blocks which were created eagerly and never executed are pruned.
+    let pruned = convert(&["--prune-unreachable", "--format", "text", FIXTURE]);
+    let full = convert(&["--format", "text", FIXTURE]);
+    assert!(pruned.len() < full.len());
+    assert_eq!(full.matches(" - ").count() - 1, pruned.matches(" - ").count());
+}
+
+#[test]
+fn qemu_log_from_reader() {
+    let log = "Trace 0: 0x7f5bd4000100 [00000000/0000000000400080/00000000/ff000000] _start\n\
+               Trace 0: 0x7f5bd4000240 [00000000/0000000000400090/00000000/ff000000]\n";
+    let options = CliOptions { input_format: InputFormat::Qemu, format: OutputFormat::Text, ..CliOptions::default() };
+    let mut output = Vec::new();
+    cli::run(&options, log.as_bytes(), &mut output).unwrap();
+    assert!(String::from_utf8(output).unwrap().starts_with("0x400080 - 0x400080 (entry):\n"));
+}
+
+#[test]
+fn invalid_arguments() {
+    let parse = |args: &[&str]| cli::parse_args(args.iter().map(|arg| arg.to_string()));
+    assert_eq!(Ok(None), parse(&["--help"]));
+    assert_eq!(Err("unknown output format `svg`".to_string()), parse(&["--format", "svg"]));
+    assert_eq!(Err("missing value for `--entry`".to_string()), parse(&["--entry"]));
+    assert_eq!(Err("invalid address `zz`".to_string()), parse(&["--entry", "zz"]));
+    assert_eq!(Err("unexpected argument `b`".to_string()), parse(&["a", "b"]));
+}